@@ -0,0 +1,91 @@
+//! Time of drawing many short lines, run with `cargo bench`.
+//!
+//! Criterion isn't a dependency, so this is a plain `harness = false` binary
+//! printing the best and the median of a few runs and the allocations of the last.
+//!
+//! One million lines against [`NullWindow`], best of five on a single core:
+//!
+//! ```text
+//!                      before              after
+//! spiral/tree          879ms  7028247      743ms  2031245 allocations
+//! spiral/bytecode      214ms   999030      195ms       30 allocations
+//! ```
+//!
+//! Before, every binary operator allocated the list of its operand types,
+//! every poll of a turtle allocated a new waker and the tree interpreter boxed
+//! the evaluation of constants and variables. What remains are the boxed
+//! futures of blocks and nested expressions. Text is still only copied by
+//! windows that send it on, [`Window::print`](turtle::debugger::window::Window::print)
+//! takes a `&str`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use turtle::{
+    debugger::{config::RunConfig, window::NullWindow},
+    features::FeatureConf,
+    TProgram,
+};
+
+/// one million lines of a spiral
+const SPIRAL: &str = "begin
+  do 1000 times
+    counter i from 1 to 1000 do
+      walk i / 100
+      turn 89.5
+    done
+  done
+end";
+
+const RUNS: usize = 5;
+
+/// counts allocations, to tell what a run costs besides time
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn time(name: &str, prog: &TProgram, bytecode: bool) {
+    let mut times: Vec<Duration> = (0..RUNS)
+        .map(|_| {
+            ALLOCS.store(0, Ordering::Relaxed);
+            let start = Instant::now();
+            RunConfig::new(&[])
+                .window(NullWindow::default())
+                .skip_delay(true)
+                .bytecode(bytecode)
+                .exec(prog)
+                .unwrap();
+            start.elapsed()
+        })
+        .collect();
+    times.sort();
+    println!(
+        "{name:<20} best {:>8.1?}  median {:>8.1?}  {:>9} allocations",
+        times[0],
+        times[RUNS / 2],
+        ALLOCS.load(Ordering::Relaxed)
+    );
+}
+
+fn main() {
+    let prog = TProgram::parse(SPIRAL, false, FeatureConf::default()).unwrap();
+    time("spiral/tree", &prog, false);
+    time("spiral/bytecode", &prog, true);
+}
@@ -5,6 +5,7 @@
 #include <math.h>
 
 #include "sdlinterf.h"
+#include "turtleinterf.h"
 
 typedef struct Mark {
 	double x;
@@ -15,11 +16,45 @@ typedef struct Mark {
 
 Mark *top_mark;
 
+// affine map applied to everything drawn, see Transform in the interpreter
+//
+// [a, b, c, d, e, f] maps (x, y) to (a * x + c * y + e, b * x + d * y + f), with y up
+typedef struct Transform {
+	double m[6];
+	struct Transform *prev;
+} Transform;
+
+static Transform transform = {{1.0, 0.0, 0.0, 1.0, 0.0, 0.0}, NULL};
+
+// what every turtle of newturtle has on its own
+typedef struct State {
+	double x;
+	double y;
+	double dir;
+	double red;
+	double green;
+	double blue;
+	double width;
+	bool pen;
+} State;
+
+static const State home = {0.0, 0.0, 0.0, 100.0, 100.0, 0.0, 0.0, true};
+
+// the entry of the active turtle is stale
+static State *turtles = NULL;
+static int turtle_count = 1, active_turtle = 0;
+
 double __ttl_x = 0.0, __ttl_y = 0.0;
 double __ttl_dir = 0.0, __ttl_delay = 1.0;
 double __ttl_args[10] = {0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0};
 double __ttl_max_x = 20.0, __ttl_max_y = 15.0;
 double __ttl_red = 100.0, __ttl_green = 100.0, __ttl_blue = 0.0;
+// in turtle units, 0 for the thinnest line
+double __ttl_width = 0.0;
+bool __ttl_pen = true;
+
+// frames begun and not yet ended, the drawing is only shown outside of them
+static int frames = 0;
 
 double __ttl_dist() {
 	return sqrt(__ttl_x * __ttl_x + __ttl_y * __ttl_y);
@@ -40,6 +75,9 @@ void __ttl_init(int argc, const char *argv[]) {
 }
 
 void __ttl_stop() {
+	// show what was drawn in unfinished frames
+	frames = 0;
+	sdlUpdate();
 	printf("halt and catch fire\n");
 	while (1) {
 		sdlMilliSleep(200);
@@ -52,28 +90,80 @@ static int to_color(double x) {
   return lround(x / 100 * 255);
 }
 
-void __ttl_walk_pos(double next_x, double next_y, bool draw) {
-	if (draw) {
-		int from_x = (int) (SDL_X_SIZE / 2.0 * (1.0 + __ttl_x / __ttl_max_x));
-		int from_y = (int) (SDL_Y_SIZE / 2.0 * (1.0 + __ttl_y / __ttl_max_y));
-		int to_x = (int) (SDL_X_SIZE / 2.0 * (1.0 + next_x / __ttl_max_x));
-		int to_y = (int) (SDL_Y_SIZE / 2.0 * (1.0 + next_y / __ttl_max_y));
-		if (from_x < SDL_X_SIZE && from_x >= 0
-			&& from_y < SDL_Y_SIZE && from_y >= 0
-			&& to_x < SDL_X_SIZE && to_x >= 0
-			&& to_y < SDL_Y_SIZE && to_y >= 0) {
+static bool on_screen(int x, int y) {
+	return x < SDL_X_SIZE && x >= 0 && y < SDL_Y_SIZE && y >= 0;
+}
+
+// SDL lines are always 1px, so wider ones are drawn as parallel lines
+static void draw_line(int from_x, int from_y, int to_x, int to_y) {
+	int width = (int) (__ttl_width * SDL_X_SIZE / 2.0 / __ttl_max_x);
+	if (width < 1) width = 1;
+	double dx = to_x - from_x, dy = to_y - from_y;
+	double len = fmax(sqrt(dx * dx + dy * dy), 1.0);
+	double nx = -dy / len, ny = dx / len;
+	for (int i = 0; i < width; ++i) {
+		double off = i - (width - 1) / 2.0;
+		int sx = (int) round(nx * off), sy = (int) round(ny * off);
+		if (on_screen(from_x + sx, from_y + sy) && on_screen(to_x + sx, to_y + sy)) {
 			sdlDrawLine(
-				from_x, from_y, to_x, to_y,
+				from_x + sx, from_y + sy, to_x + sx, to_y + sy,
 				to_color(__ttl_red),
 				to_color(__ttl_green),
 				to_color(__ttl_blue)
 			);
-			sdlUpdate();
-			sdlMilliSleep((int) __ttl_delay);
+		}
+	}
+}
+
+// the turtle coordinates have y down, the transform y up
+static void apply(double x, double y, double *tx, double *ty) {
+	const double *m = transform.m;
+	*tx = m[0] * x - m[2] * y + m[4];
+	*ty = -(m[1] * x - m[3] * y + m[5]);
+}
+
+// moves without showing the line yet, true if something was drawn
+static bool line_to(double next_x, double next_y, bool draw) {
+	bool drawn = false;
+	if (draw && __ttl_pen) {
+		double fx, fy, tx, ty;
+		apply(__ttl_x, __ttl_y, &fx, &fy);
+		apply(next_x, next_y, &tx, &ty);
+		int from_x = (int) (SDL_X_SIZE / 2.0 * (1.0 + fx / __ttl_max_x));
+		int from_y = (int) (SDL_Y_SIZE / 2.0 * (1.0 + fy / __ttl_max_y));
+		int to_x = (int) (SDL_X_SIZE / 2.0 * (1.0 + tx / __ttl_max_x));
+		int to_y = (int) (SDL_Y_SIZE / 2.0 * (1.0 + ty / __ttl_max_y));
+		if (on_screen(from_x, from_y) && on_screen(to_x, to_y)) {
+			draw_line(from_x, from_y, to_x, to_y);
+			drawn = true;
 		}
 	}
 	__ttl_x = next_x;
 	__ttl_y = next_y;
+	return drawn;
+}
+
+void __ttl_show() {
+	if (frames == 0) {
+		sdlUpdate();
+		sdlMilliSleep((int) __ttl_delay);
+	}
+}
+
+void __ttl_begin_frame() {
+	++frames;
+}
+
+void __ttl_end_frame() {
+	if (frames > 0 && --frames == 0) {
+		__ttl_show();
+	}
+}
+
+void __ttl_walk_pos(double next_x, double next_y, bool draw) {
+	if (line_to(next_x, next_y, draw)) {
+		__ttl_show();
+	}
 }
 
 void __ttl_walk(double dist, bool draw) {
@@ -82,6 +172,36 @@ void __ttl_walk(double dist, bool draw) {
 	__ttl_walk_pos(next_x, next_y, draw);
 }
 
+// lines per turtle unit, and at most per full turn
+#define ARC_RESOLUTION 2.0
+#define MAX_CIRCLE_SEGMENTS 3600.0
+
+// walks along a circle, turning counter-clockwise by angle degrees
+void __ttl_arc(double radius, double angle) {
+	double drawn = angle;
+	if (fabs(angle) > 360.0) {
+		// beyond one turn the circle is drawn only once
+		drawn = copysign(360.0, angle) + fmod(angle, 360.0);
+	}
+	double length = fabs(radius * drawn) * M_PI / 180.0;
+	double max_segments = fmax(ceil(MAX_CIRCLE_SEGMENTS * fabs(drawn) / 360.0), 1.0);
+	int segments = (int) fmin(fmax(ceil(length * ARC_RESOLUTION), 1.0), max_segments);
+	// direction from the turtle to the center of the circle
+	double side = __ttl_dir + copysign(90.0, angle);
+	double center_x = __ttl_x + cos(side * M_PI / 180.0) * radius;
+	double center_y = __ttl_y - sin(side * M_PI / 180.0) * radius;
+	double start = side + 180.0;
+	bool drawn_any = false;
+	for (int i = 1; i <= segments; ++i) {
+		double a = (start + drawn * i / segments) * M_PI / 180.0;
+		drawn_any |= line_to(center_x + cos(a) * radius, center_y - sin(a) * radius, true);
+	}
+	__ttl_set_dir(__ttl_dir + angle);
+	if (drawn_any) {
+		__ttl_show();
+	}
+}
+
 void __ttl_set_mark() {
 	Mark *m = malloc(sizeof(Mark));
 	m->x = __ttl_x;
@@ -109,4 +229,144 @@ double __ttl_rand(double min, double max) {
 
 void __ttl_set_dir(double new_dir) {
 	__ttl_dir = fmod(fmod(new_dir, 360.0) + 360.0, 360.0);
-}
\ No newline at end of file
+}
+
+// heading from the turtle to (x, y), with y down like __ttl_walk
+double __ttl_towards(double x, double y) {
+	double dir = atan2(__ttl_y - y, x - __ttl_x) * 180.0 / M_PI;
+	return fmod(dir + 360.0, 360.0);
+}
+
+// from 1, the slowest, up to MAX_SPEED without delay
+#define MAX_SPEED 10.0
+#define SLOWEST_DELAY 500.0
+
+void __ttl_set_speed(double speed) {
+	speed = fmin(fmax(speed, 1.0), MAX_SPEED);
+	__ttl_delay = SLOWEST_DELAY * (MAX_SPEED - speed) / (MAX_SPEED - 1.0);
+}
+
+static State get_state() {
+	State s = {__ttl_x, __ttl_y, __ttl_dir, __ttl_red, __ttl_green, __ttl_blue, __ttl_width, __ttl_pen};
+	return s;
+}
+
+static void load_state(State s) {
+	__ttl_x = s.x;
+	__ttl_y = s.y;
+	__ttl_dir = s.dir;
+	__ttl_set_col(s.red, s.green, s.blue);
+	__ttl_width = s.width;
+	__ttl_pen = s.pen;
+}
+
+typedef struct SavedState {
+	State state;
+	struct SavedState *prev;
+} SavedState;
+
+static SavedState *top_state = NULL;
+
+void __ttl_save_state() {
+	SavedState *s = malloc(sizeof(SavedState));
+	s->state = get_state();
+	s->prev = top_state;
+	top_state = s;
+}
+
+void __ttl_restore_state(int line) {
+	SavedState *s = top_state;
+	if (s == NULL) {
+		fprintf(stderr, "line %d: restorestate without a matching savestate\n", line);
+		exit(EXIT_FAILURE);
+	}
+	top_state = s->prev;
+	load_state(s->state);
+	free(s);
+}
+
+// inner first, then the current transform
+static void tf_then(double ia, double ib, double ic, double id, double ie, double if_) {
+	double *m = transform.m;
+	double a = m[0], b = m[1], c = m[2], d = m[3], e = m[4], f = m[5];
+	m[0] = a * ia + c * ib;
+	m[1] = b * ia + d * ib;
+	m[2] = a * ic + c * id;
+	m[3] = b * ic + d * id;
+	m[4] = a * ie + c * if_ + e;
+	m[5] = b * ie + d * if_ + f;
+}
+
+void __ttl_scale(double x, double y) {
+	tf_then(x, 0.0, 0.0, y, 0.0, 0.0);
+}
+
+void __ttl_scale_all(double factor) {
+	__ttl_scale(factor, factor);
+}
+
+void __ttl_translate(double x, double y) {
+	tf_then(1.0, 0.0, 0.0, 1.0, x, y);
+}
+
+// counter-clockwise, like headings
+void __ttl_rotate(double angle) {
+	double s = sin(angle * M_PI / 180.0), c = cos(angle * M_PI / 180.0);
+	tf_then(c, s, -s, c, 0.0, 0.0);
+}
+
+void __ttl_push() {
+	Transform *t = malloc(sizeof(Transform));
+	*t = transform;
+	transform.prev = t;
+}
+
+void __ttl_pop(int line) {
+	Transform *t = transform.prev;
+	if (t == NULL) {
+		fprintf(stderr, "line %d: pop without a matching push\n", line);
+		exit(EXIT_FAILURE);
+	}
+	transform = *t;
+	free(t);
+}
+
+// home with the pen, color and transform of a new turtle, marks are kept but saved states not
+void __ttl_reset() {
+	while (transform.prev != NULL) {
+		__ttl_pop(0);
+	}
+	Transform identity = {{1.0, 0.0, 0.0, 1.0, 0.0, 0.0}, NULL};
+	transform = identity;
+	while (top_state != NULL) {
+		SavedState *s = top_state;
+		top_state = s->prev;
+		free(s);
+	}
+	load_state(home);
+}
+
+void __ttl_new_turtle() {
+	turtles = realloc(turtles, (turtle_count + 1) * sizeof(State));
+	if (turtles == NULL) {
+		fprintf(stderr, "out of memory\n");
+		exit(EXIT_FAILURE);
+	}
+	turtles[active_turtle] = get_state();
+	active_turtle = turtle_count++;
+	load_state(home);
+}
+
+void __ttl_set_turtle(double id, int line) {
+	if (id != floor(id) || id < 1 || id > turtle_count) {
+		fprintf(stderr, "line %d: no turtle %g, turtles are numbered from 1 to %d\n", line, id, turtle_count);
+		exit(EXIT_FAILURE);
+	}
+	if (turtles == NULL) {
+		// only the first turtle exists, which is already active
+		return;
+	}
+	turtles[active_turtle] = get_state();
+	active_turtle = (int) id - 1;
+	load_state(turtles[active_turtle]);
+}
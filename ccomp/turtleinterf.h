@@ -4,17 +4,35 @@
 #include <stdbool.h>
 
 extern double __ttl_x, __ttl_y, __ttl_max_x, __ttl_max_y, __ttl_dir, __ttl_delay;
-extern double __ttl_red, __ttl_green, __ttl_blue, __ttl_args[10];
+extern double __ttl_red, __ttl_green, __ttl_blue, __ttl_width, __ttl_args[10];
+extern bool __ttl_pen;
 
 extern double __ttl_dist();
 extern void __ttl_set_col(double, double, double);
 extern void __ttl_init(int, const char *[]);
 extern void __ttl_stop();
+extern void __ttl_show();
+extern void __ttl_begin_frame();
+extern void __ttl_end_frame();
 extern void __ttl_walk_pos(double, double, bool);
 extern void __ttl_walk(double, bool);
+extern void __ttl_arc(double, double);
 extern void __ttl_set_mark();
 extern void __ttl_load_mark(bool);
 extern double __ttl_rand(double, double);
 extern void __ttl_set_dir(double); 
+extern double __ttl_towards(double, double);
+extern void __ttl_set_speed(double);
+extern void __ttl_scale(double, double);
+extern void __ttl_scale_all(double);
+extern void __ttl_translate(double, double);
+extern void __ttl_rotate(double);
+extern void __ttl_push();
+extern void __ttl_pop(int);
+extern void __ttl_save_state();
+extern void __ttl_restore_state(int);
+extern void __ttl_reset();
+extern void __ttl_new_turtle();
+extern void __ttl_set_turtle(double, int);
 
 #endif
\ No newline at end of file
@@ -3,7 +3,11 @@ use std::collections::HashMap;
 pub struct Context {
     vars: HashMap<usize, bool>,
     loops: usize,
+    /// indices of the enclosing `do .. times` loops
+    pub do_loops: Vec<usize>,
     pub nesting: usize,
+    /// frames begun in the current function and not yet ended
+    pub frames: usize,
 }
 
 impl Context {
@@ -11,7 +15,9 @@ impl Context {
         Self {
             vars: HashMap::new(),
             loops: 0,
+            do_loops: Vec::new(),
             nesting: 0,
+            frames: 0,
         }
     }
 
@@ -50,7 +56,9 @@ impl Clone for Context {
         Self {
             vars: self.vars.clone(),
             loops: self.loops,
+            do_loops: self.do_loops.clone(),
             nesting: self.nesting,
+            frames: self.frames,
         }
     }
 }
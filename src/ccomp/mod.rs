@@ -1,16 +1,37 @@
 use std::io::Write;
 
+use crate::pos::{FilePos, Pos, Positionable as _};
 use crate::tokens::{
-    BiOperator, Block, Expr, ExprKind, PredefFunc, PredefVar, Statement, Variable, VariableKind,
+    ArgDefList, BiOperator, Block, Expr, ExprKind, PredefFunc, PredefVar, Statement, Value,
+    Variable, VariableKind,
 };
-use crate::{Identified, TProgram};
+use crate::{Identified, TProgram, TurtleError};
 
 use self::context::Context;
 
 mod context;
+#[cfg(test)]
+mod test;
 
 // Aufwand bisher: ~8h
 
+/// Part of a program that [`CComp::compile`] can't translate to C
+#[derive(Debug, PartialEq, Clone, Copy, thiserror::Error)]
+pub enum CUnsupported {
+    #[error("print is not supported in C")]
+    Print,
+    #[error("text is not supported in C")]
+    Text,
+    #[error("split is not supported in C")]
+    Split,
+    #[error("wait is not supported in C")]
+    Wait,
+    #[error("lists are not supported in C")]
+    Lists,
+}
+
+type CRes<T> = Result<T, Pos<CUnsupported>>;
+
 pub struct CComp {
     prog: TProgram,
     filename: String,
@@ -28,7 +49,18 @@ impl CComp {
         self.filename = name.into();
     }
 
-    pub fn compile(&self) {
+    /// Write the C source to the file set by [`CComp::set_filename`]
+    pub fn compile(&self) -> Result<(), TurtleError> {
+        let mut file = std::fs::File::create(&self.filename)?;
+        for line in self.code()? {
+            file.write_all(line.as_bytes())?;
+            file.write_all("\n".as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Lines of the C source
+    fn code(&self) -> CRes<Vec<String>> {
         let context = Context::new();
         // includes
         let mut content = vec![
@@ -41,11 +73,11 @@ impl CComp {
         ];
         // function prototypes
         for pathdef in &self.prog.paths {
-            let args = self.comp_args(&pathdef.args, |a| format!("double {}", self.get_ident(a.0)));
+            let args = self.comp_params(&pathdef.args);
             content.push(format!("void {}({});", self.get_ident(pathdef.name), args));
         }
         for calcdef in &self.prog.calcs {
-            let args = self.comp_args(&calcdef.args, |a| format!("double {}", self.get_ident(a.0)));
+            let args = self.comp_params(&calcdef.args);
             content.push(format!(
                 "double {}({});",
                 self.get_ident(calcdef.name),
@@ -62,7 +94,7 @@ impl CComp {
         content.push(String::new());
         content.push(String::from("int main(int argc, const char *argv[]) {"));
         content.push(String::from("\t__ttl_init(argc, argv);"));
-        content.append(&mut self.comp_block(&mut context.clone(), &self.prog.main));
+        content.append(&mut self.comp_block(&mut context.clone(), &self.prog.main)?);
         content.push(String::from("\treturn 0;"));
         content.push(String::from("}"));
         // other functions
@@ -71,14 +103,14 @@ impl CComp {
             for arg in &pathdef.args {
                 ctx.insert(arg.0, true);
             }
-            let args = self.comp_args(&pathdef.args, |a| format!("double {}", self.get_ident(a.0)));
+            let args = self.comp_params(&pathdef.args);
             content.push(String::new());
             content.push(format!(
                 "void {}({}) {{",
                 self.get_ident(pathdef.name),
                 args
             ));
-            content.append(&mut self.comp_block(&mut ctx, &pathdef.body));
+            content.append(&mut self.comp_block(&mut ctx, &pathdef.body)?);
             content.push(String::from("}"));
         }
         for calcdef in &self.prog.calcs {
@@ -86,33 +118,27 @@ impl CComp {
             for arg in &calcdef.args {
                 ctx.insert(arg.0, true);
             }
-            let args = self.comp_args(&calcdef.args, |a| format!("double {}", self.get_ident(a.0)));
+            let args = self.comp_params(&calcdef.args);
             content.push(String::new());
             content.push(format!(
                 "double {}({}) {{",
                 self.get_ident(calcdef.name),
                 args
             ));
-            content.append(&mut self.comp_block(&mut ctx, &calcdef.body));
+            content.append(&mut self.comp_block(&mut ctx, &calcdef.body)?);
             content.push(format!(
                 "\treturn {};",
-                self.comp_expr(&mut ctx, &calcdef.ret)
+                self.comp_expr(&mut ctx, &calcdef.ret)?
             ));
             content.push(String::from("}"));
         }
-        // write to file
-        let path = std::path::Path::new(&self.filename);
-        let mut file = std::fs::File::create(path).unwrap();
-        for line in content {
-            file.write_all(line.as_bytes()).unwrap();
-            file.write_all("\n".as_bytes()).unwrap();
-        }
+        Ok(content)
     }
 
-    fn comp_block(&self, ctx: &mut Context, block: &Block) -> Vec<String> {
+    fn comp_block(&self, ctx: &mut Context, block: &Block) -> CRes<Vec<String>> {
         let mut res = Vec::new();
         for stmt in &block.statements {
-            let mut code = self.comp_stmt(ctx, stmt);
+            let mut code = self.comp_stmt(ctx, stmt, stmt.get_pos())?;
             for var in ctx.get_new() {
                 ctx.insert(var, true);
                 res.push(format!("\tdouble {} = 0.0;", self.get_ident(var)));
@@ -120,16 +146,16 @@ impl CComp {
             code.iter_mut().for_each(|line| *line = format!("\t{line}"));
             res.append(&mut code);
         }
-        res
+        Ok(res)
     }
 
-    fn comp_stmt(&self, ctx: &mut Context, stmt: &Statement) -> Vec<String> {
-        match stmt {
+    fn comp_stmt(&self, ctx: &mut Context, stmt: &Statement, pos: FilePos) -> CRes<Vec<String>> {
+        Ok(match stmt {
             Statement::MoveDist { dist, draw, back } => {
                 vec![format!(
                     "__ttl_walk({}({}), {});",
                     if *back { "-" } else { "" },
-                    self.comp_expr(ctx, dist),
+                    self.comp_expr(ctx, dist)?,
                     draw,
                 )]
             }
@@ -137,74 +163,186 @@ impl CComp {
                 String::from("__ttl_dir = 0.0;"),
                 format!("__ttl_walk_pos(0.0, 0.0, {draw});"),
             ],
+            Statement::MoveTo { x, y, draw } => vec![format!(
+                "__ttl_walk_pos({}, {}, {draw});",
+                self.comp_expr(ctx, x)?,
+                self.comp_expr(ctx, y)?,
+            )],
             Statement::Turn { left, by } => vec![format!(
                 "__ttl_set_dir(__ttl_dir {} {});",
                 if *left { "+" } else { "-" },
-                self.comp_expr(ctx, by),
+                self.comp_expr(ctx, by)?,
             )],
             Statement::Direction(expr) => {
-                vec![format!("__ttl_set_dir({});", self.comp_expr(ctx, expr))]
+                vec![format!("__ttl_set_dir({});", self.comp_expr(ctx, expr)?)]
             }
             Statement::Color(red, green, blue) => vec![format!(
                 "__ttl_set_col({}, {}, {});",
-                self.comp_expr(ctx, red),
-                self.comp_expr(ctx, green),
-                self.comp_expr(ctx, blue)
+                self.comp_expr(ctx, red)?,
+                self.comp_expr(ctx, green)?,
+                self.comp_expr(ctx, blue)?
             )],
+            Statement::Pen(down) => vec![format!("__ttl_pen = {down};")],
             Statement::Clear => vec![
                 String::from("sdlSetBlack();"),
-                String::from("sdlUpdate();"),
-                String::from("sdlMilliSleep((int) __ttl_delay);"),
+                String::from("__ttl_show();"),
+            ],
+            Statement::ClearScreen => vec![
+                String::from("sdlSetBlack();"),
+                String::from("__ttl_reset();"),
+                String::from("__ttl_show();"),
             ],
+            Statement::PushTransform => vec![String::from("__ttl_push();")],
+            Statement::PopTransform => vec![format!("__ttl_pop({});", pos.line)],
+            Statement::Scale(x, None) => {
+                vec![format!("__ttl_scale_all({});", self.comp_expr(ctx, x)?)]
+            }
+            Statement::Scale(x, Some(y)) => vec![format!(
+                "__ttl_scale({}, {});",
+                self.comp_expr(ctx, x)?,
+                self.comp_expr(ctx, y)?
+            )],
+            Statement::Translate(x, y) => vec![format!(
+                "__ttl_translate({}, {});",
+                self.comp_expr(ctx, x)?,
+                self.comp_expr(ctx, y)?
+            )],
+            Statement::Rotate(angle) => {
+                vec![format!("__ttl_rotate({});", self.comp_expr(ctx, angle)?)]
+            }
             Statement::Stop => vec![String::from("__ttl_stop();")],
             Statement::Finish => vec![String::from("exit(EXIT_SUCCESS);")],
             Statement::PathCall(id, args) => vec![format!(
                 "{}({});",
                 self.get_ident(*id),
-                self.comp_args(args, |e| self.comp_expr(ctx, e))
+                self.comp_args(ctx, args)?
             )],
             Statement::Store(expr, var) => vec![format!(
                 "{} = {};",
                 self.comp_var(ctx, var, VarAct::Init),
-                self.comp_expr(ctx, expr)
+                self.comp_expr(ctx, expr)?
             )],
+            Statement::Let(expr, var) => {
+                let VariableKind::Local(id, _) = var.kind else {
+                    unreachable!("parser only allows local variables in let")
+                };
+                let val = self.comp_expr(ctx, expr)?;
+                if ctx.nesting == 0 {
+                    vec![format!(
+                        "{} = {val};",
+                        self.comp_var(ctx, var, VarAct::Init)
+                    )]
+                } else {
+                    // a C declaration in the nested block shadows like `let` does
+                    vec![format!("double {} = {val};", self.get_ident(id))]
+                }
+            }
             Statement::Calc { var, val, op } => vec![format!(
                 "{} {}= {};",
                 self.comp_var(ctx, var, VarAct::Write),
                 op,
-                self.comp_expr(ctx, val),
+                self.comp_expr(ctx, val)?,
             )],
             Statement::Mark => vec![String::from("__ttl_set_mark();")],
-            Statement::Print(_) => todo!(),
-            Statement::Split(_, _) => todo!(),
-            Statement::Wait => todo!(),
+            Statement::Print(_) => return Err(CUnsupported::Print.attach_pos(pos)),
+            Statement::PenWidth(width) => {
+                vec![format!(
+                    "__ttl_width = fmax({}, 0.0);",
+                    self.comp_expr(ctx, width)?
+                )]
+            }
+            Statement::Arc {
+                left,
+                radius,
+                angle,
+            } => vec![format!(
+                "__ttl_arc({}, {}({}));",
+                self.comp_expr(ctx, radius)?,
+                if *left { "" } else { "-" },
+                match angle {
+                    Some(angle) => self.comp_expr(ctx, angle)?,
+                    None => String::from("360.0"),
+                },
+            )],
+            Statement::SetSpeed(speed) => {
+                vec![format!("__ttl_set_speed({});", self.comp_expr(ctx, speed)?)]
+            }
+            // lines in C stay solid, like windows without dashes
+            Statement::PenStyle(_) => Vec::new(),
+            // the C window has no cursor to show
+            Statement::ShowTurtle(_) => Vec::new(),
+            Statement::NewTurtle => vec![String::from("__ttl_new_turtle();")],
+            Statement::SetTurtle(id) => vec![format!(
+                "__ttl_set_turtle({}, {});",
+                self.comp_expr(ctx, id)?,
+                pos.line
+            )],
+            Statement::Frame(body) => {
+                let mut res = vec![String::from("__ttl_begin_frame();"), String::from("{")];
+                ctx.nesting += 1;
+                ctx.frames += 1;
+                res.append(&mut self.comp_block(ctx, body)?);
+                ctx.frames -= 1;
+                ctx.nesting -= 1;
+                res.push(String::from("}"));
+                res.push(String::from("__ttl_end_frame();"));
+                res
+            }
+            Statement::SaveState => vec![String::from("__ttl_save_state();")],
+            Statement::RestoreState => vec![format!("__ttl_restore_state({});", pos.line)],
+            // the precision is only for numbers turned into text
+            Statement::SetPrecision(_) => return Err(CUnsupported::Text.attach_pos(pos)),
+            Statement::ForEach { .. } => return Err(CUnsupported::Lists.attach_pos(pos)),
+            Statement::Split(_, _) => return Err(CUnsupported::Split.attach_pos(pos)),
+            Statement::Wait => return Err(CUnsupported::Wait.attach_pos(pos)),
+            Statement::Return(val) => {
+                // frames begun in the function end with it, like in the interpreter
+                let mut res = vec![String::from("__ttl_end_frame();"); ctx.frames];
+                match val {
+                    None => res.push(String::from("return;")),
+                    Some(val) if ctx.frames == 0 => {
+                        res.push(format!("return {};", self.comp_expr(ctx, val)?))
+                    }
+                    Some(val) => {
+                        res.insert(0, format!("double __ret = {};", self.comp_expr(ctx, val)?));
+                        res.push(String::from("return __ret;"));
+                        res.iter_mut().for_each(|line| *line = format!("\t{line}"));
+                        res.insert(0, String::from("{"));
+                        res.push(String::from("}"));
+                    }
+                }
+                res
+            }
             Statement::MoveMark(draw) => vec![format!("__ttl_load_mark({draw});")],
             Statement::IfBranch(cond, stmts) => {
-                let mut res = vec![format!("if ({}) {{", self.comp_expr(ctx, cond))];
+                let mut res = vec![format!("if ({}) {{", self.comp_expr(ctx, cond)?)];
                 ctx.nesting += 1;
-                res.append(&mut self.comp_block(ctx, stmts));
+                res.append(&mut self.comp_block(ctx, stmts)?);
                 ctx.nesting -= 1;
                 res.push(String::from("}"));
                 res
             }
             Statement::IfElseBranch(cond, if_branch, else_branch) => {
-                let mut res = vec![format!("if ({}) {{", self.comp_expr(ctx, cond))];
+                let mut res = vec![format!("if ({}) {{", self.comp_expr(ctx, cond)?)];
                 ctx.nesting += 1;
-                res.append(&mut self.comp_block(ctx, if_branch));
+                res.append(&mut self.comp_block(ctx, if_branch)?);
                 res.push(String::from("} else {"));
-                res.append(&mut self.comp_block(ctx, else_branch));
+                res.append(&mut self.comp_block(ctx, else_branch)?);
                 ctx.nesting -= 1;
                 res.push(String::from("}"));
                 res
             }
             Statement::DoLoop(expr, stmts) => {
+                let idx = ctx.loop_index();
                 let mut res = vec![format!(
                     "for (int __i_{0} = 0; __i_{0} < (int) ({1}); ++__i_{0}) {{",
-                    ctx.loop_index(),
-                    self.comp_expr(ctx, expr)
+                    idx,
+                    self.comp_expr(ctx, expr)?
                 )];
                 ctx.nesting += 1;
-                res.append(&mut self.comp_block(ctx, stmts));
+                ctx.do_loops.push(idx);
+                res.append(&mut self.comp_block(ctx, stmts)?);
+                ctx.do_loops.pop();
                 ctx.nesting -= 1;
                 res.push(String::from("}"));
                 res
@@ -222,22 +360,22 @@ impl CComp {
                 let mut res = vec![format!(
                     "for ({var} = {start}; {var} {cmp_op} {end}; {var} {step_op} {step}) {{",
                     var = self.comp_var(ctx, counter, VarAct::Write),
-                    start = self.comp_expr(ctx, from),
-                    end = self.comp_expr(ctx, to),
+                    start = self.comp_expr(ctx, from)?,
+                    end = self.comp_expr(ctx, to)?,
                     step = match step {
-                        Some(expr) => self.comp_expr(ctx, expr),
+                        Some(expr) => self.comp_expr(ctx, expr)?,
                         None => String::from("1.0"),
                     },
                 )];
-                res.append(&mut self.comp_block(ctx, body));
+                res.append(&mut self.comp_block(ctx, body)?);
                 ctx.nesting -= 1;
                 res.push(String::from("}"));
                 res
             }
             Statement::WhileLoop(cond, body) => {
-                let mut res = vec![format!("while ({}) {{", self.comp_expr(ctx, cond))];
+                let mut res = vec![format!("while ({}) {{", self.comp_expr(ctx, cond)?)];
                 ctx.nesting += 1;
-                res.append(&mut self.comp_block(ctx, body));
+                res.append(&mut self.comp_block(ctx, body)?);
                 ctx.nesting -= 1;
                 res.push(String::from("}"));
                 res
@@ -245,48 +383,61 @@ impl CComp {
             Statement::RepeatLoop(cond, body) => {
                 let mut res = vec![String::from("do {")];
                 ctx.nesting += 1;
-                res.append(&mut self.comp_block(ctx, body));
+                res.append(&mut self.comp_block(ctx, body)?);
                 ctx.nesting -= 1;
-                res.push(format!("}} while (!({}));", self.comp_expr(ctx, cond)));
+                res.push(format!("}} while (!({}));", self.comp_expr(ctx, cond)?));
                 res
             }
-        }
+        })
     }
 
-    fn comp_expr(&self, ctx: &mut Context, expr: &Expr) -> String {
-        match &expr.kind {
+    fn comp_expr(&self, ctx: &mut Context, expr: &Expr) -> CRes<String> {
+        Ok(match &expr.kind {
+            ExprKind::Const(Value::String(_)) => {
+                return Err(CUnsupported::Text.attach_pos(expr.start))
+            }
             ExprKind::Const(val) => format!("{val}"),
             ExprKind::Variable(var) => self.comp_var(ctx, var, VarAct::Read),
             ExprKind::BiOperation(lhs, op, rhs) => {
                 if *op == BiOperator::Exp {
                     format!(
                         "pow({}, {})",
-                        self.comp_expr(ctx, lhs),
-                        self.comp_expr(ctx, rhs)
+                        self.comp_expr(ctx, lhs)?,
+                        self.comp_expr(ctx, rhs)?
                     )
                 } else {
                     format!(
                         "{} {op} {}",
-                        self.comp_expr(ctx, lhs),
-                        self.comp_expr(ctx, rhs)
+                        self.comp_expr(ctx, lhs)?,
+                        self.comp_expr(ctx, rhs)?
                     )
                 }
             }
-            ExprKind::UnOperation(op, sub) => format!("{op}({})", self.comp_expr(ctx, sub)),
-            ExprKind::Absolute(sub) => format!("abs({})", self.comp_expr(ctx, sub)),
-            ExprKind::Bracket(sub) => format!("({})", self.comp_expr(ctx, sub)),
-            ExprKind::Convert(_, _) => todo!(),
+            ExprKind::UnOperation(op, sub) => format!("{op}({})", self.comp_expr(ctx, sub)?),
+            ExprKind::Absolute(sub) => format!("abs({})", self.comp_expr(ctx, sub)?),
+            ExprKind::Bracket(sub) => format!("({})", self.comp_expr(ctx, sub)?),
+            ExprKind::Convert(_, _) => return Err(CUnsupported::Text.attach_pos(expr.start)),
+            ExprKind::List(_) | ExprKind::Index(_, _) => {
+                return Err(CUnsupported::Lists.attach_pos(expr.start))
+            }
             ExprKind::FuncCall(fnname, args) => {
-                let args = self.comp_args(args, |e| self.comp_expr(ctx, e));
+                let args = self.comp_args(ctx, args)?;
                 let (transform_angle, c_func) = match fnname {
                     PredefFunc::Sin => (true, "sin"),
                     PredefFunc::Cos => (true, "cos"),
                     PredefFunc::Tan => (true, "tan"),
                     PredefFunc::Sqrt => (false, "sqrt"),
                     PredefFunc::Rand => (false, "__ttl_rand"),
-                    PredefFunc::Substr => (false, "__TODO__"),
-                    PredefFunc::Strlen => (false, "strlen"),
-                    _ => todo!(),
+                    PredefFunc::Substr | PredefFunc::Strlen => {
+                        return Err(CUnsupported::Text.attach_pos(expr.start))
+                    }
+                    PredefFunc::Listlen => return Err(CUnsupported::Lists.attach_pos(expr.start)),
+                    PredefFunc::Abs => (false, "fabs"),
+                    PredefFunc::Floor => (false, "floor"),
+                    PredefFunc::Ceil => (false, "ceil"),
+                    PredefFunc::Random => return Ok(format!("__ttl_rand(0, {args})")),
+                    PredefFunc::Arctan => return Ok(format!("(atan({args}) * 180.0 / M_PI)")),
+                    PredefFunc::Towards => (false, "__ttl_towards"),
                 };
                 if transform_angle {
                     format!("{c_func}(({args}) * M_PI / 180.0)")
@@ -294,12 +445,10 @@ impl CComp {
                     format!("{c_func}({args})")
                 }
             }
-            ExprKind::CalcCall(id, args) => format!(
-                "{}({})",
-                self.get_ident(*id),
-                self.comp_args(args, |e| self.comp_expr(ctx, e))
-            ),
-        }
+            ExprKind::CalcCall(id, args) => {
+                format!("{}({})", self.get_ident(*id), self.comp_args(ctx, args)?)
+            }
+        })
     }
 
     fn comp_var(&self, ctx: &mut Context, var: &Variable, act: VarAct) -> String {
@@ -335,6 +484,10 @@ impl CComp {
                     PredefVar::Red => String::from("__ttl_red"),
                     PredefVar::Green => String::from("__ttl_green"),
                     PredefVar::Blue => String::from("__ttl_blue"),
+                    PredefVar::RepCount => match ctx.do_loops.last() {
+                        Some(idx) => format!("(__i_{idx} + 1)"),
+                        None => String::from("0"),
+                    },
                 }
             }
         }
@@ -362,16 +515,20 @@ impl CComp {
     //     }
     // }
 
-    fn comp_args<T, F: FnMut(&T) -> String>(&self, args: &[T], map: F) -> String {
-        let mut full = args
+    fn comp_args(&self, ctx: &mut Context, args: &[Expr]) -> CRes<String> {
+        let args = args
             .iter()
-            .map(map)
-            .fold(String::new(), |f, a| f + ", " + &a);
-        if full.is_empty() {
-            full
-        } else {
-            full.split_off(2)
-        }
+            .map(|e| self.comp_expr(ctx, e))
+            .collect::<CRes<Vec<_>>>()?;
+        Ok(args.join(", "))
+    }
+
+    /// parameter list of a path or calculation
+    fn comp_params(&self, args: &ArgDefList) -> String {
+        args.iter()
+            .map(|a| format!("double {}", self.get_ident(a.0)))
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 
     fn get_ident(&self, id: usize) -> String {
@@ -0,0 +1,108 @@
+use crate::{features::FeatureConf, pos::FilePos, TProgram};
+
+use super::{CComp, CUnsupported};
+
+fn c_code(code: &str) -> Result<String, (CUnsupported, FilePos)> {
+    let prog = TProgram::parse(code, false, FeatureConf::default()).unwrap();
+    CComp::new(prog)
+        .code()
+        .map(|lines| lines.join("\n"))
+        .map_err(|err| (*err, err.get_pos()))
+}
+
+#[test]
+fn pen_up_down() {
+    let code = c_code("begin penup walk 1 pendown walk 1 end").unwrap();
+    assert!(code.contains("\t__ttl_pen = false;\n\t__ttl_walk((1), true);\n\t__ttl_pen = true;\n"));
+}
+
+#[test]
+fn unsupported_statement() {
+    assert_eq!(
+        c_code("begin\n  walk 1\n  print 'hi'\nend"),
+        Err((CUnsupported::Print, FilePos::line_col(3, 3)))
+    );
+    assert_eq!(
+        c_code("\" +feature types\nbegin\n  setprecision 2\nend"),
+        Err((CUnsupported::Text, FilePos::line_col(3, 3)))
+    );
+    assert_eq!(
+        c_code("\" +feature types\nbegin\n  walk [1 2][1]\nend"),
+        Err((CUnsupported::Lists, FilePos::line_col(3, 8)))
+    );
+    assert_eq!(
+        c_code("\" +feature types\nbegin\n  foreach x in [1 2] do walk x done\nend"),
+        Err((CUnsupported::Lists, FilePos::line_col(3, 3)))
+    );
+}
+
+#[test]
+fn pen_width_and_turtles() {
+    let code = c_code("begin\n  penwidth 2\n  newturtle\n  setturtle 1\nend").unwrap();
+    assert!(code.contains(
+        "\t__ttl_width = fmax(2, 0.0);\n\t__ttl_new_turtle();\n\t__ttl_set_turtle(1, 4);\n"
+    ));
+}
+
+#[test]
+fn arcs() {
+    let code = c_code("\" +feature shapes\nbegin circle 2 arc left 1, 90 end").unwrap();
+    assert!(code.contains("\t__ttl_arc(2, -(360.0));\n\t__ttl_arc(1, (90));\n"));
+}
+
+#[test]
+fn clearscreen() {
+    let code = c_code("begin walk 1 clearscreen end").unwrap();
+    assert!(code.contains("\tsdlSetBlack();\n\t__ttl_reset();\n\t__ttl_show();\n"));
+}
+
+#[test]
+fn transforms() {
+    let code = c_code(
+        "\" +feature transforms\nbegin\n  push\n  scale 2\n  scale 1, 3\n  translate 1, 2\n  rotate 90\n  pop\nend",
+    )
+    .unwrap();
+    assert!(code.contains(
+        "\t__ttl_push();\n\t__ttl_scale_all(2);\n\t__ttl_scale(1, 3);\n\t__ttl_translate(1, 2);\n\t__ttl_rotate(90);\n\t__ttl_pop(8);\n"
+    ));
+}
+
+#[test]
+fn show_turtle() {
+    let code = c_code("begin showturtle walk 1 hideturtle end").unwrap();
+    assert!(code.contains("\t__ttl_init(argc, argv);\n\t__ttl_walk((1), true);\n\treturn 0;\n"));
+}
+
+#[test]
+fn frames() {
+    let code = c_code("begin frame walk 1 endframe end").unwrap();
+    assert!(code.contains(
+        "\t__ttl_begin_frame();\n\t{\n\t\t__ttl_walk((1), true);\n\t}\n\t__ttl_end_frame();\n"
+    ));
+}
+
+#[test]
+fn saved_states() {
+    let code = c_code("begin\n  savestate\n  walk 1\n  restorestate\nend").unwrap();
+    assert!(code
+        .contains("\t__ttl_save_state();\n\t__ttl_walk((1), true);\n\t__ttl_restore_state(4);\n"));
+}
+
+#[test]
+fn returns() {
+    let code = c_code(
+        "path p\n  frame\n    return\n  endframe\n  return\nendpath\n\
+         calculation c(x)\n  if x > 0 then return x endif\n  returns 0\nendcalc\n\
+         begin path p walk c(1) end",
+    )
+    .unwrap();
+    assert!(code
+        .contains("\t\t__ttl_end_frame();\n\t\treturn;\n\t}\n\t__ttl_end_frame();\n\treturn;\n}"));
+    assert!(code.contains("\tif (x > 0) {\n\t\treturn x;\n\t}\n\treturn 0;\n}"));
+}
+
+#[test]
+fn towards() {
+    let code = c_code("begin direction towards(1, 2) end").unwrap();
+    assert!(code.contains("\t__ttl_set_dir(__ttl_towards(1, 2));\n"));
+}
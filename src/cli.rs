@@ -1,5 +1,5 @@
 use clap::{Args, Parser, Subcommand, ValueEnum};
-use turtle::{features::Feature, pos::FilePos};
+use turtle::{features::Feature, pos::FilePos, Lint};
 
 #[derive(Parser)]
 #[command(version)]
@@ -17,6 +17,18 @@ pub enum TCommand {
         /// const-fold before execution
         #[arg(short, long)]
         optimized: bool,
+        /// execute as bytecode, unless the program uses split or events
+        #[arg(long)]
+        bytecode: bool,
+        /// only compile and report errors, like `check`
+        #[arg(long)]
+        check: bool,
+        /// draw into an .svg or .png file instead of a window
+        #[arg(long)]
+        output: Option<String>,
+        /// extent of the output as WxH in turtle units, fits the drawing by default
+        #[arg(long, value_parser = parse_size, requires = "output")]
+        size: Option<(f64, f64)>,
         #[command(flatten)]
         opt: RunOpt,
     },
@@ -57,15 +69,29 @@ pub enum TCommand {
         #[arg(short = 's', long)]
         print_symbols: bool,
     },
+    /// Print source in canonical layout
+    Format {
+        /// turtle source file
+        file: String,
+        /// overwrite the file instead of printing
+        #[arg(short, long)]
+        write: bool,
+    },
 }
 
 #[derive(Args)]
 #[group()]
 pub struct Source {
-    /// turtle source file
+    /// turtle source file, `-` reads from stdin
     pub file: String,
     #[command(flatten)]
     pub features: Features,
+    /// report warnings of this kind as errors
+    #[arg(short = 'W', long = "deny")]
+    pub deny: Vec<Lint>,
+    /// contents of `file`, read on first use
+    #[arg(skip)]
+    pub code: std::cell::OnceCell<String>,
 }
 
 #[derive(Args)]
@@ -85,6 +111,15 @@ pub struct RunOpt {
     /// display mechanism
     #[arg(short, long, default_value = "sdl")]
     pub window: Display,
+    /// abort after executing this many statements
+    #[arg(long, visible_alias = "max-steps")]
+    pub step_limit: Option<u64>,
+    /// seed for `rand` and `random`
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// ignore @delay and draw at full speed, implied for the void window
+    #[arg(long)]
+    pub no_delay: bool,
     /// args passed to turtle
     #[arg(last = true)]
     pub args: Vec<String>,
@@ -112,6 +147,15 @@ pub enum Interf {
     VSCode,
 }
 
+fn parse_size(size: &str) -> Result<(f64, f64), String> {
+    let (w, h) = size.split_once('x').ok_or("expected WxH")?;
+    let num = |n: &str| match n.trim().parse::<f64>() {
+        Ok(n) if n > 0.0 => Ok(n),
+        _ => Err(format!("invalid size `{n}`")),
+    };
+    Ok((num(w)?, num(h)?))
+}
+
 #[cfg(test)]
 mod test {
     use clap::CommandFactory;
@@ -0,0 +1,475 @@
+use std::num::ParseIntError;
+
+/// a color in the turtle color space, every channel goes from 0 to 100
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TColor {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    /// opacity, 100 is fully opaque
+    pub a: f64,
+}
+
+impl TColor {
+    /// Creates a fully opaque color
+    pub const fn new(r: f64, g: f64, b: f64) -> Self {
+        Self::with_alpha(r, g, b, 100.0)
+    }
+
+    pub const fn with_alpha(r: f64, g: f64, b: f64, a: f64) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Creates a color from 8 bit channels
+    pub fn from_rgb8(r: u8, g: u8, b: u8) -> Self {
+        Self::from_rgba8(r, g, b, 255)
+    }
+
+    pub fn from_rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        let scale = |c: u8| f64::from(c) / 2.55;
+        Self::with_alpha(scale(r), scale(g), scale(b), scale(a))
+    }
+
+    pub fn is_opaque(&self) -> bool {
+        self.a >= 100.0
+    }
+
+    /// Looks up a CSS color name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        COLOR_NAMES
+            .binary_search_by_key(&name.as_str(), |(n, _)| n)
+            .ok()
+            .map(|idx| {
+                let [r, g, b] = COLOR_NAMES[idx].1;
+                Self::from_rgb8(r, g, b)
+            })
+    }
+
+    /// Parses `#rgb`, `#rrggbb` or `#rrggbbaa`, the leading `#` is optional
+    pub fn from_hex(hex: &str) -> Result<Self, ColorParseError> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(ColorParseError::InvalidDigit(c));
+        }
+        let channel =
+            |i: usize, len: usize| u8::from_str_radix(&digits[i * len..(i + 1) * len], 16);
+        match digits.len() {
+            3 => {
+                let [r, g, b] = [channel(0, 1)?, channel(1, 1)?, channel(2, 1)?];
+                Ok(Self::from_rgb8(r * 17, g * 17, b * 17))
+            }
+            6 => Ok(Self::from_rgb8(
+                channel(0, 2)?,
+                channel(1, 2)?,
+                channel(2, 2)?,
+            )),
+            8 => Ok(Self::from_rgba8(
+                channel(0, 2)?,
+                channel(1, 2)?,
+                channel(2, 2)?,
+                channel(3, 2)?,
+            )),
+            len => Err(ColorParseError::InvalidLength(len)),
+        }
+    }
+
+    /// Blends component-wise towards `other`, `t` is clamped to `0..=1`.
+    ///
+    /// This interpolates the stored channels directly, so it is not gamma-correct.
+    pub fn lerp(&self, other: &TColor, t: f64) -> TColor {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: f64, b: f64| a + (b - a) * t;
+        Self::with_alpha(
+            mix(self.r, other.r),
+            mix(self.g, other.g),
+            mix(self.b, other.b),
+            mix(self.a, other.a),
+        )
+    }
+
+    /// Hue in degrees (wrapped), saturation and value from 0 to 100
+    pub fn from_hsv(h: f64, s: f64, v: f64) -> Self {
+        let s = s.clamp(0.0, 100.0) / 100.0;
+        let v = v.clamp(0.0, 100.0);
+        let chroma = v * s;
+        Self::from_hue(h, chroma, v - chroma)
+    }
+
+    /// Hue in degrees (wrapped), saturation and lightness from 0 to 100
+    pub fn from_hsl(h: f64, s: f64, l: f64) -> Self {
+        let s = s.clamp(0.0, 100.0) / 100.0;
+        let l = l.clamp(0.0, 100.0);
+        let chroma = (100.0 - (2.0 * l - 100.0).abs()) * s;
+        Self::from_hue(h, chroma, l - chroma / 2.0)
+    }
+
+    fn from_hue(h: f64, chroma: f64, min: f64) -> Self {
+        let h = h.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u8 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        Self::new(r + min, g + min, b + min)
+    }
+
+    /// Returns hue in degrees, saturation and value from 0 to 100
+    pub fn to_hsv(&self) -> (f64, f64, f64) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let chroma = max - min;
+        let h = if chroma == 0.0 {
+            0.0
+        } else if max == self.r {
+            60.0 * ((self.g - self.b) / chroma).rem_euclid(6.0)
+        } else if max == self.g {
+            60.0 * ((self.b - self.r) / chroma + 2.0)
+        } else {
+            60.0 * ((self.r - self.g) / chroma + 4.0)
+        };
+        let s = if max == 0.0 {
+            0.0
+        } else {
+            chroma / max * 100.0
+        };
+        (h, s, max)
+    }
+}
+
+impl Default for TColor {
+    fn default() -> Self {
+        Self::new(0.0, 0.0, 0.0)
+    }
+}
+
+impl From<(f64, f64, f64)> for TColor {
+    fn from((r, g, b): (f64, f64, f64)) -> Self {
+        Self::new(r, g, b)
+    }
+}
+
+/// `[r, g, b]`, or `[r, g, b, a]` if the color isn't opaque
+#[cfg(feature = "serde")]
+impl serde::Serialize for TColor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_opaque() {
+            (self.r, self.g, self.b).serialize(serializer)
+        } else {
+            (self.r, self.g, self.b, self.a).serialize(serializer)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for TColor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Vec::<f64>::deserialize(deserializer)?[..] {
+            [r, g, b] => Ok(Self::new(r, g, b)),
+            [r, g, b, a] => Ok(Self::with_alpha(r, g, b, a)),
+            ref other => Err(serde::de::Error::invalid_length(
+                other.len(),
+                &"3 or 4 color channels",
+            )),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
+pub enum ColorParseError {
+    #[error("color needs 3, 6 or 8 hex digits, got {0}")]
+    InvalidLength(usize),
+    #[error("invalid hex digit '{0}' in color")]
+    InvalidDigit(char),
+    #[error("{0}")]
+    ParseError(#[from] ParseIntError),
+    #[error("unknown color '{0}'")]
+    UnknownName(String),
+}
+
+/// CSS named colors, sorted for binary search
+const COLOR_NAMES: &[(&str, [u8; 3])] = &[
+    ("aliceblue", [240, 248, 255]),
+    ("antiquewhite", [250, 235, 215]),
+    ("aqua", [0, 255, 255]),
+    ("aquamarine", [127, 255, 212]),
+    ("azure", [240, 255, 255]),
+    ("beige", [245, 245, 220]),
+    ("bisque", [255, 228, 196]),
+    ("black", [0, 0, 0]),
+    ("blanchedalmond", [255, 235, 205]),
+    ("blue", [0, 0, 255]),
+    ("blueviolet", [138, 43, 226]),
+    ("brown", [165, 42, 42]),
+    ("burlywood", [222, 184, 135]),
+    ("cadetblue", [95, 158, 160]),
+    ("chartreuse", [127, 255, 0]),
+    ("chocolate", [210, 105, 30]),
+    ("coral", [255, 127, 80]),
+    ("cornflowerblue", [100, 149, 237]),
+    ("cornsilk", [255, 248, 220]),
+    ("crimson", [220, 20, 60]),
+    ("cyan", [0, 255, 255]),
+    ("darkblue", [0, 0, 139]),
+    ("darkcyan", [0, 139, 139]),
+    ("darkgoldenrod", [184, 134, 11]),
+    ("darkgray", [169, 169, 169]),
+    ("darkgreen", [0, 100, 0]),
+    ("darkgrey", [169, 169, 169]),
+    ("darkkhaki", [189, 183, 107]),
+    ("darkmagenta", [139, 0, 139]),
+    ("darkolivegreen", [85, 107, 47]),
+    ("darkorange", [255, 140, 0]),
+    ("darkorchid", [153, 50, 204]),
+    ("darkred", [139, 0, 0]),
+    ("darksalmon", [233, 150, 122]),
+    ("darkseagreen", [143, 188, 143]),
+    ("darkslateblue", [72, 61, 139]),
+    ("darkslategray", [47, 79, 79]),
+    ("darkslategrey", [47, 79, 79]),
+    ("darkturquoise", [0, 206, 209]),
+    ("darkviolet", [148, 0, 211]),
+    ("deeppink", [255, 20, 147]),
+    ("deepskyblue", [0, 191, 255]),
+    ("dimgray", [105, 105, 105]),
+    ("dimgrey", [105, 105, 105]),
+    ("dodgerblue", [30, 144, 255]),
+    ("firebrick", [178, 34, 34]),
+    ("floralwhite", [255, 250, 240]),
+    ("forestgreen", [34, 139, 34]),
+    ("fuchsia", [255, 0, 255]),
+    ("gainsboro", [220, 220, 220]),
+    ("ghostwhite", [248, 248, 255]),
+    ("gold", [255, 215, 0]),
+    ("goldenrod", [218, 165, 32]),
+    ("gray", [128, 128, 128]),
+    ("green", [0, 128, 0]),
+    ("greenyellow", [173, 255, 47]),
+    ("grey", [128, 128, 128]),
+    ("honeydew", [240, 255, 240]),
+    ("hotpink", [255, 105, 180]),
+    ("indianred", [205, 92, 92]),
+    ("indigo", [75, 0, 130]),
+    ("ivory", [255, 255, 240]),
+    ("khaki", [240, 230, 140]),
+    ("lavender", [230, 230, 250]),
+    ("lavenderblush", [255, 240, 245]),
+    ("lawngreen", [124, 252, 0]),
+    ("lemonchiffon", [255, 250, 205]),
+    ("lightblue", [173, 216, 230]),
+    ("lightcoral", [240, 128, 128]),
+    ("lightcyan", [224, 255, 255]),
+    ("lightgoldenrodyellow", [250, 250, 210]),
+    ("lightgray", [211, 211, 211]),
+    ("lightgreen", [144, 238, 144]),
+    ("lightgrey", [211, 211, 211]),
+    ("lightpink", [255, 182, 193]),
+    ("lightsalmon", [255, 160, 122]),
+    ("lightseagreen", [32, 178, 170]),
+    ("lightskyblue", [135, 206, 250]),
+    ("lightslategray", [119, 136, 153]),
+    ("lightslategrey", [119, 136, 153]),
+    ("lightsteelblue", [176, 196, 222]),
+    ("lightyellow", [255, 255, 224]),
+    ("lime", [0, 255, 0]),
+    ("limegreen", [50, 205, 50]),
+    ("linen", [250, 240, 230]),
+    ("magenta", [255, 0, 255]),
+    ("maroon", [128, 0, 0]),
+    ("mediumaquamarine", [102, 205, 170]),
+    ("mediumblue", [0, 0, 205]),
+    ("mediumorchid", [186, 85, 211]),
+    ("mediumpurple", [147, 112, 219]),
+    ("mediumseagreen", [60, 179, 113]),
+    ("mediumslateblue", [123, 104, 238]),
+    ("mediumspringgreen", [0, 250, 154]),
+    ("mediumturquoise", [72, 209, 204]),
+    ("mediumvioletred", [199, 21, 133]),
+    ("midnightblue", [25, 25, 112]),
+    ("mintcream", [245, 255, 250]),
+    ("mistyrose", [255, 228, 225]),
+    ("moccasin", [255, 228, 181]),
+    ("navajowhite", [255, 222, 173]),
+    ("navy", [0, 0, 128]),
+    ("oldlace", [253, 245, 230]),
+    ("olive", [128, 128, 0]),
+    ("olivedrab", [107, 142, 35]),
+    ("orange", [255, 165, 0]),
+    ("orangered", [255, 69, 0]),
+    ("orchid", [218, 112, 214]),
+    ("palegoldenrod", [238, 232, 170]),
+    ("palegreen", [152, 251, 152]),
+    ("paleturquoise", [175, 238, 238]),
+    ("palevioletred", [219, 112, 147]),
+    ("papayawhip", [255, 239, 213]),
+    ("peachpuff", [255, 218, 185]),
+    ("peru", [205, 133, 63]),
+    ("pink", [255, 192, 203]),
+    ("plum", [221, 160, 221]),
+    ("powderblue", [176, 224, 230]),
+    ("purple", [128, 0, 128]),
+    ("rebeccapurple", [102, 51, 153]),
+    ("red", [255, 0, 0]),
+    ("rosybrown", [188, 143, 143]),
+    ("royalblue", [65, 105, 225]),
+    ("saddlebrown", [139, 69, 19]),
+    ("salmon", [250, 128, 114]),
+    ("sandybrown", [244, 164, 96]),
+    ("seagreen", [46, 139, 87]),
+    ("seashell", [255, 245, 238]),
+    ("sienna", [160, 82, 45]),
+    ("silver", [192, 192, 192]),
+    ("skyblue", [135, 206, 235]),
+    ("slateblue", [106, 90, 205]),
+    ("slategray", [112, 128, 144]),
+    ("slategrey", [112, 128, 144]),
+    ("snow", [255, 250, 250]),
+    ("springgreen", [0, 255, 127]),
+    ("steelblue", [70, 130, 180]),
+    ("tan", [210, 180, 140]),
+    ("teal", [0, 128, 128]),
+    ("thistle", [216, 191, 216]),
+    ("tomato", [255, 99, 71]),
+    ("turquoise", [64, 224, 208]),
+    ("violet", [238, 130, 238]),
+    ("wheat", [245, 222, 179]),
+    ("white", [255, 255, 255]),
+    ("whitesmoke", [245, 245, 245]),
+    ("yellow", [255, 255, 0]),
+    ("yellowgreen", [154, 205, 50]),
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names_sorted() {
+        assert!(COLOR_NAMES.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn named() {
+        assert_eq!(TColor::from_name("red"), Some(TColor::new(100.0, 0.0, 0.0)));
+        assert_eq!(
+            TColor::from_name("White"),
+            Some(TColor::new(100.0, 100.0, 100.0))
+        );
+        assert_eq!(TColor::from_name("grey"), TColor::from_name("gray"));
+        assert_eq!(TColor::from_name("notacolor"), None);
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(TColor::from_hex("#f00"), Ok(TColor::new(100.0, 0.0, 0.0)));
+        assert_eq!(TColor::from_hex("00ff00"), Ok(TColor::new(0.0, 100.0, 0.0)));
+        assert_eq!(
+            TColor::from_hex("#0000ff33"),
+            Ok(TColor::with_alpha(0.0, 0.0, 100.0, 20.0))
+        );
+        assert_eq!(
+            TColor::from_hex("#ffff"),
+            Err(ColorParseError::InvalidLength(4))
+        );
+        assert_eq!(
+            TColor::from_hex("#ggg"),
+            Err(ColorParseError::InvalidDigit('g'))
+        );
+        assert_eq!(
+            TColor::from_hex("#"),
+            Err(ColorParseError::InvalidLength(0))
+        );
+    }
+
+    #[test]
+    fn hsv() {
+        assert_eq!(
+            TColor::from_hsv(0.0, 100.0, 100.0),
+            TColor::new(100.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            TColor::from_hsv(120.0, 100.0, 100.0),
+            TColor::new(0.0, 100.0, 0.0)
+        );
+        assert_eq!(
+            TColor::from_hsv(240.0, 100.0, 100.0),
+            TColor::new(0.0, 0.0, 100.0)
+        );
+        assert_eq!(
+            TColor::from_hsv(-240.0, 100.0, 100.0),
+            TColor::new(0.0, 100.0, 0.0)
+        );
+        assert_eq!(
+            TColor::from_hsv(720.0, 150.0, 100.0),
+            TColor::new(100.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            TColor::from_hsv(42.0, 0.0, 50.0),
+            TColor::new(50.0, 50.0, 50.0)
+        );
+        assert_eq!(TColor::new(0.0, 0.0, 100.0).to_hsv(), (240.0, 100.0, 100.0));
+        assert_eq!(
+            TColor::new(100.0, 100.0, 0.0).to_hsv(),
+            (60.0, 100.0, 100.0)
+        );
+        assert_eq!(TColor::new(30.0, 30.0, 30.0).to_hsv(), (0.0, 0.0, 30.0));
+        assert_eq!(TColor::new(0.0, 0.0, 0.0).to_hsv(), (0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn hsl() {
+        assert_eq!(
+            TColor::from_hsl(0.0, 100.0, 50.0),
+            TColor::new(100.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            TColor::from_hsl(120.0, 100.0, 50.0),
+            TColor::new(0.0, 100.0, 0.0)
+        );
+        assert_eq!(
+            TColor::from_hsl(240.0, 100.0, 50.0),
+            TColor::new(0.0, 0.0, 100.0)
+        );
+        assert_eq!(
+            TColor::from_hsl(0.0, 100.0, 100.0),
+            TColor::new(100.0, 100.0, 100.0)
+        );
+        assert_eq!(
+            TColor::from_hsl(300.0, 0.0, 25.0),
+            TColor::new(25.0, 25.0, 25.0)
+        );
+    }
+
+    #[test]
+    fn lerp() {
+        let black = TColor::new(0.0, 0.0, 0.0);
+        let col = TColor::with_alpha(100.0, 50.0, 20.0, 0.0);
+        assert_eq!(black.lerp(&col, 0.0), black);
+        assert_eq!(black.lerp(&col, 1.0), col);
+        assert_eq!(
+            black.lerp(&col, 0.5),
+            TColor::with_alpha(50.0, 25.0, 10.0, 50.0)
+        );
+        assert_eq!(black.lerp(&col, -2.0), black);
+        assert_eq!(black.lerp(&col, 7.5), col);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_alpha() {
+        let opaque = TColor::new(100.0, 50.0, 0.0);
+        assert_eq!(serde_json::to_string(&opaque).unwrap(), "[100.0,50.0,0.0]");
+        let translucent = TColor::with_alpha(100.0, 50.0, 0.0, 25.0);
+        let json = serde_json::to_string(&translucent).unwrap();
+        assert_eq!(json, "[100.0,50.0,0.0,25.0]");
+        assert_eq!(serde_json::from_str::<TColor>(&json).unwrap(), translucent);
+        assert_eq!(
+            serde_json::from_str::<TColor>("[100,50,0]").unwrap(),
+            opaque
+        );
+    }
+}
@@ -1,15 +1,35 @@
-use crate::{pos::FilePos, TProgram};
+use std::{
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+
+use crate::{
+    pos::{FilePos, Pos},
+    TProgram,
+};
 
+#[cfg(not(feature = "sdl"))]
+use super::window::VoidWindow;
+#[cfg(feature = "sdl")]
+use super::window::{ChannelWindow, SdlWindow};
 use super::{
     interface::{DbgInterface, Terminal},
-    window::{ChannelWindow, SdlWindow},
-    Debugger, Window,
+    Debugger, RuntimeError, TColor, Window, DEFAULT_MAX_DEPTH,
 };
 
 pub struct RunConfig<'a, W, I> {
     args: &'a [String],
     window: W,
     kind: RunKind<I>,
+    max_depth: usize,
+    step_limit: Option<u64>,
+    timeout: Option<Duration>,
+    cancel: Option<Arc<AtomicBool>>,
+    palette: Vec<TColor>,
+    seed: Option<u64>,
+    skip_delay: bool,
+    keep_open: bool,
+    bytecode: bool,
 }
 
 pub enum RunKind<I> {
@@ -17,12 +37,37 @@ pub enum RunKind<I> {
     Debug(I, Vec<FilePos>),
 }
 
+#[cfg(feature = "sdl")]
 impl<'a> RunConfig<'a, ChannelWindow, Terminal> {
+    /// Draws in an SDL window unless [`RunConfig::window`] replaces it
+    pub fn new(args: &'a [String]) -> Self {
+        Self::with_window(args, SdlWindow::create("Turtle Graphics".to_string()))
+    }
+}
+
+#[cfg(not(feature = "sdl"))]
+impl<'a> RunConfig<'a, VoidWindow, Terminal> {
+    /// Draws nowhere without the `sdl` feature, pass a window to [`RunConfig::window`]
     pub fn new(args: &'a [String]) -> Self {
+        Self::with_window(args, VoidWindow::default())
+    }
+}
+
+impl<'a, W: Window> RunConfig<'a, W, Terminal> {
+    fn with_window(args: &'a [String], window: W) -> Self {
         Self {
             args,
-            window: SdlWindow::create("Turtle Graphics".to_string()),
+            window,
             kind: RunKind::Interpret,
+            max_depth: DEFAULT_MAX_DEPTH,
+            step_limit: None,
+            timeout: None,
+            cancel: None,
+            palette: Vec::new(),
+            seed: None,
+            skip_delay: false,
+            keep_open: true,
+            bytecode: false,
         }
     }
 }
@@ -33,6 +78,15 @@ impl<'a, W: Window, I: DbgInterface> RunConfig<'a, W, I> {
             args: self.args,
             window,
             kind: self.kind,
+            max_depth: self.max_depth,
+            step_limit: self.step_limit,
+            timeout: self.timeout,
+            cancel: self.cancel,
+            palette: self.palette,
+            seed: self.seed,
+            skip_delay: self.skip_delay,
+            keep_open: self.keep_open,
+            bytecode: self.bytecode,
         }
     }
 
@@ -46,6 +100,15 @@ impl<'a, W: Window, I: DbgInterface> RunConfig<'a, W, I> {
             args: self.args,
             window: self.window,
             kind: RunKind::Debug(interface, bp),
+            max_depth: self.max_depth,
+            step_limit: self.step_limit,
+            timeout: self.timeout,
+            cancel: self.cancel,
+            palette: self.palette,
+            seed: self.seed,
+            skip_delay: self.skip_delay,
+            keep_open: self.keep_open,
+            bytecode: self.bytecode,
         }
     }
 
@@ -56,13 +119,95 @@ impl<'a, W: Window, I: DbgInterface> RunConfig<'a, W, I> {
         self
     }
 
-    pub fn exec(self, prog: &TProgram) {
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    pub fn step_limit(mut self, limit: Option<u64>) -> Self {
+        self.step_limit = limit;
+        self
+    }
+
+    /// Wall-clock limit of the run, see [`Debugger::set_timeout`]
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Flag to stop the run from another thread, see [`Debugger::set_cancel`]
+    pub fn cancel(mut self, cancel: Option<Arc<AtomicBool>>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Colors registered with the window before the run, see [`Window::set_palette`]
+    pub fn palette(mut self, colors: Vec<TColor>) -> Self {
+        self.palette = colors;
+        self
+    }
+
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn skip_delay(mut self, skip: bool) -> Self {
+        self.skip_delay = skip;
+        self
+    }
+
+    /// Wait for the window to close after `stop`, see [`Debugger::set_keep_open`]
+    pub fn keep_open(mut self, keep: bool) -> Self {
+        self.keep_open = keep;
+        self
+    }
+
+    /// Interpret the program as bytecode, see [`TProgram::compile_bytecode`]
+    ///
+    /// Programs that can't be compiled run on the syntax tree as usual,
+    /// debugging always does.
+    pub fn bytecode(mut self, bytecode: bool) -> Self {
+        self.bytecode = bytecode;
+        self
+    }
+
+    pub fn exec(self, prog: &TProgram) -> Result<(), Pos<RuntimeError>> {
         match self.kind {
             RunKind::Interpret => {
-                Debugger::new(prog, self.args, self.window, false, Vec::new()).run();
+                let mut dbg = Debugger::new(prog, self.args, self.window, false, Vec::new());
+                dbg.set_max_depth(self.max_depth);
+                dbg.set_step_limit(self.step_limit);
+                dbg.set_timeout(self.timeout);
+                dbg.set_cancel(self.cancel);
+                if !self.palette.is_empty() {
+                    dbg.set_palette(&self.palette);
+                }
+                dbg.set_skip_delay(self.skip_delay);
+                dbg.set_keep_open(self.keep_open);
+                if let Some(seed) = self.seed {
+                    dbg.set_seed(seed);
+                }
+                match self.bytecode.then(|| prog.compile_bytecode()) {
+                    Some(Ok(code)) => dbg.run_bytecode(&code),
+                    _ => dbg.run(),
+                }
             }
             RunKind::Debug(interf, breakpoints) => {
-                Debugger::new(prog, self.args, self.window, true, breakpoints).debug_in(interf);
+                let mut dbg = Debugger::new(prog, self.args, self.window, true, breakpoints);
+                dbg.set_max_depth(self.max_depth);
+                dbg.set_step_limit(self.step_limit);
+                dbg.set_timeout(self.timeout);
+                dbg.set_cancel(self.cancel);
+                if !self.palette.is_empty() {
+                    dbg.set_palette(&self.palette);
+                }
+                dbg.set_skip_delay(self.skip_delay);
+                dbg.set_keep_open(self.keep_open);
+                if let Some(seed) = self.seed {
+                    dbg.set_seed(seed);
+                }
+                dbg.debug_in(interf)
             }
         }
     }
@@ -1,13 +1,19 @@
 use std::{
     cell::{Cell, RefCell},
+    collections::HashSet,
     rc::Rc,
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, Instant},
 };
 
 use crate::{
     features::{Feature, FeatureState},
-    pos::{FilePos, Positionable},
-    prog::semcheck::{self, Vars},
-    tokens::{EventKind, StmtKind, ValType, Value, VariableKind},
+    pos::{FilePos, Pos, Positionable},
+    prog::{
+        bytecode::Bytecode,
+        semcheck::{self, Vars},
+    },
+    tokens::{EventKind, Expr, PredefVar, StmtKind, ValType, Value, VariableKind},
     TProgram, TurtleError,
 };
 
@@ -16,10 +22,19 @@ use super::{
     runner::{StepResult, TurtleRunner},
     turtle::FuncType,
     varlist::VarList,
-    window::{Window, WindowEvent},
-    Breakpoint, DbgEvent, DebugErr, FrameInfo, GlobalCtx, ProgEnd, TurtleInfo, VarDump,
+    vm::Vm,
+    window::{LineStyle, Window, WindowEvent, DEFAULT_PEN_WIDTH},
+    Breakpoint, DbgEvent, DebugErr, FrameInfo, FrameResult, GlobalCtx, ProgEnd, Rng, RuntimeError,
+    TColor, TCoord, TurtleInfo, VarDump, WatchId, DEFAULT_ARC_RESOLUTION, DEFAULT_MAX_DEPTH,
 };
 
+/// Runs the turtles of a program, one at a time.
+///
+/// Turtles take turns in a fixed order: each runs until its next line, arc or
+/// `wait`, then the turtles behind it follow in the order they were created.
+/// A turtle started by `split` joins the end and draws its first line within the
+/// same turn. Only window events and an unseeded `rand` can make two runs of a
+/// program draw differently.
 pub struct DebugController<'p, W> {
     pub prog: &'p TProgram,
     ctx: Rc<GlobalCtx<W>>,
@@ -30,6 +45,9 @@ pub struct DebugController<'p, W> {
     stmt_count: usize,
     events: Vec<DbgEvent>,
     is_sync: bool,
+    watch_id: WatchId,
+    watches: Vec<(WatchId, Expr)>,
+    keep_open: bool,
 }
 
 impl<'p, W: Window + 'p> DebugController<'p, W> {
@@ -62,10 +80,24 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
             vars: RefCell::new(VarList::new()),
             args,
             delay: Cell::new(1.0),
+            skip_delay: Cell::new(false),
             wait_end: Cell::new(false),
+            pen_width: Cell::new(DEFAULT_PEN_WIDTH),
+            line_style: Cell::new(LineStyle::Solid),
+            frames: Cell::new(0),
+            precision: Cell::new(None),
             window: RefCell::new(window),
             debug,
             breakpoints: RefCell::new(breakpoints),
+            line_breakpoints: RefCell::new(HashSet::new()),
+            max_depth: Cell::new(DEFAULT_MAX_DEPTH),
+            arc_resolution: Cell::new(DEFAULT_ARC_RESOLUTION),
+            steps: Cell::new(0),
+            step_limit: Cell::new(None),
+            deadline: Cell::new(None),
+            cancel: RefCell::new(None),
+            rng: RefCell::new(Rng::from_entropy()),
+            error: RefCell::new(None),
         });
         let runner = TurtleRunner::new(prog, ctx.clone());
         Self {
@@ -78,14 +110,92 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
             stmt_count: 0,
             events: Vec::new(),
             is_sync: true,
+            watch_id: 0,
+            watches: Vec::new(),
+            keep_open: true,
         }
     }
 
-    pub fn debug_in(&mut self, mut interf: impl DbgInterface) {
-        self.ctx.window.borrow_mut().init_with(20.0, 15.0);
-        if interf.exec(self) == ProgEnd::AllTurtlesFinished {
+    pub fn debug_in(&mut self, mut interf: impl DbgInterface) -> Result<(), Pos<RuntimeError>> {
+        self.init_window();
+        let end = interf.exec(self);
+        if let Some(err) = self.ctx.error.borrow().clone() {
+            return Err(err);
+        }
+        if end == ProgEnd::AllTurtlesFinished {
             self.finished();
-        };
+        }
+        Ok(())
+    }
+
+    /// Done by [`DebugController::run`] and the debugger, call it before the first
+    /// [`DebugController::run_frame`]
+    pub fn init_window(&mut self) {
+        self.ctx.window.borrow_mut().init_with(20.0, 15.0);
+    }
+
+    /// the error that stopped the turtles, if any
+    pub(super) fn runtime_error(&self) -> Option<Pos<RuntimeError>> {
+        self.ctx.error.borrow().clone()
+    }
+
+    /// Limits how deeply paths and calculations may call each other
+    pub fn set_max_depth(&mut self, depth: usize) {
+        self.ctx.max_depth.set(depth);
+    }
+
+    /// Number of lines per turtle unit used to draw arcs and circles
+    pub fn set_arc_resolution(&mut self, lines: f64) {
+        self.ctx.arc_resolution.set(lines);
+    }
+
+    /// Draws at full speed, ignoring the `@delay` between lines
+    ///
+    /// Meant for runs where nobody watches, like tests or a headless window.
+    pub fn set_skip_delay(&mut self, skip: bool) {
+        self.ctx.skip_delay.set(skip);
+    }
+
+    /// Makes `rand` and `random` reproducible
+    ///
+    /// Without a seed the generator starts from [`Rng::from_entropy`].
+    pub fn set_seed(&mut self, seed: u64) {
+        *self.ctx.rng.borrow_mut() = Rng::new(seed);
+    }
+
+    /// Whether `stop` waits for the window to be closed, which never happens for headless windows
+    pub fn set_keep_open(&mut self, keep: bool) {
+        self.keep_open = keep;
+    }
+
+    /// Colors the window may refer to by index, see [`Window::set_palette`]
+    pub fn set_palette(&mut self, colors: &[TColor]) {
+        self.ctx.window.borrow_mut().set_palette(colors);
+    }
+
+    /// Aborts the program once more than `limit` statements were executed
+    ///
+    /// `None` disables the limit, which is the default
+    pub fn set_step_limit(&mut self, limit: Option<u64>) {
+        self.ctx.step_limit.set(limit);
+    }
+
+    /// Aborts the program with [`RuntimeError::Timeout`] once `timeout` passed since this call
+    ///
+    /// The clock is read every [`TIMEOUT_CHECK_STEPS`](super::TIMEOUT_CHECK_STEPS) statements
+    /// and before every `@delay`, which is cut short at the deadline. Only waiting for the
+    /// window overshoots the timeout. `None` disables the timeout, which is the default.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.ctx
+            .deadline
+            .set(timeout.map(|timeout| (Instant::now() + timeout, timeout)));
+    }
+
+    /// Aborts the program with [`RuntimeError::Cancelled`] once another thread sets `cancel`
+    ///
+    /// The flag is checked before every statement and while `stop` waits for the window.
+    pub fn set_cancel(&mut self, cancel: Option<Arc<AtomicBool>>) {
+        *self.ctx.cancel.borrow_mut() = cancel;
     }
 
     /////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -127,6 +237,11 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
     ///
     /// afterwards, `@delay` is applied and events are handled
     fn sync_turtles(&mut self) -> Result<(), ProgEnd> {
+        self.sync(true)
+    }
+
+    /// `delay` applies `@delay` before handling the window's events
+    fn sync(&mut self, delay: bool) -> Result<(), ProgEnd> {
         let (finished, remaining) = self.turtles.split_at_mut(self.active_turtle + 1);
         let mut splits: Vec<_> = finished
             .iter_mut()
@@ -145,6 +260,9 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
             }
             splits = new_splits;
         }
+        if let Some(err) = self.ctx.error.borrow().clone() {
+            return Err(ProgEnd::Error(err));
+        }
         let active_id = self.active_id();
         self.turtles.retain(|(_, ttl)| !ttl.finished);
         if self.active_turtle >= self.turtles.len() || self.active_id() != active_id {
@@ -153,9 +271,10 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
         if self.turtles.is_empty() {
             return Err(ProgEnd::AllTurtlesFinished);
         }
-        let delay = self.ctx.delay.get() as u64;
-        if delay > 0 {
-            std::thread::sleep(std::time::Duration::from_millis(delay));
+        if delay {
+            self.ctx
+                .apply_delay()
+                .map_err(|why| ProgEnd::Error(why.attach_pos(self.curr_pos().1)))?;
         }
         let events = self
             .ctx
@@ -163,19 +282,23 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
             .borrow_mut()
             .events()
             .into_iter()
-            .map(|evt| match evt {
-                WindowEvent::WindowExited => Err(ProgEnd::WindowExited),
-                WindowEvent::KeyPressed(c) => {
-                    Ok((EventKind::Key, vec![Value::String(c.to_string())]))
+            .filter_map(|evt| match evt {
+                WindowEvent::WindowExited | WindowEvent::Closed => Some(Err(ProgEnd::WindowExited)),
+                WindowEvent::KeyPressed(key) => {
+                    Some(Ok((EventKind::Key, vec![Value::String(key.to_string())])))
                 }
-                WindowEvent::MouseClicked(coord, btn) => Ok((
+                WindowEvent::KeyReleased(_)
+                | WindowEvent::MouseMoved(_)
+                | WindowEvent::MouseReleased(..)
+                | WindowEvent::Resized(..) => None,
+                WindowEvent::MouseClicked(coord, btn) => Some(Ok((
                     EventKind::Mouse,
                     vec![
                         Value::Number(coord.0),
                         Value::Number(coord.1),
                         Value::Boolean(btn),
                     ],
-                )),
+                ))),
             })
             .collect::<Result<Vec<_>, _>>()?;
         for (kind, args) in events {
@@ -202,14 +325,17 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
     }
 
     pub fn finished(&self) {
-        if self.ctx.wait_end.get() {
+        self.ctx.end_frames();
+        if self.keep_open && self.ctx.wait_end.get() {
             println!("halt and catch fire");
-            while !self
-                .ctx
-                .window
-                .borrow_mut()
-                .events()
-                .contains(&WindowEvent::WindowExited)
+            while !self.ctx.is_cancelled()
+                && !self
+                    .ctx
+                    .window
+                    .borrow_mut()
+                    .events()
+                    .iter()
+                    .any(|evt| matches!(evt, WindowEvent::WindowExited | WindowEvent::Closed))
             {
                 std::thread::sleep(std::time::Duration::from_millis(200));
             }
@@ -222,17 +348,52 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
     //
     /////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-    pub fn run(&mut self) {
-        self.ctx.window.borrow_mut().init_with(20.0, 15.0);
-        while !self.turtles.is_empty() {
-            self.active().run_sleep();
-            match self.sync_turtles() {
-                Ok(()) => {}
-                Err(ProgEnd::AllTurtlesFinished) => break,
-                Err(ProgEnd::WindowExited) => return,
+    pub fn run(&mut self) -> Result<(), Pos<RuntimeError>> {
+        self.init_window();
+        loop {
+            match self.run_frame()? {
+                FrameResult::Next(_) => self
+                    .ctx
+                    .apply_delay()
+                    .map_err(|why| why.attach_pos(self.curr_pos().1))?,
+                FrameResult::Finished(_) => break,
+                FrameResult::Exited => return Ok(()),
             }
         }
         self.finished();
+        Ok(())
+    }
+
+    /// Run until every turtle drew once more, but leave waiting to the caller.
+    ///
+    /// For hosts without threads or blocking sleeps, like a browser: call it
+    /// again after the delay of [`FrameResult::Next`] until the program ends.
+    pub fn run_frame(&mut self) -> Result<FrameResult, Pos<RuntimeError>> {
+        if self.turtles.is_empty() {
+            return Ok(FrameResult::Finished(self.ctx.wait_end.get()));
+        }
+        self.active().run_sleep();
+        match self.sync(false) {
+            Ok(()) => Ok(FrameResult::Next(self.ctx.delay_ms())),
+            Err(ProgEnd::AllTurtlesFinished) => {
+                self.ctx.end_frames();
+                Ok(FrameResult::Finished(self.ctx.wait_end.get()))
+            }
+            Err(ProgEnd::WindowExited) => Ok(FrameResult::Exited),
+            Err(ProgEnd::Error(err)) => Err(err),
+        }
+    }
+
+    /// Like [`DebugController::run`], but executes `code` compiled from the program
+    pub fn run_bytecode(&mut self, code: &Bytecode) -> Result<(), Pos<RuntimeError>> {
+        self.init_window();
+        match Vm::new(code, &self.ctx, &self.prog.symbols).run() {
+            Ok(()) | Err(ProgEnd::AllTurtlesFinished) => {}
+            Err(ProgEnd::WindowExited) => return Ok(()),
+            Err(ProgEnd::Error(err)) => return Err(err),
+        }
+        self.finished();
+        Ok(())
     }
 
     /////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -246,6 +407,7 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
             StepResult::Exec(_) => self.stmt_count += 1,
             StepResult::Sync => {}
             StepResult::Breakpoint(idx) => self.events.push(DbgEvent::BreakpointHit(idx)),
+            StepResult::LineBreakpoint(pos) => self.events.push(DbgEvent::LineBreakpointHit(pos)),
             StepResult::Finished => self.events.push(DbgEvent::TurtleFinished(self.active_id())),
         }
     }
@@ -267,10 +429,18 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
                 self.sync_turtles()?;
                 Ok(None)
             }
-            StepResult::Breakpoint(_) => Ok(None),
+            StepResult::Breakpoint(_) | StepResult::LineBreakpoint(_) => Ok(None),
         }
     }
 
+    /// Executes a single statement and returns the position of the next one,
+    /// or `None` once the active turtle finished
+    pub fn step(&mut self) -> Result<Option<FilePos>, ProgEnd> {
+        self.step_single()?;
+        let ttl = self.active_ref();
+        Ok((!ttl.finished).then(|| ttl.curr_pos().1))
+    }
+
     pub fn step_over(&mut self) -> Result<(), ProgEnd> {
         let stack_size = self.active().stack_size();
         while self.step_single()?.is_some() && self.active().stack_size() > stack_size {}
@@ -292,7 +462,10 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
         self.after_sync();
         let res = self.active().run_breakpoints();
         self.collect_events(res);
-        if !matches!(res, StepResult::Breakpoint(_)) {
+        if !matches!(
+            res,
+            StepResult::Breakpoint(_) | StepResult::LineBreakpoint(_)
+        ) {
             self.sync_turtles()?;
         }
         Ok(())
@@ -308,11 +481,17 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
                 StepResult::Sync | StepResult::Finished => {
                     self.sync_turtles()?;
                 }
-                StepResult::Breakpoint(_) => return Ok(()),
+                StepResult::Breakpoint(_) | StepResult::LineBreakpoint(_) => return Ok(()),
             }
         }
     }
 
+    /// Runs until a breakpoint is hit and returns the position of the next statement
+    pub fn resume(&mut self) -> Result<FilePos, ProgEnd> {
+        self.run_breakpoints()?;
+        Ok(self.curr_pos().1)
+    }
+
     /////////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
     //
     //   debug commands - configuration
@@ -345,6 +524,15 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
         id
     }
 
+    /// Breaks before the first statement executed on `line`
+    pub fn add_line_breakpoint(&mut self, line: usize) {
+        self.ctx.line_breakpoints.borrow_mut().insert(line);
+    }
+
+    pub fn remove_line_breakpoint(&mut self, line: usize) {
+        self.ctx.line_breakpoints.borrow_mut().remove(&line);
+    }
+
     pub fn delete_breakpoint(&mut self, id: usize) {
         self.ctx.breakpoints.borrow_mut().retain(|bp| bp.id != id);
     }
@@ -375,17 +563,48 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
         }
     }
 
-    pub fn eval_expr(&mut self, frame: Option<usize>, expr: &str) -> Result<Value, DebugErr> {
-        let mut expr = self.prog.with_parser(expr, |p| Ok(p.parse_expr()?))?;
+    fn parse_expr(&self, expr: &str) -> Result<Expr, DebugErr> {
+        let expr = self.prog.with_parser(expr, |p| Ok(p.parse_expr()?))?;
         if expr.side_effects(self.prog, &mut Vec::new()) {
             return Err(DebugErr::ExprSideEffects);
         }
+        Ok(expr)
+    }
+
+    fn checked_eval(&mut self, frame: Option<usize>, mut expr: Expr) -> Result<Value, DebugErr> {
         let mut ctx = self.prog.get_context();
         let (_, vars) = expr.val_type(&mut ctx)?;
         self.check_undef(vars, expr.collect_variables())?;
         Ok(self.active().eval(expr, frame))
     }
 
+    pub fn eval_expr(&mut self, frame: Option<usize>, expr: &str) -> Result<Value, DebugErr> {
+        let expr = self.parse_expr(expr)?;
+        self.checked_eval(frame, expr)
+    }
+
+    /// Parses `expr` and keeps it to be evaluated by [`Self::watches`]
+    pub fn add_watch(&mut self, expr: &str) -> Result<WatchId, DebugErr> {
+        let expr = self.parse_expr(expr)?;
+        let id = self.watch_id;
+        self.watch_id += 1;
+        self.watches.push((id, expr));
+        Ok(id)
+    }
+
+    pub fn remove_watch(&mut self, id: WatchId) {
+        self.watches.retain(|(watch, _)| *watch != id);
+    }
+
+    /// Evaluates every watch in the active turtle's current frame
+    pub fn watches(&mut self) -> Vec<(WatchId, Result<Value, DebugErr>)> {
+        let watches = self.watches.clone();
+        watches
+            .into_iter()
+            .map(|(id, expr)| (id, self.checked_eval(None, expr)))
+            .collect()
+    }
+
     /// returns true if turtle finished
     pub fn exec_stmt(&mut self, stmt: &str) -> Result<bool, DebugErr> {
         let mut stmt = self.prog.with_parser(stmt, |p| Ok(p.parse_stm()?))?;
@@ -456,6 +675,7 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
                 }
             }
             crate::tokens::ParseToken::StartBlock(_) => return Err(DebugErr::MainBlock),
+            crate::tokens::ParseToken::Include(_) => return Err(DebugErr::Include),
         }
         Ok(())
     }
@@ -504,10 +724,43 @@ impl<'p, W: Window + 'p> DebugController<'p, W> {
         self.active_ref().vardump(frame)
     }
 
+    /// local and global variables of the active turtle's current frame,
+    /// globals are prefixed with `@`
+    pub fn variables(&self) -> Vec<(String, Value)> {
+        let vars = self
+            .vardump(None)
+            .expect("current frame should always exist");
+        vars.locals.into_iter().chain(vars.globals).collect()
+    }
+
+    /// looks up a variable by its name in the source, `@` selects globals
+    pub fn get_variable(&self, name: &str) -> Option<Value> {
+        let mut vars = self.vardump(None).ok()?;
+        if let Some(global) = name.strip_prefix('@') {
+            if let Ok(pdv) = global.parse::<PredefVar>() {
+                vars.predef.swap_remove(&pdv)
+            } else {
+                vars.globals.swap_remove(name)
+            }
+        } else {
+            vars.locals.swap_remove(name)
+        }
+    }
+
     pub fn curr_pos(&self) -> (FuncType, FilePos) {
         self.active_ref().curr_pos()
     }
 
+    /// position of the active turtle
+    pub fn position(&self) -> TCoord {
+        self.active_ref().turtle_state().0
+    }
+
+    /// heading of the active turtle in degrees
+    pub fn heading(&self) -> f64 {
+        self.active_ref().turtle_state().1
+    }
+
     pub fn list_turtles(&self) -> (bool, Vec<TurtleInfo>) {
         let mut ttls = Vec::new();
         for (id, ttl) in &self.turtles {
@@ -1,10 +1,12 @@
 use std::io::Write as _;
 
 use crate::{
-    debugger::{window::Window, Debugger, FuncType, ProgEnd},
-    prog::parser::{ParseError, Parser, TokenExpectation},
+    debugger::{
+        session::{self, Input},
+        window::Window,
+        Debugger, FuncType, ProgEnd,
+    },
     tokens::{EventKind, PredefVar},
-    TurtleError,
 };
 
 use super::DbgInterface;
@@ -88,42 +90,13 @@ impl Shell {
             });
             return TryParseResult::Finished;
         }
-        enum Found {
-            Stmt,
-            Func,
-            None,
-        }
         let code = inp.strip_prefix("!").unwrap_or(inp);
         let no_func = code != inp;
-        let res = run.prog.with_parser(code, |p| {
-            let p_stmt = |p: &mut Parser<'_, '_>| match p.parse_stm() {
-                Err(why) => match *why {
-                    ParseError::UnexpectedEnd => Ok(Found::None),
-                    _ => Err(TurtleError::ParseError(why)),
-                },
-                Ok(_) => Ok(Found::Stmt),
-            };
-            if no_func {
-                p_stmt(p)
-            } else {
-                match p.parse_next() {
-                    Some(Err(why)) => match *why {
-                        ParseError::UnexpectedToken(_, TokenExpectation::BlockStart) => {
-                            p.reset();
-                            p_stmt(p)
-                        }
-                        ParseError::UnexpectedEnd => Ok(Found::None),
-                        _ => Err(TurtleError::ParseError(why)),
-                    },
-                    Some(Ok(_)) => Ok(Found::Func),
-                    None => Ok(Found::None),
-                }
-            }
-        });
+        let res = session::classify(run.prog, code, !no_func);
         match res {
-            Ok(Found::Stmt) => TryParseResult::Cmd(ShellCmd::Exec(code.to_string())),
-            Ok(Found::Func) => TryParseResult::Cmd(ShellCmd::Func(code.to_string())),
-            Ok(Found::None) => TryParseResult::Unfinished,
+            Ok(Input::Stmt) => TryParseResult::Cmd(ShellCmd::Exec(code.to_string())),
+            Ok(Input::Func) => TryParseResult::Cmd(ShellCmd::Func(code.to_string())),
+            Ok(Input::Unfinished) => TryParseResult::Unfinished,
             Err(why) => {
                 eprintln!("{why}");
                 TryParseResult::Finished
@@ -140,6 +140,7 @@ impl<I: Iterator<Item = String>> CommonInterface for Strings<I> {
             match evt {
                 DbgEvent::TurtleFinished(id) => ok!("turtle #{id} finished"),
                 DbgEvent::BreakpointHit(id) => ok!("breakpoint #{id} hit"),
+                DbgEvent::LineBreakpointHit(pos) => ok!("line breakpoint {} hit", pos.line),
             };
         }
         Ok(())
@@ -138,6 +138,7 @@ impl CommonInterface for Terminal {
             match evt {
                 DbgEvent::TurtleFinished(id) => println!("turtle #{id} finished"),
                 DbgEvent::BreakpointHit(id) => println!("breakpoint #{id} hit"),
+                DbgEvent::LineBreakpointHit(pos) => println!("line breakpoint {} hit", pos.line),
             }
         }
         Ok(())
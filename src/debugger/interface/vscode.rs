@@ -67,7 +67,7 @@ impl CommonInterface for VSCode {
             VSCodeCmd::StepIn => _ = run.step_single()?,
             VSCodeCmd::StepOut => run.step_out()?,
             VSCodeCmd::Run => run.run_breakpoints()?,
-            VSCodeCmd::Break(line) => _ = run.add_breakpoint(FilePos::new(line, 1)),
+            VSCodeCmd::Break(line) => _ = run.add_breakpoint(FilePos::line_col(line, 1)),
             VSCodeCmd::RBreak(line) => {
                 for bp in run.list_breakpoints() {
                     if line.is_none_or(|l| bp.pos.line == l) {
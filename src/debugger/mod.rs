@@ -1,43 +1,68 @@
 use std::{
     cell::{Cell, RefCell},
+    collections::HashSet,
     fmt::{Display, Write as _},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     task::{Wake, Waker},
+    time::{Duration, Instant},
 };
 
 use indexmap::IndexMap;
 use turtle::Turtle;
 use varlist::VarList;
-use window::Window;
+use window::{LineStyle, Window};
 
 use crate::{
-    pos::FilePos,
-    tokens::{EventKind, PredefVar, StmtKind, Value},
+    pos::{FilePos, Pos},
+    prog::TypeError,
+    tokens::{EventKind, PredefVar, StmtKind, ValType, Value},
     SymbolTable, TurtleError,
 };
 
+pub use color::{ColorParseError, TColor};
 pub use controller::DebugController as Debugger;
+pub use rng::Rng;
+pub use session::{Session, SessionError};
 pub use turtle::FuncType;
 
+mod color;
 pub mod config;
 mod controller;
 pub mod interface;
+mod rng;
 mod runner;
+mod session;
 mod task;
 #[cfg(test)]
 mod test;
+mod transform;
 mod turtle;
 mod varlist;
+mod vm;
 pub mod window;
 
 // have a cat
 
 /// a point in the turtle coordinate system
-type TCoord = (f64, f64);
-/// a color in the turtle color space
-type TColor = (f64, f64, f64);
-
-const START_COLOR: TColor = (100.0, 100.0, 0.0);
+pub type TCoord = (f64, f64);
+const START_COLOR: TColor = TColor::new(100.0, 100.0, 0.0);
+/// default for the maximum number of nested path and calculation calls
+pub const DEFAULT_MAX_DEPTH: usize = 10_000;
+/// statements between two checks of the timeout, reading the clock isn't free
+pub const TIMEOUT_CHECK_STEPS: u64 = 1024;
+/// default lines per turtle unit when drawing arcs
+pub const DEFAULT_ARC_RESOLUTION: f64 = 2.0;
+/// fastest `setspeed`, drawing without any delay
+pub const MAX_SPEED: f64 = 10.0;
+/// `@delay` in milliseconds of the slowest `setspeed`, which is 1
+const SLOWEST_DELAY: f64 = 500.0;
+/// most lines of a full circle, however large, so huge arcs still draw quickly
+pub const MAX_CIRCLE_SEGMENTS: f64 = 3600.0;
+/// decimal places `setprecision` allows, about the digits an `f64` carries
+const MAX_PRECISION: f64 = 17.0;
 
 #[derive(Debug, PartialEq, Clone)]
 enum DbgAction {
@@ -52,9 +77,14 @@ enum DbgAction {
 
 struct TurtleWaker;
 
+thread_local! {
+    static WAKER: Waker = Arc::new(TurtleWaker).into();
+}
+
 impl TurtleWaker {
+    /// shared by all polls, runners poll once per drawn line
     fn get_waker() -> Waker {
-        Arc::new(Self).into()
+        WAKER.with(Waker::clone)
     }
 }
 
@@ -62,10 +92,61 @@ impl Wake for TurtleWaker {
     fn wake(self: std::sync::Arc<Self>) {}
 }
 
+/// What to do after [`Debugger::run_frame`]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FrameResult {
+    /// run the next frame after this many milliseconds
+    Next(u64),
+    /// all turtles finished, `true` if the drawing should stay until the window is closed
+    Finished(bool),
+    /// the window was closed
+    Exited,
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum ProgEnd {
     WindowExited,
     AllTurtlesFinished,
+    Error(Pos<RuntimeError>),
+}
+
+/// errors that abort a running program
+#[derive(Debug, PartialEq, Clone, thiserror::Error)]
+pub enum RuntimeError {
+    #[error("maximum call depth of {0} exceeded")]
+    RecursionLimit(usize),
+    #[error("step limit of {0} statements exceeded")]
+    StepLimitExceeded(u64),
+    #[error("timeout of {0:?} exceeded")]
+    Timeout(Duration),
+    #[error("cancelled by the host")]
+    Cancelled,
+    #[error("loop count {0} is not an exact integer")]
+    NonIntegerCount(f64),
+    #[error("variable {0} is read after its let went out of scope")]
+    UndefinedVariable(String),
+    #[error("pop without a matching push")]
+    UnmatchedPop,
+    #[error("restorestate without a matching savestate")]
+    UnmatchedRestore,
+    #[error("no turtle {0}, turtles are numbered from 1 to {1}")]
+    UnknownTurtle(f64, usize),
+    #[error("index {0} is not an exact integer")]
+    NonIntegerIndex(f64),
+    #[error("index {0} is not from 1 to {1}")]
+    IndexOutOfBounds(f64, usize),
+    #[error("{0}")]
+    Type(TypeError),
+}
+
+impl RuntimeError {
+    /// Why `idx` is no index of `list`
+    pub fn bad_index(list: &Value, idx: &Value) -> Self {
+        match idx.int() {
+            Some(_) => RuntimeError::IndexOutOfBounds(idx.num(), list.num() as usize),
+            None => RuntimeError::NonIntegerIndex(idx.num()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -91,10 +172,33 @@ struct GlobalCtx<W> {
     vars: RefCell<VarList>,
     args: [Value; 9],
     delay: Cell<f64>,
+    /// ignore `@delay`, draw at full speed
+    skip_delay: Cell<bool>,
     wait_end: Cell<bool>,
+    /// pen width last set on the window, shared by all turtles
+    pen_width: Cell<f64>,
+    line_style: Cell<LineStyle>,
+    /// frames begun on the window and not ended yet
+    frames: Cell<usize>,
+    /// decimal places of numbers in text, set by `setprecision`
+    precision: Cell<Option<usize>>,
     window: RefCell<W>,
     debug: bool,
     breakpoints: RefCell<Vec<Breakpoint>>,
+    line_breakpoints: RefCell<HashSet<usize>>,
+    max_depth: Cell<usize>,
+    /// lines per turtle unit of an arc
+    arc_resolution: Cell<f64>,
+    /// statements executed by all turtles so far
+    steps: Cell<u64>,
+    step_limit: Cell<Option<u64>>,
+    /// when the timeout ends and how long it is
+    deadline: Cell<Option<(Instant, Duration)>>,
+    /// set by the host to stop the program
+    cancel: RefCell<Option<Arc<AtomicBool>>>,
+    rng: RefCell<Rng>,
+    /// first runtime error raised by any turtle
+    error: RefCell<Option<Pos<RuntimeError>>>,
 }
 
 impl<W: Window> GlobalCtx<W> {
@@ -117,6 +221,116 @@ impl<W: Window> GlobalCtx<W> {
         }
     }
 
+    /// counts a statement or loop iteration against the step limit and the timeout
+    pub fn count_step(&self) -> Result<(), RuntimeError> {
+        if self.is_cancelled() {
+            return Err(RuntimeError::Cancelled);
+        }
+        let steps = self.steps.get() + 1;
+        self.steps.set(steps);
+        if let Some(limit) = self.step_limit.get().filter(|&limit| steps > limit) {
+            return Err(RuntimeError::StepLimitExceeded(limit));
+        }
+        match self.deadline.get() {
+            Some((end, timeout))
+                if steps.is_multiple_of(TIMEOUT_CHECK_STEPS) && Instant::now() >= end =>
+            {
+                Err(RuntimeError::Timeout(timeout))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel
+            .borrow()
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+
+    /// Sets `@delay` for a speed from 1, the slowest, to [`MAX_SPEED`] without delay
+    pub fn set_speed(&self, speed: f64) {
+        let speed = speed.clamp(1.0, MAX_SPEED);
+        self.delay
+            .set(SLOWEST_DELAY * (MAX_SPEED - speed) / (MAX_SPEED - 1.0));
+    }
+
+    /// `@delay`, or 0 if delays are skipped
+    pub fn delay_ms(&self) -> u64 {
+        if self.skip_delay.get() {
+            0
+        } else {
+            self.delay.get() as u64
+        }
+    }
+
+    /// waits `@delay` milliseconds through the window unless delays are skipped
+    ///
+    /// Fails instead if the host cancelled or the timeout passed, and never waits beyond it.
+    pub fn apply_delay(&self) -> Result<(), RuntimeError> {
+        let mut delay = Duration::from_millis(self.delay_ms());
+        if delay.is_zero() {
+            return Ok(());
+        }
+        if self.is_cancelled() {
+            return Err(RuntimeError::Cancelled);
+        }
+        if let Some((end, timeout)) = self.deadline.get() {
+            let left = end.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Err(RuntimeError::Timeout(timeout));
+            }
+            delay = delay.min(left);
+        }
+        self.window.borrow_mut().delay(delay);
+        Ok(())
+    }
+
+    pub fn use_pen_width(&self, width: f64) {
+        if self.pen_width.replace(width) != width {
+            self.window.borrow_mut().set_pen_width(width);
+        }
+    }
+
+    pub fn use_line_style(&self, style: LineStyle) {
+        if self.line_style.replace(style) != style {
+            self.window.borrow_mut().set_line_style(style);
+        }
+    }
+
+    /// a negative count restores the default format of [`Value::string`]
+    pub fn set_precision(&self, digits: f64) {
+        self.precision
+            .set((digits >= 0.0).then(|| digits.round().min(MAX_PRECISION) as usize));
+    }
+
+    /// [`Value::convert`] with the precision of `setprecision`
+    pub fn convert(&self, val: &Value, to: ValType) -> Value {
+        match to {
+            ValType::String => Value::String(val.string_with(self.precision.get())),
+            _ => val.convert(to),
+        }
+    }
+
+    pub fn begin_frame(&self) {
+        self.frames.set(self.frames.get() + 1);
+        self.window.borrow_mut().begin_frame();
+    }
+
+    pub fn end_frame(&self) {
+        if self.frames.get() > 0 {
+            self.frames.set(self.frames.get() - 1);
+            self.window.borrow_mut().end_frame();
+        }
+    }
+
+    /// ends the frames of turtles that finished inside one, so their drawing is shown
+    pub fn end_frames(&self) {
+        while self.frames.get() > 0 {
+            self.end_frame();
+        }
+    }
+
     pub fn breakpoint_hit(&self, last_pos: FilePos, curr_pos: FilePos) -> Option<usize> {
         for bp in &*self.breakpoints.borrow() {
             if bp.enabled && last_pos < bp.pos && bp.pos < curr_pos {
@@ -125,12 +339,27 @@ impl<W: Window> GlobalCtx<W> {
         }
         None
     }
+
+    pub fn raise(&self, err: Pos<RuntimeError>) {
+        let mut error = self.error.borrow_mut();
+        if error.is_none() {
+            *error = Some(err);
+        }
+    }
+
+    /// only fires when execution enters the line from another one
+    pub fn line_breakpoint_hit(&self, last_pos: FilePos, curr_pos: FilePos) -> bool {
+        last_pos.line != curr_pos.line && self.line_breakpoints.borrow().contains(&curr_pos.line)
+    }
 }
 
+pub type WatchId = usize;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DbgEvent {
     TurtleFinished(usize),
     BreakpointHit(usize),
+    LineBreakpointHit(FilePos),
 }
 
 pub struct VarDump {
@@ -188,6 +417,8 @@ pub enum DebugErr {
     ExprSideEffects,
     #[error("cannot modify main block")]
     MainBlock,
+    #[error("files can only be included by the program itself")]
+    Include,
     #[error(
         "cannot replace definition for {0} as another already exists with different arguments"
     )]
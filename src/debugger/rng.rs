@@ -0,0 +1,68 @@
+use std::{collections::hash_map::RandomState, hash::BuildHasher as _};
+
+/// xorshift64* generator behind `rand` and `random`
+///
+/// Fast and reproducible, but not suitable for anything security related.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// The seed is scrambled first, so small seeds (and 0) work fine.
+    pub fn new(seed: u64) -> Self {
+        // splitmix64
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        // xorshift gets stuck at 0
+        Self(if z == 0 { 0x9e37_79b9_7f4a_7c15 } else { z })
+    }
+
+    /// Seeded from the per-process random keys of the std hash maps
+    pub fn from_entropy() -> Self {
+        let keys = RandomState::new();
+        // there is no clock on wasm32-unknown-unknown, `now` panics
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        return Self::new(keys.hash_one(std::time::SystemTime::now()));
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        Self::new(keys.hash_one(()))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// uniformly distributed in `[0, 1)`
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Rng;
+
+    #[test]
+    fn same_seed_same_sequence() {
+        let mut a = Rng::new(0);
+        let mut b = Rng::new(0);
+        let first: Vec<_> = (0..100).map(|_| a.next_u64()).collect();
+        assert!(first.iter().all(|&x| x == b.next_u64()));
+        assert_ne!(first[..50], first[50..]);
+        assert_ne!(Rng::new(1).next_u64(), first[0]);
+    }
+
+    #[test]
+    fn unit_interval() {
+        let mut rng = Rng::new(7);
+        let vals: Vec<_> = (0..1000).map(|_| rng.next_f64()).collect();
+        assert!(vals.iter().all(|x| (0.0..1.0).contains(x)));
+        let mean = vals.iter().sum::<f64>() / vals.len() as f64;
+        assert!((0.45..0.55).contains(&mean), "{mean}");
+    }
+}
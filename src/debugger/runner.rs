@@ -16,7 +16,7 @@ use super::{
     task::{DbgCommand, TurtleTask},
     turtle::{FuncType, Turtle},
     window::Window,
-    DbgAction, DebugErr, EventKind, FrameInfo, GlobalCtx, TurtleWaker, VarDump,
+    DbgAction, DebugErr, EventKind, FrameInfo, GlobalCtx, TCoord, TurtleWaker, VarDump,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -24,6 +24,7 @@ pub enum StepResult {
     Exec(StmtKind),
     Sync,
     Breakpoint(usize),
+    LineBreakpoint(FilePos),
     Finished,
 }
 
@@ -192,6 +193,9 @@ impl<'p, W: Window + 'p> TurtleRunner<'p, W> {
                     if let Some(idx) = self.ctx.breakpoint_hit(self.last_pos, act.get_pos()) {
                         return StepResult::Breakpoint(idx);
                     }
+                    if self.ctx.line_breakpoint_hit(self.last_pos, act.get_pos()) {
+                        return StepResult::LineBreakpoint(act.get_pos());
+                    }
                     if let Some(kind) = res {
                         return StepResult::Exec(kind);
                     }
@@ -219,6 +223,9 @@ impl<'p, W: Window + 'p> TurtleRunner<'p, W> {
                     if let Some(idx) = self.ctx.breakpoint_hit(self.last_pos, act.get_pos()) {
                         return StepResult::Breakpoint(idx);
                     }
+                    if self.ctx.line_breakpoint_hit(self.last_pos, act.get_pos()) {
+                        return StepResult::LineBreakpoint(act.get_pos());
+                    }
                 }
                 DbgAction::Sleep => return StepResult::Sync,
                 DbgAction::Finished(wait) => {
@@ -244,8 +251,13 @@ impl<'p, W: Window + 'p> TurtleRunner<'p, W> {
         (frame.func, frame.curr_pos)
     }
 
+    pub fn turtle_state(&self) -> (TCoord, f64) {
+        let ttl = self.turtle.borrow();
+        (ttl.pos(), ttl.dir)
+    }
+
     pub fn vardump(&self, frame: Option<usize>) -> Result<VarDump, DebugErr> {
-        let mut ttl = self.turtle.borrow_mut();
+        let ttl = self.turtle.borrow();
         let index = frame.unwrap_or(ttl.stack.len() - 1);
         let locals = &ttl
             .stack
@@ -275,7 +287,8 @@ impl<'p, W: Window + 'p> TurtleRunner<'p, W> {
                 .into_iter()
                 .map(|pdv| {
                     let var = VariableKind::GlobalPreDef(pdv).at(FilePos::default());
-                    (pdv, ttl.get_var(&self.ctx, &var))
+                    let val = ttl.get_var(&self.ctx, &var);
+                    (pdv, val.expect("predefined variables are always set"))
                 })
                 .collect(),
         })
@@ -316,7 +329,7 @@ impl<'p, W: Window + 'p> TurtleRunner<'p, W> {
     }
 
     pub fn exec(&mut self, stmt: Statement) -> bool {
-        _ = self.cmds.send(DbgCommand::Exec(stmt));
+        _ = self.cmds.send(DbgCommand::Exec(Box::new(stmt)));
         self.run_cmd();
         self.finished
     }
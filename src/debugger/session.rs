@@ -0,0 +1,130 @@
+use crate::{
+    pos::{FilePos, Pos, Positionable as _},
+    prog::parser::{ParseError, Parser, TokenExpectation},
+    TProgram, TurtleError,
+};
+
+use super::{window::Window, DebugErr, Debugger, RuntimeError};
+
+/// What a piece of shell input turned out to be
+pub(super) enum Input {
+    Stmt,
+    Func,
+    /// more lines are needed to complete it
+    Unfinished,
+}
+
+/// Decides how `code` is run, `allow_funcs` also accepts path and calculation definitions
+pub(super) fn classify(
+    prog: &TProgram,
+    code: &str,
+    allow_funcs: bool,
+) -> Result<Input, TurtleError> {
+    prog.with_parser(code, |p| {
+        let p_stmt = |p: &mut Parser<'_, '_>| match p.parse_stm() {
+            Err(why) => match *why {
+                ParseError::UnexpectedEnd => Ok(Input::Unfinished),
+                _ => Err(TurtleError::ParseError(why)),
+            },
+            Ok(_) => Ok(Input::Stmt),
+        };
+        if !allow_funcs {
+            return p_stmt(p);
+        }
+        match p.parse_next() {
+            Some(Err(why)) => match *why {
+                ParseError::UnexpectedToken(_, TokenExpectation::BlockStart) => {
+                    p.reset();
+                    p_stmt(p)
+                }
+                ParseError::UnexpectedEnd => Ok(Input::Unfinished),
+                _ => Err(TurtleError::ParseError(why)),
+            },
+            Some(Ok(_)) => Ok(Input::Func),
+            None => Ok(Input::Unfinished),
+        }
+    })
+}
+
+/// Errors returned by [`Session::feed`]
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    /// The input so far is the start of a statement or definition
+    #[error("input is incomplete")]
+    NeedMoreInput,
+    #[error("{0}")]
+    Debug(Box<DebugErr>),
+    #[error("{0}")]
+    Runtime(RuntimeError),
+    #[error("the turtle has finished")]
+    Finished,
+}
+
+/// Runs statements one input at a time, like the turtle shell.
+///
+/// The turtle and all variables are kept between calls to [`Session::feed`],
+/// definitions of paths and calculations are added to the program.
+pub struct Session<'p, W> {
+    dbg: Debugger<'p, W>,
+    /// lines of an unfinished input
+    pending: String,
+    finished: bool,
+}
+
+impl<'p, W: Window + 'p> Session<'p, W> {
+    /// `prog` provides the functions available from the start, usually just `begin end`
+    pub fn new(prog: &'p TProgram, window: W) -> Self {
+        let mut dbg = Debugger::new(prog, &[], window, true, Vec::new());
+        dbg.init_window();
+        Self {
+            dbg,
+            pending: String::new(),
+            finished: false,
+        }
+    }
+
+    /// Run `line`, or keep it until the input is complete.
+    ///
+    /// After [`SessionError::NeedMoreInput`] the next line continues the same input,
+    /// any other error discards it. Runtime errors stop the turtle like `finish` does.
+    pub fn feed(&mut self, line: &str) -> Result<(), Pos<SessionError>> {
+        if self.finished {
+            return Err(SessionError::Finished.attach_pos(FilePos::default()));
+        }
+        if !self.pending.is_empty() {
+            self.pending.push('\n');
+        }
+        self.pending += line;
+        let res = match classify(self.dbg.prog, &self.pending, true) {
+            Ok(Input::Unfinished) => {
+                return Err(SessionError::NeedMoreInput.attach_pos(FilePos::default()))
+            }
+            Ok(Input::Func) => self.dbg.add_func(&self.pending),
+            Ok(Input::Stmt) => match self.dbg.exec_stmt(&self.pending) {
+                Ok(finished) => {
+                    self.finished = finished;
+                    Ok(())
+                }
+                Err(why) => Err(why),
+            },
+            Err(why) => Err(DebugErr::TurtleError(why)),
+        };
+        self.pending.clear();
+        if let Some(err) = self.dbg.runtime_error() {
+            self.finished = true;
+            return Err(err.map(SessionError::Runtime));
+        }
+        res.map_err(|why| {
+            let pos = match &why {
+                DebugErr::TurtleError(err) => err.pos(),
+                _ => None,
+            };
+            SessionError::Debug(Box::new(why)).attach_pos(pos.unwrap_or_default())
+        })
+    }
+
+    /// The interpreter behind the session, e.g. to query the turtle
+    pub fn debugger(&mut self) -> &mut Debugger<'p, W> {
+        &mut self.dbg
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     cell::{Cell, RefCell},
     future::Future,
+    pin::Pin,
     rc::Rc,
     sync::mpsc::{Receiver, Sender},
     task::Poll,
@@ -11,13 +12,13 @@ use crate::{
         turtle::{FuncType, StackFrame},
         varlist::VarList,
     },
-    pos::FilePos,
+    pos::{FilePos, Pos, Positionable as _},
     prog::PathDef,
-    tokens::{Block, Expr, ExprKind, Statement, Value},
+    tokens::{ArgDefList, Block, Expr, ExprKind, Statement, Value, Variable, VariableKind},
     TProgram,
 };
 
-use super::{turtle::Turtle, window::Window, DbgAction, EventKind, GlobalCtx};
+use super::{turtle::Turtle, window::Window, DbgAction, EventKind, GlobalCtx, RuntimeError};
 
 struct TurtleFuture(bool);
 
@@ -37,9 +38,38 @@ impl Future for TurtleFuture {
     }
 }
 
+/// Polls the inner future on a new stack segment when the native stack runs low.
+///
+/// Every nested block or call adds a few frames to each poll, so deep recursion
+/// in a turtle program would otherwise overflow long before the depth limit.
+/// Dropping a task that failed deep in a recursion nests just as deeply.
+struct GrowStack<F>(Option<Pin<Box<F>>>);
+
+impl<F> GrowStack<F> {
+    fn new(fut: F) -> Self {
+        Self(Some(Box::pin(fut)))
+    }
+}
+
+impl<F: Future> Future for GrowStack<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<Self::Output> {
+        let fut = self.0.as_mut().expect("polled after drop");
+        stacker::maybe_grow(64 * 1024, 1024 * 1024, || fut.as_mut().poll(cx))
+    }
+}
+
+impl<F> Drop for GrowStack<F> {
+    fn drop(&mut self) {
+        let fut = self.0.take();
+        stacker::maybe_grow(64 * 1024, 1024 * 1024, || drop(fut));
+    }
+}
+
 pub enum DbgCommand {
     Eval(Expr),
-    Exec(Statement),
+    Exec(Box<Statement>),
 }
 
 pub struct TurtleTask<'p, W: Window> {
@@ -50,6 +80,14 @@ pub struct TurtleTask<'p, W: Window> {
     cmds: Receiver<DbgCommand>,
     curr_pos: FilePos,
     narrate: Rc<Cell<bool>>,
+    /// running a debugger command, where unset variables read as their default
+    in_cmd: bool,
+    /// a `return` is leaving the blocks up to its path or calculation
+    returning: bool,
+    /// result of a calculation left with `return`
+    ret_val: Option<Value>,
+    /// path called last in a path, run in its frame once the caller's blocks are left
+    tail_call: Option<(usize, Vec<Value>)>,
 }
 
 impl<'p, W: Window> TurtleTask<'p, W> {
@@ -69,11 +107,15 @@ impl<'p, W: Window> TurtleTask<'p, W> {
             cmds,
             curr_pos: FilePos::default(),
             narrate,
+            in_cmd: false,
+            returning: false,
+            ret_val: None,
+            tail_call: None,
         }
     }
 
     pub async fn execute(mut self) {
-        self.dbg_block(&self.prog.main).await
+        self.dbg_block(&self.prog.main, false).await
     }
 
     pub async fn execute_path(self, name: usize, args: Vec<Value>) {
@@ -97,28 +139,63 @@ impl<'p, W: Window> TurtleTask<'p, W> {
         {
             let mut ttl = self.turtle.borrow_mut();
             let frame = ttl.stack.last_mut().unwrap();
-            for (&(arg, _), val) in path.args.iter().zip(args.into_iter()) {
+            for (&(arg, _), val) in path.args.iter().zip(args) {
                 frame.vars.set_var(arg, val);
             }
             frame.func = ft;
         }
-        self.dbg_block(&path.body).await;
+        self.dbg_block(&path.body, true).await;
+        self.returning = false;
+        if let Some((id, args)) = self.tail_call.take() {
+            self.dbg_path(id, args).await;
+        }
     }
 
-    async fn dbg_block(&mut self, block: &Block) {
+    /// Runs a path in the innermost frame, then the paths it calls last in the same frame
+    async fn dbg_path(&mut self, mut id: usize, mut args: Vec<Value>) {
+        loop {
+            let path = self.prog.get_path(id).expect("should be caught by parser");
+            self.reset_frame(FuncType::Path(id), &path.args, args, path.body.begin);
+            self.dbg_block(&path.body, true).await;
+            self.returning = false;
+            let Some(next) = self.tail_call.take() else {
+                break;
+            };
+            (id, args) = next;
+        }
+    }
+
+    /// Binds the arguments in a fresh innermost frame, dropping the previous call's locals
+    fn reset_frame(&self, func: FuncType, params: &ArgDefList, args: Vec<Value>, begin: FilePos) {
+        assert_eq!(params.len(), args.len());
+        let mut ttl = self.turtle.borrow_mut();
+        let frame = ttl.stack.last_mut().unwrap();
+        frame.vars = VarList::new();
+        for (&(id, _), val) in params.iter().zip(args) {
+            frame.vars.set_var(id, val);
+        }
+        frame.func = func;
+        frame.curr_pos = begin;
+        frame.repcount = 0;
+    }
+
+    /// `tail` if the block is the last thing its path runs
+    async fn dbg_block(&mut self, block: &Block, tail: bool) {
         let fut = async {
             if self.ctx.debug {
                 let _ = self.action.send((DbgAction::BlockEntered, block.begin));
             }
             self.check_cmds().await;
-            for stmt in &block.statements {
+            self.turtle.borrow_mut().push_scope();
+            for (idx, stmt) in block.statements.iter().enumerate() {
                 self.curr_pos = stmt.get_pos();
                 self.turtle.borrow_mut().stack.last_mut().unwrap().curr_pos = self.curr_pos;
+                self.count_step(self.curr_pos).await;
                 // before
                 self.ret(DbgAction::BeforeStmt, self.ctx.debug).await;
                 self.check_cmds().await;
                 // execute
-                self.dbg_stmt(stmt).await;
+                self.dbg_stmt(stmt, tail && block.ends_after(idx)).await;
                 self.check_cmds().await;
                 // after
                 if self.narrate.get() {
@@ -126,27 +203,31 @@ impl<'p, W: Window> TurtleTask<'p, W> {
                 }
                 self.ret(DbgAction::AfterStmt(stmt.kind()), self.ctx.debug)
                     .await;
+                if self.returning {
+                    break;
+                }
             }
+            self.turtle.borrow_mut().pop_scope();
         };
-        Box::pin(fut).await
+        GrowStack::new(fut).await
     }
 
     async fn check_cmds(&mut self) {
         while let Ok(cmd) = self.cmds.try_recv() {
-            match cmd {
-                DbgCommand::Eval(expr) => {
-                    let res = self.dbg_expr(&expr).await;
-                    self.ret(DbgAction::CmdResult(Some(res)), true).await;
-                }
+            let outer = std::mem::replace(&mut self.in_cmd, true);
+            let res = match cmd {
+                DbgCommand::Eval(expr) => Some(self.dbg_expr(&expr).await),
                 DbgCommand::Exec(stmt) => {
-                    self.dbg_stmt(&stmt).await;
-                    self.ret(DbgAction::CmdResult(None), true).await;
+                    self.dbg_stmt(&stmt, false).await;
+                    None
                 }
-            }
+            };
+            self.in_cmd = outer;
+            self.ret(DbgAction::CmdResult(res), true).await;
         }
     }
 
-    async fn dbg_stmt(&mut self, stmt: &Statement) {
+    async fn dbg_stmt(&mut self, stmt: &Statement, tail: bool) {
         match stmt {
             Statement::MoveDist { dist, draw, back } => {
                 let dist = self.dbg_expr(dist).await;
@@ -159,14 +240,36 @@ impl<'p, W: Window> TurtleTask<'p, W> {
                 self.turtle.borrow_mut().move_home(&self.ctx, *draw);
                 self.ret(DbgAction::Sleep, *draw).await;
             }
+            Statement::MoveTo { x, y, draw } => {
+                let x = self.dbg_expr(x).await.num();
+                let y = self.dbg_expr(y).await.num();
+                self.turtle.borrow_mut().move_to(&self.ctx, (x, y), *draw);
+                self.ret(DbgAction::Sleep, *draw).await;
+            }
             Statement::Turn { left, by } => {
                 let angle = self.dbg_expr(by).await.num();
                 let new_dir = self.turtle.borrow().dir + if *left { angle } else { -angle };
                 self.turtle.borrow_mut().set_dir(new_dir);
+                self.turtle.borrow().show(&self.ctx);
             }
             Statement::Direction(expr) => {
                 let new_dir = self.dbg_expr(expr).await;
                 self.turtle.borrow_mut().set_dir(new_dir.num());
+                self.turtle.borrow().show(&self.ctx);
+            }
+            Statement::Arc {
+                left,
+                radius,
+                angle,
+            } => {
+                let radius = self.dbg_expr(radius).await.num();
+                let angle = match angle {
+                    Some(angle) => self.dbg_expr(angle).await.num(),
+                    None => 360.0,
+                };
+                let angle = if *left { angle } else { -angle };
+                self.turtle.borrow_mut().move_arc(&self.ctx, radius, angle);
+                self.ret(DbgAction::Sleep, true).await;
             }
             Statement::Color(ex1, ex2, ex3) => {
                 let r = self.dbg_expr(ex1).await.num();
@@ -174,35 +277,117 @@ impl<'p, W: Window> TurtleTask<'p, W> {
                 let b = self.dbg_expr(ex3).await.num();
                 self.turtle.borrow_mut().set_col(r, g, b);
             }
+            Statement::PenWidth(expr) => {
+                let width = self.dbg_expr(expr).await.num();
+                self.turtle.borrow_mut().pen_width = width.max(0.0);
+            }
+            Statement::SetSpeed(expr) => {
+                let speed = self.dbg_expr(expr).await.num();
+                self.ctx.set_speed(speed);
+            }
+            Statement::PushTransform => self.turtle.borrow_mut().push_transform(),
+            Statement::PopTransform => {
+                let popped = self.turtle.borrow_mut().pop_transform();
+                if !popped {
+                    self.fail(RuntimeError::UnmatchedPop.attach_pos(self.curr_pos))
+                        .await;
+                }
+            }
+            Statement::SaveState => self.turtle.borrow_mut().save_state(),
+            Statement::RestoreState => {
+                let restored = self.turtle.borrow_mut().restore_state(&self.ctx);
+                if !restored {
+                    self.fail(RuntimeError::UnmatchedRestore.attach_pos(self.curr_pos))
+                        .await;
+                }
+            }
+            Statement::NewTurtle => self.turtle.borrow_mut().new_turtle(&self.ctx),
+            Statement::SetTurtle(expr) => {
+                let id = self.dbg_expr(expr).await;
+                let found = id
+                    .int()
+                    .is_some_and(|id| self.turtle.borrow_mut().set_turtle(&self.ctx, id));
+                if !found {
+                    let count = self.turtle.borrow().turtle_count();
+                    let err = RuntimeError::UnknownTurtle(id.num(), count);
+                    self.fail(err.attach_pos(self.curr_pos)).await;
+                }
+            }
+            Statement::Scale(x, y) => {
+                let x = self.dbg_expr(x).await.num();
+                let y = match y {
+                    Some(y) => self.dbg_expr(y).await.num(),
+                    None => x,
+                };
+                let mut turtle = self.turtle.borrow_mut();
+                turtle.transform = turtle.transform.scale(x, y);
+            }
+            Statement::Translate(x, y) => {
+                let x = self.dbg_expr(x).await.num();
+                let y = self.dbg_expr(y).await.num();
+                let mut turtle = self.turtle.borrow_mut();
+                turtle.transform = turtle.transform.translate(x, y);
+            }
+            Statement::Rotate(angle) => {
+                let angle = self.dbg_expr(angle).await.num();
+                let mut turtle = self.turtle.borrow_mut();
+                turtle.transform = turtle.transform.rotate(angle);
+            }
+            Statement::PenStyle(style) => self.turtle.borrow_mut().line_style = *style,
+            Statement::Pen(down) => self.turtle.borrow_mut().pen_down = *down,
+            Statement::ShowTurtle(visible) => {
+                self.turtle.borrow_mut().set_visible(&self.ctx, *visible)
+            }
             Statement::Clear => {
                 self.ctx.window.borrow_mut().clear();
                 self.ret(DbgAction::Sleep, true).await;
             }
+            Statement::ClearScreen => {
+                self.ctx.window.borrow_mut().clear();
+                self.turtle.borrow_mut().reset();
+                self.turtle.borrow().show(&self.ctx);
+                self.ret(DbgAction::Sleep, true).await;
+            }
             Statement::Stop => self.ret(DbgAction::Finished(true), true).await,
             Statement::Finish => self.ret(DbgAction::Finished(false), true).await,
+            Statement::Return(val) => {
+                if let Some(val) = val {
+                    self.ret_val = Some(self.dbg_expr(val).await);
+                }
+                self.returning = true;
+            }
             Statement::PathCall(id, args) => {
-                let path = self.prog.get_path(*id).expect("should be caught by parser");
                 let args = self.dbg_args(args).await;
-                assert_eq!(path.args.len(), args.len());
-                let mut vars = VarList::new();
-                for (i, arg) in args.into_iter().enumerate() {
-                    vars.set_var(path.args[i].0, arg);
+                if tail {
+                    // the caller's frame is done, so it is reused instead of nesting
+                    self.tail_call = Some((*id, args));
+                    self.returning = true;
+                    return;
                 }
+                self.check_depth(self.curr_pos).await;
                 let frame = StackFrame {
-                    vars,
+                    vars: VarList::new(),
                     func: FuncType::Path(*id),
-                    curr_pos: path.body.begin,
+                    curr_pos: self.curr_pos,
+                    repcount: 0,
                 };
                 self.turtle.borrow_mut().stack.push(frame);
-                self.dbg_block(&path.body).await;
+                self.dbg_path(*id, args).await;
                 self.turtle.borrow_mut().stack.pop();
             }
             Statement::Store(expr, var) => {
                 let val = self.dbg_expr(expr).await;
                 self.turtle.borrow_mut().set_var(&self.ctx, var, val);
             }
+            Statement::Let(expr, var) => {
+                let val = self.dbg_expr(expr).await;
+                let VariableKind::Local(id, _) = var.kind else {
+                    unreachable!("parser only allows local variables in let")
+                };
+                self.turtle.borrow_mut().declare_var(id, val);
+            }
             Statement::Calc { var, val, op } => {
-                let lhs = self.turtle.borrow_mut().get_var(&self.ctx, var);
+                let lhs = self.read_var(var).await;
                 let rhs = self.dbg_expr(val).await;
                 self.turtle
                     .borrow_mut()
@@ -215,7 +400,15 @@ impl<'p, W: Window> TurtleTask<'p, W> {
             }
             Statement::Print(expr) => {
                 let msg = self.dbg_expr(expr).await.string();
-                self.ctx.window.borrow_mut().print(&msg);
+                let pos = {
+                    let turtle = self.turtle.borrow();
+                    turtle.transform.apply(turtle.pos())
+                };
+                self.ctx.window.borrow_mut().print_at(pos, &msg);
+            }
+            Statement::SetPrecision(expr) => {
+                let digits = self.dbg_expr(expr).await.num();
+                self.ctx.set_precision(digits);
             }
             Statement::Split(id, args) => {
                 let args = self.dbg_args(args).await;
@@ -227,21 +420,32 @@ impl<'p, W: Window> TurtleTask<'p, W> {
             Statement::Wait => self.ret(DbgAction::Sleep, true).await,
             Statement::IfBranch(cond, stmts) => {
                 if self.dbg_expr(cond).await.bool() {
-                    self.dbg_block(stmts).await;
+                    self.dbg_block(stmts, tail).await;
                 }
             }
             Statement::IfElseBranch(cond, if_stmts, else_stmts) => {
                 if self.dbg_expr(cond).await.bool() {
-                    self.dbg_block(if_stmts).await;
+                    self.dbg_block(if_stmts, tail).await;
                 } else {
-                    self.dbg_block(else_stmts).await;
+                    self.dbg_block(else_stmts, tail).await;
                 }
             }
             Statement::DoLoop(expr, stmts) => {
-                let count = self.dbg_expr(expr).await.num().floor();
-                for _ in 0..count as isize {
-                    self.dbg_block(stmts).await;
+                let val = self.dbg_expr(expr).await;
+                let Some(count) = val.int() else {
+                    self.fail(RuntimeError::NonIntegerCount(val.num()).attach_pos(expr.start))
+                        .await;
+                    unreachable!("failed task is never resumed")
+                };
+                let outer = self.set_repcount(0);
+                for i in 1..=count {
+                    self.set_repcount(i as usize);
+                    self.dbg_block(stmts, false).await;
+                    if self.returning {
+                        break;
+                    }
                 }
+                self.set_repcount(outer);
             }
             Statement::CounterLoop {
                 counter,
@@ -261,72 +465,161 @@ impl<'p, W: Window> TurtleTask<'p, W> {
                 self.turtle
                     .borrow_mut()
                     .set_var(&self.ctx, counter, Value::Number(init));
-                while *up != (self.turtle.borrow_mut().get_var(&self.ctx, counter).num() >= end) {
-                    self.dbg_block(body).await;
-                    let next_val =
-                        self.turtle.borrow_mut().get_var(&self.ctx, counter).num() + step;
+                let pos = self.curr_pos;
+                while *up != (self.read_var(counter).await.num() >= end) {
+                    self.dbg_block(body, false).await;
+                    if self.returning {
+                        break;
+                    }
+                    self.count_step(pos).await;
+                    let next_val = self.read_var(counter).await.num() + step;
                     self.turtle
                         .borrow_mut()
                         .set_var(&self.ctx, counter, Value::Number(next_val));
                 }
             }
+            Statement::ForEach { item, list, body } => {
+                let Value::List(list) = self.dbg_expr(list).await else {
+                    unreachable!("should be checked by semcheck")
+                };
+                let VariableKind::Local(id, _) = item.kind else {
+                    unreachable!("parser only allows local variables in foreach")
+                };
+                let pos = self.curr_pos;
+                for val in list {
+                    self.turtle.borrow_mut().push_scope();
+                    self.turtle.borrow_mut().declare_var(id, Value::Number(val));
+                    self.dbg_block(body, false).await;
+                    self.turtle.borrow_mut().pop_scope();
+                    if self.returning {
+                        break;
+                    }
+                    self.count_step(pos).await;
+                }
+            }
             Statement::WhileLoop(cond, stmts) => {
+                let pos = self.curr_pos;
                 while self.dbg_expr(cond).await.bool() {
-                    self.dbg_block(stmts).await;
+                    self.dbg_block(stmts, false).await;
+                    if self.returning {
+                        break;
+                    }
+                    self.count_step(pos).await;
                 }
             }
             Statement::RepeatLoop(cond, stmts) => {
-                self.dbg_block(stmts).await;
-                while !self.dbg_expr(cond).await.bool() {
-                    self.dbg_block(stmts).await;
+                let pos = self.curr_pos;
+                self.dbg_block(stmts, false).await;
+                while !self.returning && !self.dbg_expr(cond).await.bool() {
+                    self.count_step(pos).await;
+                    self.dbg_block(stmts, false).await;
                 }
             }
+            Statement::Frame(stmts) => {
+                self.ctx.begin_frame();
+                self.dbg_block(stmts, false).await;
+                self.ctx.end_frame();
+            }
         }
     }
 
     pub(super) async fn dbg_expr(&mut self, expr: &Expr) -> Value {
+        // leaves don't recurse, so they skip boxing a future on a grown stack
+        match &expr.kind {
+            ExprKind::Const(val) => return val.clone(),
+            ExprKind::Variable(var) => return self.read_var(var).await,
+            _ => {}
+        }
         let fut = async {
             match &expr.kind {
-                ExprKind::Const(val) => val.clone(),
-                ExprKind::Variable(var) => self.turtle.borrow_mut().get_var(&self.ctx, var),
+                ExprKind::Const(_) | ExprKind::Variable(_) => unreachable!("evaluated above"),
                 ExprKind::BiOperation(lhs, op, rhs) => {
                     let lhs = self.dbg_expr(lhs).await;
                     let rhs = self.dbg_expr(rhs).await;
-                    op.eval(&lhs, &rhs)
+                    match op.try_eval(&lhs, &rhs) {
+                        Ok(val) => val,
+                        Err(why) => {
+                            self.fail(RuntimeError::Type(why).attach_pos(expr.start))
+                                .await;
+                            unreachable!("failed task is never resumed")
+                        }
+                    }
                 }
-                ExprKind::UnOperation(op, expr) => {
-                    let val = self.dbg_expr(expr).await;
-                    op.eval(&val)
+                ExprKind::UnOperation(op, inner) => {
+                    let val = self.dbg_expr(inner).await;
+                    match op.try_eval(&val) {
+                        Ok(val) => val,
+                        Err(why) => {
+                            self.fail(RuntimeError::Type(why).attach_pos(expr.start))
+                                .await;
+                            unreachable!("failed task is never resumed")
+                        }
+                    }
                 }
                 ExprKind::Absolute(expr) => Value::Number(self.dbg_expr(expr).await.num().abs()),
+                ExprKind::List(items) => {
+                    let mut list = Vec::with_capacity(items.len());
+                    for item in items {
+                        list.push(self.dbg_expr(item).await.num());
+                    }
+                    Value::List(list)
+                }
+                ExprKind::Index(list, idx) => {
+                    let list = self.dbg_expr(list).await;
+                    let idx = self.dbg_expr(idx).await;
+                    match list.item(&idx) {
+                        Some(val) => Value::Number(val),
+                        None => {
+                            self.fail(RuntimeError::bad_index(&list, &idx).attach_pos(expr.start))
+                                .await;
+                            unreachable!("failed task is never resumed")
+                        }
+                    }
+                }
                 ExprKind::Bracket(expr) => self.dbg_expr(expr).await,
-                ExprKind::Convert(from, to) => self.dbg_expr(from).await.convert(*to),
-                ExprKind::FuncCall(pdf, args) => pdf.eval(&self.dbg_args(args).await),
-                ExprKind::CalcCall(id, args) => {
-                    let calc = self
-                        .prog
-                        .get_calc(*id)
-                        .expect("should be checked by parser");
+                ExprKind::Convert(from, to) => {
+                    let val = self.dbg_expr(from).await;
+                    self.ctx.convert(&val, *to)
+                }
+                ExprKind::FuncCall(pdf, args) => {
                     let args = self.dbg_args(args).await;
-                    assert_eq!(calc.args.len(), args.len());
-                    let mut vars = VarList::new();
-                    for (i, arg) in args.into_iter().enumerate() {
-                        vars.set_var(calc.args[i].0, arg);
-                    }
+                    let pos = self.turtle.borrow().pos();
+                    pdf.eval(&args, &mut self.ctx.rng.borrow_mut(), pos)
+                }
+                ExprKind::CalcCall(id, args) => {
+                    let mut args = self.dbg_args(args).await;
+                    self.check_depth(expr.start).await;
                     let frame = StackFrame {
-                        vars,
+                        vars: VarList::new(),
                         func: FuncType::Calc(*id),
-                        curr_pos: calc.body.begin,
+                        curr_pos: expr.start,
+                        repcount: 0,
                     };
                     self.turtle.borrow_mut().stack.push(frame);
-                    self.dbg_block(&calc.body).await;
-                    let res = self.dbg_expr(&calc.ret).await;
+                    let mut id = *id;
+                    let res = loop {
+                        let calc = self.prog.get_calc(id).expect("should be checked by parser");
+                        self.reset_frame(FuncType::Calc(id), &calc.args, args, calc.body.begin);
+                        self.dbg_block(&calc.body, false).await;
+                        self.returning = false;
+                        if let Some(val) = self.ret_val.take() {
+                            break val;
+                        }
+                        // a result calculated by another call is its result, in the same frame
+                        match &calc.ret.kind {
+                            ExprKind::CalcCall(next, next_args) => {
+                                args = self.dbg_args(next_args).await;
+                                id = *next;
+                            }
+                            _ => break self.dbg_expr(&calc.ret).await,
+                        }
+                    };
                     self.turtle.borrow_mut().stack.pop();
                     res
                 }
             }
         };
-        Box::pin(fut).await
+        GrowStack::new(fut).await
     }
 
     async fn dbg_args(&mut self, args: &[Expr]) -> Vec<Value> {
@@ -337,6 +630,59 @@ impl<'p, W: Window> TurtleTask<'p, W> {
         res
     }
 
+    /// sets `@repcount` of the current frame and returns the previous value
+    fn set_repcount(&self, count: usize) -> usize {
+        let mut ttl = self.turtle.borrow_mut();
+        std::mem::replace(&mut ttl.stack.last_mut().unwrap().repcount, count)
+    }
+
+    /// aborts the turtle if another call would exceed the maximum depth
+    async fn check_depth(&mut self, pos: FilePos) {
+        // the bottom frame belongs to main / the started path
+        let max = self.ctx.max_depth.get();
+        if self.turtle.borrow().stack.len() > max {
+            self.fail(RuntimeError::RecursionLimit(max).attach_pos(pos))
+                .await;
+        }
+    }
+
+    /// counts a statement or loop iteration against the step limit and the timeout
+    async fn count_step(&mut self, pos: FilePos) {
+        if let Err(why) = self.ctx.count_step() {
+            self.fail(why.attach_pos(pos)).await;
+        }
+    }
+
+    /// Value of `var`, fails if it's a local whose `let` went out of scope.
+    ///
+    /// Other variables start out with their type's default, like in programs
+    /// before `let` existed, and as event handlers usually set globals only later.
+    async fn read_var(&mut self, var: &Variable) -> Value {
+        let val = self.turtle.borrow().get_var(&self.ctx, var);
+        let let_bound = match var.kind {
+            VariableKind::Local(id, _) => self.turtle.borrow().is_let_bound(id),
+            _ => false,
+        };
+        match (val, var.kind) {
+            (Some(val), _) => val,
+            (None, VariableKind::Local(id, _)) if let_bound && !self.in_cmd => {
+                let name = self.prog.symbols.get_index(id).unwrap().0.clone();
+                self.fail(RuntimeError::UndefinedVariable(name).attach_pos(var.pos))
+                    .await;
+                unreachable!("failed task is never resumed")
+            }
+            (None, VariableKind::Local(_, ty) | VariableKind::Global(_, ty)) => ty.default(),
+            (None, VariableKind::GlobalPreDef(_)) => unreachable!("always defined"),
+        }
+    }
+
+    async fn fail(&mut self, err: Pos<RuntimeError>) {
+        self.ctx.raise(err);
+        self.ret(DbgAction::Finished(false), true).await;
+        // never resume the failed task
+        std::future::pending::<()>().await;
+    }
+
     async fn ret(&mut self, act: DbgAction, cond: bool) {
         if cond {
             if let DbgAction::Finished(wait) = act {
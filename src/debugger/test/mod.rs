@@ -1,11 +1,29 @@
-use std::sync::mpsc;
+use std::{
+    cell::RefCell,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
-use crate::{features::FeatureConf, TProgram};
+use crate::{
+    features::{Feature, FeatureConf, FeatureState},
+    pos::{FilePos, Pos, Positionable as _},
+    tokens::Value,
+    TProgram, TurtleError,
+};
 
 use super::{
     config::RunConfig,
     interface::Strings,
-    window::{ChannelWindow, WindowCmd, WindowEvent},
+    window::{
+        CallbackWindow, ChannelWindow, LineStyle, NullWindow, RecordingWindow, StatsWindow, Window,
+        WindowCmd, WindowEvent,
+    },
+    Debugger, FrameResult, ProgEnd, RuntimeError, Session, SessionError, TColor, DEFAULT_MAX_DEPTH,
 };
 
 const TEST_SRC_CIRCLE: &str = "
@@ -81,8 +99,11 @@ fn debug_test(code: &str, inputs: &[TestInput<'_>]) -> Vec<TestResult> {
         }),
         dbg_tx,
     );
-    let cfg = RunConfig::new(&[]).debug_in(itf).window(window);
-    cfg.exec(&prog);
+    let cfg = RunConfig::new(&[])
+        .debug_in(itf)
+        .window(window)
+        .skip_delay(true);
+    cfg.exec(&prog).unwrap();
     collect_results();
     results
 }
@@ -102,9 +123,1528 @@ fn example_test() {
             TestResult::Window(WindowCmd::Draw(
                 (0.675, 0.0),
                 (0.6736678225654924, -0.05652076441067613),
-                (100.0, 100.0, 0.0)
+                TColor::new(100.0, 100.0, 0.0)
             )),
+            TestResult::Window(WindowCmd::Flush),
             TestResult::Stdout("executed 10 statements".to_string()),
         ]
     );
 }
+
+/// Runs frames until the program ends
+fn run_frames<'p, W: Window + 'p>(
+    dbg: &mut Debugger<'p, W>,
+) -> Result<FrameResult, Pos<RuntimeError>> {
+    loop {
+        match dbg.run_frame()? {
+            FrameResult::Next(_) => {}
+            end => return Ok(end),
+        }
+    }
+}
+
+#[test]
+fn stop_when_window_closed() {
+    let prog = TProgram::parse(
+        "begin while true do walk 1 done end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let (window, _cmds, events) = ChannelWindow::construct();
+    drop(events);
+    let mut dbg = Debugger::new(&prog, &[], window, false, Vec::new());
+    dbg.init_window();
+    assert_eq!(run_frames(&mut dbg), Ok(FrameResult::Exited));
+    // the backend might drop the commands first
+    let (window, cmds, _events) = ChannelWindow::construct();
+    drop(cmds);
+    let mut dbg = Debugger::new(&prog, &[], window, false, Vec::new());
+    dbg.init_window();
+    assert_eq!(run_frames(&mut dbg), Ok(FrameResult::Exited));
+}
+
+#[test]
+fn headless_run() {
+    let prog = TProgram::parse(
+        "begin store 0 in @delay do 10000 times walk 1 done end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let mut window = StatsWindow::new(NullWindow::default());
+    RunConfig::new(&[]).window(&mut window).exec(&prog).unwrap();
+    assert_eq!(window.draw_count(), 10000);
+    assert_eq!(window.line_length(), 10000.0);
+}
+
+#[test]
+fn split_pen_width() {
+    let prog = TProgram::parse(
+        "path other()
+           penwidth 2
+           walk 1
+           walk 1
+         endpath
+         begin
+           store 0 in @delay
+           split other()
+           walk 1
+           walk 1
+         end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let (window, cmds, _events) = ChannelWindow::construct();
+    RunConfig::new(&[]).window(window).exec(&prog).unwrap();
+    let line = |from, to| WindowCmd::Draw((from, 0.0), (to, 0.0), TColor::new(100.0, 100.0, 0.0));
+    assert_eq!(
+        cmds.try_iter()
+            .filter(|cmd| *cmd != WindowCmd::Flush)
+            .collect::<Vec<_>>(),
+        [
+            line(0.0, 0.05),
+            WindowCmd::SetPenWidth(0.1),
+            line(0.0, 0.05),
+            WindowCmd::SetPenWidth(0.0),
+            line(0.05, 0.1),
+            WindowCmd::SetPenWidth(0.1),
+            line(0.05, 0.1),
+        ]
+    );
+}
+
+#[test]
+fn split_order() {
+    let prog = TProgram::parse(
+        "path other()
+           direction 90
+           do 3 times walk 1 done
+         endpath
+         begin
+           split other()
+           do 3 times walk 2 done
+         end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let run = || {
+        let mut rec = RecordingWindow::new(NullWindow::default());
+        RunConfig::new(&[])
+            .window(&mut rec)
+            .skip_delay(true)
+            .exec(&prog)
+            .unwrap();
+        rec.into_log()
+            .into_iter()
+            .filter_map(|cmd| match cmd {
+                WindowCmd::Draw(_, (x, y), _) => Some((x.round(), y.round())),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let lines = run();
+    assert_eq!(
+        lines,
+        [
+            (2.0, 0.0),
+            (0.0, 1.0),
+            (4.0, 0.0),
+            (0.0, 2.0),
+            (6.0, 0.0),
+            (0.0, 3.0)
+        ]
+    );
+    assert_eq!(run(), lines);
+}
+
+#[test]
+fn pen_style() {
+    let prog = TProgram::parse(
+        "begin
+           store 0 in @delay
+           penstyle dashed
+           walk 1
+           penstyle dashed
+           walk 1
+           penstyle solid
+           walk 1
+         end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let line = |from, to| WindowCmd::Draw((from, 0.0), (to, 0.0), TColor::new(100.0, 100.0, 0.0));
+    for bytecode in [false, true] {
+        let (window, cmds, _events) = ChannelWindow::construct();
+        RunConfig::new(&[])
+            .window(window)
+            .bytecode(bytecode)
+            .exec(&prog)
+            .unwrap();
+        assert_eq!(
+            cmds.try_iter()
+                .filter(|cmd| *cmd != WindowCmd::Flush)
+                .collect::<Vec<_>>(),
+            [
+                WindowCmd::SetLineStyle(LineStyle::Dashed),
+                line(0.0, 0.05),
+                line(0.05, 0.1),
+                WindowCmd::SetLineStyle(LineStyle::Solid),
+                line(0.1, 0.15),
+            ]
+        );
+    }
+}
+
+#[test]
+fn show_turtle() {
+    let prog = TProgram::parse(
+        "begin
+           store 0 in @delay
+           walk 1
+           showturtle
+           walk 2
+           turn left 90
+           hideturtle
+           walk 1
+         end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    for bytecode in [false, true] {
+        let (window, cmds) = logging_window();
+        RunConfig::new(&[])
+            .window(window)
+            .bytecode(bytecode)
+            .exec(&prog)
+            .unwrap();
+        let cursor = cmds
+            .borrow()
+            .iter()
+            .filter_map(|cmd| match *cmd {
+                WindowCmd::SetTurtle((x, y), heading, visible) => {
+                    Some(((x.round(), y.round()), heading.round(), visible))
+                }
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cursor,
+            [
+                ((1.0, 0.0), 0.0, true),
+                ((3.0, 0.0), 0.0, true),
+                ((3.0, 0.0), 90.0, true),
+                ((3.0, 0.0), 90.0, false),
+            ]
+        );
+    }
+}
+
+#[test]
+fn palette() {
+    let prog = TProgram::parse(
+        "begin store 0 in @delay walk 1 color 100, 0, 0 walk 1 end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let (window, cmds) = logging_window();
+    let yellow = TColor::new(100.0, 100.0, 0.0);
+    RunConfig::new(&[])
+        .window(window)
+        .palette(vec![yellow])
+        .exec(&prog)
+        .unwrap();
+    assert_eq!(
+        cmds.borrow()
+            .iter()
+            .filter(|cmd| **cmd != WindowCmd::Flush)
+            .cloned()
+            .collect::<Vec<_>>(),
+        [
+            WindowCmd::SetPalette(vec![yellow]),
+            WindowCmd::DrawIndexed((0.0, 0.0), (1.0, 0.0), 0),
+            WindowCmd::Draw((1.0, 0.0), (2.0, 0.0), TColor::new(100.0, 0.0, 0.0)),
+        ]
+    );
+}
+
+#[test]
+fn frame() {
+    let prog = TProgram::parse(
+        "begin
+           store 0 in @delay
+           walk 1
+           frame
+             walk 1
+             walk 1
+           endframe
+           frame
+             walk 1
+             stop
+           endframe
+         end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let line = |from, to| WindowCmd::Draw((from, 0.0), (to, 0.0), TColor::new(100.0, 100.0, 0.0));
+    for bytecode in [false, true] {
+        let (window, cmds) = logging_window();
+        RunConfig::new(&[])
+            .window(window)
+            .bytecode(bytecode)
+            .keep_open(false)
+            .exec(&prog)
+            .unwrap();
+        assert_eq!(
+            cmds.borrow()
+                .iter()
+                .filter(|cmd| **cmd != WindowCmd::Flush)
+                .cloned()
+                .collect::<Vec<_>>(),
+            [
+                line(0.0, 1.0),
+                WindowCmd::BeginFrame,
+                line(1.0, 2.0),
+                line(2.0, 3.0),
+                WindowCmd::EndFrame,
+                // `stop` leaves the frame, which is still ended
+                WindowCmd::BeginFrame,
+                line(3.0, 4.0),
+                WindowCmd::EndFrame,
+            ]
+        );
+    }
+}
+
+#[test]
+fn pen_up_moves_silently() {
+    let prog = TProgram::parse(
+        "begin store 0 in @delay penup walk 100 pendown walk 1 end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let (window, cmds, _events) = ChannelWindow::construct();
+    RunConfig::new(&[]).window(window).exec(&prog).unwrap();
+    assert_eq!(
+        cmds.try_iter()
+            .filter(|cmd| *cmd != WindowCmd::Flush)
+            .collect::<Vec<_>>(),
+        [WindowCmd::Draw(
+            (5.0, 0.0),
+            (5.05, 0.0),
+            TColor::new(100.0, 100.0, 0.0)
+        )]
+    );
+}
+
+#[test]
+fn home_resets_turtle() {
+    let prog = TProgram::parse(
+        "begin walk 100 turn right 90 home end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let mut dbg = Debugger::new(&prog, &[], NullWindow::default(), true, Vec::new());
+    dbg.step_single().unwrap();
+    dbg.step_single().unwrap();
+    assert_eq!(dbg.position(), (100.0, 0.0));
+    assert_eq!(dbg.heading(), 270.0);
+    dbg.step_single().unwrap();
+    assert_eq!(dbg.position(), (0.0, 0.0));
+    assert_eq!(dbg.heading(), 0.0);
+}
+
+#[test]
+fn clearscreen_resets_turtle() {
+    let prog = TProgram::parse(
+        "begin walk 10 turn 90 penup color 0, 0, 100 clearscreen walk 1 clear walk 1 end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let (window, cmds, _events) = ChannelWindow::construct();
+    let mut dbg = Debugger::new(&prog, &[], window, true, Vec::new());
+    dbg.set_skip_delay(true);
+    for _ in 0..5 {
+        dbg.step_single().unwrap();
+    }
+    assert_eq!(dbg.position(), (0.0, 0.0));
+    assert_eq!(dbg.heading(), 0.0);
+    let drawn: Vec<_> = cmds.try_iter().filter(|c| *c != WindowCmd::Flush).collect();
+    assert_eq!(drawn.last(), Some(&WindowCmd::Clear));
+    // pen and color are back to their defaults
+    dbg.step_single().unwrap();
+    assert!(cmds
+        .try_iter()
+        .any(|c| matches!(c, WindowCmd::Draw(_, _, col) if col == super::START_COLOR)));
+    dbg.step_single().unwrap();
+    assert_eq!(dbg.position(), (1.0, 0.0));
+}
+
+#[test]
+fn absolute_moves() {
+    let prog = TProgram::parse(
+        "begin turn 30 walk to 10, 10 jump to 10, 0 walk 1 end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let mut dbg = Debugger::new(&prog, &[], NullWindow::default(), true, Vec::new());
+    dbg.step_single().unwrap();
+    dbg.step_single().unwrap();
+    assert_eq!(dbg.position(), (10.0, 10.0));
+    assert_eq!(dbg.heading(), 330.0);
+    dbg.step_single().unwrap();
+    assert_eq!(dbg.position(), (10.0, 0.0));
+    assert_eq!(
+        eval_all(
+            &mut dbg,
+            &["towards(0, 0)", "towards(10, 5)", "towards(20, -10)"]
+        ),
+        [180.0, 90.0, 315.0]
+    );
+}
+
+#[test]
+fn circle_returns_to_start() {
+    let prog = TProgram::parse(
+        "\" +feature shapes
+         begin jump 3 turn left 20 circle 5 circle left 0.3 arc 2, 90 walk 1 end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let mut dbg = Debugger::new(&prog, &[], NullWindow::default(), true, Vec::new());
+    dbg.step_single().unwrap();
+    dbg.step_single().unwrap();
+    for _ in 0..2 {
+        dbg.step_single().unwrap();
+        let (x, y) = dbg.position();
+        assert!(
+            (x - 3.0).abs() < 1e-9 && y.abs() < 1e-9,
+            "ended at ({x}, {y})"
+        );
+        assert!((dbg.heading() - 20.0).abs() < 1e-9);
+    }
+    // a single line along the chord
+    dbg.set_arc_resolution(0.0);
+    dbg.step_single().unwrap();
+    let chord = 2.0 * 2f64.sqrt();
+    let (x, y) = dbg.position();
+    assert!((x - 3.0 - chord * 25f64.to_radians().cos()).abs() < 1e-9);
+    assert!((y + chord * 25f64.to_radians().sin()).abs() < 1e-9);
+    assert!((dbg.heading() - 290.0).abs() < 1e-9);
+}
+
+#[test]
+fn huge_circle_is_bounded() {
+    let lines = |code: &str| {
+        let prog = TProgram::parse(code, false, FeatureConf::default()).unwrap();
+        let mut window = StatsWindow::new(NullWindow::default());
+        RunConfig::new(&[]).window(&mut window).exec(&prog).unwrap();
+        window.draw_count()
+    };
+    assert_eq!(lines("\" +feature shapes\nbegin circle 1e12 end"), 3600);
+    assert_eq!(
+        lines("\" +feature shapes\nbegin arc 1e12, 36000090 end"),
+        4500
+    );
+}
+
+fn transformed_lines(code: &str) -> Result<Vec<WindowCmd>, Pos<RuntimeError>> {
+    let prog = TProgram::parse(code, false, FeatureConf::default()).unwrap();
+    let (window, cmds, _events) = ChannelWindow::construct();
+    RunConfig::new(&[]).window(window).exec(&prog)?;
+    Ok(cmds
+        .try_iter()
+        .filter(|cmd| matches!(cmd, WindowCmd::Draw(..)))
+        .collect())
+}
+
+/// everything drawn by `code`, on the syntax tree and as bytecode
+fn both_paths(code: &str) -> [Result<Vec<WindowCmd>, Pos<RuntimeError>>; 2] {
+    let prog = TProgram::parse(code, false, FeatureConf::default()).unwrap();
+    [false, true].map(|bytecode| {
+        let (window, cmds, _events) = ChannelWindow::construct();
+        RunConfig::new(&[])
+            .window(window)
+            .seed(Some(7))
+            .skip_delay(true)
+            .bytecode(bytecode)
+            .exec(&prog)?;
+        Ok(cmds.try_iter().collect())
+    })
+}
+
+/// number of lines drawn with a color of their own
+fn draws(cmds: &[WindowCmd]) -> usize {
+    cmds.iter()
+        .filter(|cmd| matches!(cmd, WindowCmd::Draw(..)))
+        .count()
+}
+
+/// the text of all `Print` commands
+fn prints(cmds: impl IntoIterator<Item = WindowCmd>) -> Vec<String> {
+    cmds.into_iter()
+        .filter_map(|cmd| match cmd {
+            WindowCmd::Print(_, msg) => Some(msg),
+            _ => None,
+        })
+        .collect()
+}
+
+type CmdLog = Rc<RefCell<Vec<WindowCmd>>>;
+
+/// window without a thread, the commands it gets end up in the log
+fn logging_window() -> (CallbackWindow<impl FnMut(WindowCmd)>, CmdLog) {
+    let cmds = Rc::new(RefCell::new(Vec::new()));
+    let log = cmds.clone();
+    let (window, _events) = CallbackWindow::construct(move |cmd| log.borrow_mut().push(cmd));
+    (window, cmds)
+}
+
+#[test]
+fn bytecode_spiral() {
+    let code = include_str!("../../../examples/spirale.tg").replace("stop", "finish");
+    let [tree, bytecode] = both_paths(&code);
+    assert!(tree.as_ref().is_ok_and(|cmds| cmds.len() > 100));
+    assert_eq!(tree, bytecode);
+
+    let [tree, bytecode] = both_paths(
+        "calculation half(n)
+           store n / 2 in n
+         returns n
+         endcalc
+         begin
+           walk rand(1, 5)
+           do half(3) times walk 1 done
+         end",
+    );
+    assert_eq!(
+        tree,
+        Err(RuntimeError::NonIntegerCount(1.5).attach_pos(FilePos::line_col(7, 15)))
+    );
+    assert_eq!(tree, bytecode);
+}
+
+#[test]
+fn scale_transform() {
+    let col = TColor::new(100.0, 100.0, 0.0);
+    assert_eq!(
+        transformed_lines(
+            "\" +feature transforms
+             begin store 0 in @delay scale 2 walk 1 walk 1 end"
+        ),
+        Ok(vec![
+            WindowCmd::Draw((0.0, 0.0), (0.1, 0.0), col),
+            WindowCmd::Draw((0.1, 0.0), (0.2, 0.0), col),
+        ])
+    );
+}
+
+#[test]
+fn push_pop_transform() {
+    let col = TColor::new(100.0, 100.0, 0.0);
+    assert_eq!(
+        transformed_lines(
+            "\" +feature transforms
+             begin
+               store 0 in @delay
+               push translate 10, 0 rotate 90 walk 2 pop
+               walk 2
+             end"
+        ),
+        // the turtle itself moved to (2, 0)
+        Ok(vec![
+            WindowCmd::Draw((0.5, 0.0), (0.5, 2.0 / 15.0), col),
+            WindowCmd::Draw((0.1, 0.0), (0.2, 0.0), col),
+        ])
+    );
+    assert_eq!(
+        transformed_lines(
+            "\" +feature transforms
+             begin push pop pop end"
+        ),
+        Err(RuntimeError::UnmatchedPop.attach_pos(FilePos::line_col(2, 29)))
+    );
+}
+
+#[test]
+fn save_restore_state() {
+    let [tree, bytecode] = both_paths(
+        "path flag
+           savestate
+           penwidth 2
+           color 100, 0, 0
+           turn left 90
+           walk 1
+           penup
+           walk 5
+           restorestate
+         endpath
+         begin
+           walk 1
+           path flag
+           walk 1
+         end",
+    );
+    assert_eq!(tree, bytecode);
+    let yellow = TColor::new(100.0, 100.0, 0.0);
+    assert_eq!(
+        tree.map(|cmds| cmds
+            .into_iter()
+            .filter(|cmd| *cmd != WindowCmd::Flush)
+            .collect::<Vec<_>>()),
+        Ok(vec![
+            WindowCmd::Draw((0.0, 0.0), (0.05, 0.0), yellow),
+            WindowCmd::SetPenWidth(0.1),
+            WindowCmd::Draw(
+                (0.05, 0.0),
+                (0.05, 1.0 / 15.0),
+                TColor::new(100.0, 0.0, 0.0)
+            ),
+            // the caller's turtle goes on where it called the path
+            WindowCmd::SetPenWidth(0.0),
+            WindowCmd::Draw((0.05, 0.0), (0.1, 0.0), yellow),
+        ])
+    );
+
+    let [tree, bytecode] = both_paths("begin savestate restorestate restorestate end");
+    assert_eq!(
+        tree,
+        Err(RuntimeError::UnmatchedRestore.attach_pos(FilePos::line_col(1, 30)))
+    );
+    assert_eq!(tree, bytecode);
+}
+
+#[test]
+fn two_turtles() {
+    let [tree, bytecode] = both_paths(
+        "begin
+           walk 1
+           newturtle
+           color 100, 0, 0
+           penwidth 2
+           walk 2
+           setturtle 1
+           walk 1
+           setturtle 2
+           walk 1
+         end",
+    );
+    assert_eq!(tree, bytecode);
+    let yellow = TColor::new(100.0, 100.0, 0.0);
+    let red = TColor::new(100.0, 0.0, 0.0);
+    assert_eq!(
+        tree.map(|cmds| cmds
+            .into_iter()
+            .filter(|cmd| *cmd != WindowCmd::Flush)
+            .collect::<Vec<_>>()),
+        Ok(vec![
+            WindowCmd::Draw((0.0, 0.0), (0.05, 0.0), yellow),
+            // the new turtle starts at home
+            WindowCmd::SetPenWidth(0.1),
+            WindowCmd::Draw((0.0, 0.0), (0.1, 0.0), red),
+            WindowCmd::SetPenWidth(0.0),
+            WindowCmd::Draw((0.05, 0.0), (0.1, 0.0), yellow),
+            WindowCmd::SetPenWidth(0.1),
+            WindowCmd::Draw((0.1, 0.0), (0.15, 0.0), red),
+        ])
+    );
+
+    let [tree, bytecode] = both_paths("begin newturtle setturtle 3 end");
+    assert_eq!(
+        tree,
+        Err(RuntimeError::UnknownTurtle(3.0, 2).attach_pos(FilePos::line_col(1, 17)))
+    );
+    assert_eq!(tree, bytecode);
+}
+
+/// the delays a run waits through the window, with both interpreters
+fn delays(code: &str, skip: bool) -> [Vec<Duration>; 2] {
+    let prog = TProgram::parse(code, false, FeatureConf::default()).unwrap();
+    [false, true].map(|bytecode| {
+        let (window, cmds, _events) = ChannelWindow::construct();
+        RunConfig::new(&[])
+            .window(window)
+            .skip_delay(skip)
+            .bytecode(bytecode)
+            .exec(&prog)
+            .unwrap();
+        cmds.try_iter()
+            .filter_map(|cmd| match cmd {
+                WindowCmd::Delay(time) => Some(time),
+                _ => None,
+            })
+            .collect()
+    })
+}
+
+#[test]
+fn skip_delay() {
+    let [tree, bytecode] = delays(
+        "begin store 1000 in @delay do 5 times walk 1 done end",
+        true,
+    );
+    assert_eq!(tree, []);
+    assert_eq!(bytecode, []);
+}
+
+#[test]
+fn set_speed() {
+    // the fastest speed doesn't wait at all
+    let [tree, bytecode] = delays("begin setspeed 10 do 5 times walk 1 done end", false);
+    assert_eq!(tree, []);
+    assert_eq!(bytecode, []);
+    let [tree, bytecode] = delays(
+        "begin setspeed 9.1 walk 1 setspeed 100 walk 1 setspeed 8.2 walk 1 end",
+        false,
+    );
+    assert_eq!(
+        tree,
+        [Duration::from_millis(50), Duration::from_millis(100)]
+    );
+    assert_eq!(tree, bytecode);
+}
+
+#[test]
+fn step_positions() {
+    let prog = TProgram::parse(
+        "begin
+           walk 1
+           turn 90
+           walk 2
+         end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let mut dbg = Debugger::new(&prog, &[], NullWindow::default(), true, Vec::new());
+    assert_eq!(dbg.step().unwrap(), Some(FilePos::line_col(3, 12)));
+    assert_eq!(dbg.step().unwrap(), Some(FilePos::line_col(4, 12)));
+    assert_eq!(dbg.step().unwrap(), None);
+}
+
+const TEST_SRC_LINES: &str = "begin
+  walk 1
+
+  turn 90
+  walk 2
+end";
+
+#[test]
+fn line_breakpoint() {
+    let prog = TProgram::parse(TEST_SRC_LINES, false, FeatureConf::default()).unwrap();
+    let mut dbg = Debugger::new(&prog, &[], NullWindow::default(), true, Vec::new());
+    dbg.add_line_breakpoint(4);
+    assert_eq!(dbg.resume().unwrap().line, 4);
+    assert_eq!(dbg.heading(), 0.0);
+    assert_eq!(dbg.resume(), Err(ProgEnd::AllTurtlesFinished));
+}
+
+#[test]
+fn line_breakpoint_removed() {
+    let prog = TProgram::parse(TEST_SRC_LINES, false, FeatureConf::default()).unwrap();
+    let mut dbg = Debugger::new(&prog, &[], NullWindow::default(), true, Vec::new());
+    dbg.add_line_breakpoint(4);
+    dbg.remove_line_breakpoint(4);
+    assert_eq!(dbg.resume(), Err(ProgEnd::AllTurtlesFinished));
+}
+
+#[test]
+fn line_breakpoint_empty_line() {
+    let prog = TProgram::parse(TEST_SRC_LINES, false, FeatureConf::default()).unwrap();
+    let mut dbg = Debugger::new(&prog, &[], NullWindow::default(), true, Vec::new());
+    dbg.add_line_breakpoint(3);
+    assert_eq!(dbg.resume(), Err(ProgEnd::AllTurtlesFinished));
+}
+
+#[test]
+fn inspect_variables() {
+    let prog = TProgram::parse(
+        "begin store 5 in x store 2 in @size walk x end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let mut dbg = Debugger::new(&prog, &[], NullWindow::default(), true, Vec::new());
+    dbg.step().unwrap();
+    dbg.step().unwrap();
+    assert_eq!(
+        dbg.variables(),
+        [
+            ("x".to_string(), Value::Number(5.0)),
+            ("@size".to_string(), Value::Number(2.0)),
+        ]
+    );
+    assert_eq!(dbg.get_variable("x"), Some(Value::Number(5.0)));
+    assert_eq!(dbg.get_variable("@size"), Some(Value::Number(2.0)));
+    assert_eq!(dbg.get_variable("@delay"), Some(Value::Number(1.0)));
+    assert_eq!(dbg.get_variable("size"), None);
+}
+
+#[test]
+fn watch_expression() {
+    let prog = TProgram::parse(
+        "begin walk 1 store 5 in x add 2 to x walk x end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let mut dbg = Debugger::new(&prog, &[], NullWindow::default(), true, Vec::new());
+    let watch = dbg.add_watch("x + 1").unwrap();
+    let invalid = dbg.add_watch("not x").unwrap();
+    let values = |dbg: &mut Debugger<_>| {
+        dbg.watches()
+            .into_iter()
+            .map(|(id, val)| (id, val.ok()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        values(&mut dbg),
+        [(watch, Some(Value::Number(1.0))), (invalid, None)]
+    );
+    dbg.step().unwrap();
+    dbg.step().unwrap();
+    assert_eq!(values(&mut dbg)[0], (watch, Some(Value::Number(6.0))));
+    dbg.step().unwrap();
+    assert_eq!(values(&mut dbg)[0], (watch, Some(Value::Number(8.0))));
+}
+
+#[test]
+fn recursion_limit() {
+    let prog = TProgram::parse(
+        "path down()\n  walk 1\n  path down()\n  walk 1\nendpath\nbegin\n  path down()\nend",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let res = RunConfig::new(&[])
+        .window(NullWindow::default())
+        .max_depth(50)
+        .exec(&prog);
+    assert_eq!(
+        res,
+        Err(RuntimeError::RecursionLimit(50).attach_pos(FilePos::line_col(3, 3)))
+    );
+}
+
+#[test]
+fn default_recursion_limit() {
+    let prog = TProgram::parse(
+        "calculation down(n)\nreturns down(n + 1) + 1\nendcalc\nbegin\n  walk down(0)\nend",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let mut dbg = Debugger::new(&prog, &[], NullWindow::default(), false, Vec::new());
+    let err = dbg.run().unwrap_err();
+    assert_eq!(*err, RuntimeError::RecursionLimit(DEFAULT_MAX_DEPTH));
+}
+
+#[test]
+fn tail_recursion() {
+    // far deeper than the default depth limit
+    let [tree, bytecode] = both_paths(
+        "path spiral(n)
+           if n > 0 then
+             walk 1
+             turn 1
+             path spiral(n - 1)
+           endif
+         endpath
+         calculation count(n, acc)
+           if n < 1 then return acc endif
+         returns count(n - 1, acc + 1)
+         endcalc
+         begin
+           path spiral(count(30000, 0))
+         end",
+    );
+    assert_eq!(tree.as_deref().map(draws), Ok(30000));
+    assert_eq!(tree, bytecode);
+}
+
+#[test]
+fn step_limit() {
+    let prog = TProgram::parse(
+        "begin\n  while 1 = 1 do\n    walk 1\n  done\nend",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let (window, cmds, _events) = ChannelWindow::construct();
+    let res = RunConfig::new(&[])
+        .window(window)
+        .step_limit(Some(100))
+        .exec(&prog);
+    assert_eq!(
+        res,
+        Err(RuntimeError::StepLimitExceeded(100).attach_pos(FilePos::line_col(2, 3)))
+    );
+    let lines = draws(&cmds.try_iter().collect::<Vec<_>>());
+    // the loop statement and each further iteration take a step as well
+    assert_eq!(lines, 50);
+}
+
+#[test]
+fn step_limit_empty_loop() {
+    let prog = TProgram::parse(
+        "begin while 1 = 1 do done end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let res = RunConfig::new(&[])
+        .window(NullWindow::default())
+        .step_limit(Some(10))
+        .exec(&prog);
+    assert_eq!(
+        res.map_err(|err| err.into_inner()),
+        Err(RuntimeError::StepLimitExceeded(10))
+    );
+}
+
+#[test]
+fn timeout() {
+    let prog = TProgram::parse(
+        "begin\n  while 1 = 1 do\n    @n = 1\n  done\nend",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let timeout = Duration::from_millis(100);
+    for bytecode in [false, true] {
+        let start = Instant::now();
+        let res = RunConfig::new(&[])
+            .window(NullWindow::default())
+            .timeout(Some(timeout))
+            .bytecode(bytecode)
+            .exec(&prog);
+        let elapsed = start.elapsed();
+        assert_eq!(
+            res.map_err(|err| err.into_inner()),
+            Err(RuntimeError::Timeout(timeout))
+        );
+        assert!(elapsed >= timeout);
+        assert!(elapsed < timeout * 3, "took {elapsed:?}");
+    }
+
+    // far fewer statements than between two checks, but each waits
+    let prog = TProgram::parse(
+        "begin\n  store 100 in @delay\n  do 100 times\n    walk 1\n  done\nend",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    for bytecode in [false, true] {
+        let start = Instant::now();
+        let res = RunConfig::new(&[])
+            .window(NullWindow::default())
+            .timeout(Some(timeout))
+            .bytecode(bytecode)
+            .exec(&prog);
+        let elapsed = start.elapsed();
+        assert_eq!(
+            res,
+            Err(RuntimeError::Timeout(timeout).attach_pos(FilePos::line_col(4, 5)))
+        );
+        assert!(elapsed < timeout * 3, "took {elapsed:?}");
+    }
+}
+
+#[test]
+fn cancel() {
+    let prog = TProgram::parse(
+        "begin\n  while 1 = 1 do\n    walk 1\n    turn right 90\n  done\nend",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    for bytecode in [false, true] {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let (window, cmds, _events) = ChannelWindow::construct();
+        let stopper = {
+            let cancel = cancel.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(50));
+                cancel.store(true, Ordering::Relaxed);
+            })
+        };
+        let res = RunConfig::new(&[])
+            .window(window)
+            .skip_delay(true)
+            .cancel(Some(cancel))
+            .bytecode(bytecode)
+            .exec(&prog);
+        stopper.join().unwrap();
+        assert_eq!(
+            res.map_err(|err| err.into_inner()),
+            Err(RuntimeError::Cancelled)
+        );
+        // the window is gone with the run, so the drawing ends as well
+        assert!(cmds.iter().any(|cmd| matches!(cmd, WindowCmd::Draw(..))));
+    }
+}
+
+/// distance from home after each line, in turtle units
+fn run_walks(code: &str) -> Vec<f64> {
+    let prog = TProgram::parse(code, false, FeatureConf::default()).unwrap();
+    let (window, cmds, _events) = ChannelWindow::construct();
+    RunConfig::new(&[]).window(window).exec(&prog).unwrap();
+    cmds.try_iter()
+        .filter_map(|cmd| match cmd {
+            WindowCmd::Draw(_, to, _) => Some((to.0.hypot(to.1) * 20.0).round()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn do_times_repcount() {
+    assert!(run_walks("begin do 0 times walk 1 done end").is_empty());
+    assert!(run_walks("begin do -2 times walk 1 done end").is_empty());
+    assert_eq!(
+        run_walks("begin do 3 times walk @repcount done end"),
+        [1.0, 3.0, 6.0]
+    );
+}
+
+#[test]
+fn nested_repcount() {
+    assert_eq!(
+        run_walks(
+            "begin
+              do 2 times
+                do 2 times
+                  walk @repcount
+                done
+                walk 10 * @repcount
+              done
+              walk @repcount + 100
+            end"
+        ),
+        [1.0, 3.0, 13.0, 14.0, 16.0, 36.0, 136.0]
+    );
+}
+
+#[test]
+fn integer_counts() {
+    let [tree, bytecode] = both_paths("begin do 2 + 1 times walk 1 done end");
+    assert_eq!(tree.as_deref().map(draws), Ok(3));
+    assert_eq!(tree, bytecode);
+
+    let [tree, bytecode] = both_paths("begin do 6 / 4 * 2 times walk 1 done end");
+    assert_eq!(tree.as_deref().map(draws), Ok(3));
+    assert_eq!(tree, bytecode);
+
+    // whole, but too large to tell apart from its neighbours
+    let [tree, bytecode] = both_paths("begin do 2 ^ 60 times walk 1 done end");
+    assert_eq!(
+        tree,
+        Err(RuntimeError::NonIntegerCount(2f64.powi(60)).attach_pos(FilePos::line_col(1, 10)))
+    );
+    assert_eq!(tree, bytecode);
+}
+
+#[test]
+fn early_return() {
+    let [tree, bytecode] = both_paths(
+        "path steps(n)
+           do 10 times
+             frame
+               walk 1
+               if @repcount >= n then return endif
+             endframe
+           done
+           walk 100
+         endpath
+         begin path steps(3) walk 1 end",
+    );
+    assert_eq!(tree.as_deref().map(draws), Ok(4));
+    assert!(tree.as_ref().is_ok_and(|cmds| {
+        let begun = cmds.iter().filter(|cmd| **cmd == WindowCmd::BeginFrame);
+        let ended = cmds.iter().filter(|cmd| **cmd == WindowCmd::EndFrame);
+        begun.count() == 3 && ended.count() == 3
+    }));
+    assert_eq!(tree, bytecode);
+
+    let [tree, bytecode] = both_paths(
+        "calculation first_over(limit: num): num
+           foreach x in [1 5 9 12] do
+             do 2 times
+               if x > limit then return x * 10 endif
+             done
+           done
+         returns 0
+         endcalc
+         begin
+           do first_over(6) times walk 1 done
+           do first_over(20) + 1 times walk 1 done
+         end",
+    );
+    assert_eq!(tree.as_deref().map(draws), Ok(91));
+    assert_eq!(tree, bytecode);
+}
+
+#[test]
+fn non_integer_count() {
+    let prog = TProgram::parse(
+        "begin\n  do 2.5 times walk 1 done\nend",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let res = RunConfig::new(&[])
+        .window(NullWindow::default())
+        .exec(&prog);
+    assert_eq!(
+        res,
+        Err(RuntimeError::NonIntegerCount(2.5).attach_pos(FilePos::line_col(2, 6)))
+    );
+}
+
+#[test]
+fn square_path() {
+    let walks = run_walks(
+        "path square(size)
+          do 4 times
+            walk size
+            turn right 90
+          done
+        endpath
+        begin
+          path square(3)
+        end",
+    );
+    assert_eq!(walks.len(), 4);
+    assert_eq!(walks[0], 3.0);
+    // back home after the last side
+    assert_eq!(walks[3], 0.0);
+}
+
+#[test]
+fn path_arguments_scoped() {
+    assert_eq!(
+        run_walks(
+            "path side(len)
+              store 5 in x
+              walk len + x
+            endpath
+            begin
+              store 1 in len
+              store 100 in x
+              path side(2)
+              walk len + x
+            end"
+        ),
+        [7.0, 108.0]
+    );
+}
+
+#[test]
+fn if_branches() {
+    assert_eq!(
+        run_walks("begin store 2 in x if x >= 2 then walk 1 else walk 5 endif end"),
+        [1.0]
+    );
+    assert_eq!(
+        run_walks("begin store 2 in x if x != 2 then walk 1 else walk 5 endif end"),
+        [5.0]
+    );
+}
+
+#[test]
+fn comparison_type_mismatch() {
+    let mut features = FeatureConf::default();
+    features[Feature::Types] = FeatureState::Enabled;
+    let err = TProgram::parse(
+        "begin\n  if 'a' < 1 then\n    walk 1\n  endif\nend",
+        false,
+        features,
+    )
+    .unwrap_err();
+    match err {
+        TurtleError::TypeError(_, pos) | TurtleError::TypeErrorSpan(_, pos, _) => {
+            assert_eq!(pos.line, 2)
+        }
+        err => panic!("expected type error, got {err}"),
+    }
+}
+
+#[test]
+fn while_counter() {
+    assert_eq!(
+        run_walks(
+            "begin
+              store 0 in i
+              while i < 3 do
+                add 1 to i
+                walk i
+              done
+            end"
+        ),
+        [1.0, 3.0, 6.0]
+    );
+    assert!(run_walks("begin while 1 > 2 do walk 1 done end").is_empty());
+}
+
+fn eval_all(dbg: &mut Debugger<'_, NullWindow>, exprs: &[&str]) -> Vec<f64> {
+    exprs
+        .iter()
+        .map(|expr| dbg.eval_expr(None, expr).unwrap().num())
+        .collect()
+}
+
+#[test]
+fn math_builtins() {
+    let prog = TProgram::parse("begin walk 1 end", false, FeatureConf::default()).unwrap();
+    let mut dbg = Debugger::new(&prog, &[], NullWindow::default(), true, Vec::new());
+    assert_eq!(
+        eval_all(
+            &mut dbg,
+            &[
+                "sin(90)",
+                "cos(180)",
+                "sqrt(16)",
+                "abs(-2)",
+                "floor(2.5)",
+                "ceil(2.1)"
+            ]
+        ),
+        [1.0, -1.0, 4.0, 2.0, 2.0, 3.0]
+    );
+}
+
+#[test]
+fn seeded_random() {
+    let prog = TProgram::parse("begin walk 1 end", false, FeatureConf::default()).unwrap();
+    let mut dbg = Debugger::new(&prog, &[], NullWindow::default(), true, Vec::new());
+    let exprs = ["random(10)", "random(10)", "rand(5, 6)", "random(10)"];
+    dbg.set_seed(42);
+    let first = eval_all(&mut dbg, &exprs);
+    dbg.set_seed(42);
+    assert_eq!(eval_all(&mut dbg, &exprs), first);
+    assert!(first.iter().all(|x| (0.0..10.0).contains(x)));
+    assert!((5.0..6.0).contains(&first[2]));
+    assert_ne!(first[0], first[1]);
+}
+
+#[test]
+fn seeded_drawing() {
+    let prog = TProgram::parse(
+        "begin do 20 times walk random(5) turn rand(0, 360) done end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let draw = |seed| {
+        let (window, cmds, _events) = ChannelWindow::construct();
+        RunConfig::new(&[])
+            .window(window)
+            .seed(Some(seed))
+            .exec(&prog)
+            .unwrap();
+        cmds.try_iter().collect::<Vec<_>>()
+    };
+    assert_eq!(draw(3), draw(3));
+    assert_ne!(draw(3), draw(4));
+}
+
+#[test]
+fn let_and_assignment() {
+    assert_eq!(
+        run_walks("begin let x = 2 walk x x = x + 1 walk x @g = 1 walk @g end"),
+        [2.0, 5.0, 6.0]
+    );
+}
+
+#[test]
+fn let_shadows_in_block() {
+    assert_eq!(
+        run_walks(
+            "begin
+              let x = 1
+              if x = 1 then
+                let x = 5
+                walk x
+                x = 2
+              endif
+              walk x
+              repeat
+                let x = 3
+              until 1 = 1
+              walk x
+            end"
+        ),
+        [5.0, 6.0, 7.0]
+    );
+}
+
+#[test]
+fn undefined_variable() {
+    let prog = TProgram::parse(
+        "begin\n  let x = 1\n  if x = 1 then let y = 2 endif\n  walk x + y\nend",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let res = RunConfig::new(&[])
+        .window(NullWindow::default())
+        .exec(&prog);
+    assert_eq!(
+        res,
+        Err(RuntimeError::UndefinedVariable(String::from("y")).attach_pos(FilePos::line_col(4, 12)))
+    );
+
+    // without `let` locals start out as 0, like before
+    let [tree, bytecode] = both_paths("begin add 1 to x walk x end");
+    assert!(tree.is_ok());
+    assert_eq!(tree, bytecode);
+}
+
+#[test]
+fn print_concatenation() {
+    let prog = TProgram::parse(
+        "begin
+          store 3 in x
+          print 'x = ' x
+          print 'half: ' x / 2 ', third: ' x / 9 ' ' true
+          print 'a' + 'b'
+          y = 2
+        end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let (window, cmds, _events) = ChannelWindow::construct();
+    RunConfig::new(&[]).window(window).exec(&prog).unwrap();
+    let printed = prints(cmds.try_iter());
+    assert_eq!(printed, ["x = 3", "half: 1.5, third: 0.333333 true", "ab"]);
+}
+
+#[test]
+fn print_precision() {
+    let prog = TProgram::parse(
+        "begin
+          print '' 1 / 3 ' ' 4
+          setprecision 2
+          print '' 1 / 3 ' ' 4 ' ' 0.5 ' ' (-0.001)
+          setprecision 0
+          print '' 2.7
+          setprecision -1
+          print '' 1 / 3
+        end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    for bytecode in [false, true] {
+        let (window, cmds, _events) = ChannelWindow::construct();
+        RunConfig::new(&[])
+            .window(window)
+            .bytecode(bytecode)
+            .exec(&prog)
+            .unwrap();
+        let printed = prints(cmds.try_iter());
+        assert_eq!(
+            printed,
+            ["0.333333 4", "0.33 4 0.50 0.00", "3", "0.333333"],
+            "bytecode: {bytecode}"
+        );
+    }
+}
+
+#[test]
+fn print_any_type() {
+    let prog = TProgram::parse(
+        "begin
+          print 1 / 4
+          print 2 > 1
+          print 'text'
+        end",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    for bytecode in [false, true] {
+        let (window, cmds, _events) = ChannelWindow::construct();
+        RunConfig::new(&[])
+            .window(window)
+            .bytecode(bytecode)
+            .exec(&prog)
+            .unwrap();
+        let printed = prints(cmds.try_iter());
+        assert_eq!(printed, ["0.25", "true", "text"], "bytecode: {bytecode}");
+    }
+}
+
+#[test]
+fn lists() {
+    let [tree, bytecode] = both_paths(
+        "begin
+           let xs = [10 20 5 * 6]
+           let sum = 0
+           foreach x in xs do
+             add x to sum
+           done
+           foreach x in xs [ add x to sum ]
+           print xs
+           print xs[2] ' ' listlen(xs) ' ' sum
+           print xs [1]
+           print listlen([])
+         end",
+    );
+    let tree = tree.map(prints);
+    assert_eq!(
+        tree,
+        Ok(vec![
+            "[10 20 30]".to_string(),
+            "20 3 120".to_string(),
+            "[10 20 30][1]".to_string(),
+            "0".to_string(),
+        ])
+    );
+    assert_eq!(tree, bytecode.map(prints));
+
+    for (idx, err) in [
+        ("4", RuntimeError::IndexOutOfBounds(4.0, 3)),
+        ("0", RuntimeError::IndexOutOfBounds(0.0, 3)),
+        ("1.5", RuntimeError::NonIntegerIndex(1.5)),
+    ] {
+        let [tree, bytecode] = both_paths(&format!(
+            "begin
+               let xs = [1 2 3]
+               walk xs[{idx}]
+             end"
+        ));
+        assert_eq!(tree, Err(err.attach_pos(FilePos::line_col(3, 21))));
+        assert_eq!(tree, bytecode);
+    }
+}
+
+#[test]
+fn substr_clamps_range() {
+    let [tree, bytecode] = both_paths(
+        "begin
+           print substr('abc', 1, 10) '|' substr('abc', 2, 1) '|' substr('abc', 5, 7)
+           print substr('héllo', 0, 2) ' ' strlen('héllo')
+         end",
+    )
+    .map(|res| res.map(prints));
+    assert_eq!(tree, Ok(vec!["bc||".to_string(), "hé 5".to_string()]));
+    assert_eq!(tree, bytecode);
+}
+
+#[test]
+fn run_str_null_window() {
+    let mut window = NullWindow::default();
+    crate::run_str(
+        "begin\n  walk 10\n  turn right 90\n  walk 5\nend\n",
+        &mut window,
+    )
+    .unwrap();
+    assert_eq!(window, (20.0, 15.0));
+}
+
+#[test]
+fn run_str_compile_error() {
+    let errs = crate::run_str(
+        "begin\n  walk )\n  turn *\nend\n",
+        &mut NullWindow::default(),
+    )
+    .unwrap_err();
+    let found: Vec<_> = errs.iter().map(|e| e.get_pos()).collect();
+    assert_eq!(found, [FilePos::line_col(2, 8), FilePos::line_col(3, 8)]);
+    assert!(errs
+        .iter()
+        .all(|e| matches!(**e, crate::RunError::Compile(crate::CompileError::Parse(_)))));
+
+    let errs = crate::run_str(
+        "begin\n  do 1.5 times walk 1 done\nend\n",
+        &mut NullWindow::default(),
+    )
+    .unwrap_err();
+    assert!(matches!(
+        errs[..],
+        [ref err] if matches!(**err, crate::RunError::Runtime(RuntimeError::NonIntegerCount(_)))
+    ));
+}
+
+#[test]
+fn session_keeps_variables() {
+    let prog = TProgram::parse("begin end", false, FeatureConf::default()).unwrap();
+    let mut session = Session::new(&prog, NullWindow::default());
+    session.feed("store 5 in x").unwrap();
+    session.feed("walk x * 2").unwrap();
+    session.feed("turn left 90").unwrap();
+    session.feed("walk x").unwrap();
+    let dbg = session.debugger();
+    assert_eq!(dbg.position(), (10.0, 5.0));
+    assert_eq!(dbg.heading(), 90.0);
+}
+
+#[test]
+fn session_incomplete_input() {
+    let prog = TProgram::parse("begin end", false, FeatureConf::default()).unwrap();
+    let mut session = Session::new(&prog, NullWindow::default());
+    let need_more = |res: Result<(), Pos<SessionError>>| {
+        matches!(
+            res.map_err(Pos::into_inner),
+            Err(SessionError::NeedMoreInput)
+        )
+    };
+    assert!(need_more(session.feed("do 3 times")));
+    assert!(need_more(session.feed("  walk 10")));
+    session.feed("done").unwrap();
+    assert!(need_more(session.feed("calculation twice(n)")));
+    session.feed("returns 2 * n endcalc").unwrap();
+    session.feed("walk twice(5)").unwrap();
+    assert_eq!(session.debugger().position(), (40.0, 0.0));
+
+    let err = session.feed("walk )").unwrap_err();
+    assert!(matches!(*err, SessionError::Debug(_)));
+    session.feed("walk 1").unwrap();
+    assert_eq!(session.debugger().position(), (41.0, 0.0));
+}
+
+#[test]
+fn frames_without_threads() {
+    let prog = TProgram::parse(
+        "begin\n  store 50 in @delay\n  do 3 times walk 1 done\n  stop\nend",
+        false,
+        FeatureConf::default(),
+    )
+    .unwrap();
+    let (window, cmds) = logging_window();
+    let mut dbg = Debugger::new(&prog, &[], window, false, Vec::new());
+    dbg.init_window();
+    let mut frames = Vec::new();
+    loop {
+        match dbg.run_frame().unwrap() {
+            FrameResult::Next(delay) => frames.push(delay),
+            end => {
+                assert_eq!(end, FrameResult::Finished(true));
+                break;
+            }
+        }
+    }
+    assert_eq!(frames, [50, 50, 50]);
+    assert_eq!(draws(&cmds.borrow()), 3);
+    assert_eq!(dbg.run_frame(), Ok(FrameResult::Finished(true)));
+
+    let prog = TProgram::parse("begin walk 1 walk 1 end", false, FeatureConf::default()).unwrap();
+    let (window, events) = CallbackWindow::construct(|_| {});
+    let mut dbg = Debugger::new(&prog, &[], window, false, Vec::new());
+    dbg.init_window();
+    events.borrow_mut().push_back(WindowEvent::WindowExited);
+    assert_eq!(dbg.run_frame(), Ok(FrameResult::Exited));
+}
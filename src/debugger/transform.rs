@@ -0,0 +1,60 @@
+use std::f64::consts::PI;
+
+use super::TCoord;
+
+/// Affine map from turtle to window coordinates.
+///
+/// `[a, b, c, d, e, f]` maps `(x, y)` to `(a * x + c * y + e, b * x + d * y + f)`.
+/// Every operation acts in the coordinates already transformed, like in Logo.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform([f64; 6]);
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    pub fn apply(&self, (x, y): TCoord) -> TCoord {
+        let [a, b, c, d, e, f] = self.0;
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    /// Heading in degrees of a turtle facing `dir` once it is transformed
+    pub fn apply_dir(&self, dir: f64) -> f64 {
+        let [a, b, c, d, _, _] = self.0;
+        let (sin, cos) = (dir * PI / 180.0).sin_cos();
+        (b * cos + d * sin).atan2(a * cos + c * sin) * 180.0 / PI
+    }
+
+    /// `inner` first, then `self`
+    fn then(self, inner: Self) -> Self {
+        let [a, b, c, d, e, f] = self.0;
+        let [ia, ib, ic, id, ie, if_] = inner.0;
+        Self([
+            a * ia + c * ib,
+            b * ia + d * ib,
+            a * ic + c * id,
+            b * ic + d * id,
+            a * ie + c * if_ + e,
+            b * ie + d * if_ + f,
+        ])
+    }
+
+    pub fn scale(self, x: f64, y: f64) -> Self {
+        self.then(Self([x, 0.0, 0.0, y, 0.0, 0.0]))
+    }
+
+    pub fn translate(self, x: f64, y: f64) -> Self {
+        self.then(Self([1.0, 0.0, 0.0, 1.0, x, y]))
+    }
+
+    /// counter-clockwise, like headings
+    pub fn rotate(self, angle: f64) -> Self {
+        let (sin, cos) = (angle * PI / 180.0).sin_cos();
+        self.then(Self([cos, sin, -sin, cos, 0.0, 0.0]))
+    }
+}
@@ -6,7 +6,12 @@ use crate::{
     SymbolTable,
 };
 
-use super::{varlist::VarList, window::Window, GlobalCtx, TColor, TCoord};
+use super::{
+    transform::Transform,
+    varlist::VarList,
+    window::{LineStyle, Window, DEFAULT_PEN_WIDTH},
+    GlobalCtx, TColor, TCoord, MAX_CIRCLE_SEGMENTS,
+};
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum FuncType {
@@ -53,6 +58,31 @@ pub struct StackFrame {
     pub func: FuncType,
     pub vars: VarList,
     pub curr_pos: FilePos,
+    /// iteration of the innermost `do .. times` loop, starting at 1
+    pub repcount: usize,
+}
+
+/// What `savestate` keeps of the turtle, and what every turtle of `newturtle` has on its own
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TurtleState {
+    pos: TCoord,
+    dir: f64,
+    col: TColor,
+    pen_width: f64,
+    line_style: LineStyle,
+    pen_down: bool,
+}
+
+impl TurtleState {
+    /// a new turtle
+    const HOME: Self = Self {
+        pos: (0.0, 0.0),
+        dir: 0.0,
+        col: super::START_COLOR,
+        pen_width: DEFAULT_PEN_WIDTH,
+        line_style: LineStyle::Solid,
+        pen_down: true,
+    };
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -62,6 +92,23 @@ pub struct Turtle {
     pub stack: Vec<StackFrame>,
     marks: Vec<(TCoord, f64)>,
     col: TColor,
+    pub pen_width: f64,
+    pub line_style: LineStyle,
+    pub pen_down: bool,
+    /// shown by the window as cursor, see [`Window::set_turtle`]
+    visible: bool,
+    /// applied to everything drawn, the turtle moves in untransformed coordinates
+    pub transform: Transform,
+    /// saved by `push`
+    transforms: Vec<Transform>,
+    /// saved by `savestate`
+    states: Vec<TurtleState>,
+    /// every turtle made by `newturtle`, the entry of the active one is stale
+    ///
+    /// Marks, transforms and saved states are shared by all of them.
+    turtles: Vec<TurtleState>,
+    /// index into `turtles`
+    active: usize,
 }
 
 impl Turtle {
@@ -72,6 +119,15 @@ impl Turtle {
             stack: vec![StackFrame::default()],
             marks: Vec::new(),
             col: super::START_COLOR,
+            pen_width: DEFAULT_PEN_WIDTH,
+            line_style: LineStyle::Solid,
+            pen_down: true,
+            visible: false,
+            transform: Transform::IDENTITY,
+            transforms: Vec::new(),
+            states: Vec::new(),
+            turtles: vec![TurtleState::HOME],
+            active: 0,
         }
     }
 
@@ -82,9 +138,22 @@ impl Turtle {
             stack: vec![StackFrame::default()],
             marks: self.marks.clone(),
             col: self.col,
+            pen_width: self.pen_width,
+            line_style: self.line_style,
+            pen_down: self.pen_down,
+            visible: self.visible,
+            transform: self.transform,
+            transforms: self.transforms.clone(),
+            states: self.states.clone(),
+            turtles: self.turtles.clone(),
+            active: self.active,
         }
     }
 
+    pub fn pos(&self) -> TCoord {
+        self.pos
+    }
+
     pub fn move_dist(&mut self, ctx: &GlobalCtx<impl Window>, dist: f64, back: bool, draw: bool) {
         let dir = if back { self.dir + 180.0 } else { self.dir };
         let next_pos = (
@@ -94,16 +163,158 @@ impl Turtle {
         self.move_to(ctx, next_pos, draw);
     }
 
+    /// Walk along a circle, turning counter-clockwise by `angle` degrees
+    ///
+    /// The arc is drawn as lines, their number set by the arc resolution and at most
+    /// [`MAX_CIRCLE_SEGMENTS`] per full turn. Beyond one turn the circle is drawn only once
+    /// before the rest of the angle.
+    pub fn move_arc(&mut self, ctx: &GlobalCtx<impl Window>, radius: f64, angle: f64) {
+        let drawn = if angle.abs() > 360.0 {
+            360f64.copysign(angle) + angle % 360.0
+        } else {
+            angle
+        };
+        let length = radius.abs() * drawn.abs() * PI / 180.0;
+        let max_segments = (MAX_CIRCLE_SEGMENTS * drawn.abs() / 360.0).ceil().max(1.0);
+        let segments = (length * ctx.arc_resolution.get())
+            .ceil()
+            .clamp(1.0, max_segments) as usize;
+        // direction from the turtle to the center of the circle
+        let side = self.dir + 90f64.copysign(angle);
+        let center = (
+            self.pos.0 + (side * PI / 180.0).cos() * radius,
+            self.pos.1 + (side * PI / 180.0).sin() * radius,
+        );
+        let start = side + 180.0;
+        for i in 1..=segments {
+            let a = (start + drawn * i as f64 / segments as f64) * PI / 180.0;
+            let next_pos = (center.0 + a.cos() * radius, center.1 + a.sin() * radius);
+            self.move_to(ctx, next_pos, true);
+        }
+        self.set_dir(self.dir + angle);
+        self.show(ctx);
+    }
+
     pub fn move_home(&mut self, ctx: &GlobalCtx<impl Window>, draw: bool) {
         self.dir = 0.0;
         self.move_to(ctx, (0.0, 0.0), draw);
     }
 
-    fn move_to(&mut self, ctx: &GlobalCtx<impl Window>, to: (f64, f64), draw: bool) {
-        if draw {
-            ctx.window.borrow_mut().draw(self.pos, to, self.col);
+    pub fn move_to(&mut self, ctx: &GlobalCtx<impl Window>, to: (f64, f64), draw: bool) {
+        if draw && self.pen_down {
+            ctx.use_pen_width(self.pen_width);
+            ctx.use_line_style(self.line_style);
+            let (from, to) = (self.transform.apply(self.pos), self.transform.apply(to));
+            ctx.window.borrow_mut().draw(from, to, self.col);
         }
         self.pos = to;
+        self.show(ctx);
+    }
+
+    /// Move the window's cursor to the turtle, if it is visible
+    pub fn show(&self, ctx: &GlobalCtx<impl Window>) {
+        if self.visible {
+            self.send_cursor(ctx);
+        }
+    }
+
+    pub fn set_visible(&mut self, ctx: &GlobalCtx<impl Window>, visible: bool) {
+        self.visible = visible;
+        self.send_cursor(ctx);
+    }
+
+    fn send_cursor(&self, ctx: &GlobalCtx<impl Window>) {
+        ctx.window.borrow_mut().set_turtle(
+            self.transform.apply(self.pos),
+            self.transform.apply_dir(self.dir),
+            self.visible,
+        );
+    }
+
+    /// Home with the pen, color and transform of a new turtle, marks and visibility are kept
+    pub fn reset(&mut self) {
+        self.transform = Transform::IDENTITY;
+        self.transforms.clear();
+        self.states.clear();
+        self.load(TurtleState::HOME);
+    }
+
+    pub fn push_transform(&mut self) {
+        self.transforms.push(self.transform);
+    }
+
+    /// `false` if there was no saved transform
+    pub fn pop_transform(&mut self) -> bool {
+        match self.transforms.pop() {
+            Some(t) => {
+                self.transform = t;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn state(&self) -> TurtleState {
+        TurtleState {
+            pos: self.pos,
+            dir: self.dir,
+            col: self.col,
+            pen_width: self.pen_width,
+            line_style: self.line_style,
+            pen_down: self.pen_down,
+        }
+    }
+
+    fn load(&mut self, state: TurtleState) {
+        self.pos = state.pos;
+        self.dir = state.dir;
+        self.col = state.col;
+        self.pen_width = state.pen_width;
+        self.line_style = state.line_style;
+        self.pen_down = state.pen_down;
+    }
+
+    pub fn save_state(&mut self) {
+        self.states.push(self.state());
+    }
+
+    /// Jump back to the last saved state, `false` if there was none
+    pub fn restore_state(&mut self, ctx: &GlobalCtx<impl Window>) -> bool {
+        let Some(state) = self.states.pop() else {
+            return false;
+        };
+        self.load(state);
+        self.show(ctx);
+        true
+    }
+
+    /// Number of turtles, the first one exists from the start
+    pub fn turtle_count(&self) -> usize {
+        self.turtles.len()
+    }
+
+    /// Add a turtle at home with a fresh pen and make it the active one
+    pub fn new_turtle(&mut self, ctx: &GlobalCtx<impl Window>) {
+        self.turtles[self.active] = self.state();
+        self.turtles.push(TurtleState::HOME);
+        self.active = self.turtles.len() - 1;
+        self.load(TurtleState::HOME);
+        self.show(ctx);
+    }
+
+    /// Make turtle `id` the active one, counting from 1, `false` if there is no such turtle
+    pub fn set_turtle(&mut self, ctx: &GlobalCtx<impl Window>, id: i64) -> bool {
+        let Some(idx) = usize::try_from(id - 1)
+            .ok()
+            .filter(|idx| *idx < self.turtles.len())
+        else {
+            return false;
+        };
+        self.turtles[self.active] = self.state();
+        self.active = idx;
+        self.load(self.turtles[idx]);
+        self.show(ctx);
+        true
     }
 
     pub fn new_mark(&mut self) {
@@ -116,28 +327,29 @@ impl Turtle {
         self.move_to(ctx, mark.0, draw);
     }
 
-    pub fn get_var(&mut self, ctx: &GlobalCtx<impl Window>, var: &Variable) -> Value {
-        match &var.kind {
-            VariableKind::Local(id, ty) => self
-                .stack
-                .last_mut()
-                .unwrap()
-                .vars
-                .get_var(*id, *ty)
-                .clone(),
-            VariableKind::Global(id, ty) => ctx.vars.borrow_mut().get_var(*id, *ty).clone(),
+    /// `None` if the variable wasn't set yet
+    pub fn get_var(&self, ctx: &GlobalCtx<impl Window>, var: &Variable) -> Option<Value> {
+        Some(match &var.kind {
+            VariableKind::Local(id, _) => self.stack.last().unwrap().vars.get_var(*id)?.clone(),
+            VariableKind::Global(id, _) => ctx.vars.borrow().get_var(*id)?.clone(),
             VariableKind::GlobalPreDef(pdv) => Value::Number(match pdv {
                 PredefVar::Dir => self.dir,
                 PredefVar::Dist => (self.pos.0 * self.pos.0 + self.pos.1 * self.pos.1).sqrt(),
                 PredefVar::X => self.pos.0,
                 PredefVar::Y => self.pos.1,
                 PredefVar::Pi => PI,
-                PredefVar::Red => self.col.0,
-                PredefVar::Green => self.col.1,
-                PredefVar::Blue => self.col.2,
-                _ => return ctx.get_var(*pdv),
+                PredefVar::Red => self.col.r,
+                PredefVar::Green => self.col.g,
+                PredefVar::Blue => self.col.b,
+                PredefVar::RepCount => self.stack.last().unwrap().repcount as f64,
+                _ => return Some(ctx.get_var(*pdv)),
             }),
-        }
+        })
+    }
+
+    /// Whether the local `id` of the running function was declared by `let`
+    pub fn is_let_bound(&self, id: usize) -> bool {
+        self.stack.last().unwrap().vars.was_declared(id)
     }
 
     pub fn set_var(&mut self, ctx: &GlobalCtx<impl Window>, var: &Variable, val: Value) {
@@ -146,24 +358,37 @@ impl Turtle {
             VariableKind::Global(id, _) => ctx.vars.borrow_mut().set_var(*id, val),
             VariableKind::GlobalPreDef(pdv) => match pdv {
                 PredefVar::Red => {
-                    self.col.0 = val.num();
+                    self.col.r = val.num();
                 }
                 PredefVar::Green => {
-                    self.col.1 = val.num();
+                    self.col.g = val.num();
                 }
                 PredefVar::Blue => {
-                    self.col.2 = val.num();
+                    self.col.b = val.num();
                 }
                 _ => ctx.set_var(*pdv, val),
             },
         }
     }
 
+    /// `let` binding of a local in the innermost scope of the current frame
+    pub fn declare_var(&mut self, id: usize, val: Value) {
+        self.stack.last_mut().unwrap().vars.declare_var(id, val);
+    }
+
+    pub fn push_scope(&mut self) {
+        self.stack.last_mut().unwrap().vars.push_scope();
+    }
+
+    pub fn pop_scope(&mut self) {
+        self.stack.last_mut().unwrap().vars.pop_scope();
+    }
+
     pub fn set_dir(&mut self, dir: f64) {
         self.dir = dir.rem_euclid(360.0);
     }
 
     pub fn set_col(&mut self, r: f64, g: f64, b: f64) {
-        self.col = (r, g, b);
+        self.col = TColor::new(r, g, b);
     }
 }
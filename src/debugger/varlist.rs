@@ -1,37 +1,85 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::tokens::{ValType, Value};
+use crate::tokens::Value;
 
-#[derive(Debug, Default, Clone, PartialEq)]
+/// Variables of one stack frame or the global scope.
+///
+/// Nested blocks push an inner scope, which shadows the outer ones and is
+/// dropped again when the block is left.
+#[derive(Debug, Clone, PartialEq)]
 pub struct VarList {
-    vars: HashMap<usize, Value>,
+    scopes: Vec<HashMap<usize, Value>>,
+    /// variables ever declared by `let`, even if their scope was dropped
+    declared: HashSet<usize>,
+}
+
+impl Default for VarList {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl VarList {
     pub fn new() -> Self {
         Self {
-            vars: HashMap::new(),
+            scopes: vec![HashMap::new()],
+            declared: HashSet::new(),
         }
     }
 
-    pub fn get_var(&mut self, id: usize, ty: ValType) -> &Value {
-        self.vars.entry(id).or_insert_with(|| ty.default())
+    /// Innermost visible value, `None` if the variable was never set
+    pub fn get_var(&self, id: usize) -> Option<&Value> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(&id))
     }
 
+    /// Overwrite the innermost visible variable, or create it in the outermost scope
     pub fn set_var(&mut self, id: usize, val: Value) {
-        self.vars.insert(id, val);
+        let scope = match self
+            .scopes
+            .iter()
+            .rposition(|scope| scope.contains_key(&id))
+        {
+            Some(idx) => &mut self.scopes[idx],
+            None => &mut self.scopes[0],
+        };
+        scope.insert(id, val);
     }
 
-    pub fn iter(&self) -> <&VarList as IntoIterator>::IntoIter {
-        self.into_iter()
+    /// Create the variable in the innermost scope, shadowing outer ones
+    pub fn declare_var(&mut self, id: usize, val: Value) {
+        self.declared.insert(id);
+        self.scopes
+            .last_mut()
+            .expect("outermost scope is never popped")
+            .insert(id, val);
+    }
+
+    /// Whether `let` declared the variable, reading it unset is an error then
+    pub fn was_declared(&self, id: usize) -> bool {
+        self.declared.contains(&id)
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
     }
-}
 
-impl<'a> IntoIterator for &'a VarList {
-    type Item = (&'a usize, &'a Value);
-    type IntoIter = std::collections::hash_map::Iter<'a, usize, Value>;
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.vars.iter()
+    /// Visible variables, shadowed ones are skipped
+    pub fn iter(&self) -> impl Iterator<Item = (&usize, &Value)> {
+        self.scopes
+            .iter()
+            .enumerate()
+            .flat_map(move |(depth, scope)| {
+                scope.iter().filter(move |(id, _)| {
+                    !self.scopes[depth + 1..]
+                        .iter()
+                        .any(|inner| inner.contains_key(id))
+                })
+            })
     }
 }
@@ -0,0 +1,409 @@
+use crate::{
+    pos::Positionable as _,
+    prog::bytecode::{Bytecode, Op},
+    tokens::{Value, Variable, VariableKind},
+    SymbolTable,
+};
+
+use super::{
+    turtle::{FuncType, StackFrame, Turtle},
+    varlist::VarList,
+    window::{Window, WindowEvent},
+    GlobalCtx, ProgEnd, RuntimeError,
+};
+
+/// Runs [`Bytecode`] for a single turtle, mirroring [`TurtleTask`](super::task::TurtleTask)
+pub(super) struct Vm<'c, W> {
+    code: &'c Bytecode,
+    ctx: &'c GlobalCtx<W>,
+    /// names for errors
+    symbols: &'c SymbolTable,
+    turtle: Turtle,
+    stack: Vec<Value>,
+    calls: Vec<Call>,
+    pc: usize,
+}
+
+/// A running path or calculation, what a `return` has to restore
+struct Call {
+    ret_addr: usize,
+    /// operand stack without the arguments
+    height: usize,
+    /// window frames begun before the call
+    frames: usize,
+    has_value: bool,
+}
+
+impl<'c, W: Window> Vm<'c, W> {
+    pub fn new(code: &'c Bytecode, ctx: &'c GlobalCtx<W>, symbols: &'c SymbolTable) -> Self {
+        Self {
+            code,
+            ctx,
+            symbols,
+            turtle: Turtle::new(),
+            stack: Vec::new(),
+            calls: Vec::new(),
+            pc: 0,
+        }
+    }
+
+    /// Runs until the program ends, which is always an `Err`
+    pub fn run(&mut self) -> Result<(), ProgEnd> {
+        loop {
+            let op = &self.code.ops[self.pc];
+            self.pc += 1;
+            self.exec(op)?;
+        }
+    }
+
+    fn exec(&mut self, op: &'c Op) -> Result<(), ProgEnd> {
+        match op {
+            Op::Push(val) => self.stack.push(val.clone()),
+            Op::Load(var) => {
+                let val = self.read_var(var)?;
+                self.stack.push(val);
+            }
+            Op::Store(var) => {
+                let val = self.pop();
+                self.turtle.set_var(self.ctx, var, val);
+            }
+            Op::Declare(id) => {
+                let val = self.pop();
+                self.turtle.declare_var(*id, val);
+            }
+            Op::Binary(op) => {
+                let rhs = self.pop();
+                let lhs = self.pop();
+                match op.try_eval(&lhs, &rhs) {
+                    Ok(val) => self.stack.push(val),
+                    Err(why) => return self.fail(RuntimeError::Type(why)),
+                }
+            }
+            Op::Unary(op) => {
+                let val = self.pop();
+                match op.try_eval(&val) {
+                    Ok(val) => self.stack.push(val),
+                    Err(why) => return self.fail(RuntimeError::Type(why)),
+                }
+            }
+            Op::MakeList(len) => {
+                let items = self.stack.split_off(self.stack.len() - len);
+                let items = items.iter().map(Value::num).collect();
+                self.stack.push(Value::List(items));
+            }
+            Op::Index => {
+                let idx = self.pop();
+                let list = self.pop();
+                match list.item(&idx) {
+                    Some(val) => self.stack.push(Value::Number(val)),
+                    None => return self.fail(RuntimeError::bad_index(&list, &idx)),
+                }
+            }
+            Op::Abs => {
+                let val = self.pop_num();
+                self.stack.push(Value::Number(val.abs()));
+            }
+            Op::Convert(to) => {
+                let val = self.pop();
+                self.stack.push(self.ctx.convert(&val, *to));
+            }
+            Op::Func(pdf, argc) => {
+                let args = self.stack.split_off(self.stack.len() - argc);
+                let val = pdf.eval(&args, &mut self.ctx.rng.borrow_mut(), self.turtle.pos());
+                self.stack.push(val);
+            }
+            Op::Call(idx) => {
+                let func = &self.code.funcs[*idx];
+                let args = self.stack.split_off(self.stack.len() - func.args.len());
+                let max = self.ctx.max_depth.get();
+                if self.turtle.stack.len() > max {
+                    return self.fail(RuntimeError::RecursionLimit(max));
+                }
+                let mut vars = VarList::new();
+                for (&id, arg) in func.args.iter().zip(args) {
+                    vars.set_var(id, arg);
+                }
+                self.turtle.stack.push(StackFrame {
+                    vars,
+                    func: func.kind,
+                    curr_pos: self.code.pos[func.entry],
+                    repcount: 0,
+                });
+                self.calls.push(Call {
+                    ret_addr: self.pc,
+                    height: self.stack.len(),
+                    frames: self.ctx.frames.get(),
+                    has_value: matches!(func.kind, FuncType::Calc(_)),
+                });
+                self.pc = func.entry;
+            }
+            Op::Return => {
+                let call = self.calls.pop().expect("return outside of a call");
+                // a `return` inside loops leaves their counters behind
+                let val = call.has_value.then(|| self.pop());
+                self.stack.truncate(call.height);
+                self.stack.extend(val);
+                while self.ctx.frames.get() > call.frames {
+                    self.ctx.end_frame();
+                }
+                self.turtle.stack.pop();
+                self.pc = call.ret_addr;
+            }
+            Op::TailCall(idx) => {
+                let func = &self.code.funcs[*idx];
+                let args = self.stack.split_off(self.stack.len() - func.args.len());
+                let call = self.calls.last_mut().expect("tail call outside of a call");
+                self.stack.truncate(call.height);
+                call.has_value = matches!(func.kind, FuncType::Calc(_));
+                let mut vars = VarList::new();
+                for (&id, arg) in func.args.iter().zip(args) {
+                    vars.set_var(id, arg);
+                }
+                *self.turtle.stack.last_mut().unwrap() = StackFrame {
+                    vars,
+                    func: func.kind,
+                    curr_pos: self.code.pos[func.entry],
+                    repcount: 0,
+                };
+                self.pc = func.entry;
+            }
+            Op::Jump(to) => self.pc = *to,
+            Op::JumpIf(cond, to) => {
+                if self.pop().bool() == *cond {
+                    self.pc = *to;
+                }
+            }
+            Op::EnterScope => self.turtle.push_scope(),
+            Op::LeaveScope => self.turtle.pop_scope(),
+            Op::Step => {
+                if let Err(why) = self.ctx.count_step() {
+                    return self.fail(why);
+                }
+            }
+            Op::DoStart => {
+                let val = self.pop();
+                let Some(count) = val.int() else {
+                    return self.fail(RuntimeError::NonIntegerCount(val.num()));
+                };
+                let outer = self.set_repcount(0);
+                self.stack.push(Value::Number(outer as f64));
+                self.stack.push(Value::Number(count as f64));
+                self.stack.push(Value::Number(0.0));
+            }
+            Op::DoNext(end) => {
+                let iter = self.pop_num() + 1.0;
+                if iter > self.peek(0).num() {
+                    self.pop();
+                    let outer = self.pop_num();
+                    self.set_repcount(outer as usize);
+                    self.pc = *end;
+                } else {
+                    self.stack.push(Value::Number(iter));
+                    self.set_repcount(iter as usize);
+                }
+            }
+            Op::CounterStart(counter) => {
+                let step = self.pop();
+                let end = self.pop();
+                let init = self.pop();
+                self.turtle.set_var(self.ctx, counter, init);
+                self.stack.push(end);
+                self.stack.push(step);
+            }
+            Op::CounterTest { counter, up, end } => {
+                let curr = self.read_var(counter)?.num();
+                if *up == (curr >= self.peek(1).num()) {
+                    self.stack.truncate(self.stack.len() - 2);
+                    self.pc = *end;
+                }
+            }
+            Op::CounterNext(counter) => {
+                let next = self.read_var(counter)?.num() + self.peek(0).num();
+                self.turtle.set_var(self.ctx, counter, Value::Number(next));
+            }
+            Op::ForEachNext(end) => {
+                let idx = self.pop_num();
+                let next = Value::Number(idx + 1.0);
+                match self.peek(0).item(&next) {
+                    Some(val) => {
+                        self.stack.push(next);
+                        self.stack.push(Value::Number(val));
+                    }
+                    None => {
+                        self.pop();
+                        self.pc = *end;
+                    }
+                }
+            }
+            Op::MoveDist { draw, back } => {
+                let dist = self.pop_num();
+                self.turtle.move_dist(self.ctx, dist, *back, *draw);
+                self.sleep(*draw)?;
+            }
+            Op::MoveHome(draw) => {
+                self.turtle.move_home(self.ctx, *draw);
+                self.sleep(*draw)?;
+            }
+            Op::MoveTo(draw) => {
+                let y = self.pop_num();
+                let x = self.pop_num();
+                self.turtle.move_to(self.ctx, (x, y), *draw);
+                self.sleep(*draw)?;
+            }
+            Op::Turn(left) => {
+                let angle = self.pop_num();
+                let new_dir = self.turtle.dir + if *left { angle } else { -angle };
+                self.turtle.set_dir(new_dir);
+                self.turtle.show(self.ctx);
+            }
+            Op::Direction => {
+                let dir = self.pop_num();
+                self.turtle.set_dir(dir);
+                self.turtle.show(self.ctx);
+            }
+            Op::Arc => {
+                let angle = self.pop_num();
+                let radius = self.pop_num();
+                self.turtle.move_arc(self.ctx, radius, angle);
+                self.sleep(true)?;
+            }
+            Op::Color => {
+                let b = self.pop_num();
+                let g = self.pop_num();
+                let r = self.pop_num();
+                self.turtle.set_col(r, g, b);
+            }
+            Op::PenWidth => self.turtle.pen_width = self.pop_num().max(0.0),
+            Op::SetSpeed => self.ctx.set_speed(self.pop_num()),
+            Op::PenStyle(style) => self.turtle.line_style = *style,
+            Op::PushTransform => self.turtle.push_transform(),
+            Op::PopTransform => {
+                if !self.turtle.pop_transform() {
+                    return self.fail(RuntimeError::UnmatchedPop);
+                }
+            }
+            Op::SaveState => self.turtle.save_state(),
+            Op::RestoreState => {
+                if !self.turtle.restore_state(self.ctx) {
+                    return self.fail(RuntimeError::UnmatchedRestore);
+                }
+            }
+            Op::NewTurtle => self.turtle.new_turtle(self.ctx),
+            Op::SetTurtle => {
+                let id = self.pop();
+                if !id
+                    .int()
+                    .is_some_and(|id| self.turtle.set_turtle(self.ctx, id))
+                {
+                    let count = self.turtle.turtle_count();
+                    return self.fail(RuntimeError::UnknownTurtle(id.num(), count));
+                }
+            }
+            Op::Scale(both) => {
+                let y = self.pop_num();
+                let x = if *both { self.pop_num() } else { y };
+                self.turtle.transform = self.turtle.transform.scale(x, y);
+            }
+            Op::Translate => {
+                let y = self.pop_num();
+                let x = self.pop_num();
+                self.turtle.transform = self.turtle.transform.translate(x, y);
+            }
+            Op::Rotate => {
+                let angle = self.pop_num();
+                self.turtle.transform = self.turtle.transform.rotate(angle);
+            }
+            Op::Pen(down) => self.turtle.pen_down = *down,
+            Op::ShowTurtle(visible) => self.turtle.set_visible(self.ctx, *visible),
+            Op::BeginFrame => self.ctx.begin_frame(),
+            Op::EndFrame => self.ctx.end_frame(),
+            Op::Clear => {
+                self.ctx.window.borrow_mut().clear();
+                self.sleep(true)?;
+            }
+            Op::ClearScreen => {
+                self.ctx.window.borrow_mut().clear();
+                self.turtle.reset();
+                self.turtle.show(self.ctx);
+                self.sleep(true)?;
+            }
+            Op::Mark => self.turtle.new_mark(),
+            Op::MoveMark(draw) => {
+                self.turtle.move_mark(self.ctx, *draw);
+                self.sleep(*draw)?;
+            }
+            Op::Print => {
+                let msg = self.pop().string();
+                let pos = self.turtle.transform.apply(self.turtle.pos());
+                self.ctx.window.borrow_mut().print_at(pos, &msg);
+            }
+            Op::SetPrecision => {
+                let digits = self.pop_num();
+                self.ctx.set_precision(digits);
+            }
+            Op::Wait => self.sleep(true)?,
+            Op::Finish(wait) => {
+                self.ctx.wait_end.set(*wait);
+                return Err(ProgEnd::AllTurtlesFinished);
+            }
+        }
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("operand stack underflow")
+    }
+
+    fn pop_num(&mut self) -> f64 {
+        self.pop().num()
+    }
+
+    /// `depth` values below the top
+    fn peek(&self, depth: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - depth]
+    }
+
+    /// sets `@repcount` of the current frame and returns the previous value
+    fn set_repcount(&mut self, count: usize) -> usize {
+        std::mem::replace(&mut self.turtle.stack.last_mut().unwrap().repcount, count)
+    }
+
+    fn read_var(&self, var: &Variable) -> Result<Value, ProgEnd> {
+        match (self.turtle.get_var(self.ctx, var), var.kind) {
+            (Some(val), _) => Ok(val),
+            (None, VariableKind::Local(id, _)) if self.turtle.is_let_bound(id) => {
+                let name = self.symbols.get_index(id).unwrap().0.clone();
+                Err(ProgEnd::Error(
+                    RuntimeError::UndefinedVariable(name).attach_pos(var.pos),
+                ))
+            }
+            (None, VariableKind::Local(_, ty) | VariableKind::Global(_, ty)) => Ok(ty.default()),
+            (None, VariableKind::GlobalPreDef(_)) => unreachable!("always defined"),
+        }
+    }
+
+    /// error at the instruction being executed
+    fn fail(&self, err: RuntimeError) -> Result<(), ProgEnd> {
+        Err(ProgEnd::Error(err.attach_pos(self.code.pos[self.pc - 1])))
+    }
+
+    /// what the controller does between two lines of the only turtle
+    fn sleep(&self, cond: bool) -> Result<(), ProgEnd> {
+        if cond {
+            if let Err(why) = self.ctx.apply_delay() {
+                return self.fail(why);
+            }
+            let exited = self
+                .ctx
+                .window
+                .borrow_mut()
+                .events()
+                .iter()
+                .any(|evt| matches!(evt, WindowEvent::WindowExited | WindowEvent::Closed));
+            if exited {
+                return Err(ProgEnd::WindowExited);
+            }
+        }
+        Ok(())
+    }
+}
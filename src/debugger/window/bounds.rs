@@ -0,0 +1,71 @@
+use crate::debugger::{TColor, TCoord};
+
+use super::{Window, WindowEvent};
+
+/// Records the extent of everything drawn, see [`BoundsWindow::bounds`].
+///
+/// Nothing is shown, so a program can be run against it first to size the
+/// real window.
+#[derive(Debug, Default)]
+pub struct BoundsWindow {
+    max_coord: TCoord,
+    bounds: Option<(TCoord, TCoord)>,
+}
+
+impl BoundsWindow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lower left and upper right corner of the bounding box of all lines,
+    /// fills and text positions, the origin if nothing was drawn
+    pub fn bounds(&self) -> (TCoord, TCoord) {
+        self.bounds.unwrap_or_default()
+    }
+
+    fn include(&mut self, (x, y): TCoord) {
+        let (min, max) = self.bounds.get_or_insert(((x, y), (x, y)));
+        *min = (min.0.min(x), min.1.min(y));
+        *max = (max.0.max(x), max.1.max(y));
+    }
+}
+
+impl Window for BoundsWindow {
+    fn init(&mut self) {}
+
+    fn get_max_coords(&self) -> TCoord {
+        self.max_coord
+    }
+
+    fn set_max_x(&mut self, max_x: f64) {
+        self.max_coord.0 = max_x;
+    }
+
+    fn set_max_y(&mut self, max_y: f64) {
+        self.max_coord.1 = max_y;
+    }
+
+    fn draw(&mut self, from: TCoord, to: TCoord, _col: TColor) {
+        self.include(from);
+        self.include(to);
+    }
+
+    fn fill(&mut self, points: &[TCoord], _col: TColor) {
+        for &p in points {
+            self.include(p);
+        }
+    }
+
+    // the extent covers the whole run, not only what is still visible
+    fn clear(&mut self) {}
+
+    fn print(&mut self, _msg: &str) {}
+
+    fn print_at(&mut self, pos: TCoord, _msg: &str) {
+        self.include(pos);
+    }
+
+    fn events(&mut self) -> Vec<WindowEvent> {
+        Vec::new()
+    }
+}
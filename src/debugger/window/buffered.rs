@@ -1,10 +1,12 @@
+use std::time::Duration;
+
 use crate::debugger::{TColor, TCoord};
 
-use super::Window;
+use super::{LineStyle, Window, WindowCmd, WindowEvent};
 
 pub struct BufferedWindow<W> {
     inner: W,
-    buffer: Vec<(TCoord, TCoord, TColor)>,
+    buffer: Vec<WindowCmd>,
 }
 
 impl<W: Window> BufferedWindow<W> {
@@ -15,11 +17,26 @@ impl<W: Window> BufferedWindow<W> {
         }
     }
 
+    /// Draw the buffer again, as one frame
     pub fn repaint(&mut self) {
+        self.inner.begin_frame();
         self.inner.clear();
-        for &(from, to, col) in &self.buffer {
-            self.inner.draw(from, to, col);
+        for cmd in &self.buffer {
+            match cmd {
+                WindowCmd::Draw(from, to, col) => self.inner.draw(*from, *to, *col),
+                WindowCmd::DrawBatch(lines) => {
+                    for &(from, to, col) in lines {
+                        self.inner.draw(from, to, col);
+                    }
+                }
+                WindowCmd::Fill(points, col) => self.inner.fill(points, *col),
+                WindowCmd::SetPenWidth(width) => self.inner.set_pen_width(*width),
+                WindowCmd::ClearRect(from, to) => self.inner.clear_rect(*from, *to),
+                WindowCmd::SetLineStyle(style) => self.inner.set_line_style(*style),
+                _ => {}
+            }
         }
+        self.inner.end_frame();
     }
 }
 
@@ -43,21 +60,84 @@ impl<W: Window> Window for BufferedWindow<W> {
     }
 
     fn draw(&mut self, from: TCoord, to: TCoord, col: TColor) {
-        self.buffer.push((from, to, col));
+        self.buffer.push(WindowCmd::Draw(from, to, col));
         self.inner.draw(from, to, col);
     }
 
+    fn fill(&mut self, points: &[TCoord], col: TColor) {
+        self.buffer.push(WindowCmd::Fill(points.to_vec(), col));
+        self.inner.fill(points, col);
+    }
+
     fn clear(&mut self) {
         self.buffer.clear();
         self.inner.clear();
     }
 
+    fn set_palette(&mut self, colors: &[TColor]) {
+        self.inner.set_palette(colors);
+    }
+
+    fn clear_rect(&mut self, from: TCoord, to: TCoord) {
+        self.buffer.push(WindowCmd::ClearRect(from, to));
+        self.inner.clear_rect(from, to);
+    }
+
+    fn set_pen_width(&mut self, width: f64) {
+        self.buffer.push(WindowCmd::SetPenWidth(width));
+        self.inner.set_pen_width(width);
+    }
+
+    fn set_line_style(&mut self, style: LineStyle) {
+        self.buffer.push(WindowCmd::SetLineStyle(style));
+        self.inner.set_line_style(style);
+    }
+
     fn print(&mut self, msg: &str) {
         self.inner.print(msg);
     }
 
-    fn events(&mut self) -> Vec<super::WindowEvent> {
-        self.inner.events()
+    fn print_at(&mut self, pos: TCoord, msg: &str) {
+        self.inner.print_at(pos, msg);
+    }
+
+    fn set_font_size(&mut self, pt: f64) {
+        self.inner.set_font_size(pt);
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.inner.set_title(title);
+    }
+
+    fn set_turtle(&mut self, pos: TCoord, heading: f64, visible: bool) {
+        self.inner.set_turtle(pos, heading, visible);
+    }
+
+    fn begin_frame(&mut self) {
+        self.inner.begin_frame();
+    }
+
+    fn end_frame(&mut self) {
+        self.inner.end_frame();
+    }
+
+    fn delay(&mut self, time: Duration) {
+        self.inner.delay(time);
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush();
+    }
+
+    fn events(&mut self) -> Vec<WindowEvent> {
+        let events = self.inner.events();
+        if events
+            .iter()
+            .any(|evt| matches!(evt, WindowEvent::Resized(..)))
+        {
+            self.repaint();
+        }
+        events
     }
 
     fn buffered(self) -> BufferedWindow<impl Window>
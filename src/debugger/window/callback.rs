@@ -0,0 +1,123 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc, time::Duration};
+
+use crate::debugger::{TColor, TCoord};
+
+use super::{LineStyle, Window, WindowCmd, WindowEvent};
+
+/// Events waiting for the next [`Window::events`] of a [`CallbackWindow`]
+pub type EventQueue = Rc<RefCell<VecDeque<WindowEvent>>>;
+
+/// Hands every command to a function instead of a channel, for hosts without threads.
+///
+/// The commands are in turtle coordinates like the log of a
+/// [`RecordingWindow`](super::RecordingWindow).
+pub struct CallbackWindow<F> {
+    callback: F,
+    events: EventQueue,
+    max_coord: TCoord,
+    /// end of the last line, sent for text without a position
+    last_pos: TCoord,
+    palette: Vec<TColor>,
+}
+
+impl<F: FnMut(WindowCmd)> CallbackWindow<F> {
+    /// The queue is shared with the window, events pushed to it are reported by the window
+    pub fn construct(callback: F) -> (Self, EventQueue) {
+        let events = EventQueue::default();
+        let window = Self {
+            callback,
+            events: events.clone(),
+            max_coord: (0.0, 0.0),
+            last_pos: (0.0, 0.0),
+            palette: Vec::new(),
+        };
+        (window, events)
+    }
+}
+
+impl<F: FnMut(WindowCmd)> Window for CallbackWindow<F> {
+    fn init(&mut self) {}
+
+    fn get_max_coords(&self) -> TCoord {
+        self.max_coord
+    }
+
+    fn set_max_x(&mut self, max_x: f64) {
+        self.max_coord.0 = max_x;
+    }
+
+    fn set_max_y(&mut self, max_y: f64) {
+        self.max_coord.1 = max_y;
+    }
+
+    fn draw(&mut self, from: TCoord, to: TCoord, col: TColor) {
+        self.last_pos = to;
+        (self.callback)(WindowCmd::line(&self.palette, from, to, col));
+    }
+
+    fn fill(&mut self, points: &[TCoord], col: TColor) {
+        (self.callback)(WindowCmd::Fill(points.to_vec(), col));
+    }
+
+    fn clear(&mut self) {
+        (self.callback)(WindowCmd::Clear);
+    }
+
+    fn set_palette(&mut self, colors: &[TColor]) {
+        self.palette = colors.to_vec();
+        (self.callback)(WindowCmd::SetPalette(self.palette.clone()));
+    }
+
+    fn clear_rect(&mut self, from: TCoord, to: TCoord) {
+        (self.callback)(WindowCmd::ClearRect(from, to));
+    }
+
+    fn set_pen_width(&mut self, width: f64) {
+        (self.callback)(WindowCmd::SetPenWidth(width));
+    }
+
+    fn set_line_style(&mut self, style: LineStyle) {
+        (self.callback)(WindowCmd::SetLineStyle(style));
+    }
+
+    fn print(&mut self, msg: &str) {
+        (self.callback)(WindowCmd::Print(self.last_pos, msg.to_string()));
+    }
+
+    fn print_at(&mut self, pos: TCoord, msg: &str) {
+        (self.callback)(WindowCmd::Print(pos, msg.to_string()));
+    }
+
+    fn set_font_size(&mut self, pt: f64) {
+        (self.callback)(WindowCmd::SetFontSize(pt));
+    }
+
+    fn set_title(&mut self, title: &str) {
+        (self.callback)(WindowCmd::SetTitle(title.to_string()));
+    }
+
+    fn set_turtle(&mut self, pos: TCoord, heading: f64, visible: bool) {
+        (self.callback)(WindowCmd::SetTurtle(pos, heading, visible));
+    }
+
+    fn begin_frame(&mut self) {
+        (self.callback)(WindowCmd::BeginFrame);
+    }
+
+    fn end_frame(&mut self) {
+        (self.callback)(WindowCmd::EndFrame);
+    }
+
+    /// Doesn't block, the host waits on [`WindowCmd::Delay`] if it can
+    fn delay(&mut self, time: Duration) {
+        (self.callback)(WindowCmd::Delay(time));
+    }
+
+    fn flush(&mut self) {
+        (self.callback)(WindowCmd::Flush);
+    }
+
+    fn events(&mut self) -> Vec<WindowEvent> {
+        self.events.borrow_mut().drain(..).collect()
+    }
+}
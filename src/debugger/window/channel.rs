@@ -1,33 +1,292 @@
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, SendError, Sender, TryRecvError},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use crate::debugger::{TColor, TCoord};
 
-use super::{Window, WindowCmd, WindowEvent};
+use super::{LineStyle, Window, WindowCmd, WindowEvent};
 
 type InitFn = Box<dyn FnOnce() + Send>;
 
+/// number of lines collected before they are sent as [`WindowCmd::DrawBatch`]
+const BATCH_SIZE: usize = 256;
+
+/// A [`WindowEvent`] with the order and time it was sent in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StampedEvent {
+    /// counts up from 0 for every event sent through the same channel
+    pub seq: u64,
+    pub time: Instant,
+    pub event: WindowEvent,
+}
+
+/// Sending half of the events of a [`ChannelWindow`], stamps every event it sends
+#[derive(Debug, Clone)]
+pub struct EventSender {
+    events: Sender<StampedEvent>,
+    seq: Arc<AtomicU64>,
+}
+
+impl EventSender {
+    pub fn send(&self, event: WindowEvent) -> Result<(), SendError<WindowEvent>> {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let time = Instant::now();
+        self.events
+            .send(StampedEvent { seq, time, event })
+            .map_err(|err| SendError(err.0.event))
+    }
+}
+
+/// Channel for the events of a [`ChannelWindow`]
+pub fn event_channel() -> (EventSender, Receiver<StampedEvent>) {
+    let (events, rx) = mpsc::channel();
+    let seq = Arc::new(AtomicU64::new(0));
+    (EventSender { events, seq }, rx)
+}
+
+/// Where turtle `(0, 0)` is placed in the window
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    #[default]
+    Center,
+    /// the visible area reaches twice the max coordinates from the corner
+    TopLeft,
+}
+
+/// Forwards everything to another thread as [`WindowCmd`].
+///
+/// Lines are collected and sent in batches, which happens when enough lines are drawn,
+/// any other command is sent or events are read. A single pending line is sent as plain
+/// [`WindowCmd::Draw`]. Lines in a color of the palette refer to it by index, as
+/// [`WindowCmd::DrawIndexedBatch`] and [`WindowCmd::DrawIndexed`].
+///
+/// Coordinates are normalized by the max coordinates. An axis without an extent, because it
+/// was never set or set to zero, is passed through unscaled instead.
 pub struct ChannelWindow {
     max_coord: (f64, f64),
     pub(super) init: InitFn,
     commands: Sender<WindowCmd>,
-    events: Receiver<WindowEvent>,
+    events: Receiver<StampedEvent>,
+    /// sequence number for `Closed`, which isn't sent by the backend
+    next_seq: u64,
+    lines: Vec<(TCoord, TCoord, TColor)>,
+    /// pending lines in a palette color, only one of `lines` and `indexed` is filled
+    indexed: Vec<(TCoord, TCoord, usize)>,
+    palette: Vec<TColor>,
+    /// end of the last line, where text without a position goes
+    last_pos: TCoord,
+    size: Option<(u32, u32)>,
+    /// `Closed` was reported
+    closed: bool,
+    /// the backend dropped its end of the commands, nothing is sent anymore
+    gone: bool,
+    origin: Origin,
+    y_down: bool,
+    coalesce_moves: bool,
 }
 
 impl ChannelWindow {
-    pub fn new(commands: Sender<WindowCmd>, events: Receiver<WindowEvent>, init: InitFn) -> Self {
+    pub fn new(commands: Sender<WindowCmd>, events: Receiver<StampedEvent>, init: InitFn) -> Self {
         Self {
             max_coord: (0.0, 0.0),
             init,
             commands,
             events,
+            next_seq: 0,
+            lines: Vec::new(),
+            indexed: Vec::new(),
+            palette: Vec::new(),
+            last_pos: (0.0, 0.0),
+            size: None,
+            closed: false,
+            gone: false,
+            origin: Origin::Center,
+            y_down: false,
+            coalesce_moves: false,
         }
     }
 
-    pub fn construct() -> (Self, Receiver<WindowCmd>, Sender<WindowEvent>) {
+    pub fn origin(mut self, origin: Origin) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Let turtle y coordinates grow downwards, they grow upwards by default
+    pub fn y_down(mut self, y_down: bool) -> Self {
+        self.y_down = y_down;
+        self
+    }
+
+    /// Report only the last of several `MouseMoved` in a row, off by default
+    pub fn coalesce_moves(mut self, coalesce: bool) -> Self {
+        self.coalesce_moves = coalesce;
+        self
+    }
+
+    pub fn construct() -> (Self, Receiver<WindowCmd>, EventSender) {
         let (cmd_tx, cmd_rx) = mpsc::channel();
-        let (evt_tx, evt_rx) = mpsc::channel();
+        let (evt_tx, evt_rx) = event_channel();
         (Self::new(cmd_tx, evt_rx, Box::new(|| ())), cmd_rx, evt_tx)
     }
+
+    /// Size of the backend in pixels, if it has reported one.
+    ///
+    /// Commands are normalized to `-1.0..=1.0`, so a resize doesn't change them.
+    pub fn pixel_size(&self) -> Option<(u32, u32)> {
+        self.size
+    }
+
+    /// Block until the backend sends an event, at most for `timeout` if given.
+    ///
+    /// Mouse positions are scaled like in [`Window::events`]. `None` if the timeout
+    /// elapsed or the backend is gone and that was already reported.
+    pub fn wait_event(&mut self, timeout: Option<Duration>) -> Option<WindowEvent> {
+        self.wait_stamped(timeout).map(|evt| evt.event)
+    }
+
+    /// [`ChannelWindow::wait_event`] with the event's sequence number and time
+    pub fn wait_stamped(&mut self, timeout: Option<Duration>) -> Option<StampedEvent> {
+        self.send_lines();
+        if self.gone {
+            return self.disconnected();
+        }
+        let res = match timeout {
+            Some(timeout) => self.events.recv_timeout(timeout),
+            None => self
+                .events
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match res {
+            Ok(evt) => Some(self.received(evt)),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => self.disconnected(),
+        }
+    }
+
+    /// Like [`Window::events`], but with the sequence number and time of every event
+    pub fn stamped_events(&mut self) -> Vec<StampedEvent> {
+        self.send_lines();
+        if self.gone {
+            return self.disconnected().into_iter().collect();
+        }
+        let mut events = Vec::new();
+        loop {
+            match self.events.try_recv() {
+                Ok(evt) => {
+                    let evt = self.received(evt);
+                    let is_move =
+                        |evt: &StampedEvent| matches!(evt.event, WindowEvent::MouseMoved(_));
+                    match events.last_mut() {
+                        Some(last) if self.coalesce_moves && is_move(last) && is_move(&evt) => {
+                            *last = evt
+                        }
+                        _ => events.push(evt),
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    events.extend(self.disconnected());
+                    break;
+                }
+            }
+        }
+        events
+    }
+
+    /// map an event from the backend to turtle coordinates
+    fn received(&mut self, mut evt: StampedEvent) -> StampedEvent {
+        self.next_seq = evt.seq + 1;
+        if let WindowEvent::MouseClicked(pos, _)
+        | WindowEvent::MouseMoved(pos)
+        | WindowEvent::MouseReleased(pos, _) = &mut evt.event
+        {
+            *pos = self.denormalize(*pos);
+        }
+        if let WindowEvent::Resized(width, height) = evt.event {
+            self.size = Some((width, height));
+        }
+        evt
+    }
+
+    /// the backend is gone, tell about it only once
+    fn disconnected(&mut self) -> Option<StampedEvent> {
+        (!std::mem::replace(&mut self.closed, true)).then(|| StampedEvent {
+            seq: self.next_seq,
+            time: Instant::now(),
+            event: WindowEvent::Closed,
+        })
+    }
+
+    /// send all lines drawn so far
+    fn send_lines(&mut self) {
+        let cmd = match (self.lines.len(), self.indexed.len()) {
+            (0, 0) => return,
+            (1, _) => {
+                let (from, to, col) = self.lines.pop().unwrap();
+                WindowCmd::Draw(from, to, col)
+            }
+            (_, 1) => {
+                let (from, to, idx) = self.indexed.pop().unwrap();
+                WindowCmd::DrawIndexed(from, to, idx)
+            }
+            (_, 0) => WindowCmd::DrawBatch(std::mem::take(&mut self.lines)),
+            _ => WindowCmd::DrawIndexedBatch(std::mem::take(&mut self.indexed)),
+        };
+        self.send_raw(cmd);
+    }
+
+    fn send(&mut self, cmd: WindowCmd) {
+        self.send_lines();
+        self.send_raw(cmd);
+    }
+
+    /// a backend that is gone is reported by the next [`Window::events`]
+    fn send_raw(&mut self, cmd: WindowCmd) {
+        if !self.gone && self.commands.send(cmd).is_err() {
+            self.gone = true;
+        }
+    }
+
+    /// turtle units per normalized unit, `1.0` for an axis without extent
+    fn scale(&self) -> TCoord {
+        let axis = |max: f64| if max == 0.0 { 1.0 } else { max };
+        (axis(self.max_coord.0), axis(self.max_coord.1))
+    }
+
+    /// normalized position of turtle `(0, 0)` and direction of the y axis
+    fn offset(&self) -> (TCoord, f64) {
+        let origin = match self.origin {
+            Origin::Center => (0.0, 0.0),
+            Origin::TopLeft => (-1.0, 1.0),
+        };
+        (origin, if self.y_down { -1.0 } else { 1.0 })
+    }
+
+    /// map turtle coordinates to `-1.0..=1.0`
+    fn normalize(&self, coord: TCoord) -> TCoord {
+        let scale = self.scale();
+        let (origin, y_dir) = self.offset();
+        (
+            coord.0 / scale.0 + origin.0,
+            y_dir * coord.1 / scale.1 + origin.1,
+        )
+    }
+
+    /// inverse of [`ChannelWindow::normalize`]
+    fn denormalize(&self, coord: TCoord) -> TCoord {
+        let scale = self.scale();
+        let (origin, y_dir) = self.offset();
+        (
+            (coord.0 - origin.0) * scale.0,
+            y_dir * (coord.1 - origin.1) * scale.1,
+        )
+    }
 }
 
 impl Window for ChannelWindow {
@@ -51,31 +310,113 @@ impl Window for ChannelWindow {
     }
 
     fn draw(&mut self, from: TCoord, to: TCoord, col: TColor) {
-        let from = (from.0 / self.max_coord.0, from.1 / self.max_coord.1);
-        let to = (to.0 / self.max_coord.0, to.1 / self.max_coord.1);
-        self.commands.send(WindowCmd::Draw(from, to, col)).unwrap();
+        self.last_pos = to;
+        let (from, to) = (self.normalize(from), self.normalize(to));
+        // keep the order of the lines, so a batch holds either kind only
+        let pending = match self.palette.iter().position(|&entry| entry == col) {
+            Some(idx) => {
+                if !self.lines.is_empty() {
+                    self.send_lines();
+                }
+                self.indexed.push((from, to, idx));
+                self.indexed.len()
+            }
+            None => {
+                if !self.indexed.is_empty() {
+                    self.send_lines();
+                }
+                self.lines.push((from, to, col));
+                self.lines.len()
+            }
+        };
+        if pending >= BATCH_SIZE {
+            self.send_lines();
+        }
+    }
+
+    fn fill(&mut self, points: &[TCoord], col: TColor) {
+        let points = points.iter().map(|&p| self.normalize(p)).collect();
+        self.send(WindowCmd::Fill(points, col));
     }
 
     fn clear(&mut self) {
-        self.commands.send(WindowCmd::Clear).unwrap();
+        self.send(WindowCmd::Clear);
+    }
+
+    fn set_palette(&mut self, colors: &[TColor]) {
+        self.palette = colors.to_vec();
+        self.send(WindowCmd::SetPalette(self.palette.clone()));
+    }
+
+    fn clear_rect(&mut self, from: TCoord, to: TCoord) {
+        let cmd = WindowCmd::ClearRect(self.normalize(from), self.normalize(to));
+        self.send(cmd);
+    }
+
+    fn set_pen_width(&mut self, width: f64) {
+        let cmd = WindowCmd::SetPenWidth(width / self.scale().0);
+        self.send(cmd);
+    }
+
+    fn set_line_style(&mut self, style: LineStyle) {
+        self.send(WindowCmd::SetLineStyle(style));
     }
 
     fn print(&mut self, msg: &str) {
-        self.commands
-            .send(WindowCmd::Print(msg.to_string()))
-            .unwrap();
+        self.print_at(self.last_pos, msg);
+    }
+
+    fn print_at(&mut self, pos: TCoord, msg: &str) {
+        let cmd = WindowCmd::Print(self.normalize(pos), msg.to_string());
+        self.send(cmd);
+    }
+
+    fn set_font_size(&mut self, pt: f64) {
+        self.send(WindowCmd::SetFontSize(pt));
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.send(WindowCmd::SetTitle(title.to_string()));
+    }
+
+    fn set_turtle(&mut self, pos: TCoord, heading: f64, visible: bool) {
+        // the axes may be scaled differently or flipped, which turns the heading too
+        let (scale, (_, y_dir)) = (self.scale(), self.offset());
+        let (sin, cos) = heading.to_radians().sin_cos();
+        let heading = (y_dir * sin / scale.1).atan2(cos / scale.0).to_degrees();
+        let cmd = WindowCmd::SetTurtle(self.normalize(pos), heading, visible);
+        self.send(cmd);
+    }
+
+    fn begin_frame(&mut self) {
+        self.send(WindowCmd::BeginFrame);
+    }
+
+    fn end_frame(&mut self) {
+        self.send(WindowCmd::EndFrame);
+    }
+
+    /// Sleeps here, so the backend doesn't fall behind the interpreter and events stay current
+    fn delay(&mut self, time: Duration) {
+        self.send(WindowCmd::Delay(time));
+        std::thread::sleep(time);
+    }
+
+    fn flush(&mut self) {
+        self.send(WindowCmd::Flush);
     }
 
     fn events(&mut self) -> Vec<WindowEvent> {
-        self.events
-            .try_iter()
-            .map(|mut evt| {
-                if let WindowEvent::MouseClicked(pos, _) = &mut evt {
-                    pos.0 *= self.max_coord.0;
-                    pos.1 *= self.max_coord.1;
-                }
-                evt
-            })
+        self.stamped_events()
+            .into_iter()
+            .map(|evt| evt.event)
             .collect()
     }
 }
+
+impl Drop for ChannelWindow {
+    fn drop(&mut self) {
+        // the other side might be gone already, there is no one to tell then
+        self.send(WindowCmd::Flush);
+    }
+}
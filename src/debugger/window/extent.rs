@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use crate::debugger::{TColor, TCoord};
+
+use super::{LineStyle, Window, WindowEvent};
+
+/// Keeps the inner window at a fixed extent, e.g. to fit an export to the drawing.
+///
+/// The program still reads and sets its own `@max_x` and `@max_y`, they just
+/// don't reach the inner window.
+pub struct ExtentWindow<W> {
+    inner: W,
+    max_coord: TCoord,
+}
+
+impl<W: Window> ExtentWindow<W> {
+    pub fn new(mut inner: W, extent: TCoord) -> Self {
+        inner.set_max_x(extent.0);
+        inner.set_max_y(extent.1);
+        Self {
+            inner,
+            max_coord: extent,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Window> Window for ExtentWindow<W> {
+    fn init(&mut self) {
+        self.inner.init();
+    }
+
+    fn get_max_coords(&self) -> TCoord {
+        self.max_coord
+    }
+
+    fn set_max_x(&mut self, max_x: f64) {
+        self.max_coord.0 = max_x;
+    }
+
+    fn set_max_y(&mut self, max_y: f64) {
+        self.max_coord.1 = max_y;
+    }
+
+    fn draw(&mut self, from: TCoord, to: TCoord, col: TColor) {
+        self.inner.draw(from, to, col);
+    }
+
+    fn fill(&mut self, points: &[TCoord], col: TColor) {
+        self.inner.fill(points, col);
+    }
+
+    fn clear(&mut self) {
+        self.inner.clear();
+    }
+
+    fn set_palette(&mut self, colors: &[TColor]) {
+        self.inner.set_palette(colors);
+    }
+
+    fn clear_rect(&mut self, from: TCoord, to: TCoord) {
+        self.inner.clear_rect(from, to);
+    }
+
+    fn set_pen_width(&mut self, width: f64) {
+        self.inner.set_pen_width(width);
+    }
+
+    fn set_line_style(&mut self, style: LineStyle) {
+        self.inner.set_line_style(style);
+    }
+
+    fn print(&mut self, msg: &str) {
+        self.inner.print(msg);
+    }
+
+    fn print_at(&mut self, pos: TCoord, msg: &str) {
+        self.inner.print_at(pos, msg);
+    }
+
+    fn set_font_size(&mut self, pt: f64) {
+        self.inner.set_font_size(pt);
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.inner.set_title(title);
+    }
+
+    fn set_turtle(&mut self, pos: TCoord, heading: f64, visible: bool) {
+        self.inner.set_turtle(pos, heading, visible);
+    }
+
+    fn begin_frame(&mut self) {
+        self.inner.begin_frame();
+    }
+
+    fn end_frame(&mut self) {
+        self.inner.end_frame();
+    }
+
+    fn delay(&mut self, time: Duration) {
+        self.inner.delay(time);
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush();
+    }
+
+    fn events(&mut self) -> Vec<WindowEvent> {
+        self.inner.events()
+    }
+}
@@ -0,0 +1,144 @@
+use std::fmt::Write as _;
+
+use crate::debugger::{TColor, TCoord};
+
+use super::{Window, WindowEvent};
+
+/// Collects the drawing as G-code for a pen plotter, see [`GCodeWindow::finish`].
+///
+/// The pen is lifted for every jump between lines, a change of color pauses
+/// the program with `M0` to swap the pen by hand. Text is only kept as comment.
+#[derive(Debug)]
+pub struct GCodeWindow {
+    max_coord: TCoord,
+    /// millimeters per turtle unit
+    scale: f64,
+    z_up: f64,
+    z_down: f64,
+    /// feed rate of drawn lines in mm/min
+    feed: f64,
+    code: String,
+    /// position of the pen in turtle units and whether it touches the paper
+    pen: Option<(TCoord, bool)>,
+    col: Option<TColor>,
+}
+
+impl Default for GCodeWindow {
+    fn default() -> Self {
+        Self {
+            max_coord: (0.0, 0.0),
+            scale: 1.0,
+            z_up: 5.0,
+            z_down: 0.0,
+            feed: 1000.0,
+            code: String::new(),
+            pen: None,
+            col: None,
+        }
+    }
+}
+
+impl GCodeWindow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Millimeters per turtle unit
+    pub fn scale(mut self, mm: f64) -> Self {
+        self.scale = mm;
+        self
+    }
+
+    /// Z heights of the lifted and the lowered pen
+    pub fn z_heights(mut self, up: f64, down: f64) -> Self {
+        self.z_up = up;
+        self.z_down = down;
+        self
+    }
+
+    /// Feed rate of drawn lines in mm/min
+    pub fn feed_rate(mut self, feed: f64) -> Self {
+        self.feed = feed;
+        self
+    }
+
+    /// The G-code of everything drawn since the last clear, ending with the pen lifted
+    pub fn finish(mut self) -> String {
+        self.pen_up();
+        format!(
+            "G21 ; millimeters\nG90 ; absolute positions\nG0 Z{}\n{}",
+            num(self.z_up),
+            self.code
+        )
+    }
+
+    fn pen_up(&mut self) {
+        if let Some((_, down @ true)) = &mut self.pen {
+            *down = false;
+            let _ = writeln!(self.code, "G0 Z{}", num(self.z_up));
+        }
+    }
+
+    fn coords(&self, (x, y): TCoord) -> String {
+        format!("X{} Y{}", num(x * self.scale), num(y * self.scale))
+    }
+}
+
+/// rounded to micrometers, without a trailing `.0` or `-0`
+fn num(val: f64) -> f64 {
+    (val * 1000.0).round() / 1000.0 + 0.0
+}
+
+impl Window for GCodeWindow {
+    fn init(&mut self) {}
+
+    fn get_max_coords(&self) -> TCoord {
+        self.max_coord
+    }
+
+    fn set_max_x(&mut self, max_x: f64) {
+        self.max_coord.0 = max_x;
+    }
+
+    fn set_max_y(&mut self, max_y: f64) {
+        self.max_coord.1 = max_y;
+    }
+
+    fn draw(&mut self, from: TCoord, to: TCoord, col: TColor) {
+        if self.col.is_some_and(|curr| curr != col) {
+            self.pen_up();
+            let _ = writeln!(
+                self.code,
+                "M0 ; change pen to rgb({},{},{})",
+                (col.r * 2.55).round(),
+                (col.g * 2.55).round(),
+                (col.b * 2.55).round()
+            );
+        }
+        self.col = Some(col);
+        if self.pen != Some((from, true)) {
+            self.pen_up();
+            let _ = writeln!(self.code, "G0 {}", self.coords(from));
+            let _ = writeln!(self.code, "G1 Z{}", num(self.z_down));
+        }
+        let _ = writeln!(self.code, "G1 {} F{}", self.coords(to), num(self.feed));
+        self.pen = Some((to, true));
+    }
+
+    fn clear(&mut self) {
+        self.code.clear();
+        self.pen = None;
+        self.col = None;
+    }
+
+    fn print(&mut self, msg: &str) {
+        // comments end at the line's end
+        for line in msg.lines() {
+            let _ = writeln!(self.code, "; {line}");
+        }
+    }
+
+    fn events(&mut self) -> Vec<WindowEvent> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,49 @@
+//! Drawing from WebAssembly through a function imported from JavaScript.
+//!
+//! The module has to provide `turtle.command(ptr, len)`, which gets one command
+//! as UTF-8 JSON in the format of [`JsonWindow`](super::JsonWindow), e.g.
+//!
+//! ```js
+//! const imports = {
+//!   turtle: {
+//!     command(ptr, len) {
+//!       const bytes = new Uint8Array(instance.exports.memory.buffer, ptr, len);
+//!       draw(JSON.parse(new TextDecoder().decode(bytes)));
+//!     },
+//!   },
+//! };
+//! ```
+//!
+//! A `SetPenWidth` of `0` asks for the thinnest line, canvases ignore a `lineWidth` of 0
+//! so it has to be drawn 1 pixel wide.
+//!
+//! Drive the program with [`Debugger::run_frame`](crate::debugger::Debugger::run_frame),
+//! it never blocks.
+
+use super::{CallbackWindow, EventQueue, WindowCmd, WindowEvent};
+
+#[link(wasm_import_module = "turtle")]
+extern "C" {
+    #[link_name = "command"]
+    fn js_command(ptr: *const u8, len: usize);
+}
+
+pub type JsWindow = CallbackWindow<fn(WindowCmd)>;
+
+fn send(cmd: WindowCmd) {
+    let json = serde_json::to_string(&cmd).expect("commands are valid JSON");
+    // SAFETY: the import only reads `len` bytes from `ptr`, which live until it returns
+    unsafe { js_command(json.as_ptr(), json.len()) }
+}
+
+/// A window sending its commands to `turtle.command`
+pub fn js_window() -> (JsWindow, EventQueue) {
+    CallbackWindow::construct(send)
+}
+
+/// Queue an event given as JSON, like `{"MouseClicked":[[1.0,2.0],true]}`
+pub fn push_event(queue: &EventQueue, json: &str) -> Result<(), serde_json::Error> {
+    let evt: WindowEvent = serde_json::from_str(json)?;
+    queue.borrow_mut().push_back(evt);
+    Ok(())
+}
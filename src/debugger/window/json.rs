@@ -0,0 +1,179 @@
+use std::io::{self, BufRead, Write};
+
+use crate::debugger::{TColor, TCoord};
+
+use super::{LineStyle, Window, WindowCmd, WindowEvent};
+
+/// Writes every command as one line of JSON, see [`read_commands`] for the way back.
+///
+/// Each line is a [`WindowCmd`] in turtle coordinates, colors are `[r, g, b]` or
+/// `[r, g, b, a]` with channels from 0 to 100. Commands with data are an object
+/// with a single key, the others a plain string:
+///
+/// ```text
+/// {"Draw":[[0.0,0.0],[10.0,5.0],[100.0,0.0,0.0]]}
+/// {"DrawBatch":[[[0.0,0.0],[1.0,0.0],[100.0,100.0,100.0]]]}
+/// {"SetPalette":[[100.0,100.0,0.0],[0.0,0.0,100.0]]}
+/// {"DrawIndexed":[[0.0,0.0],[1.0,0.0],1]}
+/// {"Fill":[[[0.0,0.0],[1.0,0.0],[0.0,1.0]],[0.0,0.0,100.0,50.0]]}
+/// {"SetPenWidth":0.5}
+/// {"SetLineStyle":"Dashed"}
+/// "Clear"
+/// {"ClearRect":[[-5.0,-5.0],[5.0,5.0]]}
+/// {"Print":[[10.0,5.0],"text"]}
+/// {"SetFontSize":12.0}
+/// {"SetTitle":"title"}
+/// {"SetTurtle":[[10.0,5.0],90.0,true]}
+/// "BeginFrame"
+/// "EndFrame"
+/// "Flush"
+/// ```
+///
+/// [`WindowEvent`]s serialize the same way, e.g. `{"MouseClicked":[[1.0,2.0],true]}`
+/// or `"WindowExited"`, but the window itself never reports any.
+pub struct JsonWindow<O> {
+    out: O,
+    max_coord: TCoord,
+    /// end of the last line, written for text without a position
+    last_pos: TCoord,
+    /// the first failed write, nothing is written after it
+    error: Option<io::Error>,
+    palette: Vec<TColor>,
+}
+
+impl<O: Write> JsonWindow<O> {
+    pub fn new(out: O) -> Self {
+        Self {
+            out,
+            max_coord: (0.0, 0.0),
+            last_pos: (0.0, 0.0),
+            error: None,
+            palette: Vec::new(),
+        }
+    }
+
+    /// Flush the output and report the first error while writing
+    pub fn finish(mut self) -> io::Result<O> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn write(&mut self, cmd: &WindowCmd) {
+        if self.error.is_some() {
+            return;
+        }
+        let res = serde_json::to_writer(&mut self.out, cmd)
+            .map_err(io::Error::from)
+            .and_then(|_| self.out.write_all(b"\n"));
+        if let Err(err) = res {
+            self.error = Some(err);
+        }
+    }
+}
+
+/// A line of a command stream that couldn't be read
+#[derive(Debug, thiserror::Error)]
+pub enum JsonError {
+    #[error("{0}")]
+    Io(#[from] io::Error),
+    #[error("line {line}: {err}")]
+    Parse { line: usize, err: serde_json::Error },
+}
+
+/// Parse the lines written by [`JsonWindow`], empty lines are skipped.
+///
+/// Pass the commands to [`replay`](super::replay) to draw them on another window.
+pub fn read_commands(input: impl BufRead) -> impl Iterator<Item = Result<WindowCmd, JsonError>> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .map(|(idx, line)| {
+            serde_json::from_str(&line?).map_err(|err| JsonError::Parse { line: idx + 1, err })
+        })
+}
+
+impl<O: Write> Window for JsonWindow<O> {
+    fn init(&mut self) {}
+
+    fn get_max_coords(&self) -> TCoord {
+        self.max_coord
+    }
+
+    fn set_max_x(&mut self, max_x: f64) {
+        self.max_coord.0 = max_x;
+    }
+
+    fn set_max_y(&mut self, max_y: f64) {
+        self.max_coord.1 = max_y;
+    }
+
+    fn draw(&mut self, from: TCoord, to: TCoord, col: TColor) {
+        self.last_pos = to;
+        self.write(&WindowCmd::line(&self.palette, from, to, col));
+    }
+
+    fn fill(&mut self, points: &[TCoord], col: TColor) {
+        self.write(&WindowCmd::Fill(points.to_vec(), col));
+    }
+
+    fn clear(&mut self) {
+        self.write(&WindowCmd::Clear);
+    }
+
+    fn set_palette(&mut self, colors: &[TColor]) {
+        self.palette = colors.to_vec();
+        self.write(&WindowCmd::SetPalette(self.palette.clone()));
+    }
+
+    fn clear_rect(&mut self, from: TCoord, to: TCoord) {
+        self.write(&WindowCmd::ClearRect(from, to));
+    }
+
+    fn set_pen_width(&mut self, width: f64) {
+        self.write(&WindowCmd::SetPenWidth(width));
+    }
+
+    fn set_line_style(&mut self, style: LineStyle) {
+        self.write(&WindowCmd::SetLineStyle(style));
+    }
+
+    fn print(&mut self, msg: &str) {
+        self.write(&WindowCmd::Print(self.last_pos, msg.to_string()));
+    }
+
+    fn print_at(&mut self, pos: TCoord, msg: &str) {
+        self.write(&WindowCmd::Print(pos, msg.to_string()));
+    }
+
+    fn set_font_size(&mut self, pt: f64) {
+        self.write(&WindowCmd::SetFontSize(pt));
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.write(&WindowCmd::SetTitle(title.to_string()));
+    }
+
+    fn set_turtle(&mut self, pos: TCoord, heading: f64, visible: bool) {
+        self.write(&WindowCmd::SetTurtle(pos, heading, visible));
+    }
+
+    fn begin_frame(&mut self) {
+        self.write(&WindowCmd::BeginFrame);
+    }
+
+    fn end_frame(&mut self) {
+        self.write(&WindowCmd::EndFrame);
+    }
+
+    fn flush(&mut self) {
+        self.write(&WindowCmd::Flush);
+    }
+
+    fn events(&mut self) -> Vec<WindowEvent> {
+        Vec::new()
+    }
+}
@@ -1,13 +1,56 @@
+use std::time::Duration;
+
 use super::{TColor, TCoord};
 
+mod bounds;
 mod buffered;
+mod callback;
 mod channel;
+mod extent;
+mod gcode;
+#[cfg(feature = "wasm")]
+mod js;
+#[cfg(feature = "json")]
+mod json;
+#[cfg(feature = "raster")]
+mod raster;
+mod recording;
+#[cfg(feature = "sdl")]
 mod sdl;
+mod stats;
+mod svg;
+#[cfg(test)]
+mod test;
+mod tikz;
 
+pub use bounds::BoundsWindow;
 pub use buffered::BufferedWindow;
-pub use channel::ChannelWindow;
+pub use callback::{CallbackWindow, EventQueue};
+pub use channel::{event_channel, ChannelWindow, EventSender, Origin, StampedEvent};
+pub use extent::ExtentWindow;
+pub use gcode::GCodeWindow;
+#[cfg(feature = "wasm")]
+pub use js::{js_window, push_event, JsWindow};
+#[cfg(feature = "json")]
+pub use json::{read_commands, JsonError, JsonWindow};
+#[cfg(feature = "raster")]
+pub use raster::RasterWindow;
+pub use recording::{replay, RecordingWindow};
+#[cfg(feature = "sdl")]
 pub use sdl::SdlWindow;
+pub use stats::{Stats, StatsWindow};
+pub use svg::SvgWindow;
+pub use tikz::TikzWindow;
+
+/// Pen width of a new turtle, the thinnest line the window can draw.
+///
+/// Lines are at least a pixel wide there, see [`Window::set_pen_width`].
+pub const DEFAULT_PEN_WIDTH: f64 = 0.0;
+
+/// A window that ignores everything, the tuple is its extent
 pub type VoidWindow = (f64, f64);
+/// Headless window for tests and benchmarks, needs no threads or channels
+pub type NullWindow = VoidWindow;
 
 pub trait Window {
     fn init(&mut self);
@@ -17,15 +60,105 @@ pub trait Window {
         self.init();
     }
 
+    /// Largest coordinates visible in the window, in turtle units.
+    ///
+    /// They describe the logical extent and are independent of the window's size in pixels.
     fn get_max_coords(&self) -> TCoord;
     fn set_max_x(&mut self, max_x: f64);
     fn set_max_y(&mut self, max_y: f64);
 
     fn draw(&mut self, from: TCoord, to: TCoord, col: TColor);
+
+    /// Fill the polygon given by `points`.
+    ///
+    /// By default, the polygon is split into a triangle fan (so it has to be convex)
+    /// and every triangle is hatched with lines.
+    fn fill(&mut self, points: &[TCoord], col: TColor) {
+        const STEPS: usize = 32;
+        let Some((&first, rest)) = points.split_first() else {
+            return;
+        };
+        for edge in rest.windows(2) {
+            let (a, b) = (edge[0], edge[1]);
+            for step in 0..=STEPS {
+                let t = step as f64 / STEPS as f64;
+                self.draw(first, (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t), col);
+            }
+        }
+    }
+
     fn clear(&mut self);
 
+    /// Colors drawn often, registered before drawing.
+    ///
+    /// Windows writing commands refer to these colors by their index with
+    /// [`WindowCmd::DrawIndexed`] instead of repeating them on every line.
+    /// Backends may ignore this.
+    fn set_palette(&mut self, _colors: &[TColor]) {}
+
+    /// Clear the rectangle between the corners `from` and `to`, in turtle units.
+    ///
+    /// Backends that can't clear a part of the window clear all of it.
+    fn clear_rect(&mut self, _from: TCoord, _to: TCoord) {
+        self.clear();
+    }
+
+    /// Width of following lines in turtle units.
+    ///
+    /// [`DEFAULT_PEN_WIDTH`] is the thinnest line the backend can draw, which is also used
+    /// if this is never called. Backends may ignore this.
+    fn set_pen_width(&mut self, _width: f64) {}
+
+    /// Style of following lines, see [`LineStyle::dashes`] for the pattern.
+    ///
+    /// Backends that can't draw dashes keep drawing solid lines.
+    fn set_line_style(&mut self, _style: LineStyle) {}
+
     fn print(&mut self, msg: &str);
 
+    /// Show `msg` at `pos`, in turtle units.
+    ///
+    /// Backends that can't place text show it like [`Window::print`].
+    fn print_at(&mut self, _pos: TCoord, msg: &str) {
+        self.print(msg);
+    }
+
+    /// Size of following text in points.
+    ///
+    /// `0.0` selects the default size, which is also used if this is never
+    /// called. Backends may ignore this.
+    fn set_font_size(&mut self, _pt: f64) {}
+
+    fn set_title(&mut self, _title: &str) {}
+
+    /// Show the turtle at `pos` in turtle units, facing `heading` degrees counter-clockwise.
+    ///
+    /// The cursor isn't part of the drawing: each call replaces the last one and
+    /// `visible = false` removes it. Backends may ignore this.
+    fn set_turtle(&mut self, _pos: TCoord, _heading: f64, _visible: bool) {}
+
+    /// Start a frame, everything until the matching [`Window::end_frame`] is shown at once.
+    ///
+    /// Frames nest, only the end of the outermost one shows the drawing.
+    /// Backends may draw right away instead.
+    fn begin_frame(&mut self) {}
+
+    fn end_frame(&mut self) {}
+
+    /// Wait `time` after a line, so the drawing is animated as set by `@delay`.
+    ///
+    /// Called on the interpreter's thread, which is blocked meanwhile. By default
+    /// this sleeps, windows that pass commands on also pass the delay on.
+    fn delay(&mut self, time: Duration) {
+        std::thread::sleep(time);
+    }
+
+    /// Make sure everything drawn so far is shown.
+    ///
+    /// Windows may hold back commands, so call this before [`Window::events`]
+    /// if reacting to events relies on the drawing being visible.
+    fn flush(&mut self) {}
+
     fn events(&mut self) -> Vec<WindowEvent>;
 
     fn buffered(self) -> BufferedWindow<impl Window>
@@ -57,31 +190,314 @@ impl Window for Box<dyn Window> {
         (**self).draw(from, to, col);
     }
 
+    fn fill(&mut self, points: &[TCoord], col: TColor) {
+        (**self).fill(points, col);
+    }
+
     fn clear(&mut self) {
         (**self).clear();
     }
 
+    fn set_palette(&mut self, colors: &[TColor]) {
+        (**self).set_palette(colors);
+    }
+
+    fn clear_rect(&mut self, from: TCoord, to: TCoord) {
+        (**self).clear_rect(from, to);
+    }
+
+    fn set_pen_width(&mut self, width: f64) {
+        (**self).set_pen_width(width);
+    }
+
+    fn set_line_style(&mut self, style: LineStyle) {
+        (**self).set_line_style(style);
+    }
+
     fn print(&mut self, msg: &str) {
         (**self).print(msg);
     }
 
+    fn print_at(&mut self, pos: TCoord, msg: &str) {
+        (**self).print_at(pos, msg);
+    }
+
+    fn set_font_size(&mut self, pt: f64) {
+        (**self).set_font_size(pt);
+    }
+
+    fn set_title(&mut self, title: &str) {
+        (**self).set_title(title);
+    }
+
+    fn set_turtle(&mut self, pos: TCoord, heading: f64, visible: bool) {
+        (**self).set_turtle(pos, heading, visible);
+    }
+
+    fn begin_frame(&mut self) {
+        (**self).begin_frame();
+    }
+
+    fn end_frame(&mut self) {
+        (**self).end_frame();
+    }
+
+    fn delay(&mut self, time: Duration) {
+        (**self).delay(time);
+    }
+
+    fn flush(&mut self) {
+        (**self).flush();
+    }
+
     fn events(&mut self) -> Vec<WindowEvent> {
         (**self).events()
     }
 }
 
+impl<W: Window + ?Sized> Window for &mut W {
+    fn init(&mut self) {
+        (**self).init();
+    }
+
+    fn get_max_coords(&self) -> TCoord {
+        (**self).get_max_coords()
+    }
+
+    fn set_max_x(&mut self, max_x: f64) {
+        (**self).set_max_x(max_x);
+    }
+
+    fn set_max_y(&mut self, max_y: f64) {
+        (**self).set_max_y(max_y);
+    }
+
+    fn draw(&mut self, from: TCoord, to: TCoord, col: TColor) {
+        (**self).draw(from, to, col);
+    }
+
+    fn fill(&mut self, points: &[TCoord], col: TColor) {
+        (**self).fill(points, col);
+    }
+
+    fn clear(&mut self) {
+        (**self).clear();
+    }
+
+    fn set_palette(&mut self, colors: &[TColor]) {
+        (**self).set_palette(colors);
+    }
+
+    fn clear_rect(&mut self, from: TCoord, to: TCoord) {
+        (**self).clear_rect(from, to);
+    }
+
+    fn set_pen_width(&mut self, width: f64) {
+        (**self).set_pen_width(width);
+    }
+
+    fn set_line_style(&mut self, style: LineStyle) {
+        (**self).set_line_style(style);
+    }
+
+    fn print(&mut self, msg: &str) {
+        (**self).print(msg);
+    }
+
+    fn print_at(&mut self, pos: TCoord, msg: &str) {
+        (**self).print_at(pos, msg);
+    }
+
+    fn set_font_size(&mut self, pt: f64) {
+        (**self).set_font_size(pt);
+    }
+
+    fn set_title(&mut self, title: &str) {
+        (**self).set_title(title);
+    }
+
+    fn set_turtle(&mut self, pos: TCoord, heading: f64, visible: bool) {
+        (**self).set_turtle(pos, heading, visible);
+    }
+
+    fn begin_frame(&mut self) {
+        (**self).begin_frame();
+    }
+
+    fn end_frame(&mut self) {
+        (**self).end_frame();
+    }
+
+    fn delay(&mut self, time: Duration) {
+        (**self).delay(time);
+    }
+
+    fn flush(&mut self) {
+        (**self).flush();
+    }
+
+    fn events(&mut self) -> Vec<WindowEvent> {
+        (**self).events()
+    }
+}
+
+/// Input reported by [`Window::events`].
+///
+/// Backends send key events once per physical press and release, translated into a [`Key`]
+/// (keys without a [`Key`] equivalent are dropped).
+/// Mouse coordinates are sent in the same space the window draws in.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WindowEvent {
+    /// the user asked to quit
     WindowExited,
-    KeyPressed(char),
+    /// the window is gone, nothing will be shown anymore
+    Closed,
+    KeyPressed(Key),
+    KeyReleased(Key),
+    /// `true` for the left button
     MouseClicked(TCoord, bool),
+    MouseMoved(TCoord),
+    /// `true` for the left button
+    MouseReleased(TCoord, bool),
+    /// new size of the window in pixels
+    Resized(u32, u32),
+}
+
+/// Keys a window can report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Key {
+    /// lowercase ascii letter
+    Letter(char),
+    Digit(u8),
+    Up,
+    Down,
+    Left,
+    Right,
+    Space,
+    Enter,
 }
 
-#[derive(Debug, PartialEq)]
+impl Key {
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'a'..='z' | 'A'..='Z' => Some(Key::Letter(c.to_ascii_lowercase())),
+            '0'..='9' => Some(Key::Digit(c as u8 - b'0')),
+            ' ' => Some(Key::Space),
+            '\n' | '\r' => Some(Key::Enter),
+            _ => None,
+        }
+    }
+}
+
+/// The text a key event handler receives
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Key::Letter(c) => write!(f, "{c}"),
+            Key::Digit(d) => write!(f, "{d}"),
+            Key::Up => write!(f, "up"),
+            Key::Down => write!(f, "down"),
+            Key::Left => write!(f, "left"),
+            Key::Right => write!(f, "right"),
+            Key::Space => write!(f, " "),
+            Key::Enter => write!(f, "enter"),
+        }
+    }
+}
+
+/// How lines are drawn, set by `penstyle`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl LineStyle {
+    /// Lengths of a dash and the following gap for lines `width` wide, `None` if solid.
+    ///
+    /// Dots are dashes of length 0 and need round line caps to show up.
+    pub fn dashes(self, width: f64) -> Option<(f64, f64)> {
+        match self {
+            LineStyle::Solid => None,
+            LineStyle::Dashed => Some((4.0 * width, 3.0 * width)),
+            LineStyle::Dotted => Some((0.0, 2.0 * width)),
+        }
+    }
+}
+
+impl std::fmt::Display for LineStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LineStyle::Solid => write!(f, "solid"),
+            LineStyle::Dashed => write!(f, "dashed"),
+            LineStyle::Dotted => write!(f, "dotted"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum WindowCmd {
     Draw(TCoord, TCoord, TColor),
+    /// several [`WindowCmd::Draw`] in order
+    DrawBatch(Vec<(TCoord, TCoord, TColor)>),
+    /// colors later referred to by [`WindowCmd::DrawIndexed`]
+    SetPalette(Vec<TColor>),
+    /// [`WindowCmd::Draw`] with a color of the last palette
+    DrawIndexed(TCoord, TCoord, usize),
+    /// several [`WindowCmd::DrawIndexed`] in order
+    DrawIndexedBatch(Vec<(TCoord, TCoord, usize)>),
+    Fill(Vec<TCoord>, TColor),
+    /// pen width, normalized like the x coordinate, `0.0` for the thinnest line
+    SetPenWidth(f64),
+    SetLineStyle(LineStyle),
     Clear,
-    Print(String),
+    /// corners normalized like [`WindowCmd::Draw`]
+    ClearRect(TCoord, TCoord),
+    /// text at a position normalized like [`WindowCmd::Draw`]
+    Print(TCoord, String),
+    /// font size in points, not normalized
+    SetFontSize(f64),
+    SetTitle(String),
+    /// cursor at a position normalized like [`WindowCmd::Draw`], its heading and whether it's shown
+    SetTurtle(TCoord, f64, bool),
+    /// the following commands up to the matching [`WindowCmd::EndFrame`] form one frame
+    BeginFrame,
+    EndFrame,
+    /// the interpreter waited this long after a line, see [`Window::delay`]
+    Delay(Duration),
+    /// everything before was flushed
+    Flush,
+}
+
+impl WindowCmd {
+    /// Draw the line with an index into `palette` if possible
+    fn line(palette: &[TColor], from: TCoord, to: TCoord, col: TColor) -> Self {
+        match palette.iter().position(|&entry| entry == col) {
+            Some(idx) => Self::DrawIndexed(from, to, idx),
+            None => Self::Draw(from, to, col),
+        }
+    }
+
+    /// Whether the command changes what is shown, as opposed to settings
+    pub fn is_visible(&self) -> bool {
+        matches!(
+            self,
+            Self::Draw(..)
+                | Self::DrawBatch(_)
+                | Self::DrawIndexed(..)
+                | Self::DrawIndexedBatch(_)
+                | Self::Fill(..)
+                | Self::Clear
+                | Self::ClearRect(..)
+                | Self::Print(..)
+        )
+    }
 }
 
 impl Window for VoidWindow {
@@ -0,0 +1,236 @@
+use std::{fs::File, io, io::BufWriter, path::Path};
+
+use crate::debugger::{TColor, TCoord};
+
+use super::{Window, WindowEvent, DEFAULT_PEN_WIDTH};
+
+/// Draws into an RGBA image in memory, which can be saved with [`RasterWindow::save_png`].
+///
+/// The image has `scale` pixels per turtle unit and is resized (and cleared)
+/// whenever the maximum coordinates change.
+#[derive(Debug)]
+pub struct RasterWindow {
+    scale: f64,
+    max_coord: TCoord,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    pen_width: f64,
+    background: [u8; 4],
+}
+
+impl Default for RasterWindow {
+    /// same size as the SDL window
+    fn default() -> Self {
+        Self::new(20.0)
+    }
+}
+
+impl RasterWindow {
+    pub fn new(scale: f64) -> Self {
+        Self {
+            scale,
+            max_coord: (0.0, 0.0),
+            width: 0,
+            height: 0,
+            pixels: Vec::new(),
+            pen_width: DEFAULT_PEN_WIDTH,
+            background: [0, 0, 0, 255],
+        }
+    }
+
+    pub fn with_background(mut self, col: TColor) -> Self {
+        self.background = rgba(col);
+        self.clear();
+        self
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// RGBA value of a pixel, `(0, 0)` is the top left corner
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let idx = (y * self.width + x) as usize * 4;
+        self.pixels[idx..idx + 4].try_into().ok()
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(file, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer
+            .write_image_data(&self.pixels)
+            .map_err(io::Error::other)
+    }
+
+    fn resize(&mut self) {
+        self.width = (2.0 * self.max_coord.0 * self.scale).round().max(0.0) as u32;
+        self.height = (2.0 * self.max_coord.1 * self.scale).round().max(0.0) as u32;
+        self.clear();
+    }
+
+    fn map_coords(&self, coord: TCoord) -> (i64, i64) {
+        let (x, y) = self.map_exact(coord);
+        (x.round() as i64, y.round() as i64)
+    }
+
+    /// Pixel position of `coord` before rounding
+    fn map_exact(&self, coord: TCoord) -> TCoord {
+        (
+            (coord.0 + self.max_coord.0) * self.scale,
+            (self.max_coord.1 - coord.1) * self.scale,
+        )
+    }
+
+    fn put(&mut self, x: i64, y: i64, col: [u8; 4]) {
+        let radius = (self.pen_width * self.scale / 2.0) as i64;
+        for py in y - radius..=y + radius {
+            for px in x - radius..=x + radius {
+                if (0..self.width as i64).contains(&px) && (0..self.height as i64).contains(&py) {
+                    let idx = (py as usize * self.width as usize + px as usize) * 4;
+                    blend(&mut self.pixels[idx..idx + 4], col);
+                }
+            }
+        }
+    }
+}
+
+fn rgba(col: TColor) -> [u8; 4] {
+    [
+        (col.r * 2.55).round() as u8,
+        (col.g * 2.55).round() as u8,
+        (col.b * 2.55).round() as u8,
+        (col.a * 2.55).round() as u8,
+    ]
+}
+
+/// Part of the line from `from` to `to` inside the rectangle from `min` to `max`,
+/// `None` if it misses it (Liang–Barsky)
+fn clip(from: TCoord, to: TCoord, min: TCoord, max: TCoord) -> Option<(TCoord, TCoord)> {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    if !dx.is_finite() || !dy.is_finite() {
+        return None;
+    }
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    for (p, q) in [
+        (-dx, from.0 - min.0),
+        (dx, max.0 - from.0),
+        (-dy, from.1 - min.1),
+        (dy, max.1 - from.1),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else if p < 0.0 {
+            t0 = t0.max(q / p);
+        } else {
+            t1 = t1.min(q / p);
+        }
+    }
+    let at = |t: f64| (from.0 + t * dx, from.1 + t * dy);
+    (t0 <= t1).then(|| (at(t0), at(t1)))
+}
+
+/// Source-over compositing of `src` onto `dst`
+fn blend(dst: &mut [u8], src: [u8; 4]) {
+    let src_a = f64::from(src[3]) / 255.0;
+    let dst_a = f64::from(dst[3]) / 255.0;
+    let out_a = src_a + dst_a * (1.0 - src_a);
+    if out_a == 0.0 {
+        dst.fill(0);
+        return;
+    }
+    for i in 0..3 {
+        let c = (f64::from(src[i]) * src_a + f64::from(dst[i]) * dst_a * (1.0 - src_a)) / out_a;
+        dst[i] = c.round() as u8;
+    }
+    dst[3] = (out_a * 255.0).round() as u8;
+}
+
+impl Window for RasterWindow {
+    fn init(&mut self) {}
+
+    fn get_max_coords(&self) -> TCoord {
+        self.max_coord
+    }
+
+    fn set_max_x(&mut self, max_x: f64) {
+        self.max_coord.0 = max_x;
+        self.resize();
+    }
+
+    fn set_max_y(&mut self, max_y: f64) {
+        self.max_coord.1 = max_y;
+        self.resize();
+    }
+
+    /// Bresenham, on the part of the line that can touch the image
+    fn draw(&mut self, from: TCoord, to: TCoord, col: TColor) {
+        let col = rgba(col);
+        let radius = (self.pen_width * self.scale / 2.0).trunc();
+        let min = (-radius, -radius);
+        let max = (
+            f64::from(self.width) - 1.0 + radius,
+            f64::from(self.height) - 1.0 + radius,
+        );
+        let Some((from, to)) = clip(self.map_exact(from), self.map_exact(to), min, max) else {
+            return;
+        };
+        let round = |(x, y): TCoord| (x.round() as i64, y.round() as i64);
+        let ((mut x, mut y), (x1, y1)) = (round(from), round(to));
+        let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+        let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+        let mut err = dx + dy;
+        loop {
+            self.put(x, y, col);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.pixels = self
+            .background
+            .repeat(self.width as usize * self.height as usize);
+    }
+
+    fn clear_rect(&mut self, from: TCoord, to: TCoord) {
+        let (from, to) = (self.map_coords(from), self.map_coords(to));
+        let clamp = |val: i64, max: u32| val.clamp(0, i64::from(max)) as usize;
+        let xs = clamp(from.0.min(to.0), self.width)..clamp(from.0.max(to.0), self.width);
+        for y in clamp(from.1.min(to.1), self.height)..clamp(from.1.max(to.1), self.height) {
+            let row = y * self.width as usize;
+            for x in xs.clone() {
+                let idx = (row + x) * 4;
+                self.pixels[idx..idx + 4].copy_from_slice(&self.background);
+            }
+        }
+    }
+
+    fn set_pen_width(&mut self, width: f64) {
+        self.pen_width = width;
+    }
+
+    fn print(&mut self, _: &str) {}
+
+    fn events(&mut self) -> Vec<WindowEvent> {
+        Vec::new()
+    }
+}
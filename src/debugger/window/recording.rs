@@ -0,0 +1,239 @@
+use std::time::Duration;
+
+use crate::debugger::{TColor, TCoord};
+
+use super::{LineStyle, Window, WindowCmd, WindowEvent};
+
+/// Keeps a log of everything drawn on the inner window.
+///
+/// Commands are logged in turtle coordinates, so [`replay`] scales them exactly once
+/// through the target window. Events are passed through as they are.
+pub struct RecordingWindow<W> {
+    inner: W,
+    log: Vec<WindowCmd>,
+    /// undone parts of the log, the most recent last
+    redo: Vec<Vec<WindowCmd>>,
+    /// end of the last line, logged for text without a position
+    last_pos: TCoord,
+}
+
+impl<W: Window> RecordingWindow<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            log: Vec::new(),
+            redo: Vec::new(),
+            last_pos: (0.0, 0.0),
+        }
+    }
+
+    pub fn log(&self) -> &[WindowCmd] {
+        &self.log
+    }
+
+    pub fn into_log(self) -> Vec<WindowCmd> {
+        self.log
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    /// Take back the last visible command and redraw the rest of the log.
+    ///
+    /// A command inside a frame takes back the whole outermost frame.
+    /// Returns false if nothing was drawn yet.
+    pub fn undo(&mut self) -> bool {
+        let Some(mut idx) = self.log.iter().rposition(WindowCmd::is_visible) else {
+            return false;
+        };
+        let mut open = Vec::new();
+        for (pos, cmd) in self.log[..idx].iter().enumerate() {
+            match cmd {
+                WindowCmd::BeginFrame => open.push(pos),
+                WindowCmd::EndFrame => _ = open.pop(),
+                _ => {}
+            }
+        }
+        if let Some(&begin) = open.first() {
+            idx = begin;
+        }
+        self.redo.push(self.log.split_off(idx));
+        self.inner.clear();
+        replay(&self.log, &mut self.inner);
+        self.inner.flush();
+        true
+    }
+
+    /// Draw what the last [`undo`](Self::undo) took back.
+    ///
+    /// Anything drawn after the undo discards the commands that could be redone,
+    /// settings and flushes keep them.
+    pub fn redo(&mut self) -> bool {
+        let Some(cmds) = self.redo.pop() else {
+            return false;
+        };
+        replay(&cmds, &mut self.inner);
+        self.inner.flush();
+        self.log.extend(cmds);
+        true
+    }
+
+    fn push(&mut self, cmd: WindowCmd) {
+        if cmd.is_visible() {
+            self.redo.clear();
+        }
+        self.log.push(cmd);
+    }
+}
+
+/// Issue recorded commands on `target` again
+///
+/// Indexed colors are resolved against the last palette among `cmds`,
+/// lines with an unknown index are skipped.
+pub fn replay(cmds: &[WindowCmd], target: &mut impl Window) {
+    let mut palette: &[TColor] = &[];
+    for cmd in cmds {
+        match cmd {
+            WindowCmd::Draw(from, to, col) => target.draw(*from, *to, *col),
+            WindowCmd::DrawBatch(lines) => {
+                for &(from, to, col) in lines {
+                    target.draw(from, to, col);
+                }
+            }
+            WindowCmd::SetPalette(colors) => {
+                palette = colors;
+                target.set_palette(colors);
+            }
+            WindowCmd::DrawIndexed(from, to, idx) => {
+                if let Some(&col) = palette.get(*idx) {
+                    target.draw(*from, *to, col);
+                }
+            }
+            WindowCmd::DrawIndexedBatch(lines) => {
+                for &(from, to, idx) in lines {
+                    if let Some(&col) = palette.get(idx) {
+                        target.draw(from, to, col);
+                    }
+                }
+            }
+            WindowCmd::Fill(points, col) => target.fill(points, *col),
+            WindowCmd::SetPenWidth(width) => target.set_pen_width(*width),
+            WindowCmd::SetLineStyle(style) => target.set_line_style(*style),
+            WindowCmd::Clear => target.clear(),
+            WindowCmd::ClearRect(from, to) => target.clear_rect(*from, *to),
+            WindowCmd::Print(pos, msg) => target.print_at(*pos, msg),
+            WindowCmd::SetFontSize(pt) => target.set_font_size(*pt),
+            WindowCmd::SetTitle(title) => target.set_title(title),
+            WindowCmd::SetTurtle(pos, heading, visible) => {
+                target.set_turtle(*pos, *heading, *visible)
+            }
+            WindowCmd::BeginFrame => target.begin_frame(),
+            WindowCmd::EndFrame => target.end_frame(),
+            WindowCmd::Delay(time) => target.delay(*time),
+            WindowCmd::Flush => target.flush(),
+        }
+    }
+}
+
+impl<W: Window> Window for RecordingWindow<W> {
+    fn init(&mut self) {
+        self.inner.init();
+    }
+
+    fn get_max_coords(&self) -> TCoord {
+        self.inner.get_max_coords()
+    }
+
+    fn set_max_x(&mut self, max_x: f64) {
+        self.inner.set_max_x(max_x);
+    }
+
+    fn set_max_y(&mut self, max_y: f64) {
+        self.inner.set_max_y(max_y);
+    }
+
+    fn draw(&mut self, from: TCoord, to: TCoord, col: TColor) {
+        self.last_pos = to;
+        self.push(WindowCmd::Draw(from, to, col));
+        self.inner.draw(from, to, col);
+    }
+
+    fn fill(&mut self, points: &[TCoord], col: TColor) {
+        self.push(WindowCmd::Fill(points.to_vec(), col));
+        self.inner.fill(points, col);
+    }
+
+    fn clear(&mut self) {
+        self.push(WindowCmd::Clear);
+        self.inner.clear();
+    }
+
+    fn set_palette(&mut self, colors: &[TColor]) {
+        self.inner.set_palette(colors);
+    }
+
+    fn clear_rect(&mut self, from: TCoord, to: TCoord) {
+        self.push(WindowCmd::ClearRect(from, to));
+        self.inner.clear_rect(from, to);
+    }
+
+    fn set_pen_width(&mut self, width: f64) {
+        self.push(WindowCmd::SetPenWidth(width));
+        self.inner.set_pen_width(width);
+    }
+
+    fn set_line_style(&mut self, style: LineStyle) {
+        self.push(WindowCmd::SetLineStyle(style));
+        self.inner.set_line_style(style);
+    }
+
+    fn print(&mut self, msg: &str) {
+        self.push(WindowCmd::Print(self.last_pos, msg.to_string()));
+        self.inner.print(msg);
+    }
+
+    fn print_at(&mut self, pos: TCoord, msg: &str) {
+        self.push(WindowCmd::Print(pos, msg.to_string()));
+        self.inner.print_at(pos, msg);
+    }
+
+    fn set_font_size(&mut self, pt: f64) {
+        self.push(WindowCmd::SetFontSize(pt));
+        self.inner.set_font_size(pt);
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.push(WindowCmd::SetTitle(title.to_string()));
+        self.inner.set_title(title);
+    }
+
+    /// Not logged, the cursor is not part of the drawing
+    fn set_turtle(&mut self, pos: TCoord, heading: f64, visible: bool) {
+        self.inner.set_turtle(pos, heading, visible);
+    }
+
+    fn begin_frame(&mut self) {
+        self.push(WindowCmd::BeginFrame);
+        self.inner.begin_frame();
+    }
+
+    fn end_frame(&mut self) {
+        self.push(WindowCmd::EndFrame);
+        self.inner.end_frame();
+    }
+
+    /// not logged, replaying the drawing shouldn't wait
+    fn delay(&mut self, time: Duration) {
+        self.inner.delay(time);
+    }
+
+    fn flush(&mut self) {
+        self.push(WindowCmd::Flush);
+        self.inner.flush();
+    }
+
+    fn events(&mut self) -> Vec<WindowEvent> {
+        self.inner.events()
+    }
+}
@@ -1,23 +1,43 @@
-use std::{
-    sync::mpsc::{Receiver, Sender},
-    thread,
-    time::Duration,
-};
+use std::{sync::mpsc::Receiver, thread, time::Duration};
 
-use sdl2::{event::Event, pixels::Color, rect::Point, render::Canvas, EventPump};
+use sdl2::{
+    event::{Event, WindowEvent as SdlWindowEvent},
+    keyboard::Keycode,
+    mouse::MouseButton,
+    pixels::{Color, PixelFormatEnum},
+    rect::{Point, Rect},
+    render::{BlendMode, Canvas},
+    EventPump,
+};
 
-use super::{ChannelWindow, WindowCmd, WindowEvent};
+use super::{
+    ChannelWindow, EventSender, Key, TColor, TCoord, WindowCmd, WindowEvent, DEFAULT_PEN_WIDTH,
+};
 
 pub struct SdlWindow {
     canvas: Canvas<sdl2::video::Window>,
     event_pump: EventPump,
     cmds: Receiver<WindowCmd>,
-    events: Sender<WindowEvent>,
+    events: EventSender,
     wait_exit: Option<usize>,
+    /// normalized like coordinates
+    pen_width: f64,
+    size: (u32, u32),
+    /// normalized position and heading of the turtle, if shown
+    turtle: Option<(TCoord, f64)>,
+    /// the pixels the cursor covers, restored before drawing anything else
+    under_cursor: Option<(Rect, Vec<u8>)>,
+    /// depth of the open frames, whose commands wait in `frame`
+    frames: usize,
+    frame: Vec<WindowCmd>,
+    /// colors of indexed lines
+    palette: Vec<TColor>,
 }
 
 const WIDTH: u32 = 800;
 const HEIGHT: u32 = 600;
+/// length of the cursor in pixels
+const CURSOR_SIZE: f64 = 14.0;
 
 impl SdlWindow {
     pub fn create(title: String) -> ChannelWindow {
@@ -26,16 +46,18 @@ impl SdlWindow {
         window
     }
 
-    fn spawn(title: String, cmds: Receiver<WindowCmd>, events: Sender<WindowEvent>) {
+    fn spawn(title: String, cmds: Receiver<WindowCmd>, events: EventSender) {
         thread::spawn(move || {
             let sdl_context = sdl2::init().unwrap();
             let video_sub = sdl_context.video().unwrap();
             let window = video_sub
                 .window(&title, WIDTH, HEIGHT)
                 .position_centered()
+                .resizable()
                 .build()
                 .unwrap();
             let mut canvas = window.into_canvas().build().unwrap();
+            canvas.set_blend_mode(BlendMode::Blend);
             canvas.clear();
             let event_pump = sdl_context.event_pump().unwrap();
             let this = SdlWindow {
@@ -44,6 +66,13 @@ impl SdlWindow {
                 cmds,
                 events,
                 wait_exit: None,
+                pen_width: DEFAULT_PEN_WIDTH,
+                size: (WIDTH, HEIGHT),
+                turtle: None,
+                under_cursor: None,
+                frames: 0,
+                frame: Vec::new(),
+                palette: Vec::new(),
             };
             this.run();
         });
@@ -51,33 +80,155 @@ impl SdlWindow {
 
     fn map_coords(&self, coord: (f64, f64)) -> Point {
         Point::new(
-            (WIDTH as f64 / 2.0 * (1.0 + coord.0)) as i32,
-            (HEIGHT as f64 / 2.0 * (1.0 - coord.1)) as i32,
+            (self.size.0 as f64 / 2.0 * (1.0 + coord.0)) as i32,
+            (self.size.1 as f64 / 2.0 * (1.0 - coord.1)) as i32,
         )
     }
 
-    fn run(mut self) {
-        loop {
-            for cmd in self.cmds.try_iter() {
-                match cmd {
-                    WindowCmd::Clear => {
-                        self.canvas.set_draw_color(Color::BLACK);
-                        self.canvas.clear();
-                    }
-                    WindowCmd::Draw(from, to, col) => {
-                        let color = Color::RGB(
-                            (col.0 * 2.55) as u8,
-                            (col.1 * 2.55) as u8,
-                            (col.2 * 2.55) as u8,
-                        );
-                        let start = self.map_coords(from);
-                        let end = self.map_coords(to);
-                        self.canvas.set_draw_color(color);
-                        self.canvas.draw_line(start, end).unwrap();
+    /// SDL lines are always 1px, so wider ones are drawn as parallel lines
+    fn draw_line(&mut self, start: Point, end: Point) {
+        let pen_width = ((self.pen_width * self.size.0 as f64 / 2.0) as i32).max(1);
+        if pen_width == 1 {
+            self.canvas.draw_line(start, end).unwrap();
+            return;
+        }
+        let (dx, dy) = ((end.x() - start.x()) as f64, (end.y() - start.y()) as f64);
+        let len = dx.hypot(dy).max(1.0);
+        let (nx, ny) = (-dy / len, dx / len);
+        for i in 0..pen_width {
+            let off = i as f64 - (pen_width - 1) as f64 / 2.0;
+            let shift = Point::new((nx * off).round() as i32, (ny * off).round() as i32);
+            self.canvas.draw_line(start + shift, end + shift).unwrap();
+        }
+    }
+
+    /// Draw the turtle as triangle pointing in its heading, after saving what's below
+    fn draw_cursor(&mut self) {
+        let Some((pos, heading)) = self.turtle else {
+            return;
+        };
+        let tip = self.map_coords(pos);
+        let corner = |angle: f64, len: f64| {
+            let (sin, cos) = (heading + angle).to_radians().sin_cos();
+            Point::new(
+                tip.x() + (cos * len).round() as i32,
+                tip.y() - (sin * len).round() as i32,
+            )
+        };
+        let points = [
+            corner(0.0, 0.0),
+            corner(160.0, CURSOR_SIZE),
+            corner(200.0, CURSOR_SIZE),
+        ];
+        let screen = Rect::new(0, 0, self.size.0, self.size.1);
+        let Some(area) = Rect::from_enclose_points(&points, screen) else {
+            return;
+        };
+        let Ok(pixels) = self.canvas.read_pixels(area, PixelFormatEnum::ARGB8888) else {
+            return;
+        };
+        self.under_cursor = Some((area, pixels));
+        self.canvas.set_draw_color(Color::RGB(0, 200, 0));
+        fill_polygon(&mut self.canvas, &points);
+    }
+
+    fn erase_cursor(&mut self) {
+        let Some((area, pixels)) = self.under_cursor.take() else {
+            return;
+        };
+        let creator = self.canvas.texture_creator();
+        let Ok(mut texture) =
+            creator.create_texture_static(PixelFormatEnum::ARGB8888, area.width(), area.height())
+        else {
+            return;
+        };
+        texture.set_blend_mode(BlendMode::None);
+        if texture
+            .update(None, &pixels, area.width() as usize * 4)
+            .is_ok()
+        {
+            let _ = self.canvas.copy(&texture, None, area);
+        }
+    }
+
+    fn exec(&mut self, cmd: WindowCmd) {
+        if self.frames > 0 && !matches!(cmd, WindowCmd::BeginFrame | WindowCmd::EndFrame) {
+            self.frame.push(cmd);
+            return;
+        }
+        match cmd {
+            WindowCmd::Clear => {
+                self.canvas.set_draw_color(Color::BLACK);
+                self.canvas.clear();
+            }
+            WindowCmd::ClearRect(from, to) => {
+                let corners = [self.map_coords(from), self.map_coords(to)];
+                if let Some(area) = Rect::from_enclose_points(&corners, None) {
+                    self.canvas.set_draw_color(Color::BLACK);
+                    let _ = self.canvas.fill_rect(area);
+                }
+            }
+            WindowCmd::Draw(from, to, col) => {
+                let start = self.map_coords(from);
+                let end = self.map_coords(to);
+                self.canvas.set_draw_color(map_color(col));
+                self.draw_line(start, end);
+            }
+            WindowCmd::DrawBatch(lines) => {
+                for (from, to, col) in lines {
+                    self.exec(WindowCmd::Draw(from, to, col));
+                }
+            }
+            WindowCmd::SetPalette(colors) => self.palette = colors,
+            WindowCmd::DrawIndexed(from, to, idx) => {
+                if let Some(&col) = self.palette.get(idx) {
+                    self.exec(WindowCmd::Draw(from, to, col));
+                }
+            }
+            WindowCmd::DrawIndexedBatch(lines) => {
+                for (from, to, idx) in lines {
+                    self.exec(WindowCmd::DrawIndexed(from, to, idx));
+                }
+            }
+            WindowCmd::Fill(points, col) => {
+                let points: Vec<_> = points.into_iter().map(|p| self.map_coords(p)).collect();
+                self.canvas.set_draw_color(map_color(col));
+                fill_polygon(&mut self.canvas, &points);
+            }
+            WindowCmd::SetPenWidth(width) => {
+                self.pen_width = width;
+            }
+            WindowCmd::Print(_, msg) => println!("{msg}"),
+            // lines stay solid
+            WindowCmd::SetLineStyle(_) => {}
+            // text goes to stdout, there is no font to size
+            WindowCmd::SetFontSize(_) => {}
+            WindowCmd::SetTitle(title) => _ = self.canvas.window_mut().set_title(&title),
+            WindowCmd::SetTurtle(pos, heading, visible) => {
+                self.turtle = visible.then_some((pos, heading));
+            }
+            WindowCmd::BeginFrame => self.frames += 1,
+            WindowCmd::EndFrame => {
+                self.frames = self.frames.saturating_sub(1);
+                if self.frames == 0 {
+                    for cmd in std::mem::take(&mut self.frame) {
+                        self.exec(cmd);
                     }
-                    WindowCmd::Print(msg) => println!("{msg}"),
                 }
             }
+            // the interpreter already waited
+            WindowCmd::Delay(_) => {}
+            WindowCmd::Flush => self.canvas.present(),
+        }
+    }
+
+    fn run(mut self) {
+        loop {
+            self.erase_cursor();
+            while let Ok(cmd) = self.cmds.try_recv() {
+                self.exec(cmd);
+            }
+            self.draw_cursor();
             self.canvas.present();
             thread::sleep(Duration::from_millis(16));
             if let Some(rem) = &mut self.wait_exit {
@@ -93,13 +244,20 @@ impl SdlWindow {
     fn collect_events(&mut self) {
         let events = self.event_pump.poll_iter().flat_map(|evt| match evt {
             Event::KeyDown {
+                keycode: Some(kc),
+                repeat: false,
+                ..
+            } => map_key(kc).map(WindowEvent::KeyPressed),
+            Event::KeyUp {
                 keycode: Some(kc), ..
-            } => kc
-                .into_i32()
-                .try_into()
-                .ok()
-                .and_then(char::from_u32)
-                .map(WindowEvent::KeyPressed),
+            } => map_key(kc).map(WindowEvent::KeyReleased),
+            Event::Window {
+                win_event: SdlWindowEvent::SizeChanged(width, height),
+                ..
+            } => {
+                self.size = (width as u32, height as u32);
+                Some(WindowEvent::Resized(self.size.0, self.size.1))
+            }
             Event::Quit { .. } => {
                 self.wait_exit.get_or_insert(5);
                 Some(WindowEvent::WindowExited)
@@ -107,11 +265,17 @@ impl SdlWindow {
             Event::MouseButtonDown {
                 x, y, mouse_btn, ..
             } => Some(WindowEvent::MouseClicked(
-                (
-                    2.0 * x as f64 / WIDTH as f64 - 1.0,
-                    1.0 - y as f64 * 2.0 / HEIGHT as f64,
-                ),
-                mouse_btn == sdl2::mouse::MouseButton::Left,
+                unmap_coords(self.size, x, y),
+                mouse_btn == MouseButton::Left,
+            )),
+            Event::MouseMotion { x, y, .. } => {
+                Some(WindowEvent::MouseMoved(unmap_coords(self.size, x, y)))
+            }
+            Event::MouseButtonUp {
+                x, y, mouse_btn, ..
+            } => Some(WindowEvent::MouseReleased(
+                unmap_coords(self.size, x, y),
+                mouse_btn == MouseButton::Left,
             )),
             _ => None,
         });
@@ -120,3 +284,64 @@ impl SdlWindow {
         }
     }
 }
+
+/// scanline fill, SDL itself only knows rectangles
+fn fill_polygon(canvas: &mut Canvas<sdl2::video::Window>, points: &[Point]) {
+    let (Some(min), Some(max)) = (
+        points.iter().map(|p| p.y()).min(),
+        points.iter().map(|p| p.y()).max(),
+    ) else {
+        return;
+    };
+    for y in min..=max {
+        let yf = y as f64 + 0.5;
+        let mut xs: Vec<f64> = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .filter(|(a, b)| (a.y() as f64 <= yf) != (b.y() as f64 <= yf))
+            .map(|(a, b)| {
+                let t = (yf - a.y() as f64) / (b.y() - a.y()) as f64;
+                a.x() as f64 + t * (b.x() - a.x()) as f64
+            })
+            .collect();
+        xs.sort_by(f64::total_cmp);
+        for pair in xs.chunks_exact(2) {
+            let from = Point::new(pair[0].round() as i32, y);
+            let to = Point::new(pair[1].round() as i32, y);
+            canvas.draw_line(from, to).unwrap();
+        }
+    }
+}
+
+/// pixel position to `-1.0..=1.0`
+fn unmap_coords(size: (u32, u32), x: i32, y: i32) -> (f64, f64) {
+    (
+        2.0 * x as f64 / size.0 as f64 - 1.0,
+        1.0 - y as f64 * 2.0 / size.1 as f64,
+    )
+}
+
+fn map_color(col: TColor) -> Color {
+    Color::RGBA(
+        (col.r * 2.55) as u8,
+        (col.g * 2.55) as u8,
+        (col.b * 2.55) as u8,
+        (col.a * 2.55) as u8,
+    )
+}
+
+fn map_key(kc: Keycode) -> Option<Key> {
+    match kc {
+        Keycode::Up => Some(Key::Up),
+        Keycode::Down => Some(Key::Down),
+        Keycode::Left => Some(Key::Left),
+        Keycode::Right => Some(Key::Right),
+        Keycode::Return | Keycode::KpEnter => Some(Key::Enter),
+        kc => kc
+            .into_i32()
+            .try_into()
+            .ok()
+            .and_then(char::from_u32)
+            .and_then(Key::from_char),
+    }
+}
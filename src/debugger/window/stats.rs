@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use crate::debugger::{TColor, TCoord};
+
+use super::{LineStyle, Window, WindowEvent};
+
+/// Counters collected by a [`StatsWindow`]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub draws: usize,
+    pub clears: usize,
+    pub prints: usize,
+    /// summed up length of all lines in turtle units
+    pub line_length: f64,
+}
+
+/// Counts what is drawn on the inner window, everything is forwarded unchanged.
+pub struct StatsWindow<W> {
+    inner: W,
+    stats: Stats,
+}
+
+impl<W: Window> StatsWindow<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    pub fn draw_count(&self) -> usize {
+        self.stats.draws
+    }
+
+    pub fn clear_count(&self) -> usize {
+        self.stats.clears
+    }
+
+    pub fn print_count(&self) -> usize {
+        self.stats.prints
+    }
+
+    pub fn line_length(&self) -> f64 {
+        self.stats.line_length
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Window> Window for StatsWindow<W> {
+    fn init(&mut self) {
+        self.inner.init();
+    }
+
+    fn get_max_coords(&self) -> TCoord {
+        self.inner.get_max_coords()
+    }
+
+    fn set_max_x(&mut self, max_x: f64) {
+        self.inner.set_max_x(max_x);
+    }
+
+    fn set_max_y(&mut self, max_y: f64) {
+        self.inner.set_max_y(max_y);
+    }
+
+    fn draw(&mut self, from: TCoord, to: TCoord, col: TColor) {
+        self.stats.draws += 1;
+        self.stats.line_length += (to.0 - from.0).hypot(to.1 - from.1);
+        self.inner.draw(from, to, col);
+    }
+
+    fn fill(&mut self, points: &[TCoord], col: TColor) {
+        self.inner.fill(points, col);
+    }
+
+    fn clear(&mut self) {
+        self.stats.clears += 1;
+        self.inner.clear();
+    }
+
+    fn set_palette(&mut self, colors: &[TColor]) {
+        self.inner.set_palette(colors);
+    }
+
+    fn clear_rect(&mut self, from: TCoord, to: TCoord) {
+        self.inner.clear_rect(from, to);
+    }
+
+    fn set_pen_width(&mut self, width: f64) {
+        self.inner.set_pen_width(width);
+    }
+
+    fn set_line_style(&mut self, style: LineStyle) {
+        self.inner.set_line_style(style);
+    }
+
+    fn print(&mut self, msg: &str) {
+        self.stats.prints += 1;
+        self.inner.print(msg);
+    }
+
+    fn print_at(&mut self, pos: TCoord, msg: &str) {
+        self.stats.prints += 1;
+        self.inner.print_at(pos, msg);
+    }
+
+    fn set_font_size(&mut self, pt: f64) {
+        self.inner.set_font_size(pt);
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.inner.set_title(title);
+    }
+
+    fn set_turtle(&mut self, pos: TCoord, heading: f64, visible: bool) {
+        self.inner.set_turtle(pos, heading, visible);
+    }
+
+    fn begin_frame(&mut self) {
+        self.inner.begin_frame();
+    }
+
+    fn end_frame(&mut self) {
+        self.inner.end_frame();
+    }
+
+    fn delay(&mut self, time: Duration) {
+        self.inner.delay(time);
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush();
+    }
+
+    fn events(&mut self) -> Vec<WindowEvent> {
+        self.inner.events()
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt::Write as _;
+
+use crate::debugger::{TColor, TCoord};
+
+use super::{LineStyle, Window, WindowEvent};
+
+/// Collects the drawing as SVG document, see [`SvgWindow::finish`].
+#[derive(Debug, Default)]
+pub struct SvgWindow {
+    max_coord: TCoord,
+    pen_width: f64,
+    line_style: LineStyle,
+    font_size: f64,
+    elements: String,
+    /// height of the text stacked by `print`
+    text_height: f64,
+    /// open frames, each one a group
+    frames: usize,
+}
+
+impl SvgWindow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The complete SVG document of everything drawn since the last clear
+    ///
+    /// Frames that were never ended are closed.
+    pub fn finish(mut self) -> String {
+        for _ in 0..self.frames {
+            self.elements.push_str("</g>\n");
+        }
+        let (max_x, max_y) = self.max_coord;
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n\
+             <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"black\"/>\n\
+             <g stroke-linecap=\"round\">\n{}</g>\n</svg>\n",
+            -max_x,
+            -max_y,
+            2.0 * max_x,
+            2.0 * max_y,
+            -max_x,
+            -max_y,
+            2.0 * max_x,
+            2.0 * max_y,
+            self.elements
+        )
+    }
+
+    fn stroke(&self) -> String {
+        let (mut stroke, width) = if self.pen_width > 0.0 {
+            (
+                format!("stroke-width=\"{}\"", self.pen_width),
+                self.pen_width,
+            )
+        } else {
+            // dashes of the thinnest line are sized for its width of 1
+            (
+                "stroke-width=\"1\" vector-effect=\"non-scaling-stroke\"".to_string(),
+                1.0,
+            )
+        };
+        if let Some((dash, gap)) = self.line_style.dashes(width) {
+            let _ = write!(stroke, " stroke-dasharray=\"{dash} {gap}\"");
+        }
+        stroke
+    }
+
+    /// font size in turtle units, the document has no physical size for points
+    fn font_size(&self) -> f64 {
+        if self.font_size > 0.0 {
+            self.font_size
+        } else {
+            1.0
+        }
+    }
+}
+
+/// SVG's y axis points down
+fn flip(coord: TCoord) -> TCoord {
+    // not `-coord.1`, that would print `-0`
+    (coord.0, 0.0 - coord.1)
+}
+
+fn rgb(col: TColor) -> String {
+    format!(
+        "rgb({},{},{})",
+        (col.r * 2.55).round(),
+        (col.g * 2.55).round(),
+        (col.b * 2.55).round()
+    )
+}
+
+/// Extra opacity attribute, empty for opaque colors
+fn opacity(col: TColor, attr: &str) -> String {
+    if col.is_opaque() {
+        String::new()
+    } else {
+        format!(" {attr}-opacity=\"{}\"", col.a.max(0.0) / 100.0)
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+impl Window for SvgWindow {
+    fn init(&mut self) {}
+
+    fn get_max_coords(&self) -> TCoord {
+        self.max_coord
+    }
+
+    fn set_max_x(&mut self, max_x: f64) {
+        self.max_coord.0 = max_x;
+    }
+
+    fn set_max_y(&mut self, max_y: f64) {
+        self.max_coord.1 = max_y;
+    }
+
+    fn draw(&mut self, from: TCoord, to: TCoord, col: TColor) {
+        let (from, to) = (flip(from), flip(to));
+        let _ = writeln!(
+            self.elements,
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\"{} {}/>",
+            from.0,
+            from.1,
+            to.0,
+            to.1,
+            rgb(col),
+            opacity(col, "stroke"),
+            self.stroke()
+        );
+    }
+
+    fn fill(&mut self, points: &[TCoord], col: TColor) {
+        let points = points
+            .iter()
+            .map(|&p| {
+                let (x, y) = flip(p);
+                format!("{x},{y}")
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let _ = writeln!(
+            self.elements,
+            "<polygon points=\"{points}\" fill=\"{}\"{}/>",
+            rgb(col),
+            opacity(col, "fill")
+        );
+    }
+
+    fn clear(&mut self) {
+        self.elements.clear();
+        self.text_height = 0.0;
+        // the groups of open frames are still closed later
+        for _ in 0..self.frames {
+            self.elements.push_str("<g>\n");
+        }
+    }
+
+    /// Covers the area with the background, SVG can't take back what's below
+    fn clear_rect(&mut self, from: TCoord, to: TCoord) {
+        let (from, to) = (flip(from), flip(to));
+        let _ = writeln!(
+            self.elements,
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"black\"/>",
+            from.0.min(to.0),
+            from.1.min(to.1),
+            (to.0 - from.0).abs(),
+            (to.1 - from.1).abs()
+        );
+    }
+
+    fn set_pen_width(&mut self, width: f64) {
+        self.pen_width = width;
+    }
+
+    fn set_line_style(&mut self, style: LineStyle) {
+        self.line_style = style;
+    }
+
+    fn print(&mut self, msg: &str) {
+        // printed lines are stacked in the top left corner
+        self.text_height += self.font_size();
+        let _ = writeln!(
+            self.elements,
+            "<text x=\"{}\" y=\"{}\" font-size=\"{}\" fill=\"white\">{}</text>",
+            -self.max_coord.0,
+            -self.max_coord.1 + self.text_height,
+            self.font_size(),
+            escape(msg)
+        );
+    }
+
+    fn print_at(&mut self, pos: TCoord, msg: &str) {
+        let (x, y) = flip(pos);
+        let _ = writeln!(
+            self.elements,
+            "<text x=\"{x}\" y=\"{y}\" font-size=\"{}\" fill=\"white\">{}</text>",
+            self.font_size(),
+            escape(msg)
+        );
+    }
+
+    fn set_font_size(&mut self, pt: f64) {
+        self.font_size = pt;
+    }
+
+    fn begin_frame(&mut self) {
+        self.frames += 1;
+        self.elements.push_str("<g>\n");
+    }
+
+    fn end_frame(&mut self) {
+        if self.frames > 0 {
+            self.frames -= 1;
+            self.elements.push_str("</g>\n");
+        }
+    }
+
+    fn events(&mut self) -> Vec<WindowEvent> {
+        Vec::new()
+    }
+}
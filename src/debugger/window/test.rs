@@ -0,0 +1,881 @@
+use super::*;
+
+#[test]
+fn key_events_untouched() {
+    let (mut window, _cmds, events) = ChannelWindow::construct();
+    window.init_with(20.0, 15.0);
+    events.send(WindowEvent::KeyPressed(Key::Left)).unwrap();
+    events
+        .send(WindowEvent::MouseClicked((0.5, -1.0), true))
+        .unwrap();
+    events
+        .send(WindowEvent::KeyReleased(Key::Letter('w')))
+        .unwrap();
+    assert_eq!(
+        window.events(),
+        [
+            WindowEvent::KeyPressed(Key::Left),
+            WindowEvent::MouseClicked((10.0, -15.0), true),
+            WindowEvent::KeyReleased(Key::Letter('w')),
+        ]
+    );
+}
+
+#[test]
+fn key_from_char() {
+    assert_eq!(Key::from_char('Q'), Some(Key::Letter('q')));
+    assert_eq!(Key::from_char('7'), Some(Key::Digit(7)));
+    assert_eq!(Key::from_char('+'), None);
+    assert_eq!(Key::Digit(7).to_string(), "7");
+}
+
+#[test]
+fn fill_normalized() {
+    let (mut window, cmds, _events) = ChannelWindow::construct();
+    window.init_with(20.0, 10.0);
+    window.fill(
+        &[(0.0, 0.0), (10.0, 0.0), (0.0, -5.0)],
+        TColor::new(100.0, 0.0, 0.0),
+    );
+    assert_eq!(
+        cmds.try_iter().collect::<Vec<_>>(),
+        [WindowCmd::Fill(
+            vec![(0.0, 0.0), (0.5, 0.0), (0.0, -0.5)],
+            TColor::new(100.0, 0.0, 0.0)
+        )]
+    );
+}
+
+#[test]
+fn default_fill_draws() {
+    struct Lines(Vec<(TCoord, TCoord)>);
+    impl Window for Lines {
+        fn init(&mut self) {}
+        fn get_max_coords(&self) -> TCoord {
+            (1.0, 1.0)
+        }
+        fn set_max_x(&mut self, _: f64) {}
+        fn set_max_y(&mut self, _: f64) {}
+        fn draw(&mut self, from: TCoord, to: TCoord, _: TColor) {
+            self.0.push((from, to));
+        }
+        fn clear(&mut self) {}
+        fn print(&mut self, _: &str) {}
+        fn events(&mut self) -> Vec<WindowEvent> {
+            Vec::new()
+        }
+    }
+
+    let mut lines = Lines(Vec::new());
+    lines.fill(
+        &[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+        TColor::new(0.0, 0.0, 0.0),
+    );
+    assert!(lines.0.iter().all(|&(from, _)| from == (0.0, 0.0)));
+    assert_eq!(lines.0.first().unwrap().1, (1.0, 0.0));
+    assert_eq!(lines.0.last().unwrap().1, (0.0, 1.0));
+}
+
+#[test]
+fn pen_width_normalized() {
+    let (mut window, cmds, _events) = ChannelWindow::construct();
+    window.init_with(20.0, 15.0);
+    window.set_pen_width(2.0);
+    window.draw((0.0, 0.0), (10.0, 0.0), TColor::new(0.0, 0.0, 0.0));
+    window.flush();
+    assert_eq!(
+        cmds.try_iter().collect::<Vec<_>>(),
+        [
+            WindowCmd::SetPenWidth(0.1),
+            WindowCmd::Draw((0.0, 0.0), (0.5, 0.0), TColor::new(0.0, 0.0, 0.0)),
+            WindowCmd::Flush,
+        ]
+    );
+}
+
+#[test]
+fn turtle_cursor_normalized() {
+    let (mut window, cmds, _events) = ChannelWindow::construct();
+    window.init_with(20.0, 10.0);
+    window.set_turtle((10.0, 5.0), 45.0, true);
+    let Some(WindowCmd::SetTurtle(pos, heading, true)) = cmds.try_iter().next() else {
+        panic!("cursor not sent");
+    };
+    assert_eq!(pos, (0.5, 0.5));
+    // a step of 1 in both directions is twice as far along y in this window
+    assert!(
+        (heading - 2f64.atan().to_degrees()).abs() < 1e-9,
+        "{heading}"
+    );
+}
+
+#[test]
+fn clear_rect_normalized() {
+    let (mut window, cmds, _events) = ChannelWindow::construct();
+    window.init_with(20.0, 10.0);
+    window.clear_rect((-10.0, 5.0), (20.0, -10.0));
+    assert_eq!(
+        cmds.try_iter().collect::<Vec<_>>(),
+        [WindowCmd::ClearRect((-0.5, 0.5), (1.0, -1.0))]
+    );
+}
+
+#[test]
+fn batched_draws() {
+    let (mut window, cmds, _events) = ChannelWindow::construct();
+    window.init_with(10.0, 10.0);
+    for i in 0..1000 {
+        window.draw((0.0, 0.0), (i as f64, 1.0), TColor::new(0.0, 0.0, 0.0));
+    }
+    window.flush();
+    let cmds: Vec<_> = cmds.try_iter().collect();
+    assert!(cmds.len() < 10);
+    let lines: Vec<_> = cmds
+        .into_iter()
+        .flat_map(|cmd| match cmd {
+            WindowCmd::DrawBatch(lines) => lines,
+            WindowCmd::Flush => Vec::new(),
+            cmd => panic!("unexpected command {cmd:?}"),
+        })
+        .collect();
+    assert_eq!(lines.len(), 1000);
+    assert_eq!(
+        lines[999],
+        ((0.0, 0.0), (99.9, 0.1), TColor::new(0.0, 0.0, 0.0))
+    );
+}
+
+#[test]
+fn batched_palette_draws() {
+    let (red, blue) = (TColor::new(100.0, 0.0, 0.0), TColor::new(0.0, 0.0, 100.0));
+    let (mut window, cmds, _events) = ChannelWindow::construct();
+    window.init_with(10.0, 10.0);
+    window.set_palette(&[red]);
+    window.draw((0.0, 0.0), (10.0, 0.0), red);
+    window.draw((10.0, 0.0), (10.0, 10.0), red);
+    window.draw((10.0, 10.0), (0.0, 10.0), blue);
+    window.draw((0.0, 10.0), (0.0, 0.0), red);
+    window.flush();
+    assert_eq!(
+        cmds.try_iter().collect::<Vec<_>>(),
+        [
+            WindowCmd::SetPalette(vec![red]),
+            WindowCmd::DrawIndexedBatch(vec![
+                ((0.0, 0.0), (1.0, 0.0), 0),
+                ((1.0, 0.0), (1.0, 1.0), 0)
+            ]),
+            WindowCmd::Draw((1.0, 1.0), (0.0, 1.0), blue),
+            WindowCmd::DrawIndexed((0.0, 1.0), (0.0, 0.0), 0),
+            WindowCmd::Flush,
+        ]
+    );
+}
+
+#[test]
+fn flush_before_clear() {
+    let (mut window, cmds, _events) = ChannelWindow::construct();
+    window.init_with(10.0, 10.0);
+    window.draw((0.0, 0.0), (10.0, 10.0), TColor::new(0.0, 0.0, 0.0));
+    window.clear();
+    assert_eq!(
+        cmds.try_iter().collect::<Vec<_>>(),
+        [
+            WindowCmd::Draw((0.0, 0.0), (1.0, 1.0), TColor::new(0.0, 0.0, 0.0)),
+            WindowCmd::Clear,
+        ]
+    );
+}
+
+#[test]
+fn flush_on_drop() {
+    let (mut window, cmds, _events) = ChannelWindow::construct();
+    window.init_with(10.0, 10.0);
+    window.draw((0.0, 0.0), (10.0, 10.0), TColor::new(0.0, 0.0, 0.0));
+    window.flush();
+    window.draw((10.0, 10.0), (0.0, 0.0), TColor::new(0.0, 0.0, 0.0));
+    drop(window);
+    assert_eq!(
+        cmds.try_iter().collect::<Vec<_>>(),
+        [
+            WindowCmd::Draw((0.0, 0.0), (1.0, 1.0), TColor::new(0.0, 0.0, 0.0)),
+            WindowCmd::Flush,
+            WindowCmd::Draw((1.0, 1.0), (0.0, 0.0), TColor::new(0.0, 0.0, 0.0)),
+            WindowCmd::Flush,
+        ]
+    );
+}
+
+#[test]
+fn draw_before_sizing() {
+    let (mut window, cmds, events) = ChannelWindow::construct();
+    window.draw((2.0, -3.0), (4.0, 5.0), TColor::new(0.0, 0.0, 0.0));
+    window.set_pen_width(0.5);
+    events
+        .send(WindowEvent::MouseClicked((1.5, 2.5), true))
+        .unwrap();
+    assert_eq!(
+        window.events(),
+        [WindowEvent::MouseClicked((1.5, 2.5), true)]
+    );
+    assert_eq!(
+        cmds.try_iter().collect::<Vec<_>>(),
+        [
+            WindowCmd::Draw((2.0, -3.0), (4.0, 5.0), TColor::new(0.0, 0.0, 0.0)),
+            WindowCmd::SetPenWidth(0.5),
+        ]
+    );
+}
+
+#[test]
+fn zero_extent_axis() {
+    let (mut window, cmds, events) = ChannelWindow::construct();
+    window.init_with(20.0, 0.0);
+    window.draw((10.0, 3.0), (-20.0, -1.5), TColor::new(0.0, 0.0, 0.0));
+    window.flush();
+    events.send(WindowEvent::MouseMoved((0.5, 2.0))).unwrap();
+    assert_eq!(window.events(), [WindowEvent::MouseMoved((10.0, 2.0))]);
+    assert_eq!(
+        cmds.try_iter().next(),
+        Some(WindowCmd::Draw(
+            (0.5, 3.0),
+            (-1.0, -1.5),
+            TColor::new(0.0, 0.0, 0.0)
+        ))
+    );
+}
+
+#[test]
+fn center_origin() {
+    let (window, cmds, events) = ChannelWindow::construct();
+    let mut window = window.origin(Origin::Center);
+    window.init_with(20.0, 15.0);
+    window.draw((0.0, 0.0), (10.0, 7.5), TColor::new(0.0, 0.0, 0.0));
+    events
+        .send(WindowEvent::MouseClicked((0.0, 0.0), true))
+        .unwrap();
+    assert_eq!(
+        window.events(),
+        [WindowEvent::MouseClicked((0.0, 0.0), true)]
+    );
+    assert_eq!(
+        cmds.try_iter().next(),
+        Some(WindowCmd::Draw(
+            (0.0, 0.0),
+            (0.5, 0.5),
+            TColor::new(0.0, 0.0, 0.0)
+        ))
+    );
+}
+
+#[test]
+fn top_left_origin() {
+    let (window, cmds, events) = ChannelWindow::construct();
+    let mut window = window.origin(Origin::TopLeft).y_down(true);
+    window.init_with(20.0, 15.0);
+    window.draw((0.0, 0.0), (10.0, 7.5), TColor::new(0.0, 0.0, 0.0));
+    window.print_at((40.0, 30.0), "corner");
+    events
+        .send(WindowEvent::MouseClicked((0.0, 0.0), true))
+        .unwrap();
+    assert_eq!(
+        window.events(),
+        [WindowEvent::MouseClicked((20.0, 15.0), true)]
+    );
+    assert_eq!(
+        cmds.try_iter().collect::<Vec<_>>(),
+        [
+            WindowCmd::Draw((-1.0, 1.0), (-0.5, 0.5), TColor::new(0.0, 0.0, 0.0)),
+            WindowCmd::Print((1.0, -1.0), "corner".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn wait_for_event() {
+    let (mut window, _cmds, events) = ChannelWindow::construct();
+    window.init_with(20.0, 15.0);
+    let sender = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        events
+            .send(WindowEvent::MouseClicked((0.5, -1.0), false))
+            .unwrap();
+    });
+    assert_eq!(
+        window.wait_event(Some(std::time::Duration::from_secs(5))),
+        Some(WindowEvent::MouseClicked((10.0, -15.0), false))
+    );
+    sender.join().unwrap();
+    // the sender is gone now
+    assert_eq!(window.wait_event(None), Some(WindowEvent::Closed));
+    assert_eq!(
+        window.wait_event(Some(std::time::Duration::from_millis(1))),
+        None
+    );
+}
+
+#[test]
+fn commands_receiver_dropped() {
+    let (mut window, cmds, _events) = ChannelWindow::construct();
+    window.init_with(20.0, 15.0);
+    drop(cmds);
+    window.draw((0.0, 0.0), (1.0, 1.0), TColor::new(0.0, 0.0, 0.0));
+    window.clear();
+    window.print("still alive");
+    assert_eq!(window.events(), [WindowEvent::Closed]);
+    assert_eq!(window.events(), []);
+}
+
+#[test]
+fn wait_event_timeout() {
+    let (mut window, _cmds, _events) = ChannelWindow::construct();
+    assert_eq!(
+        window.wait_event(Some(std::time::Duration::from_millis(10))),
+        None
+    );
+}
+
+#[test]
+fn print_position() {
+    let (mut window, cmds, _events) = ChannelWindow::construct();
+    window.init_with(20.0, 15.0);
+    window.print_at((10.0, -7.5), "x");
+    window.draw((0.0, 0.0), (4.0, 3.0), TColor::new(0.0, 0.0, 0.0));
+    window.print("y");
+    window.flush();
+    let prints: Vec<_> = cmds
+        .try_iter()
+        .filter(|cmd| matches!(cmd, WindowCmd::Print(..)))
+        .collect();
+    assert_eq!(
+        prints,
+        [
+            WindowCmd::Print((0.5, -0.5), "x".to_string()),
+            WindowCmd::Print((0.2, 0.2), "y".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn font_size_command() {
+    let (mut window, cmds, _events) = ChannelWindow::construct();
+    window.init_with(20.0, 15.0);
+    window.set_font_size(12.0);
+    window.print_at((10.0, 0.0), "x");
+    window.flush();
+    assert_eq!(
+        cmds.try_iter().collect::<Vec<_>>(),
+        [
+            WindowCmd::SetFontSize(12.0),
+            WindowCmd::Print((0.5, 0.0), "x".to_string()),
+            WindowCmd::Flush,
+        ]
+    );
+}
+
+#[test]
+fn svg_font_size() {
+    let mut svg = SvgWindow::new();
+    svg.init_with(20.0, 15.0);
+    svg.print_at((1.0, 2.0), "default");
+    svg.set_font_size(3.0);
+    svg.print_at((1.0, 2.0), "big");
+    let doc = svg.finish();
+    assert!(doc.contains("<text x=\"1\" y=\"-2\" font-size=\"1\" fill=\"white\">default</text>"));
+    assert!(doc.contains("<text x=\"1\" y=\"-2\" font-size=\"3\" fill=\"white\">big</text>"));
+}
+
+#[test]
+fn svg_line_style() {
+    let mut svg = SvgWindow::new();
+    svg.init_with(20.0, 15.0);
+    let col = TColor::new(100.0, 100.0, 0.0);
+    svg.draw((0.0, 0.0), (1.0, 0.0), col);
+    svg.set_line_style(LineStyle::Dotted);
+    svg.draw((0.0, 0.0), (2.0, 0.0), col);
+    svg.set_line_style(LineStyle::Dashed);
+    svg.set_pen_width(0.5);
+    svg.draw((0.0, 0.0), (3.0, 0.0), col);
+    let doc = svg.finish();
+    let line = |x| {
+        doc.lines()
+            .find(|line| line.contains(&format!("x2=\"{x}\"")))
+            .unwrap()
+    };
+    assert!(!line(1).contains("stroke-dasharray"));
+    assert!(line(2).contains(" stroke-dasharray=\"0 2\""));
+    assert!(line(3).contains("stroke-width=\"0.5\" stroke-dasharray=\"2 1.5\""));
+}
+
+#[test]
+fn default_pen_width_visible() {
+    let col = TColor::new(100.0, 100.0, 0.0);
+    let mut svg = SvgWindow::new();
+    svg.init_with(20.0, 15.0);
+    svg.set_pen_width(DEFAULT_PEN_WIDTH);
+    svg.draw((0.0, 0.0), (1.0, 0.0), col);
+    assert!(svg
+        .finish()
+        .contains("stroke-width=\"1\" vector-effect=\"non-scaling-stroke\""));
+
+    let mut tikz = TikzWindow::new();
+    tikz.init_with(20.0, 15.0);
+    tikz.set_pen_width(DEFAULT_PEN_WIDTH);
+    tikz.draw((0.0, 0.0), (1.0, 0.0), col);
+    assert!(tikz
+        .finish()
+        .contains("\n\\draw[turtle0] (0,0) -- (1,0);\n"));
+}
+
+#[test]
+fn svg_frames() {
+    let mut svg = SvgWindow::new();
+    svg.init_with(20.0, 15.0);
+    let col = TColor::new(100.0, 100.0, 0.0);
+    svg.begin_frame();
+    svg.draw((0.0, 0.0), (1.0, 0.0), col);
+    svg.end_frame();
+    svg.end_frame();
+    svg.begin_frame();
+    svg.clear();
+    svg.draw((0.0, 0.0), (2.0, 0.0), col);
+    let doc = svg.finish();
+    let body: Vec<_> = doc
+        .lines()
+        .skip_while(|line| !line.starts_with("<g stroke"))
+        .collect();
+    assert_eq!(body.len(), 6);
+    assert_eq!(body[1], "<g>");
+    assert!(body[2].contains("x2=\"2\""));
+    assert_eq!(body[3..5], ["</g>", "</g>"]);
+}
+
+#[test]
+fn bounding_box() {
+    let mut window = BoundsWindow::new();
+    window.init_with(20.0, 15.0);
+    assert_eq!(window.bounds(), ((0.0, 0.0), (0.0, 0.0)));
+    let col = TColor::new(100.0, 100.0, 0.0);
+    window.draw((1.0, 2.0), (3.0, -4.0), col);
+    window.draw((-5.5, 0.5), (2.0, 1.0), col);
+    window.clear();
+    window.draw((30.0, 2.0), (1.0, 2.0), col);
+    assert_eq!(window.bounds(), ((-5.5, -4.0), (30.0, 2.0)));
+    // the extent doesn't need to contain the origin
+    let mut window = BoundsWindow::new();
+    window.draw((1.0, 2.0), (3.0, 4.0), col);
+    assert_eq!(window.bounds(), ((1.0, 2.0), (3.0, 4.0)));
+}
+
+#[test]
+fn svg_export() {
+    let mut svg = SvgWindow::new();
+    svg.init_with(20.0, 15.0);
+    svg.draw((0.0, 0.0), (10.0, 5.0), TColor::new(100.0, 100.0, 0.0));
+    svg.print("a < b");
+    let doc = svg.finish();
+    assert!(doc.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-20 -15 40 30\">"));
+    assert!(doc.contains("<line x1=\"0\" y1=\"0\" x2=\"10\" y2=\"-5\" stroke=\"rgb(255,255,0)\""));
+    assert!(doc.contains(">a &lt; b</text>"));
+    assert!(doc.trim_end().ends_with("</svg>"));
+}
+
+#[test]
+fn tikz_export() {
+    let mut tikz = TikzWindow::new();
+    tikz.init_with(20.0, 15.0);
+    tikz.draw((0.0, 0.0), (10.0, 5.0), TColor::new(100.0, 100.0, 0.0));
+    tikz.print_at((1.0, 2.0), "50% & more");
+    let doc = tikz.finish();
+    assert!(doc.starts_with("\\begin{tikzpicture}[x=0.25cm,y=0.25cm]\n"));
+    assert!(doc.contains("\\definecolor{turtle0}{RGB}{255,255,0}\n"));
+    assert!(doc.contains("\n\\draw[turtle0] (0,0) -- (10,5);\n"));
+    assert!(doc.contains("at (1,2) {50\\% \\& more};"));
+    assert!(doc.ends_with("\\end{tikzpicture}\n"));
+}
+
+#[test]
+fn gcode_export() {
+    let mut gcode = GCodeWindow::new().scale(2.0).z_heights(3.0, -1.0);
+    gcode.init_with(20.0, 15.0);
+    let col = TColor::new(0.0, 0.0, 0.0);
+    gcode.draw((0.0, 0.0), (10.0, 5.0), col);
+    gcode.draw((10.0, 5.0), (10.0, 0.0), col);
+    gcode.draw((-1.5, 0.0), (0.0, 0.0), col);
+    gcode.draw((0.0, 0.0), (1.0, 0.0), TColor::new(100.0, 0.0, 0.0));
+    assert_eq!(
+        gcode.finish(),
+        "G21 ; millimeters
+G90 ; absolute positions
+G0 Z3
+G0 X0 Y0
+G1 Z-1
+G1 X20 Y10 F1000
+G1 X20 Y0 F1000
+G0 Z3
+G0 X-3 Y0
+G1 Z-1
+G1 X0 Y0 F1000
+G0 Z3
+M0 ; change pen to rgb(255,0,0)
+G0 X0 Y0
+G1 Z-1
+G1 X2 Y0 F1000
+G0 Z3
+"
+    );
+}
+
+#[test]
+fn svg_clear() {
+    let mut svg = SvgWindow::new();
+    svg.init_with(20.0, 15.0);
+    svg.draw((0.0, 0.0), (10.0, 5.0), TColor::new(100.0, 100.0, 0.0));
+    svg.clear();
+    svg.draw((1.0, 1.0), (2.0, 2.0), TColor::new(0.0, 100.0, 0.0));
+    let doc = svg.finish();
+    assert_eq!(doc.matches("<line").count(), 1);
+    assert!(doc.contains("rgb(0,255,0)"));
+}
+
+#[test]
+fn svg_opacity() {
+    let mut svg = SvgWindow::new();
+    svg.init_with(20.0, 15.0);
+    svg.draw((0.0, 0.0), (1.0, 1.0), TColor::new(100.0, 0.0, 0.0));
+    svg.draw(
+        (0.0, 0.0),
+        (1.0, 1.0),
+        TColor::with_alpha(100.0, 0.0, 0.0, 50.0),
+    );
+    let doc = svg.finish();
+    assert_eq!(doc.matches("stroke-opacity=\"0.5\"").count(), 1);
+}
+
+#[cfg(feature = "raster")]
+#[test]
+fn raster_line() {
+    let mut img = RasterWindow::new(2.0).with_background(TColor::new(0.0, 0.0, 100.0));
+    img.init_with(5.0, 5.0);
+    assert_eq!(img.size(), (20, 20));
+    img.draw((-5.0, 0.0), (5.0, 0.0), TColor::new(100.0, 0.0, 0.0));
+    assert_eq!(img.pixel(0, 10), Some([255, 0, 0, 255]));
+    assert_eq!(img.pixel(19, 10), Some([255, 0, 0, 255]));
+    assert_eq!(img.pixel(10, 0), Some([0, 0, 255, 255]));
+    img.clear();
+    assert_eq!(img.pixel(0, 10), Some([0, 0, 255, 255]));
+
+    img.draw(
+        (-5.0, 0.0),
+        (5.0, 0.0),
+        TColor::with_alpha(100.0, 0.0, 0.0, 50.0),
+    );
+    assert_eq!(img.pixel(0, 10), Some([127, 0, 128, 255]));
+
+    img.clear_rect((-5.0, 1.0), (0.0, -1.0));
+    assert_eq!(img.pixel(0, 10), Some([0, 0, 255, 255]));
+    assert_eq!(img.pixel(10, 10), Some([127, 0, 128, 255]));
+
+    // only the visible part of a huge line is stepped through
+    img.clear();
+    img.draw((0.0, 1e12), (0.0, -1e12), TColor::new(100.0, 0.0, 0.0));
+    assert_eq!(img.pixel(10, 0), Some([255, 0, 0, 255]));
+    assert_eq!(img.pixel(10, 19), Some([255, 0, 0, 255]));
+    img.draw((-1e12, 10.0), (1e12, 10.0), TColor::new(100.0, 0.0, 0.0));
+    assert_eq!(img.pixel(0, 0), Some([0, 0, 255, 255]));
+
+    let path = std::env::temp_dir().join("turtle_raster_line.png");
+    img.save_png(&path).unwrap();
+    assert!(std::fs::read(&path).unwrap().starts_with(b"\x89PNG"));
+}
+
+#[test]
+fn record_and_replay() {
+    let (window, cmds, events) = ChannelWindow::construct();
+    let mut rec = RecordingWindow::new(window);
+    rec.init_with(20.0, 10.0);
+    rec.draw((0.0, 0.0), (10.0, 5.0), TColor::new(100.0, 0.0, 0.0));
+    rec.print("hi");
+    rec.clear();
+    events
+        .send(WindowEvent::MouseClicked((0.5, 0.5), true))
+        .unwrap();
+    assert_eq!(rec.events(), [WindowEvent::MouseClicked((10.0, 5.0), true)]);
+    let original: Vec<_> = cmds.try_iter().collect();
+    let log = rec.into_log();
+    assert_eq!(
+        log,
+        [
+            WindowCmd::Draw((0.0, 0.0), (10.0, 5.0), TColor::new(100.0, 0.0, 0.0)),
+            WindowCmd::Print((10.0, 5.0), "hi".to_string()),
+            WindowCmd::Clear,
+        ]
+    );
+
+    let (mut target, replayed, _events) = ChannelWindow::construct();
+    target.init_with(20.0, 10.0);
+    replay(&log, &mut target);
+    target.flush();
+    let mut replayed: Vec<_> = replayed.try_iter().collect();
+    assert_eq!(replayed.pop(), Some(WindowCmd::Flush));
+    assert_eq!(replayed, original);
+}
+
+#[test]
+fn undo_redo_drawing() {
+    let (window, cmds, _events) = ChannelWindow::construct();
+    let mut rec = RecordingWindow::new(window);
+    rec.init_with(20.0, 10.0);
+    assert!(!rec.undo());
+    let col = TColor::new(0.0, 0.0, 0.0);
+    for x in 0..3 {
+        rec.draw((x as f64, 0.0), (x as f64 + 1.0, 0.0), col);
+    }
+    rec.flush();
+    cmds.try_iter().for_each(drop);
+    let lines = |cmds: Vec<WindowCmd>| -> Vec<_> {
+        cmds.into_iter()
+            .flat_map(|cmd| match cmd {
+                WindowCmd::Draw(from, to, _) => vec![(from, to)],
+                WindowCmd::DrawBatch(lines) => lines.iter().map(|l| (l.0, l.1)).collect(),
+                _ => Vec::new(),
+            })
+            .collect()
+    };
+
+    assert!(rec.undo());
+    let replayed: Vec<_> = cmds.try_iter().collect();
+    assert_eq!(replayed[0], WindowCmd::Clear);
+    assert_eq!(
+        lines(replayed),
+        [((0.0, 0.0), (0.05, 0.0)), ((0.05, 0.0), (0.1, 0.0))]
+    );
+
+    assert!(rec.redo());
+    assert!(!rec.redo());
+    assert_eq!(
+        lines(cmds.try_iter().collect()),
+        [((0.1, 0.0), (0.15, 0.0))]
+    );
+    assert_eq!(rec.log().len(), 4);
+
+    // the interpreter flushes and sets the pen after an undo, which doesn't draw anything
+    rec.undo();
+    rec.flush();
+    rec.set_pen_width(2.0);
+    rec.set_title("undone");
+    assert!(rec.redo());
+    assert_eq!(rec.log().iter().filter(|cmd| cmd.is_visible()).count(), 3);
+
+    rec.undo();
+    rec.draw((0.0, 0.0), (0.0, 1.0), col);
+    assert!(!rec.redo());
+}
+
+#[test]
+fn undo_frame() {
+    let mut rec = RecordingWindow::new(NullWindow::default());
+    let col = TColor::new(0.0, 0.0, 0.0);
+    rec.draw((0.0, 0.0), (1.0, 0.0), col);
+    rec.begin_frame();
+    rec.draw((1.0, 0.0), (2.0, 0.0), col);
+    rec.begin_frame();
+    rec.draw((2.0, 0.0), (3.0, 0.0), col);
+    rec.end_frame();
+    rec.end_frame();
+    assert!(rec.undo());
+    assert_eq!(rec.log(), [WindowCmd::Draw((0.0, 0.0), (1.0, 0.0), col)]);
+    assert!(rec.redo());
+    assert_eq!(rec.log().len(), 7);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_round_trip() {
+    let mut rec = RecordingWindow::new(JsonWindow::new(Vec::new()));
+    rec.init_with(20.0, 15.0);
+    rec.set_title("lines");
+    rec.set_pen_width(0.5);
+    rec.draw((0.0, 0.0), (10.0, 5.0), TColor::new(100.0, 0.0, 0.0));
+    rec.print("say \"hi\"\nthere");
+    rec.fill(
+        &[(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)],
+        TColor::with_alpha(0.0, 0.0, 100.0, 50.0),
+    );
+    rec.clear();
+    rec.flush();
+    let log = rec.log().to_vec();
+    let out = rec.into_inner().finish().unwrap();
+    let text = String::from_utf8(out).unwrap();
+    assert_eq!(text.lines().count(), log.len());
+    assert!(text.contains("\n\"Clear\"\n"));
+
+    let parsed = read_commands(format!("{text}\n").as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(parsed, log);
+
+    let err = read_commands("\"Clear\"\n{\"Draw\":[]}\n".as_bytes())
+        .nth(1)
+        .unwrap();
+    assert!(matches!(err, Err(JsonError::Parse { line: 2, .. })));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_palette() {
+    let (yellow, red) = (TColor::new(100.0, 100.0, 0.0), TColor::new(100.0, 0.0, 0.0));
+    let mut json = JsonWindow::new(Vec::new());
+    json.set_palette(&[red, yellow]);
+    json.draw((0.0, 0.0), (1.0, 0.0), yellow);
+    json.draw((1.0, 0.0), (2.0, 0.0), TColor::new(0.0, 0.0, 100.0));
+    let text = String::from_utf8(json.finish().unwrap()).unwrap();
+    assert!(text.contains("{\"DrawIndexed\":[[0.0,0.0],[1.0,0.0],1]}\n"));
+
+    let cmds = read_commands(text.as_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let mut rec = RecordingWindow::new(NullWindow::default());
+    replay(&cmds, &mut rec);
+    assert_eq!(
+        rec.log(),
+        [
+            WindowCmd::Draw((0.0, 0.0), (1.0, 0.0), yellow),
+            WindowCmd::Draw((1.0, 0.0), (2.0, 0.0), TColor::new(0.0, 0.0, 100.0)),
+        ]
+    );
+}
+
+#[test]
+fn set_title() {
+    let (mut window, cmds, _events) = ChannelWindow::construct();
+    window.init_with(20.0, 15.0);
+    window.set_title("foo");
+    assert_eq!(
+        cmds.try_iter().collect::<Vec<_>>(),
+        [WindowCmd::SetTitle("foo".to_string())]
+    );
+}
+
+#[test]
+fn pointer_events_scaled() {
+    let (mut window, _cmds, events) = ChannelWindow::construct();
+    window.init_with(20.0, 15.0);
+    events
+        .send(WindowEvent::MouseClicked((0.5, 0.2), false))
+        .unwrap();
+    events.send(WindowEvent::MouseMoved((-1.0, 1.0))).unwrap();
+    events
+        .send(WindowEvent::MouseReleased((0.25, -0.4), true))
+        .unwrap();
+    assert_eq!(
+        window.events(),
+        [
+            WindowEvent::MouseClicked((10.0, 3.0), false),
+            WindowEvent::MouseMoved((-20.0, 15.0)),
+            WindowEvent::MouseReleased((5.0, -6.0), true),
+        ]
+    );
+}
+
+#[test]
+fn stamped_events_ordered() {
+    let (mut window, _cmds, events) = ChannelWindow::construct();
+    window.init_with(20.0, 15.0);
+    events
+        .send(WindowEvent::MouseClicked((0.5, 0.2), true))
+        .unwrap();
+    let clone = events.clone();
+    clone
+        .send(WindowEvent::MouseClicked((-0.5, 0.2), true))
+        .unwrap();
+    let stamped = window.stamped_events();
+    let seqs: Vec<_> = stamped.iter().map(|evt| evt.seq).collect();
+    assert_eq!(seqs, [0, 1]);
+    assert!(stamped[0].time <= stamped[1].time);
+    assert_eq!(
+        stamped[1].event,
+        WindowEvent::MouseClicked((-10.0, 3.0), true)
+    );
+
+    drop((events, clone));
+    let closed = window.wait_stamped(None).unwrap();
+    assert_eq!((closed.seq, closed.event), (2, WindowEvent::Closed));
+}
+
+#[test]
+fn coalesced_moves() {
+    let send_all = |events: &EventSender| {
+        for x in 0..5 {
+            events
+                .send(WindowEvent::MouseMoved((x as f64 / 10.0, 0.0)))
+                .unwrap();
+        }
+        events
+            .send(WindowEvent::MouseClicked((0.5, 0.0), true))
+            .unwrap();
+        events.send(WindowEvent::MouseMoved((1.0, 0.0))).unwrap();
+    };
+    let (window, _cmds, events) = ChannelWindow::construct();
+    let mut window = window.coalesce_moves(true);
+    window.init_with(20.0, 15.0);
+    send_all(&events);
+    assert_eq!(
+        window.events(),
+        [
+            WindowEvent::MouseMoved((8.0, 0.0)),
+            WindowEvent::MouseClicked((10.0, 0.0), true),
+            WindowEvent::MouseMoved((20.0, 0.0)),
+        ]
+    );
+
+    let (mut window, _cmds, events) = ChannelWindow::construct();
+    window.init_with(20.0, 15.0);
+    send_all(&events);
+    assert_eq!(window.events().len(), 7);
+}
+
+#[test]
+fn resize_unscaled() {
+    let (mut window, _cmds, events) = ChannelWindow::construct();
+    window.init_with(20.0, 15.0);
+    assert_eq!(window.pixel_size(), None);
+    events.send(WindowEvent::Resized(1024, 768)).unwrap();
+    assert_eq!(window.events(), [WindowEvent::Resized(1024, 768)]);
+    assert_eq!(window.pixel_size(), Some((1024, 768)));
+    assert_eq!(window.get_max_coords(), (20.0, 15.0));
+}
+
+#[test]
+fn closed_once() {
+    let (mut window, _cmds, events) = ChannelWindow::construct();
+    window.init_with(20.0, 15.0);
+    events.send(WindowEvent::KeyPressed(Key::Space)).unwrap();
+    drop(events);
+    assert_eq!(
+        window.events(),
+        [WindowEvent::KeyPressed(Key::Space), WindowEvent::Closed]
+    );
+    assert_eq!(window.events(), []);
+}
+
+#[test]
+fn stats_counted() {
+    let mut stats = StatsWindow::new(NullWindow::default());
+    stats.init_with(20.0, 15.0);
+    stats.draw((0.0, 0.0), (3.0, 4.0), TColor::new(0.0, 0.0, 0.0));
+    stats.draw((3.0, 4.0), (3.0, 0.0), TColor::new(0.0, 0.0, 0.0));
+    stats.print("hi");
+    stats.clear();
+    stats.draw((0.0, 0.0), (-1.0, 0.0), TColor::new(0.0, 0.0, 0.0));
+    assert_eq!(
+        stats.stats(),
+        Stats {
+            draws: 3,
+            clears: 1,
+            prints: 1,
+            line_length: 10.0,
+        }
+    );
+    assert_eq!(stats.draw_count(), 3);
+    assert_eq!(stats.get_max_coords(), (20.0, 15.0));
+}
@@ -0,0 +1,196 @@
+use std::fmt::Write as _;
+
+use crate::debugger::{TColor, TCoord};
+
+use super::{Window, WindowEvent};
+
+/// Width of the picture in cm, independent of the turtle's extent
+const PICTURE_WIDTH: f64 = 10.0;
+const PT_PER_CM: f64 = 28.45;
+
+/// Collects the drawing as TikZ picture for LaTeX, see [`TikzWindow::finish`].
+///
+/// Colors are defined with `\definecolor`, so the document needs the xcolor package.
+#[derive(Debug, Default)]
+pub struct TikzWindow {
+    max_coord: TCoord,
+    pen_width: f64,
+    font_size: f64,
+    /// colors used so far, in the order of their definition
+    colors: Vec<TColor>,
+    commands: String,
+    /// height of the text stacked by `print`
+    text_height: f64,
+}
+
+impl TikzWindow {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The complete `tikzpicture` environment of everything drawn since the last clear
+    pub fn finish(self) -> String {
+        let (max_x, max_y) = self.max_coord;
+        let unit = self.unit();
+        let mut doc = format!("\\begin{{tikzpicture}}[x={unit}cm,y={unit}cm]\n");
+        for (idx, col) in self.colors.iter().enumerate() {
+            let _ = writeln!(
+                doc,
+                "\\definecolor{{turtle{idx}}}{{RGB}}{{{},{},{}}}",
+                (col.r * 2.55).round(),
+                (col.g * 2.55).round(),
+                (col.b * 2.55).round()
+            );
+        }
+        let _ = writeln!(
+            doc,
+            "\\fill[black] ({},{}) rectangle ({max_x},{max_y});",
+            -max_x, -max_y
+        );
+        doc += &self.commands;
+        doc += "\\end{tikzpicture}\n";
+        doc
+    }
+
+    /// cm per turtle unit
+    fn unit(&self) -> f64 {
+        if self.max_coord.0 > 0.0 {
+            PICTURE_WIDTH / 2.0 / self.max_coord.0
+        } else {
+            1.0
+        }
+    }
+
+    /// name of the xcolor definition of `col`, defining it if needed
+    fn color(&mut self, col: TColor) -> String {
+        let rgb = |c: &TColor| (c.r, c.g, c.b);
+        let idx = match self.colors.iter().position(|c| rgb(c) == rgb(&col)) {
+            Some(idx) => idx,
+            None => {
+                self.colors.push(col);
+                self.colors.len() - 1
+            }
+        };
+        format!("turtle{idx}")
+    }
+
+    /// draw options for `col`, opacity is an extra option
+    fn options(&mut self, col: TColor, opacity: &str) -> String {
+        let mut opts = self.color(col);
+        if !col.is_opaque() {
+            let _ = write!(opts, ",{opacity} opacity={}", col.a.max(0.0) / 100.0);
+        }
+        opts
+    }
+
+    fn node(&mut self, pos: TCoord, anchor: &str, msg: &str) {
+        let font = if self.font_size > 0.0 {
+            format!(
+                ",font=\\fontsize{{{}}}{{{}}}\\selectfont",
+                self.font_size,
+                self.font_size * 1.2
+            )
+        } else {
+            String::new()
+        };
+        let _ = writeln!(
+            self.commands,
+            "\\node[anchor={anchor},text=white{font}] at ({},{}) {{{}}};",
+            pos.0,
+            pos.1,
+            escape(msg)
+        );
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped += "\\textbackslash{}",
+            '~' => escaped += "\\textasciitilde{}",
+            '^' => escaped += "\\textasciicircum{}",
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+impl Window for TikzWindow {
+    fn init(&mut self) {}
+
+    fn get_max_coords(&self) -> TCoord {
+        self.max_coord
+    }
+
+    fn set_max_x(&mut self, max_x: f64) {
+        self.max_coord.0 = max_x;
+    }
+
+    fn set_max_y(&mut self, max_y: f64) {
+        self.max_coord.1 = max_y;
+    }
+
+    fn draw(&mut self, from: TCoord, to: TCoord, col: TColor) {
+        let mut opts = self.options(col, "draw");
+        if self.pen_width > 0.0 {
+            let _ = write!(opts, ",line width={}cm", self.pen_width * self.unit());
+        }
+        let _ = writeln!(
+            self.commands,
+            "\\draw[{opts}] ({},{}) -- ({},{});",
+            from.0, from.1, to.0, to.1
+        );
+    }
+
+    fn fill(&mut self, points: &[TCoord], col: TColor) {
+        if points.is_empty() {
+            return;
+        }
+        let opts = self.options(col, "fill");
+        let path = points
+            .iter()
+            .map(|(x, y)| format!("({x},{y})"))
+            .collect::<Vec<_>>()
+            .join(" -- ");
+        let _ = writeln!(self.commands, "\\fill[{opts}] {path} -- cycle;");
+    }
+
+    fn clear(&mut self) {
+        self.commands.clear();
+        self.colors.clear();
+        self.text_height = 0.0;
+    }
+
+    fn set_pen_width(&mut self, width: f64) {
+        self.pen_width = width;
+    }
+
+    fn print(&mut self, msg: &str) {
+        // printed lines are stacked in the top left corner, one turtle unit each by default
+        let (max_x, max_y) = self.max_coord;
+        let pos = (-max_x, max_y - self.text_height);
+        self.text_height += if self.font_size > 0.0 {
+            self.font_size / PT_PER_CM / self.unit()
+        } else {
+            1.0
+        };
+        self.node(pos, "north west", msg);
+    }
+
+    fn print_at(&mut self, pos: TCoord, msg: &str) {
+        self.node(pos, "south west", msg);
+    }
+
+    fn set_font_size(&mut self, pt: f64) {
+        self.font_size = pt;
+    }
+
+    fn events(&mut self) -> Vec<WindowEvent> {
+        Vec::new()
+    }
+}
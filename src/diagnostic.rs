@@ -0,0 +1,128 @@
+//! Reporting compile errors independent of how they are shown.
+
+use std::fmt::Display;
+
+use crate::{
+    pos::{FilePos, Pos, SourceMap},
+    CompileError, TurtleError, Warning,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Receives every diagnostic of a compilation, e.g. to print it or turn it into LSP messages
+pub trait DiagnosticSink {
+    /// `message` is a single line, `pos` is the default position if the error has none
+    fn report(&mut self, pos: FilePos, severity: Severity, message: &str);
+}
+
+/// Prints each diagnostic to stderr followed by the source line with a caret below `pos`
+pub struct StderrReporter {
+    sources: SourceMap,
+    prefix: Option<String>,
+}
+
+impl StderrReporter {
+    pub fn new(sources: SourceMap) -> Self {
+        Self {
+            sources,
+            prefix: None,
+        }
+    }
+
+    /// Printed in front of errors instead of `error`
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+}
+
+impl DiagnosticSink for StderrReporter {
+    fn report(&mut self, pos: FilePos, severity: Severity, message: &str) {
+        let prefix = match (&self.prefix, severity) {
+            (Some(prefix), Severity::Error) => prefix.clone(),
+            _ => severity.to_string(),
+        };
+        let ctx = self.sources.render_context(pos);
+        if ctx.is_empty() {
+            eprintln!("{prefix}: {message}");
+        } else {
+            eprintln!("{prefix}: {message}\n{ctx}");
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub pos: FilePos,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Keeps the diagnostics in the order they were reported
+#[derive(Debug, Default)]
+pub struct CollectingReporter {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl CollectingReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diag| diag.severity == Severity::Error)
+    }
+}
+
+impl DiagnosticSink for CollectingReporter {
+    fn report(&mut self, pos: FilePos, severity: Severity, message: &str) {
+        self.diagnostics.push(Diagnostic {
+            pos,
+            severity,
+            message: message.to_string(),
+        });
+    }
+}
+
+impl Pos<CompileError> {
+    /// Report the error to `sink`, lex errors found in includes are reported one by one
+    pub fn emit(&self, sink: &mut (impl DiagnosticSink + ?Sized)) {
+        match &**self {
+            CompileError::Program(TurtleError::LexErrors(errs)) => {
+                for err in errs {
+                    let pos = err.get_pos();
+                    sink.report(pos, Severity::Error, &format!("{} at {pos}", **err));
+                }
+            }
+            CompileError::Program(why) => {
+                sink.report(self.get_pos(), Severity::Error, &why.to_string())
+            }
+            other => {
+                let pos = self.get_pos();
+                sink.report(pos, Severity::Error, &format!("{other} at {pos}"));
+            }
+        }
+    }
+}
+
+impl Pos<Warning> {
+    pub fn emit(&self, severity: Severity, sink: &mut (impl DiagnosticSink + ?Sized)) {
+        let pos = self.get_pos();
+        sink.report(pos, severity, &format!("{} at {pos}", **self));
+    }
+}
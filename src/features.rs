@@ -6,7 +6,7 @@ use std::{
 use clap::ValueEnum;
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
-pub struct FeatureConf([FeatureState; 4]);
+pub struct FeatureConf([FeatureState; 6]);
 
 impl FeatureConf {
     pub fn expect(&mut self, feature: Feature) -> Result<(), Feature> {
@@ -54,7 +54,7 @@ impl IndexMut<Feature> for FeatureConf {
 
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
 pub enum Feature {
-    /// introduces types: strings, booleans and numbers
+    /// introduces types: strings, booleans, numbers and lists of numbers
     Types = 0,
     /// parallel execution with multiple turtles drawing concurrently
     Multithreading,
@@ -62,6 +62,10 @@ pub enum Feature {
     Events,
     /// easier command-line arguments
     Parameters,
+    /// arcs and circles drawn in one statement
+    Shapes,
+    /// scale, move and rotate what is drawn
+    Transforms,
 }
 
 impl Display for Feature {
@@ -71,6 +75,8 @@ impl Display for Feature {
             Feature::Multithreading => write!(f, "multithreading"),
             Feature::Events => write!(f, "events"),
             Feature::Parameters => write!(f, "parameters"),
+            Feature::Shapes => write!(f, "shapes"),
+            Feature::Transforms => write!(f, "transforms"),
         }
     }
 }
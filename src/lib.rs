@@ -1,17 +1,22 @@
 use std::fmt::{Display, Write as _};
 
-use debugger::FuncType;
+use debugger::{window::Window, Debugger, FuncType, RuntimeError};
+use features::FeatureConf;
 use indexmap::IndexMap;
 
 use pos::*;
 use prog::{parser::ParseError, TypeError};
 
-pub use ccomp::CComp;
-pub use prog::TProgram;
+pub use ccomp::{CComp, CUnsupported};
+pub use prog::{
+    eval_expr, format, lexer, Bytecode, CompileError, Environment, EvalError, Lint, RenameError,
+    SymbolInfo, TProgram, Unsupported, Warning,
+};
 use tokens::{EventKind, ValType};
 
 mod ccomp;
 pub mod debugger;
+pub mod diagnostic;
 pub mod features;
 pub mod pos;
 mod prog;
@@ -45,9 +50,11 @@ impl Display for Identified {
 ///
 /// * Reading the file might return an error
 /// * The lexer might find several errors
-/// * The parser might find an error. Even if multiple errors exist, only the first is returned.
+/// * The parser might find an error. Even if multiple errors exist, only the first is returned,
+///   use [`TProgram::compile_all`] to get all of them.
 /// * There might be multiple `begin..end` blocks in a programm
 /// * There might be no `begin..end` block in a programm
+/// * Included files might be missing, include each other, or contain any of the above
 #[derive(Debug, thiserror::Error)]
 pub enum TurtleError {
     #[error("{0}")]
@@ -87,4 +94,78 @@ pub enum TurtleError {
     EventArgsLength(EventKind, usize, usize),
     #[error("{0} event handler has wrong type of argument at index {1}: got {2}, expected {3}")]
     EventArgsType(EventKind, usize, ValType, ValType),
+    #[error("{} at {}", **.0, .0.get_pos())]
+    RuntimeError(#[from] Pos<debugger::RuntimeError>),
+    #[error("{} at {}", **.0, .0.get_pos())]
+    CUnsupported(#[from] Pos<CUnsupported>),
+    #[error("cannot include '{0}' at {1}: {2}")]
+    IncludeFailed(String, FilePos, std::io::Error),
+    #[error("'{0}' is already being included at {1}")]
+    IncludeCycle(String, FilePos),
+}
+
+impl TurtleError {
+    /// Position of the error, if it points to a single place in the source
+    pub fn pos(&self) -> Option<FilePos> {
+        match self {
+            TurtleError::ParseError(e) => Some(e.get_pos()),
+            TurtleError::RuntimeError(e) => Some(e.get_pos()),
+            TurtleError::CUnsupported(e) => Some(e.get_pos()),
+            TurtleError::MultipleMains(_, pos)
+            | TurtleError::IncludeFailed(_, pos, _)
+            | TurtleError::IncludeCycle(_, pos)
+            | TurtleError::TypeError(_, pos)
+            | TurtleError::TypeErrorSpan(_, pos, _) => Some(*pos),
+            _ => None,
+        }
+    }
+
+    /// Error message followed by the offending line(s) from `sources`
+    pub fn with_context(&self, sources: &SourceMap) -> String {
+        let with_line = |msg: String, pos: FilePos| {
+            let ctx = sources.render_context(pos);
+            if ctx.is_empty() {
+                msg
+            } else {
+                format!("{msg}\n{ctx}")
+            }
+        };
+        match self {
+            TurtleError::LexErrors(errs) => errs
+                .iter()
+                .map(|e| with_line(format!("{} at {}", **e, e.get_pos()), e.get_pos()))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => match self.pos() {
+                Some(pos) => with_line(self.to_string(), pos),
+                None => self.to_string(),
+            },
+        }
+    }
+}
+
+/// One error reported by [`run_str`].
+///
+/// Compiling reports every error found in the source, while running stops at the first one.
+/// Either way every error carries the position it was found at.
+#[derive(Debug, thiserror::Error)]
+pub enum RunError {
+    #[error("{0}")]
+    Compile(CompileError),
+    #[error("{0}")]
+    Runtime(RuntimeError),
+}
+
+/// Compile `source` with the default features and run it in `window`.
+///
+/// The program gets no arguments and runs until all turtles are finished,
+/// see [`debugger::config::RunConfig`] for more control.
+pub fn run_str(source: &str, window: &mut impl Window) -> Result<(), Vec<Pos<RunError>>> {
+    let prog = TProgram::compile_all(source, None, FeatureConf::default()).map_err(|errs| {
+        errs.into_iter()
+            .map(|err| err.map(RunError::Compile))
+            .collect::<Vec<_>>()
+    })?;
+    let mut dbg = Debugger::new(&prog, &[], window, false, Vec::new());
+    dbg.run().map_err(|err| vec![err.map(RunError::Runtime)])
 }
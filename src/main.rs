@@ -3,10 +3,13 @@ use turtle::{
     debugger::{
         config::RunConfig,
         interface::{Shell, Terminal, VSCode},
-        window::{SdlWindow, VoidWindow, Window},
+        window::{BoundsWindow, ExtentWindow, SdlWindow, SvgWindow, VoidWindow, Window},
+        Rng,
     },
+    diagnostic::StderrReporter,
     features::{FeatureConf, FeatureState},
-    TProgram,
+    pos::{Pos, SourceMap},
+    CompileError, TProgram, TurtleError,
 };
 
 use cli::*;
@@ -27,24 +30,78 @@ impl Features {
 }
 
 impl Source {
+    fn is_stdin(&self) -> bool {
+        self.file == "-"
+    }
+
+    fn code(&self) -> &str {
+        if let Some(code) = self.code.get() {
+            return code;
+        }
+        let res = if self.is_stdin() {
+            std::io::read_to_string(std::io::stdin())
+        } else {
+            std::fs::read_to_string(&self.file)
+        };
+        match res {
+            Ok(code) => self.code.get_or_init(|| code),
+            Err(why) => self.fail(why.into()),
+        }
+    }
+
+    fn parse(&self, print_symbols: bool) -> Result<TProgram, TurtleError> {
+        let features = self.features.feature_conf();
+        if self.is_stdin() {
+            TProgram::parse(self.code(), print_symbols, features)
+        } else {
+            TProgram::from_file(&self.file, print_symbols, features)
+        }
+    }
+
     fn get_prog(&self) -> TProgram {
-        match TProgram::from_file(&self.file, false, self.features.feature_conf()) {
+        let prog = match self.parse(false) {
             Ok(prog) => prog,
-            Err(why) => {
-                eprintln!("invalid turtle program: {why}");
-                std::process::exit(1)
-            }
+            Err(why) => self.fail(why),
+        };
+        if !prog.report_lints(&self.deny, &mut reporter(self.code().to_string())) {
+            std::process::exit(1)
         }
+        prog
     }
 
     fn check(&self, print_symbols: bool) {
-        if let Err(why) =
-            TProgram::from_file(&self.file, print_symbols, self.features.feature_conf())
-        {
-            eprintln!("invalid turtle program: {why}");
+        if print_symbols {
+            if let Err(why) = self.parse(true) {
+                self.fail(why)
+            }
+            return;
+        }
+        let code = self.code();
+        let file = (!self.is_stdin()).then(|| std::path::Path::new(&self.file));
+        let mut sink = reporter(code.to_string());
+        let features = self.features.feature_conf();
+        if TProgram::compile_reported(code, file, features, &self.deny, &mut sink).is_none() {
             std::process::exit(1)
         }
     }
+
+    fn fail(&self, why: TurtleError) -> ! {
+        let source = match self.code.get() {
+            Some(code) => Some(code.clone()),
+            None if self.is_stdin() => None,
+            None => std::fs::read_to_string(&self.file).ok(),
+        };
+        let msg = match source {
+            Some(source) => why.with_context(&SourceMap::new(source)),
+            None => why.to_string(),
+        };
+        match why {
+            TurtleError::RuntimeError(_) => eprintln!("runtime error: {msg}"),
+            TurtleError::CUnsupported(_) => eprintln!("cannot compile to C: {msg}"),
+            _ => eprintln!("invalid turtle program: {msg}"),
+        }
+        std::process::exit(1)
+    }
 }
 
 impl Display {
@@ -59,7 +116,12 @@ impl Display {
 
 impl RunOpt {
     fn config<'a>(&'a self, title: &str) -> RunConfig<'a, Box<dyn Window>, Terminal> {
-        RunConfig::new(&self.args).window(self.window.as_boxed(title))
+        RunConfig::new(&self.args)
+            .window(self.window.as_boxed(title))
+            .step_limit(self.step_limit)
+            .seed(self.seed)
+            .skip_delay(self.no_delay || self.window == Display::Void)
+            .keep_open(self.window != Display::Void)
     }
 }
 
@@ -69,13 +131,29 @@ fn main() {
         TCommand::Run {
             source,
             optimized,
+            bytecode,
+            check,
+            output,
+            size,
             opt,
         } => {
+            if check {
+                return source.check(false);
+            }
             let mut prog = source.get_prog();
             if optimized {
                 prog.optimize();
             }
-            opt.config(&prog.title("Interpreter")).exec(&prog);
+            if let Some(output) = output {
+                return render(&prog, &source, &opt, &output, size);
+            }
+            if let Err(why) = opt
+                .config(&prog.title("Interpreter"))
+                .bytecode(bytecode)
+                .exec(&prog)
+            {
+                source.fail(why.into())
+            }
         }
         TCommand::Shell {
             features,
@@ -83,14 +161,23 @@ fn main() {
             window,
         } => {
             let prog = if let Some(file) = base {
-                Source { features, file }.get_prog()
+                Source {
+                    features,
+                    file,
+                    deny: Vec::new(),
+                    code: Default::default(),
+                }
+                .get_prog()
             } else {
                 TProgram::parse("begin end", false, features.feature_conf()).unwrap()
             };
-            RunConfig::new(&[])
+            if let Err(why) = RunConfig::new(&[])
                 .window(window.as_boxed("Turtle Shell"))
                 .debug_in(Shell)
-                .exec(&prog);
+                .exec(&prog)
+            {
+                eprintln!("runtime error: {} at {}", *why, why.get_pos());
+            }
         }
         TCommand::Debug {
             source,
@@ -103,25 +190,135 @@ fn main() {
                 .config(&prog.title("Debugger"))
                 .debug_in(Terminal)
                 .breakpoints(breakpoint);
-            match interface {
+            let res = match interface {
                 Interf::Terminal => conf.exec(&prog),
                 Interf::VSCode => conf.debug_in(VSCode).exec(&prog),
+            };
+            if let Err(why) = res {
+                source.fail(why.into())
+            }
+        }
+        TCommand::Compile { source } => {
+            if let Err(why) = compile(source.get_prog(), &source.file) {
+                source.fail(why)
             }
         }
-        TCommand::Compile { source } => compile(source.get_prog(), &source.file),
         TCommand::Check {
             source,
             print_symbols,
         } => source.check(print_symbols),
+        TCommand::Format { file, write } => format(&file, write),
+    }
+}
+
+/// Run `prog` without a GUI and save the drawing to `output`, by its extension
+fn render(prog: &TProgram, source: &Source, opt: &RunOpt, output: &str, size: Option<(f64, f64)>) {
+    enum Format {
+        Svg,
+        #[cfg(feature = "raster")]
+        Png,
+    }
+    let format = match std::path::Path::new(output)
+        .extension()
+        .and_then(|ext| ext.to_str())
+    {
+        Some("svg") => Format::Svg,
+        #[cfg(feature = "raster")]
+        Some("png") => Format::Png,
+        #[cfg(not(feature = "raster"))]
+        Some("png") => fail("png output needs the raster feature"),
+        _ => fail(&format!(
+            "unknown output format of {output}, expected .svg or .png"
+        )),
+    };
+    // both runs have to draw the same
+    let seed = opt.seed.unwrap_or_else(|| Rng::from_entropy().next_u64());
+    let run = |window: &mut dyn Window| {
+        let res = RunConfig::new(&opt.args)
+            .window(window)
+            .step_limit(opt.step_limit)
+            .seed(Some(seed))
+            .skip_delay(true)
+            .keep_open(false)
+            .exec(prog);
+        if let Err(why) = res {
+            source.fail(why.into())
+        }
+    };
+    let extent = size.map(|(w, h)| (w / 2.0, h / 2.0)).unwrap_or_else(|| {
+        let mut bounds = BoundsWindow::new();
+        run(&mut bounds);
+        let ((min_x, min_y), (max_x, max_y)) = bounds.bounds();
+        // a small margin, and never empty
+        let fit = |min: f64, max: f64| (min.abs().max(max.abs()) * 1.05).max(1.0);
+        (fit(min_x, max_x), fit(min_y, max_y))
+    });
+    let res = match format {
+        Format::Svg => {
+            let mut window = ExtentWindow::new(SvgWindow::new(), extent);
+            run(&mut window);
+            std::fs::write(output, window.into_inner().finish())
+        }
+        #[cfg(feature = "raster")]
+        Format::Png => {
+            let mut window =
+                ExtentWindow::new(turtle::debugger::window::RasterWindow::default(), extent);
+            run(&mut window);
+            window.into_inner().save_png(output)
+        }
+    };
+    if let Err(why) = res {
+        fail(&format!("cannot write {output}: {why}"))
+    }
+}
+
+fn fail(msg: &str) -> ! {
+    eprintln!("{msg}");
+    std::process::exit(1)
+}
+
+fn reporter(code: String) -> StderrReporter {
+    StderrReporter::new(SourceMap::new(code)).prefix("invalid turtle program")
+}
+
+fn report(errs: &[Pos<CompileError>], code: String) -> ! {
+    let mut sink = reporter(code);
+    for err in errs {
+        err.emit(&mut sink);
+    }
+    std::process::exit(1)
+}
+
+fn format(file: &str, write: bool) {
+    let code = match std::fs::read_to_string(file) {
+        Ok(code) => code,
+        Err(why) => {
+            eprintln!("cannot read {file}: {why}");
+            std::process::exit(1)
+        }
+    };
+    let formatted = match turtle::format(&code) {
+        Ok(formatted) => formatted,
+        Err(errs) => report(&errs, code),
+    };
+    if !write {
+        print!("{formatted}");
+    } else if let Err(why) = std::fs::write(file, formatted) {
+        eprintln!("cannot write {file}: {why}");
+        std::process::exit(1)
     }
 }
 
-fn compile(prog: TProgram, filename: &str) {
+fn compile(prog: TProgram, filename: &str) -> Result<(), TurtleError> {
     let mut cc = turtle::CComp::new(prog);
     let resfile = format!(
         "ccomp\\{}",
-        filename.replace(".tg", ".c").split('\\').last().unwrap()
+        filename
+            .replace(".tg", ".c")
+            .split('\\')
+            .next_back()
+            .unwrap()
     );
     cc.set_filename(&resfile);
-    cc.compile();
+    cc.compile()
 }
@@ -1,36 +1,221 @@
-use std::{fmt::Display, num::ParseIntError, str::FromStr};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::Display,
+    num::{NonZeroU32, ParseIntError},
+    str::FromStr,
+    sync::{Mutex, MutexGuard},
+};
+
+/// An included source file, see [`FilePos::file`].
+///
+/// The path is interned once per process, so the id stays small, `Copy` and cheap to compare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FileId(NonZeroU32);
+
+fn interned() -> MutexGuard<'static, Vec<&'static str>> {
+    static PATHS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+    PATHS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl FileId {
+    pub fn intern(path: &str) -> Self {
+        let mut paths = interned();
+        let idx = match paths.iter().position(|known| *known == path) {
+            Some(idx) => idx,
+            None => {
+                paths.push(Box::leak(path.into()));
+                paths.len() - 1
+            }
+        };
+        Self(NonZeroU32::new(idx as u32 + 1).expect("index is offset by one"))
+    }
+
+    pub fn path(&self) -> &'static str {
+        interned()[self.0.get() as usize - 1]
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FileId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.path())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FileId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let path = <Cow<'de, str>>::deserialize(deserializer)?;
+        Ok(Self::intern(&path))
+    }
+}
 
 /// A position in a file.
 ///
 /// Used when an error is found while compiling to tell the developer where to fix his code
-#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+///
+/// `offset` is the byte offset into the source, known for positions produced by the lexer.
+/// It's derived from `line` and `column`, so comparisons only look at those two and the file.
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FilePos {
     pub line: usize,
     pub column: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub offset: usize,
+    /// Included file the position is in, `None` for the main source
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub file: Option<FileId>,
 }
 
 impl FilePos {
-    pub fn new(line: usize, column: usize) -> Self {
-        Self { line, column }
+    pub fn new(line: usize, column: usize, offset: usize) -> Self {
+        Self {
+            line,
+            column,
+            offset,
+            file: None,
+        }
+    }
+
+    /// Position given without its source, like a breakpoint, so the byte offset is unknown (0)
+    pub fn line_col(line: usize, column: usize) -> Self {
+        Self::new(line, column, 0)
+    }
+
+    pub fn in_file(self, file: Option<FileId>) -> Self {
+        Self { file, ..self }
     }
 
     pub fn is_empty(&self) -> bool {
         self.column == 0 && self.line == 0
     }
+
+    /// Show the line of `source` this position points to, with a `^` below the column.
+    ///
+    /// Returns an empty string if the line doesn't exist.
+    pub fn render_context(&self, source: &str) -> String {
+        let Some(line) = self
+            .line
+            .checked_sub(1)
+            .and_then(|idx| source.lines().nth(idx))
+        else {
+            return String::new();
+        };
+        // tabs are kept so the caret ends up below the same character
+        let caret: String = line
+            .chars()
+            .chain(std::iter::repeat(' '))
+            .take(self.column.clamp(1, line.chars().count() + 1) - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        format!("{line}\n{caret}^")
+    }
+}
+
+impl PartialEq for FilePos {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for FilePos {}
+
+impl PartialOrd for FilePos {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for FilePos {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.file, self.line, self.column).cmp(&(other.file, other.line, other.column))
+    }
 }
 
 impl Display for FilePos {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "line {}, column {}", self.line, self.column)
+        write!(f, "line {}, column {}", self.line, self.column)?;
+        if let Some(file) = self.file {
+            write!(f, " in {}", file.path())?;
+        }
+        Ok(())
+    }
+}
+
+/// Source texts to show positions in, see [`FilePos::render_context`].
+///
+/// Included files that weren't added are read from their path on demand.
+#[derive(Debug, Default, Clone)]
+pub struct SourceMap {
+    main: String,
+    files: HashMap<FileId, String>,
+}
+
+impl SourceMap {
+    pub fn new(main: impl Into<String>) -> Self {
+        Self {
+            main: main.into(),
+            files: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, file: FileId, source: impl Into<String>) {
+        self.files.insert(file, source.into());
+    }
+
+    /// Text of `file`, `None` if it can't be read
+    pub fn source(&self, file: Option<FileId>) -> Option<Cow<'_, str>> {
+        match file {
+            None => Some(Cow::Borrowed(&self.main)),
+            Some(file) => match self.files.get(&file) {
+                Some(source) => Some(Cow::Borrowed(source)),
+                None => std::fs::read_to_string(file.path()).ok().map(Cow::Owned),
+            },
+        }
+    }
+
+    /// Like [`FilePos::render_context`], but in the file `pos` belongs to
+    pub fn render_context(&self, pos: FilePos) -> String {
+        self.source(pos.file)
+            .map(|source| pos.render_context(&source))
+            .unwrap_or_default()
     }
 }
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, PartialEq, thiserror::Error)]
 pub enum FilePosParseErr {
     #[error("no delimiter")]
     NoDelimiter,
     #[error("{0}")]
     ParseError(#[from] ParseIntError),
+    #[error("unexpected `{0}` after column")]
+    TrailingGarbage(String),
+}
+
+impl FilePos {
+    /// Parse `line<delim>column`, accepting only the given delimiter.
+    pub fn parse_with_delim(s: &str, delim: char) -> Result<Self, FilePosParseErr> {
+        let (line, column) = s.split_once(delim).ok_or(FilePosParseErr::NoDelimiter)?;
+        Self::from_parts(line, column)
+    }
+
+    fn from_parts(line: &str, column: &str) -> Result<Self, FilePosParseErr> {
+        let end = column
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(column.len());
+        let (column, rest) = column.split_at(end);
+        if !rest.is_empty() {
+            return Err(FilePosParseErr::TrailingGarbage(rest.to_string()));
+        }
+        Ok(Self::line_col(line.parse()?, column.parse()?))
+    }
 }
 
 impl FromStr for FilePos {
@@ -40,41 +225,108 @@ impl FromStr for FilePos {
         let (line, column) = s
             .split_once(|c: char| !c.is_ascii_digit())
             .ok_or(FilePosParseErr::NoDelimiter)?;
-        Ok(Self::new(line.parse()?, column.parse()?))
+        Self::from_parts(line, column)
     }
 }
 
+/// Start and end (inclusive) of a range in the source
+pub type Span = (FilePos, FilePos);
+
 /// Attach [`FilePos`] to any type `T`, mostly tokens
 ///
+/// Besides the start position a [`Pos`] also knows where the token ends (inclusive),
+/// so diagnostics can mark the whole range.
+///
 /// Implements [`Deref`](std::ops::Deref) to access inner value
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pos<T> {
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pos: FilePos,
+    end: FilePos,
     token: T,
 }
 
 impl<T> Pos<T> {
-    /// Create new [`Pos`] wrapper.
+    /// Create new [`Pos`] wrapper, spanning a single position.
     pub fn new(token: T, pos: FilePos) -> Self {
-        Self { pos, token }
+        Self::with_span(token, pos, pos)
+    }
+
+    /// Create new [`Pos`] wrapper spanning from `start` to `end`.
+    pub fn with_span(token: T, start: FilePos, end: FilePos) -> Self {
+        Self {
+            pos: start,
+            end,
+            token,
+        }
     }
 
-    /// Get attached [`FilePos`]
+    /// Get attached [`FilePos`], which is the start of the span
     pub fn get_pos(&self) -> FilePos {
         self.pos
     }
 
+    /// Get start and end of the attached span
+    pub fn span(&self) -> Span {
+        (self.pos, self.end)
+    }
+
     pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Pos<U> {
-        let Self { pos, token } = self;
+        let Self { pos, end, token } = self;
         Pos {
             pos,
+            end,
             token: f(token),
         }
     }
 
+    /// Like [`Pos::map`], but the error of `f` is passed through without position.
+    pub fn try_map<U, E>(self, f: impl FnOnce(T) -> Result<U, E>) -> Result<Pos<U>, E> {
+        let Self { pos, end, token } = self;
+        Ok(Pos {
+            pos,
+            end,
+            token: f(token)?,
+        })
+    }
+
+    pub fn as_ref(&self) -> Pos<&T> {
+        Pos {
+            pos: self.pos,
+            end: self.end,
+            token: &self.token,
+        }
+    }
+
     pub fn into_inner(self) -> T {
         self.token
     }
+
+    /// Compare only the tokens, positions nested inside them still count
+    pub fn eq_ignoring_pos(&self, other: &Pos<T>) -> bool
+    where
+        T: PartialEq,
+    {
+        self.token == other.token
+    }
+}
+
+impl Pos<()> {
+    /// The span covering both `a` and `b`, in whatever order they are
+    pub fn merge<A, B>(a: &Pos<A>, b: &Pos<B>) -> Span {
+        (a.pos.min(b.pos), a.end.max(b.end))
+    }
+}
+
+/// A [`Pos`] that compares equal by its token alone, see [`Pos::eq_ignoring_pos`]
+#[derive(Debug, Clone, Copy)]
+pub struct Unpositioned<T>(pub Pos<T>);
+
+impl<T: PartialEq> PartialEq for Unpositioned<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq_ignoring_pos(&other.0)
+    }
 }
 
 impl<T> std::ops::Deref for Pos<T> {
@@ -100,3 +352,182 @@ impl<T> Positionable for T {
         Pos::new(self, pos)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn span() {
+        let single = 'a'.attach_pos(FilePos::line_col(2, 3));
+        assert_eq!(
+            single.span(),
+            (FilePos::line_col(2, 3), FilePos::line_col(2, 3))
+        );
+
+        let range = Pos::with_span("abc", FilePos::line_col(1, 4), FilePos::line_col(1, 6));
+        assert_eq!(range.get_pos(), FilePos::line_col(1, 4));
+        assert_eq!(
+            range.map(str::len).span(),
+            (FilePos::line_col(1, 4), FilePos::line_col(1, 6))
+        );
+    }
+
+    #[test]
+    fn context() {
+        let src = "begin\n\twalk 10x\nend";
+        assert_eq!(
+            FilePos::line_col(2, 8).render_context(src),
+            "\twalk 10x\n\t      ^"
+        );
+        assert_eq!(
+            FilePos::line_col(1, 20).render_context(src),
+            "begin\n     ^"
+        );
+        assert_eq!(FilePos::line_col(4, 1).render_context(src), "");
+        assert_eq!(FilePos::default().render_context(src), "");
+    }
+
+    #[test]
+    fn try_map() {
+        let token = Pos::with_span("42", FilePos::line_col(3, 5), FilePos::line_col(3, 6));
+        let num = token.try_map(str::parse::<u8>).unwrap();
+        assert_eq!(num.span(), token.span());
+        assert_eq!(*num, 42);
+
+        let err = token.try_map(str::parse::<bool>).unwrap_err();
+        assert_eq!(err, "42".parse::<bool>().unwrap_err());
+
+        let by_ref = token.as_ref();
+        assert_eq!(by_ref.get_pos(), FilePos::line_col(3, 5));
+        assert_eq!(**by_ref, "42");
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("3:4".parse::<FilePos>(), Ok(FilePos::line_col(3, 4)));
+        assert_eq!("3,4".parse::<FilePos>(), Ok(FilePos::line_col(3, 4)));
+        assert_eq!(
+            "3:4 ".parse::<FilePos>(),
+            Err(FilePosParseErr::TrailingGarbage(" ".to_string()))
+        );
+        assert_eq!("34".parse::<FilePos>(), Err(FilePosParseErr::NoDelimiter));
+
+        assert_eq!(
+            FilePos::parse_with_delim("3:4", ':'),
+            Ok(FilePos::line_col(3, 4))
+        );
+        assert_eq!(
+            FilePos::parse_with_delim("3,4", ':'),
+            Err(FilePosParseErr::NoDelimiter)
+        );
+        assert!(matches!(
+            FilePos::parse_with_delim("3:4:5", ':'),
+            Err(FilePosParseErr::TrailingGarbage(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        for pos in [
+            FilePos::line_col(1, 1),
+            FilePos::new(3, 14, 40),
+            FilePos::new(120, 2, 3000),
+        ] {
+            let json = serde_json::to_string(&pos).unwrap();
+            let back: FilePos = serde_json::from_str(&json).unwrap();
+            let parsed: FilePos = format!("{}:{}", pos.line, pos.column).parse().unwrap();
+            assert_eq!(back, parsed);
+            assert_eq!(back.offset, pos.offset);
+            assert_eq!(back.to_string(), pos.to_string());
+        }
+        let token = Pos::with_span(
+            "walk".to_string(),
+            FilePos::new(2, 1, 6),
+            FilePos::new(2, 4, 9),
+        );
+        let json = serde_json::to_value(&token).unwrap();
+        assert_eq!(json["line"], 2);
+        assert_eq!(json["token"], "walk");
+        let back: Pos<String> = serde_json::from_value(json).unwrap();
+        assert_eq!(back.span(), token.span());
+        assert_eq!(*back, *token);
+
+        let included = FilePos::line_col(4, 2).in_file(Some(FileId::intern("lib/shapes.tg")));
+        let json = serde_json::to_value(included).unwrap();
+        assert_eq!(json["file"], "lib/shapes.tg");
+        assert_eq!(serde_json::from_value::<FilePos>(json).unwrap(), included);
+        assert!(serde_json::to_value(FilePos::line_col(4, 2)).unwrap()["file"].is_null());
+    }
+
+    #[test]
+    fn ignoring_pos() {
+        let walk = Pos::with_span("walk", FilePos::line_col(1, 1), FilePos::line_col(1, 4));
+        let moved = Pos::with_span("walk", FilePos::line_col(3, 5), FilePos::line_col(3, 8));
+        assert_ne!(walk, moved);
+        assert!(walk.eq_ignoring_pos(&moved));
+        assert_eq!(Unpositioned(walk), Unpositioned(moved));
+        let turn = "turn".attach_pos(FilePos::line_col(1, 1));
+        assert!(!walk.eq_ignoring_pos(&turn));
+        assert_ne!(Unpositioned(walk), Unpositioned(turn));
+    }
+
+    #[test]
+    fn merge_spans() {
+        let lhs = Pos::with_span(3, FilePos::new(1, 6, 5), FilePos::new(1, 7, 6));
+        let op = Pos::new('+', FilePos::new(1, 9, 8));
+        let rhs = Pos::with_span(4.5, FilePos::new(1, 11, 10), FilePos::new(1, 13, 12));
+        assert_eq!(
+            Pos::merge(&lhs, &rhs),
+            (FilePos::line_col(1, 6), FilePos::line_col(1, 13))
+        );
+        assert_eq!(Pos::merge(&rhs, &lhs), Pos::merge(&lhs, &rhs));
+        assert_eq!(Pos::merge(&op, &rhs).0.offset, 8);
+        assert_eq!(Pos::merge(&op, &op), op.span());
+
+        // over several lines, the later token ends before the earlier one by column
+        let first = Pos::with_span((), FilePos::line_col(2, 10), FilePos::line_col(2, 14));
+        let second = Pos::with_span((), FilePos::line_col(4, 1), FilePos::line_col(4, 3));
+        assert_eq!(
+            Pos::merge(&second, &first),
+            (FilePos::line_col(2, 10), FilePos::line_col(4, 3))
+        );
+    }
+
+    #[test]
+    fn offset() {
+        let src = "walk 10\nturn 90";
+        let (start, end) = (FilePos::new(2, 1, 8), FilePos::new(2, 4, 11));
+        assert_eq!(&src[start.offset..=end.offset], "turn");
+        assert_eq!(start, FilePos::line_col(2, 1));
+        assert!(FilePos::new(1, 9, 8) < start);
+    }
+
+    #[test]
+    fn files() {
+        let (a, b) = (FileId::intern("a.tg"), FileId::intern("lib/b.tg"));
+        assert_eq!(a, FileId::intern("a.tg"));
+        let pos = FilePos::line_col(3, 4);
+        assert_ne!(pos.in_file(Some(a)), pos.in_file(Some(b)));
+        assert_ne!(pos.in_file(Some(a)), pos);
+        assert_eq!(pos.in_file(Some(b)), pos.in_file(Some(b)));
+        assert_eq!(
+            pos.in_file(Some(b)).to_string(),
+            "line 3, column 4 in lib/b.tg"
+        );
+        assert_eq!(pos.to_string(), "line 3, column 4");
+
+        let mut sources = SourceMap::new("walk 1\nturn 2");
+        sources.insert(b, "jump 3\nwalk x");
+        assert_eq!(
+            sources.render_context(FilePos::line_col(2, 6)),
+            "turn 2\n     ^"
+        );
+        assert_eq!(
+            sources.render_context(FilePos::line_col(2, 6).in_file(Some(b))),
+            "walk x\n     ^"
+        );
+        assert_eq!(sources.render_context(pos.in_file(Some(a))), "");
+    }
+}
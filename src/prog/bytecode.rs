@@ -0,0 +1,566 @@
+use crate::{
+    debugger::{window::LineStyle, FuncType},
+    pos::{FilePos, Positionable as _},
+    tokens::{
+        BiOperator, Block, Expr, ExprKind, PredefFunc, Statement, UnOperator, ValType, Value,
+        Variable, VariableKind,
+    },
+    Pos,
+};
+
+use super::{CalcDef, PathDef, TProgram};
+
+/// Instruction of [`Bytecode`], working on an operand stack
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    Push(Value),
+    Load(Variable),
+    Store(Variable),
+    /// `let` binding, pops the value
+    Declare(usize),
+    Binary(BiOperator),
+    Unary(UnOperator),
+    Abs,
+    Convert(ValType),
+    /// pops that many numbers into a list
+    MakeList(usize),
+    /// pops the index and the list
+    Index,
+    /// predefined function with its number of arguments
+    Func(PredefFunc, usize),
+    /// index into [`Bytecode::funcs`]
+    Call(usize),
+    /// leaves the call, a calculation keeps the value on top
+    Return,
+    /// like [`Op::Call`] from the end of a path or calculation, reusing its frame
+    TailCall(usize),
+    Jump(usize),
+    /// pops a boolean and jumps if it equals the first field
+    JumpIf(bool, usize),
+    EnterScope,
+    LeaveScope,
+    /// counts a statement or loop iteration against the step limit
+    Step,
+    /// pops the count of a `do .. times` loop and pushes the saved `@repcount`,
+    /// the count and the iteration 0
+    DoStart,
+    /// next iteration, or restores `@repcount` and jumps to the end
+    DoNext(usize),
+    /// pops start, end and step, sets the counter and pushes end and step back
+    CounterStart(Variable),
+    /// jumps to the end once the counter reached the end below the step
+    CounterTest {
+        counter: Variable,
+        up: bool,
+        end: usize,
+    },
+    CounterNext(Variable),
+    /// pushes the element at the index below the list and counts the index up,
+    /// or pops both and jumps to the end
+    ForEachNext(usize),
+    MoveDist {
+        draw: bool,
+        back: bool,
+    },
+    MoveHome(bool),
+    MoveTo(bool),
+    Turn(bool),
+    Direction,
+    /// pops radius and angle, the angle is already signed
+    Arc,
+    Color,
+    PenWidth,
+    SetSpeed,
+    PenStyle(LineStyle),
+    PushTransform,
+    PopTransform,
+    SaveState,
+    RestoreState,
+    NewTurtle,
+    SetTurtle,
+    /// `false` if only one factor was given, used for both axes
+    Scale(bool),
+    Translate,
+    Rotate,
+    Pen(bool),
+    ShowTurtle(bool),
+    BeginFrame,
+    EndFrame,
+    Clear,
+    ClearScreen,
+    Mark,
+    MoveMark(bool),
+    Print,
+    SetPrecision,
+    Wait,
+    /// `true` for `stop`, which keeps the window open
+    Finish(bool),
+}
+
+/// Entry point of a path or calculation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Func {
+    pub kind: FuncType,
+    pub entry: usize,
+    pub args: Vec<usize>,
+}
+
+/// Statement that [`TProgram::compile_bytecode`] can't translate
+#[derive(Debug, PartialEq, Clone, Copy, thiserror::Error)]
+pub enum Unsupported {
+    #[error("split is not supported in bytecode")]
+    Split,
+    #[error("event handlers are not supported in bytecode")]
+    Events,
+}
+
+/// A program flattened into instructions, run by [`Debugger::run_bytecode`].
+///
+/// `pos[i]` is the source location of `ops[i]`, used for runtime errors.
+///
+/// [`Debugger::run_bytecode`]: crate::debugger::Debugger::run_bytecode
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Bytecode {
+    pub(crate) ops: Vec<Op>,
+    pub(crate) pos: Vec<FilePos>,
+    pub(crate) funcs: Vec<Func>,
+}
+
+impl Bytecode {
+    fn emit(&mut self, op: Op, pos: FilePos) -> usize {
+        self.ops.push(op);
+        self.pos.push(pos);
+        self.ops.len() - 1
+    }
+
+    /// target of the next instruction emitted
+    fn here(&self) -> usize {
+        self.ops.len()
+    }
+
+    /// point the jump at `idx` to the next instruction
+    fn patch(&mut self, idx: usize) {
+        let target = self.here();
+        match &mut self.ops[idx] {
+            Op::Jump(to)
+            | Op::JumpIf(_, to)
+            | Op::DoNext(to)
+            | Op::ForEachNext(to)
+            | Op::CounterTest { end: to, .. } => *to = target,
+            op => unreachable!("{op:?} is no jump"),
+        }
+    }
+
+    fn func_index(prog: &TProgram, kind: FuncType) -> usize {
+        match kind {
+            FuncType::Path(id) => prog.paths.iter().position(|p| p.name == id),
+            FuncType::Calc(id) => prog
+                .calcs
+                .iter()
+                .position(|c| c.name == id)
+                .map(|idx| prog.paths.len() + idx),
+            _ => None,
+        }
+        .expect("should be caught by parser")
+    }
+
+    /// `tail` if the block is the last thing its path runs
+    fn block(
+        &mut self,
+        prog: &TProgram,
+        block: &Block,
+        tail: bool,
+    ) -> Result<(), Pos<Unsupported>> {
+        self.emit(Op::EnterScope, block.begin);
+        for (idx, stmt) in block.statements.iter().enumerate() {
+            let pos = stmt.get_pos();
+            self.emit(Op::Step, pos);
+            self.stmt(prog, stmt, pos, tail && block.ends_after(idx))?;
+        }
+        self.emit(Op::LeaveScope, block.begin);
+        Ok(())
+    }
+
+    fn stmt(
+        &mut self,
+        prog: &TProgram,
+        stmt: &Statement,
+        pos: FilePos,
+        tail: bool,
+    ) -> Result<(), Pos<Unsupported>> {
+        match stmt {
+            Statement::MoveDist { dist, draw, back } => {
+                self.expr(prog, dist);
+                self.emit(
+                    Op::MoveDist {
+                        draw: *draw,
+                        back: *back,
+                    },
+                    pos,
+                );
+            }
+            Statement::MoveHome(draw) => {
+                self.emit(Op::MoveHome(*draw), pos);
+            }
+            Statement::MoveTo { x, y, draw } => {
+                self.expr(prog, x);
+                self.expr(prog, y);
+                self.emit(Op::MoveTo(*draw), pos);
+            }
+            Statement::Turn { left, by } => {
+                self.expr(prog, by);
+                self.emit(Op::Turn(*left), pos);
+            }
+            Statement::Direction(expr) => {
+                self.expr(prog, expr);
+                self.emit(Op::Direction, pos);
+            }
+            Statement::Arc {
+                left,
+                radius,
+                angle,
+            } => {
+                self.expr(prog, radius);
+                match angle {
+                    Some(angle) => self.expr(prog, angle),
+                    None => {
+                        self.emit(Op::Push(Value::Number(360.0)), pos);
+                    }
+                }
+                if !*left {
+                    self.emit(Op::Unary(UnOperator::Negate), pos);
+                }
+                self.emit(Op::Arc, pos);
+            }
+            Statement::Color(r, g, b) => {
+                self.expr(prog, r);
+                self.expr(prog, g);
+                self.expr(prog, b);
+                self.emit(Op::Color, pos);
+            }
+            Statement::PenWidth(expr) => {
+                self.expr(prog, expr);
+                self.emit(Op::PenWidth, pos);
+            }
+            Statement::SetSpeed(expr) => {
+                self.expr(prog, expr);
+                self.emit(Op::SetSpeed, pos);
+            }
+            Statement::SetPrecision(expr) => {
+                self.expr(prog, expr);
+                self.emit(Op::SetPrecision, pos);
+            }
+            Statement::PushTransform => {
+                self.emit(Op::PushTransform, pos);
+            }
+            Statement::PopTransform => {
+                self.emit(Op::PopTransform, pos);
+            }
+            Statement::SaveState => {
+                self.emit(Op::SaveState, pos);
+            }
+            Statement::RestoreState => {
+                self.emit(Op::RestoreState, pos);
+            }
+            Statement::NewTurtle => {
+                self.emit(Op::NewTurtle, pos);
+            }
+            Statement::SetTurtle(expr) => {
+                self.expr(prog, expr);
+                self.emit(Op::SetTurtle, pos);
+            }
+            Statement::Scale(x, y) => {
+                self.expr(prog, x);
+                if let Some(y) = y {
+                    self.expr(prog, y);
+                }
+                self.emit(Op::Scale(y.is_some()), pos);
+            }
+            Statement::Translate(x, y) => {
+                self.expr(prog, x);
+                self.expr(prog, y);
+                self.emit(Op::Translate, pos);
+            }
+            Statement::Rotate(angle) => {
+                self.expr(prog, angle);
+                self.emit(Op::Rotate, pos);
+            }
+            Statement::PenStyle(style) => {
+                self.emit(Op::PenStyle(*style), pos);
+            }
+            Statement::Pen(down) => {
+                self.emit(Op::Pen(*down), pos);
+            }
+            Statement::ShowTurtle(visible) => {
+                self.emit(Op::ShowTurtle(*visible), pos);
+            }
+            Statement::Clear => {
+                self.emit(Op::Clear, pos);
+            }
+            Statement::ClearScreen => {
+                self.emit(Op::ClearScreen, pos);
+            }
+            Statement::Stop => {
+                self.emit(Op::Finish(true), pos);
+            }
+            Statement::Finish => {
+                self.emit(Op::Finish(false), pos);
+            }
+            Statement::Return(val) => {
+                if let Some(val) = val {
+                    self.expr(prog, val);
+                }
+                self.emit(Op::Return, pos);
+            }
+            Statement::PathCall(id, args) => {
+                self.args(prog, args);
+                let func = Self::func_index(prog, FuncType::Path(*id));
+                self.emit(
+                    if tail {
+                        Op::TailCall(func)
+                    } else {
+                        Op::Call(func)
+                    },
+                    pos,
+                );
+            }
+            Statement::Store(expr, var) => {
+                self.expr(prog, expr);
+                self.emit(Op::Store(var.clone()), pos);
+            }
+            Statement::Let(expr, var) => {
+                self.expr(prog, expr);
+                let VariableKind::Local(id, _) = var.kind else {
+                    unreachable!("parser only allows local variables in let")
+                };
+                self.emit(Op::Declare(id), pos);
+            }
+            Statement::Calc { var, val, op } => {
+                self.emit(Op::Load(var.clone()), var.pos);
+                self.expr(prog, val);
+                self.emit(Op::Binary(*op), pos);
+                self.emit(Op::Store(var.clone()), pos);
+            }
+            Statement::Mark => {
+                self.emit(Op::Mark, pos);
+            }
+            Statement::MoveMark(draw) => {
+                self.emit(Op::MoveMark(*draw), pos);
+            }
+            Statement::Print(expr) => {
+                self.expr(prog, expr);
+                self.emit(Op::Print, pos);
+            }
+            Statement::Split(..) => return Err(Unsupported::Split.attach_pos(pos)),
+            Statement::Wait => {
+                self.emit(Op::Wait, pos);
+            }
+            Statement::IfBranch(cond, body) => {
+                self.expr(prog, cond);
+                let skip = self.emit(Op::JumpIf(false, 0), pos);
+                self.block(prog, body, tail)?;
+                self.patch(skip);
+            }
+            Statement::IfElseBranch(cond, if_body, else_body) => {
+                self.expr(prog, cond);
+                let to_else = self.emit(Op::JumpIf(false, 0), pos);
+                self.block(prog, if_body, tail)?;
+                let to_end = self.emit(Op::Jump(0), pos);
+                self.patch(to_else);
+                self.block(prog, else_body, tail)?;
+                self.patch(to_end);
+            }
+            Statement::DoLoop(count, body) => {
+                self.expr(prog, count);
+                self.emit(Op::DoStart, count.start);
+                let head = self.emit(Op::DoNext(0), pos);
+                self.block(prog, body, false)?;
+                self.emit(Op::Jump(head), pos);
+                self.patch(head);
+            }
+            Statement::CounterLoop {
+                counter,
+                from,
+                up,
+                to,
+                step,
+                body,
+            } => {
+                self.expr(prog, from);
+                self.expr(prog, to);
+                match step {
+                    Some(step) => self.expr(prog, step),
+                    None => {
+                        self.emit(Op::Push(Value::Number(1.0)), pos);
+                    }
+                }
+                if !*up {
+                    self.emit(Op::Unary(UnOperator::Negate), pos);
+                }
+                self.emit(Op::CounterStart(counter.clone()), pos);
+                let head = self.emit(
+                    Op::CounterTest {
+                        counter: counter.clone(),
+                        up: *up,
+                        end: 0,
+                    },
+                    counter.pos,
+                );
+                self.block(prog, body, false)?;
+                self.emit(Op::Step, pos);
+                self.emit(Op::CounterNext(counter.clone()), counter.pos);
+                self.emit(Op::Jump(head), pos);
+                self.patch(head);
+            }
+            Statement::ForEach { item, list, body } => {
+                let VariableKind::Local(id, _) = item.kind else {
+                    unreachable!("parser only allows local variables in foreach")
+                };
+                self.expr(prog, list);
+                self.emit(Op::Push(Value::Number(0.0)), pos);
+                let head = self.emit(Op::ForEachNext(0), item.pos);
+                self.emit(Op::EnterScope, pos);
+                self.emit(Op::Declare(id), item.pos);
+                self.block(prog, body, false)?;
+                self.emit(Op::LeaveScope, pos);
+                self.emit(Op::Step, pos);
+                self.emit(Op::Jump(head), pos);
+                self.patch(head);
+            }
+            Statement::WhileLoop(cond, body) => {
+                let head = self.here();
+                self.expr(prog, cond);
+                let exit = self.emit(Op::JumpIf(false, 0), pos);
+                self.block(prog, body, false)?;
+                self.emit(Op::Step, pos);
+                self.emit(Op::Jump(head), pos);
+                self.patch(exit);
+            }
+            Statement::RepeatLoop(cond, body) => {
+                let head = self.here();
+                self.block(prog, body, false)?;
+                self.expr(prog, cond);
+                let exit = self.emit(Op::JumpIf(true, 0), pos);
+                self.emit(Op::Step, pos);
+                self.emit(Op::Jump(head), pos);
+                self.patch(exit);
+            }
+            Statement::Frame(body) => {
+                self.emit(Op::BeginFrame, pos);
+                self.block(prog, body, false)?;
+                self.emit(Op::EndFrame, pos);
+            }
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, prog: &TProgram, expr: &Expr) {
+        let pos = expr.start;
+        match &expr.kind {
+            ExprKind::Const(val) => {
+                self.emit(Op::Push(val.clone()), pos);
+            }
+            ExprKind::Variable(var) => {
+                self.emit(Op::Load(var.clone()), var.pos);
+            }
+            ExprKind::BiOperation(lhs, op, rhs) => {
+                self.expr(prog, lhs);
+                self.expr(prog, rhs);
+                self.emit(Op::Binary(*op), pos);
+            }
+            ExprKind::UnOperation(op, expr) => {
+                self.expr(prog, expr);
+                self.emit(Op::Unary(*op), pos);
+            }
+            ExprKind::Absolute(expr) => {
+                self.expr(prog, expr);
+                self.emit(Op::Abs, pos);
+            }
+            ExprKind::Bracket(expr) => self.expr(prog, expr),
+            ExprKind::Convert(from, to) => {
+                self.expr(prog, from);
+                self.emit(Op::Convert(*to), pos);
+            }
+            ExprKind::FuncCall(pdf, args) => {
+                self.args(prog, args);
+                self.emit(Op::Func(*pdf, args.len()), pos);
+            }
+            ExprKind::CalcCall(id, args) => {
+                self.args(prog, args);
+                let func = Self::func_index(prog, FuncType::Calc(*id));
+                self.emit(Op::Call(func), pos);
+            }
+            ExprKind::List(items) => {
+                self.args(prog, items);
+                self.emit(Op::MakeList(items.len()), pos);
+            }
+            ExprKind::Index(list, idx) => {
+                self.expr(prog, list);
+                self.expr(prog, idx);
+                self.emit(Op::Index, pos);
+            }
+        }
+    }
+
+    fn args(&mut self, prog: &TProgram, args: &[Expr]) {
+        for arg in args {
+            self.expr(prog, arg);
+        }
+    }
+
+    fn path(&mut self, prog: &TProgram, path: &PathDef) -> Result<(), Pos<Unsupported>> {
+        self.block(prog, &path.body, true)?;
+        self.emit(Op::Return, path.body.begin);
+        Ok(())
+    }
+
+    fn calc(&mut self, prog: &TProgram, calc: &CalcDef) -> Result<(), Pos<Unsupported>> {
+        self.block(prog, &calc.body, false)?;
+        if let ExprKind::CalcCall(id, args) = &calc.ret.kind {
+            self.args(prog, args);
+            let func = Self::func_index(prog, FuncType::Calc(*id));
+            self.emit(Op::TailCall(func), calc.ret.start);
+        } else {
+            self.expr(prog, &calc.ret);
+            self.emit(Op::Return, calc.ret.start);
+        }
+        Ok(())
+    }
+}
+
+impl TProgram {
+    /// Translate the program into [`Bytecode`], which runs faster than the tree.
+    ///
+    /// Only single turtle programs are supported, `split` and event handlers
+    /// are rejected.
+    pub fn compile_bytecode(&self) -> Result<Bytecode, Pos<Unsupported>> {
+        if let Some(evt) = self.key_event.as_ref().or(self.mouse_event.as_ref()) {
+            return Err(Unsupported::Events.attach_pos(evt.body.begin));
+        }
+        let mut code = Bytecode::default();
+        code.block(self, &self.main, false)?;
+        code.emit(Op::Finish(false), self.main.begin);
+        // calls refer to functions by index, so fill in entries afterwards
+        let kinds = self
+            .paths
+            .iter()
+            .map(|p| (FuncType::Path(p.name), &p.args))
+            .chain(self.calcs.iter().map(|c| (FuncType::Calc(c.name), &c.args)));
+        code.funcs = kinds
+            .map(|(kind, args)| Func {
+                kind,
+                entry: 0,
+                args: args.iter().map(|&(id, _)| id).collect(),
+            })
+            .collect();
+        for (idx, path) in self.paths.iter().enumerate() {
+            code.funcs[idx].entry = code.here();
+            code.path(self, path)?;
+        }
+        for (idx, calc) in self.calcs.iter().enumerate() {
+            code.funcs[self.paths.len() + idx].entry = code.here();
+            code.calc(self, calc)?;
+        }
+        Ok(code)
+    }
+}
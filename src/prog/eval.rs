@@ -0,0 +1,219 @@
+use std::{collections::HashMap, f64::consts::PI};
+
+use crate::{
+    debugger::Rng,
+    features::FeatureConf,
+    pos::{FilePos, Pos, Positionable as _},
+    tokens::{Expr, ExprKind, ValType, Value, Variable, VariableKind},
+    SymbolTable,
+};
+
+use super::{
+    lexer::{LexError, LexToken, Lexer},
+    parser::{ParseError, Parser},
+    TypeError,
+};
+
+/// Variables visible to [`eval_expr`], named like in the source.
+///
+/// Globals and predefined variables keep their `@`, `@pi` is always defined.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    vars: HashMap<String, Value>,
+    seed: Option<u64>,
+}
+
+impl Environment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn var(mut self, name: impl Into<String>, val: Value) -> Self {
+        self.vars.insert(name.into(), val);
+        self
+    }
+
+    /// Makes `rand` and `random` reproducible
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    fn get(&self, name: &str) -> Option<Value> {
+        match self.vars.get(name) {
+            Some(val) => Some(val.clone()),
+            None if name == "@pi" => Some(Value::Number(PI)),
+            None => None,
+        }
+    }
+}
+
+/// Everything [`eval_expr`] can fail with
+#[derive(Debug, thiserror::Error)]
+pub enum EvalError {
+    #[error("{0}")]
+    Lex(LexError),
+    #[error("{0}")]
+    Parse(ParseError),
+    #[error("unexpected {0} after the expression")]
+    TrailingToken(LexToken),
+    #[error("variable {0} is not defined")]
+    UndefinedVariable(String),
+    #[error("calculation {0} is not defined")]
+    UndefinedCalc(String),
+    #[error("{0}")]
+    Type(TypeError),
+    #[error("index {0} is not an exact integer")]
+    NonIntegerIndex(f64),
+    #[error("index {0} is not from 1 to {1}")]
+    IndexOutOfBounds(f64, usize),
+}
+
+type ERes<T> = Result<T, Pos<EvalError>>;
+
+/// Evaluate a single expression like `2 * @x + 1`, without a program around it.
+///
+/// No calculations are defined, predefined functions can be used.
+pub fn eval_expr(source: &str, env: &Environment) -> ERes<Value> {
+    let mut symbols = SymbolTable::new();
+    let mut features = FeatureConf::default();
+    let tokens = Lexer::new(&mut symbols, &mut features, source.chars())
+        .map(|tok| {
+            let (start, end) = tok.span();
+            match tok.into_inner() {
+                Ok(tok) => Ok(Pos::with_span(tok, start, end)),
+                Err(why) => Err(Pos::with_span(EvalError::Lex(why), start, end)),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if tokens.is_empty() {
+        return Err(EvalError::Parse(ParseError::UnexpectedEnd).attach_pos(FilePos::default()));
+    }
+    let mut parser = Parser::new(&mut symbols, tokens, &mut features);
+    let expr = parser
+        .parse_expr()
+        .map_err(|why| why.map(EvalError::Parse))?;
+    if let Some(next) = parser.next_unparsed() {
+        let (start, end) = next.span();
+        return Err(Pos::with_span(
+            EvalError::TrailingToken((**next).clone()),
+            start,
+            end,
+        ));
+    }
+    let mut eval = Evaluator {
+        env,
+        symbols: &symbols,
+        rng: env.seed.map_or_else(Rng::from_entropy, Rng::new),
+    };
+    eval.expr(&expr)
+}
+
+struct Evaluator<'e> {
+    env: &'e Environment,
+    symbols: &'e SymbolTable,
+    rng: Rng,
+}
+
+impl Evaluator<'_> {
+    fn expr(&mut self, expr: &Expr) -> ERes<Value> {
+        let type_err = |e: TypeError| Pos::with_span(EvalError::Type(e), expr.start, expr.end);
+        Ok(match &expr.kind {
+            ExprKind::Const(val) => val.clone(),
+            ExprKind::Variable(var) => self.var(var)?,
+            ExprKind::BiOperation(lhs, op, rhs) => {
+                let lhs = self.expr(lhs)?;
+                let rhs = self.expr(rhs)?;
+                op.try_eval(&lhs, &rhs).map_err(type_err)?
+            }
+            ExprKind::UnOperation(op, inner) => {
+                let val = self.expr(inner)?;
+                op.try_eval(&val).map_err(type_err)?
+            }
+            ExprKind::Absolute(inner) => match self.expr(inner)? {
+                Value::Number(num) => Value::Number(num.abs()),
+                val => return Err(type_err(TypeError::AbsoluteValue(val.val_type()))),
+            },
+            ExprKind::Bracket(inner) => self.expr(inner)?,
+            ExprKind::Convert(from, to) => self.expr(from)?.convert(*to),
+            ExprKind::FuncCall(pdf, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.expr(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                for (idx, (arg, ty)) in args.iter().zip(pdf.args()).enumerate() {
+                    if arg.val_type() != ty {
+                        return Err(type_err(TypeError::ArgWrongType(idx, arg.val_type(), ty)));
+                    }
+                }
+                let coord = |name| match self.env.get(name) {
+                    Some(Value::Number(num)) => num,
+                    _ => 0.0,
+                };
+                let pos = (coord("@x"), coord("@y"));
+                pdf.eval(&args, &mut self.rng, pos)
+            }
+            ExprKind::CalcCall(id, _) => {
+                return Err(EvalError::UndefinedCalc(self.name(*id)).attach_pos(expr.start))
+            }
+            ExprKind::List(items) => {
+                let mut list = Vec::with_capacity(items.len());
+                for item in items {
+                    match self.expr(item)? {
+                        Value::Number(num) => list.push(num),
+                        val => {
+                            let err = TypeError::WrongType(val.val_type(), ValType::Number);
+                            return Err(Pos::with_span(EvalError::Type(err), item.start, item.end));
+                        }
+                    }
+                }
+                Value::List(list)
+            }
+            ExprKind::Index(list, idx) => {
+                let list = self.expr(list)?;
+                let idx = self.expr(idx)?;
+                match (&list, &idx) {
+                    (Value::List(items), Value::Number(num)) => match list.item(&idx) {
+                        Some(val) => Value::Number(val),
+                        None => {
+                            let err = match idx.int() {
+                                Some(_) => EvalError::IndexOutOfBounds(*num, items.len()),
+                                None => EvalError::NonIntegerIndex(*num),
+                            };
+                            return Err(Pos::with_span(err, expr.start, expr.end));
+                        }
+                    },
+                    (Value::List(_), idx) => {
+                        let err = TypeError::WrongType(idx.val_type(), ValType::Number);
+                        return Err(type_err(err));
+                    }
+                    (list, _) => {
+                        return Err(type_err(TypeError::WrongType(
+                            list.val_type(),
+                            ValType::List,
+                        )))
+                    }
+                }
+            }
+        })
+    }
+
+    fn var(&self, var: &Variable) -> ERes<Value> {
+        let name = match var.kind {
+            VariableKind::Local(id, _) => self.name(id),
+            VariableKind::Global(id, _) => format!("@{}", self.name(id)),
+            VariableKind::GlobalPreDef(pdv) => format!("@{}", pdv.get_str()),
+        };
+        self.env
+            .get(&name)
+            .ok_or_else(|| EvalError::UndefinedVariable(name).attach_pos(var.pos))
+    }
+
+    fn name(&self, id: usize) -> String {
+        self.symbols
+            .get_index(id)
+            .expect("identifier from the lexer")
+            .0
+            .clone()
+    }
+}
@@ -0,0 +1,537 @@
+use std::iter::Peekable;
+
+use crate::{
+    features::{Feature, FeatureConf, FeatureState},
+    tokens::*,
+    FilePos, Pos, SymbolTable,
+};
+
+use super::{
+    lexer::{LexToken, Lexer},
+    CalcDef, CompileError, PathDef, TProgram,
+};
+
+/// Reformat a turtle program into its canonical layout.
+///
+/// Blocks are indented by two spaces, binary operators are surrounded by spaces
+/// and optional keywords are spelled out. Comments are kept in front of or behind
+/// the statement they are next to, and single blank lines between statements survive.
+/// Formatting the result again doesn't change it.
+pub fn format(source: &str) -> Result<String, Vec<Pos<CompileError>>> {
+    let mut symbols = SymbolTable::new();
+    let mut features = FeatureConf::default();
+    let mut lexer = Lexer::new(&mut symbols, &mut features, source.chars()).keeping_comments();
+    let tokens = TProgram::lex_all(&mut lexer)?;
+    let comments = lexer.take_comments();
+    let items = TProgram::parse_items(&mut symbols, tokens.clone(), &mut features)?;
+    let mut fmt = Formatter {
+        lines: source.lines().collect(),
+        source,
+        symbols: &symbols,
+        tokens: &tokens,
+        types: features[Feature::Types] == FeatureState::Enabled,
+        comments: comments.into_iter().peekable(),
+        out: String::new(),
+        indent: 0,
+        fresh: true,
+        last_line: 0,
+    };
+    for (idx, item) in items.iter().enumerate() {
+        let end = items
+            .get(idx + 1)
+            .map_or(tokens.len(), |next| fmt.idx(item_pos(next)));
+        if idx > 0 {
+            fmt.blank_line();
+        }
+        fmt.item(item, end);
+    }
+    while let Some(comment) = fmt.comments.next() {
+        fmt.comment(comment);
+    }
+    Ok(fmt.out)
+}
+
+fn item_pos(item: &ParseToken) -> FilePos {
+    match item {
+        ParseToken::PathDef(def) | ParseToken::EventHandler(_, def) => def.body.begin,
+        ParseToken::CalcDef(def) => def.body.begin,
+        ParseToken::StartBlock(block) => block.begin,
+        ParseToken::Include(file) => file.get_pos(),
+    }
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    lines: Vec<&'a str>,
+    symbols: &'a SymbolTable,
+    tokens: &'a [Pos<LexToken>],
+    /// arguments get type hints
+    types: bool,
+    comments: Peekable<std::vec::IntoIter<Pos<String>>>,
+    out: String,
+    indent: usize,
+    /// nothing was written into the current block yet
+    fresh: bool,
+    /// source line of the last written line
+    last_line: usize,
+}
+
+impl Formatter<'_> {
+    /// Index of the token at `pos`
+    fn idx(&self, pos: FilePos) -> usize {
+        self.tokens.partition_point(|tok| tok.get_pos() < pos)
+    }
+
+    fn token_pos(&self, idx: usize) -> FilePos {
+        self.tokens[idx].get_pos()
+    }
+
+    /// Source text of the token at `idx`
+    fn text(&self, idx: usize) -> &str {
+        let (start, end) = self.tokens[idx].span();
+        let last = self.source[end.offset..]
+            .chars()
+            .next()
+            .map_or(0, char::len_utf8);
+        &self.source[start.offset..end.offset + last]
+    }
+
+    fn name(&self, id: usize) -> &str {
+        self.symbols
+            .get_index(id)
+            .expect("missing identifier in symbol table")
+            .0
+    }
+
+    fn blank_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    /// Begin a line for something at source line `line`, keeping a blank line before it
+    fn start_line(&mut self, line: usize, keep_blank: bool) {
+        let blank_before = line >= 2
+            && line != self.last_line
+            && self
+                .lines
+                .get(line - 2)
+                .is_some_and(|l| l.trim().is_empty());
+        if keep_blank && blank_before && !self.fresh {
+            self.blank_line();
+        }
+        self.out.push_str(&"  ".repeat(self.indent));
+        self.fresh = false;
+        self.last_line = line;
+    }
+
+    /// Finish the current line with the comments found behind source line `line`
+    fn end_line(&mut self, line: usize) {
+        while let Some(comment) = self
+            .comments
+            .next_if(|comment| comment.get_pos().line == line)
+        {
+            self.out.push(' ');
+            self.out.push_str(&comment);
+        }
+        self.out.push('\n');
+    }
+
+    fn comment(&mut self, comment: Pos<String>) {
+        let line = comment.get_pos().line;
+        self.start_line(line, true);
+        self.out.push_str(&comment);
+        self.end_line(line);
+    }
+
+    fn comments_before(&mut self, pos: FilePos) {
+        while let Some(comment) = self.comments.next_if(|comment| comment.get_pos() < pos) {
+            self.comment(comment);
+        }
+    }
+
+    /// Write one line at `pos`, after the comments in front of it
+    fn line(&mut self, pos: FilePos, text: &str, keep_blank: bool) {
+        self.comments_before(pos);
+        self.start_line(pos.line, keep_blank);
+        self.out.push_str(text);
+        self.end_line(pos.line);
+    }
+
+    /// Write the closing keyword of a block, which is the token at `idx`
+    fn closer(&mut self, idx: usize, text: &str) {
+        self.line(self.token_pos(idx), text, false);
+    }
+
+    /// Statements of a block closed by the token at `closer`
+    fn body(&mut self, stmts: &[Pos<Statement>], closer: usize) {
+        self.indent += 1;
+        self.fresh = true;
+        for (idx, stmt) in stmts.iter().enumerate() {
+            let end = stmts
+                .get(idx + 1)
+                .map_or(closer, |next| self.idx(next.get_pos()));
+            self.stmt(stmt, end);
+        }
+        self.comments_before(self.token_pos(closer));
+        self.indent -= 1;
+    }
+
+    fn item(&mut self, item: &ParseToken, end: usize) {
+        match item {
+            ParseToken::PathDef(def) => {
+                let head = format!("path {}{}", self.name(def.name), self.path_args(def));
+                self.block(
+                    def.body.begin,
+                    &head,
+                    &def.body.statements,
+                    end - 1,
+                    "endpath",
+                );
+            }
+            ParseToken::EventHandler(kind, def) => {
+                let head = format!("event {kind}({})", self.proto_args(&def.args));
+                self.block(
+                    def.body.begin,
+                    &head,
+                    &def.body.statements,
+                    end - 1,
+                    "endevent",
+                );
+            }
+            ParseToken::CalcDef(def) => self.calc(def, end),
+            ParseToken::StartBlock(block) => {
+                self.block(block.begin, "begin", &block.statements, end - 1, "end");
+            }
+            ParseToken::Include(file) => {
+                let path = self.text(self.idx(file.get_pos()) + 1);
+                self.line(file.get_pos(), &format!("include {path}"), true);
+            }
+        }
+    }
+
+    fn calc(&mut self, def: &CalcDef, end: usize) {
+        let mut head = format!(
+            "calculation {}({})",
+            self.name(def.name),
+            self.proto_args(&def.args)
+        );
+        if self.types {
+            head += &format!(": {}", def.ret_ty);
+        }
+        let returns = self.idx(def.ret.start) - 1;
+        self.line(def.body.begin, &head, true);
+        self.body(&def.body.statements, returns);
+        self.indent += 1;
+        let ret = format!("returns {}", self.expr(&def.ret));
+        self.line(self.token_pos(returns), &ret, false);
+        self.comments_before(self.token_pos(end - 1));
+        self.indent -= 1;
+        self.closer(end - 1, "endcalc");
+    }
+
+    /// Header line, indented statements and the closing keyword at `closer`
+    fn block(
+        &mut self,
+        pos: FilePos,
+        head: &str,
+        stmts: &[Pos<Statement>],
+        closer: usize,
+        end: &str,
+    ) {
+        self.line(pos, head, true);
+        self.body(stmts, closer);
+        self.closer(closer, end);
+    }
+
+    fn path_args(&self, def: &PathDef) -> String {
+        if def.args.is_empty() {
+            String::new()
+        } else {
+            format!("({})", self.proto_args(&def.args))
+        }
+    }
+
+    fn proto_args(&self, args: &ArgDefList) -> String {
+        let args: Vec<_> = args
+            .iter()
+            .map(|(id, ty)| {
+                if self.types {
+                    format!("{}: {ty}", self.name(*id))
+                } else {
+                    self.name(*id).to_string()
+                }
+            })
+            .collect();
+        args.join(", ")
+    }
+
+    /// `end` is the index of the token following the statement
+    fn stmt(&mut self, stmt: &Pos<Statement>, end: usize) {
+        let pos = stmt.get_pos();
+        let first = &*self.tokens[self.idx(pos)];
+        let text = match &**stmt {
+            Statement::MoveDist { dist, draw, back } => format!(
+                "{} {}{}",
+                if *draw { "walk" } else { "jump" },
+                if *back { "back " } else { "" },
+                self.expr(dist)
+            ),
+            Statement::MoveHome(draw) => format!("{} home", if *draw { "walk" } else { "jump" }),
+            Statement::MoveTo { x, y, draw } => format!(
+                "{} to {}, {}",
+                if *draw { "walk" } else { "jump" },
+                self.expr(x),
+                self.expr(y)
+            ),
+            Statement::Turn { left, by } => format!(
+                "turn {} {}",
+                if *left { "left" } else { "right" },
+                self.expr(by)
+            ),
+            Statement::Direction(dir) => format!("direction {}", self.expr(dir)),
+            Statement::Arc {
+                left,
+                radius,
+                angle,
+            } => {
+                let side = if *left { "left" } else { "right" };
+                match angle {
+                    Some(angle) => {
+                        format!("arc {side} {}, {}", self.expr(radius), self.expr(angle))
+                    }
+                    None => format!("circle {side} {}", self.expr(radius)),
+                }
+            }
+            Statement::Color(r, g, b) => {
+                // a color literal is split into three channels at its position
+                if r.start == g.start && g.start == b.start {
+                    format!("color {}", self.text(self.idx(r.start)))
+                } else {
+                    format!("color {}, {}, {}", self.expr(r), self.expr(g), self.expr(b))
+                }
+            }
+            Statement::PenWidth(width) => format!("penwidth {}", self.expr(width)),
+            Statement::SetSpeed(speed) => format!("setspeed {}", self.expr(speed)),
+            Statement::SetPrecision(digits) => format!("setprecision {}", self.expr(digits)),
+            Statement::PenStyle(style) => format!("penstyle {style}"),
+            Statement::PushTransform => "push".to_string(),
+            Statement::PopTransform => "pop".to_string(),
+            Statement::SaveState => "savestate".to_string(),
+            Statement::RestoreState => "restorestate".to_string(),
+            Statement::NewTurtle => "newturtle".to_string(),
+            Statement::SetTurtle(id) => format!("setturtle {}", self.expr(id)),
+            Statement::Scale(x, None) => format!("scale {}", self.expr(x)),
+            Statement::Scale(x, Some(y)) => format!("scale {}, {}", self.expr(x), self.expr(y)),
+            Statement::Translate(x, y) => {
+                format!("translate {}, {}", self.expr(x), self.expr(y))
+            }
+            Statement::Rotate(angle) => format!("rotate {}", self.expr(angle)),
+            Statement::Pen(down) => if *down { "pendown" } else { "penup" }.to_string(),
+            Statement::ShowTurtle(visible) => {
+                if *visible { "showturtle" } else { "hideturtle" }.to_string()
+            }
+            Statement::Clear => "clear".to_string(),
+            Statement::ClearScreen => "clearscreen".to_string(),
+            Statement::Stop => "stop".to_string(),
+            Statement::Finish => "finish".to_string(),
+            Statement::Return(None) => "return".to_string(),
+            Statement::Return(Some(val)) => format!("return {}", self.expr(val)),
+            Statement::PathCall(id, args) => format!("path {}", self.call(*id, args)),
+            Statement::Split(id, args) => format!("split {}", self.call(*id, args)),
+            Statement::Store(val, var) if *first == LexToken::Keyword(Keyword::Store) => {
+                format!("store {} in {}", self.expr(val), self.var(var))
+            }
+            Statement::Store(val, var) => format!("{} = {}", self.var(var), self.expr(val)),
+            Statement::Let(val, var) => format!("let {} = {}", self.var(var), self.expr(val)),
+            Statement::Calc { var, val, op } => {
+                let (var, val) = (self.var(var), self.expr(val));
+                match op {
+                    BiOperator::Add if *first == LexToken::Keyword(Keyword::Append) => {
+                        format!("append {val} to {var}")
+                    }
+                    BiOperator::Add => format!("add {val} to {var}"),
+                    BiOperator::Sub => format!("sub {val} from {var}"),
+                    BiOperator::Mul => format!("mul {var} by {val}"),
+                    BiOperator::Div => format!("div {var} by {val}"),
+                    op => unreachable!("cannot use {op} as statement"),
+                }
+            }
+            Statement::Mark => "mark".to_string(),
+            Statement::MoveMark(draw) => format!("{} mark", if *draw { "walk" } else { "jump" }),
+            Statement::Print(msg) => format!("print {}", self.print_args(msg)),
+            Statement::Wait => "wait".to_string(),
+            Statement::IfBranch(cond, body) => {
+                let head = format!("if {} then", self.expr(cond));
+                return self.block(pos, &head, &body.statements, end - 1, "endif");
+            }
+            Statement::IfElseBranch(cond, then, other) => {
+                let head = format!("if {} then", self.expr(cond));
+                let other_start = self.idx(other.begin);
+                self.block(pos, &head, &then.statements, other_start, "else");
+                self.body(&other.statements, end - 1);
+                return self.closer(end - 1, "endif");
+            }
+            Statement::DoLoop(count, body) => {
+                let head = format!("do {} times", self.expr(count));
+                return self.block(pos, &head, &body.statements, end - 1, "done");
+            }
+            Statement::CounterLoop {
+                counter,
+                from,
+                up,
+                to,
+                step,
+                body,
+            } => {
+                let mut head = format!(
+                    "counter {} from {} {} {}",
+                    self.var(counter),
+                    self.expr(from),
+                    if *up { "to" } else { "downto" },
+                    self.expr(to)
+                );
+                if let Some(step) = step {
+                    head += &format!(" step {}", self.expr(step));
+                }
+                head += " do";
+                return self.block(pos, &head, &body.statements, end - 1, "done");
+            }
+            Statement::ForEach { item, list, body } => {
+                let head = format!("foreach {} in {} do", self.var(item), self.expr(list));
+                return self.block(pos, &head, &body.statements, end - 1, "done");
+            }
+            Statement::WhileLoop(cond, body) => {
+                let head = format!("while {} do", self.expr(cond));
+                return self.block(pos, &head, &body.statements, end - 1, "done");
+            }
+            Statement::RepeatLoop(cond, body) => {
+                let until = self.idx(cond.start) - 1;
+                let tail = format!("until {}", self.expr(cond));
+                return self.block(pos, "repeat", &body.statements, until, &tail);
+            }
+            Statement::Frame(body) => {
+                return self.block(pos, "frame", &body.statements, end - 1, "endframe");
+            }
+        };
+        self.line(pos, &text, true);
+    }
+
+    fn call(&self, id: usize, args: &[Expr]) -> String {
+        if args.is_empty() {
+            self.name(id).to_string()
+        } else {
+            format!("{}({})", self.name(id), self.args(args))
+        }
+    }
+
+    fn args(&self, args: &[Expr]) -> String {
+        let args: Vec<_> = args.iter().map(|arg| self.expr(arg)).collect();
+        args.join(", ")
+    }
+
+    fn var(&self, var: &Variable) -> String {
+        match var.kind {
+            VariableKind::Local(id, _) => self.name(id).to_string(),
+            VariableKind::Global(id, _) => format!("@{}", self.name(id)),
+            VariableKind::GlobalPreDef(pdv) => format!("@{}", pdv.get_str()),
+        }
+    }
+
+    /// `print a b` is parsed into a concatenation of string conversions
+    /// spanning just their operand, unlike written out `string(..)` calls
+    fn print_args(&self, msg: &Expr) -> String {
+        let implicit = |part: &Expr| match &part.kind {
+            ExprKind::Convert(inner, ValType::String)
+                if inner.start == part.start && inner.end == part.end =>
+            {
+                Some(self.expr(inner))
+            }
+            _ => None,
+        };
+        let mut parts = Vec::new();
+        let mut rest = msg;
+        while let ExprKind::BiOperation(lhs, BiOperator::Add, rhs) = &rest.kind {
+            parts.push(&**rhs);
+            rest = lhs;
+        }
+        parts.push(rest);
+        if parts.iter().all(|part| implicit(part).is_none()) {
+            return self.expr(msg);
+        }
+        let parts: Vec<_> = parts
+            .iter()
+            .rev()
+            .map(|part| implicit(part).unwrap_or_else(|| self.expr(part)))
+            .collect();
+        parts.join(" ")
+    }
+
+    fn expr(&self, expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Const(val) => {
+                let idx = self.idx(expr.start);
+                match self.tokens.get(idx).map(|tok| &**tok) {
+                    Some(
+                        LexToken::IntLiteral(_)
+                        | LexToken::FloatLiteral(_)
+                        | LexToken::StringLiteral(_),
+                    ) => self.text(idx).to_string(),
+                    _ => match val {
+                        Value::String(s) => format!("'{}'", escape(s)),
+                        val => val.to_string(),
+                    },
+                }
+            }
+            ExprKind::Variable(var) => self.var(var),
+            ExprKind::BiOperation(lhs, op, rhs) => {
+                format!("{} {} {}", self.expr(lhs), operator(*op), self.expr(rhs))
+            }
+            ExprKind::UnOperation(UnOperator::Negate, val) => format!("-{}", self.expr(val)),
+            ExprKind::UnOperation(UnOperator::Not, val) => format!("not {}", self.expr(val)),
+            ExprKind::Absolute(val) => format!("|{}|", self.expr(val)),
+            ExprKind::Bracket(val) => format!("({})", self.expr(val)),
+            ExprKind::Convert(val, ty) => format!("{ty}({})", self.expr(val)),
+            ExprKind::FuncCall(func, args) => format!("{func}({})", self.args(args)),
+            ExprKind::CalcCall(id, args) => format!("{}({})", self.name(*id), self.args(args)),
+            ExprKind::List(items) => {
+                let items: Vec<_> = items.iter().map(|item| self.expr(item)).collect();
+                format!("[{}]", items.join(" "))
+            }
+            ExprKind::Index(list, idx) => format!("{}[{}]", self.expr(list), self.expr(idx)),
+        }
+    }
+}
+
+/// Spelling of `op` in turtle source
+fn operator(op: BiOperator) -> &'static str {
+    match op {
+        BiOperator::Equal => "=",
+        BiOperator::UnEqual => "<>",
+        BiOperator::And => "and",
+        BiOperator::Or => "or",
+        BiOperator::Add => "+",
+        BiOperator::Sub => "-",
+        BiOperator::Mul => "*",
+        BiOperator::Div => "/",
+        BiOperator::Exp => "^",
+        BiOperator::Less => "<",
+        BiOperator::LessEqual => "<=",
+        BiOperator::Greater => ">",
+        BiOperator::GreaterEqual => ">=",
+    }
+}
+
+fn escape(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\'' => res.push_str("\\'"),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            c => res.push(c),
+        }
+    }
+    res
+}
@@ -0,0 +1,87 @@
+use std::path::{Path, PathBuf};
+
+use crate::{
+    features::FeatureConf, pos::FileId, tokens::ParseToken, Pos, SymbolTable, TurtleError,
+};
+
+use super::{lexer::Lexer, parser::Parser, RawProg};
+
+/// Resolves `include` directives relative to the including file.
+pub(super) struct Includes {
+    /// files currently being parsed, innermost last
+    stack: Vec<PathBuf>,
+}
+
+impl Includes {
+    /// `file` is the including source, `None` for code without a file
+    pub fn new(file: Option<&Path>) -> Self {
+        Self {
+            stack: file.map(canonical).into_iter().collect(),
+        }
+    }
+
+    fn dir(&self) -> &Path {
+        self.stack
+            .last()
+            .and_then(|file| file.parent())
+            .unwrap_or(Path::new(""))
+    }
+
+    /// Parse the items of `file` into `raw`, together with everything it includes
+    pub fn include(
+        &mut self,
+        file: Pos<String>,
+        raw: &mut RawProg,
+        symbols: &mut SymbolTable,
+        features: &mut FeatureConf,
+    ) -> Result<(), TurtleError> {
+        let path = self.dir().join(&*file);
+        let code = std::fs::read_to_string(&path)
+            .map_err(|why| TurtleError::IncludeFailed(file.to_string(), file.get_pos(), why))?;
+        let id = FileId::intern(&path.display().to_string());
+        let path = canonical(&path);
+        if self.stack.contains(&path) {
+            return Err(TurtleError::IncludeCycle(file.to_string(), file.get_pos()));
+        }
+        self.stack.push(path);
+        let res = self.load(&code, id, raw, symbols, features);
+        self.stack.pop();
+        res
+    }
+
+    fn load(
+        &mut self,
+        code: &str,
+        file: FileId,
+        raw: &mut RawProg,
+        symbols: &mut SymbolTable,
+        features: &mut FeatureConf,
+    ) -> Result<(), TurtleError> {
+        let ltokens = Lexer::new(symbols, features, code.chars())
+            .in_file(file)
+            .collect_tokens()?;
+        let items = Parser::new(symbols, ltokens, features).collect::<Result<Vec<_>, _>>()?;
+        self.insert_all(items, raw, symbols, features)
+    }
+
+    /// Insert parsed items into `raw`, following their includes
+    pub fn insert_all(
+        &mut self,
+        items: Vec<ParseToken>,
+        raw: &mut RawProg,
+        symbols: &mut SymbolTable,
+        features: &mut FeatureConf,
+    ) -> Result<(), TurtleError> {
+        for item in items {
+            match item {
+                ParseToken::Include(file) => self.include(file, raw, symbols, features)?,
+                item => raw.insert_item(item)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
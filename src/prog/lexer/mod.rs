@@ -1,30 +1,44 @@
 use std::{
     fmt::Display,
     num::{ParseFloatError, ParseIntError},
+    ops::Range,
 };
 
 use clap::ValueEnum;
 
 use crate::{
+    debugger::{ColorParseError, TColor},
     features::{Feature, FeatureConf},
     tokens::{Keyword, PredefVar},
-    FilePos, Identified, Pos,
+    FileId, FilePos, Identified, Pos,
 };
-use crate::{Positionable, SymbolTable, TurtleError};
+use crate::{SymbolTable, TurtleError};
 
 #[cfg(test)]
 mod test;
 
 pub type LResult = Pos<Result<LexToken, LexError>>;
 
+/// characters that are passed on to the parser as [`LexToken::Symbol`]
+const SYMBOLS: &str = "+-*/^=<>!(),|:[]";
+
 #[derive(PartialEq, Debug)]
 pub struct Lexer<'s, 'f> {
     chars: Vec<char>,
     offset: usize,
+    byte_offset: usize,
     pub line: usize,
     column: usize,
     last_col: usize,
     start: bool,
+    /// keyword of the last token, some tokens are lexed differently after it
+    ///
+    /// The path after `include` is lexed even without strings enabled, see
+    /// [`Keyword::is_contextual`] for keywords only recognized after another.
+    prev_keyword: Option<Keyword>,
+    file: Option<FileId>,
+    /// comments seen so far, `None` unless kept for the formatter
+    comments: Option<Vec<Pos<String>>>,
     symbols: &'s mut SymbolTable,
     features: &'f mut FeatureConf,
 }
@@ -38,48 +52,225 @@ impl<'s, 'f> Lexer<'s, 'f> {
         Self {
             chars: iter.collect(),
             offset: 0,
+            byte_offset: 0,
             line: 1,
             column: 1,
             last_col: 1,
             start: true,
+            prev_keyword: None,
+            file: None,
+            comments: None,
             symbols,
             features,
         }
     }
 
+    /// Tag all positions with `file`, used for included sources
+    pub fn in_file(mut self, file: FileId) -> Self {
+        self.file = Some(file);
+        self
+    }
+
+    /// Record comments instead of only skipping them, see [`Lexer::take_comments`]
+    pub fn keeping_comments(mut self) -> Self {
+        self.comments = Some(Vec::new());
+        self
+    }
+
+    /// Comments found so far in source order, with their delimiters
+    pub fn take_comments(&mut self) -> Vec<Pos<String>> {
+        self.comments
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Tokens of `source` after `edit`, reusing the tokens `old` of the text before it.
+    ///
+    /// Only the tokens around the edit are lexed again, the ones after it are moved
+    /// to their new position. `symbols` and `features` have to be the ones `old` was
+    /// lexed with, comments are not kept.
+    pub fn relex(
+        symbols: &'s mut SymbolTable,
+        features: &'f mut FeatureConf,
+        old: &[Pos<LexToken>],
+        source: &str,
+        edit: &TextEdit,
+    ) -> Result<Vec<Pos<LexToken>>, TurtleError> {
+        let file = old.first().and_then(|tok| tok.get_pos().file);
+        let mut lexer = Self::new(symbols, features, source.chars());
+        lexer.file = file;
+        let char_len = |pos: FilePos| {
+            source[pos.offset..]
+                .chars()
+                .next()
+                .map_or(1, char::len_utf8)
+        };
+        // a token is only kept with an unchanged char after it, as that ended it
+        let keep = old
+            .iter()
+            .take_while(|tok| {
+                let end = tok.span().1;
+                // the source before the edit is unchanged, so there the offsets still fit
+                end.offset < edit.range.start && end.offset + char_len(end) < edit.range.start
+            })
+            .count();
+        let Some(last) = keep.checked_sub(1).map(|idx| &old[idx]) else {
+            // the header comments may change the features
+            return lexer.collect_tokens();
+        };
+        let end = last.span().1;
+        lexer.byte_offset = end.offset + char_len(end);
+        lexer.offset = source[..lexer.byte_offset].chars().count();
+        lexer.line = end.line;
+        lexer.column = end.column + 1;
+        lexer.start = false;
+        lexer.prev_keyword = match **last {
+            LexToken::Keyword(kw) => Some(kw),
+            _ => None,
+        };
+
+        let delta = edit.text.len() as isize - edit.range.len() as isize;
+        let moved = |offset: usize| offset.saturating_add_signed(delta);
+        let mut tail = old.partition_point(|tok| tok.get_pos().offset < edit.range.end);
+        let mut res = old[..keep].to_vec();
+        let mut errs = Vec::new();
+        while let Some(lres) = lexer.next_token() {
+            let (start, end) = lres.span();
+            let token = match lres.into_inner() {
+                Ok(token) => token,
+                Err(why) => {
+                    errs.push(Pos::with_span(why, start, end));
+                    continue;
+                }
+            };
+            while tail < old.len() && moved(old[tail].get_pos().offset) < start.offset {
+                tail += 1;
+            }
+            // from here on the source and thus the tokens are the same as before
+            if tail < old.len()
+                && moved(old[tail].get_pos().offset) == start.offset
+                && *old[tail] == token
+            {
+                let from = old[tail].get_pos();
+                let lines = start.line as isize - from.line as isize;
+                let columns = start.column as isize - from.column as isize;
+                let shift = |pos: FilePos| FilePos {
+                    line: pos.line.saturating_add_signed(lines),
+                    column: if pos.line == from.line {
+                        pos.column.saturating_add_signed(columns)
+                    } else {
+                        pos.column
+                    },
+                    offset: moved(pos.offset),
+                    file: pos.file,
+                };
+                res.extend(old[tail..].iter().map(|tok| {
+                    let (start, end) = tok.span();
+                    Pos::with_span(tok.clone().into_inner(), shift(start), shift(end))
+                }));
+                break;
+            }
+            res.push(Pos::with_span(token, start, end));
+        }
+        if errs.is_empty() {
+            shadow_functions(lexer.symbols, &mut res);
+            Ok(res)
+        } else {
+            Err(TurtleError::LexErrors(errs))
+        }
+    }
+
+    /// Spans of the remaining tokens and comments in source order, for syntax highlighting.
+    ///
+    /// Comments are kept from now on and taken from [`Lexer::take_comments`], lex errors
+    /// are [`SyntaxClass::Invalid`]. Whitespace is everything in between.
+    pub fn highlight(&mut self) -> Vec<Pos<SyntaxClass>> {
+        self.comments.get_or_insert_with(Vec::new);
+        let mut res = Vec::new();
+        while let Some(lres) = self.next_token() {
+            res.extend(self.take_comments().iter().map(comment_class));
+            let (start, end) = lres.span();
+            let class = (*lres)
+                .as_ref()
+                .map_or(SyntaxClass::Invalid, LexToken::class);
+            res.push(Pos::with_span(class, start, end));
+        }
+        res.extend(self.take_comments().iter().map(comment_class));
+        res
+    }
+
+    /// The remaining tokens one at a time, dropping the iterator pauses the lexer
+    pub fn tokens(&mut self) -> Tokens<'_, 's, 'f> {
+        Tokens(self)
+    }
+
+    /// All remaining tokens, or every lex error among them.
+    ///
+    /// Calls of a calculation named like a function of [`Keyword::needs_call`] are calls
+    /// of the calculation, so these keywords are turned back into its identifier.
     pub fn collect_tokens(&mut self) -> Result<Vec<Pos<LexToken>>, TurtleError> {
         let mut errs = Vec::new();
         let mut res = Vec::new();
-        for lres in self {
-            let pos = lres.get_pos();
-            match lres.into_inner() {
-                Ok(token) => res.push(token.attach_pos(pos)),
-                Err(why) => errs.push(why.attach_pos(pos)),
+        for tres in self.tokens() {
+            match tres {
+                Ok(token) => res.push(token),
+                Err(why) => errs.push(why),
             }
         }
         if !errs.is_empty() {
             Err(TurtleError::LexErrors(errs))
         } else {
+            shadow_functions(self.symbols, &mut res);
             Ok(res)
         }
     }
 
     pub fn next_token(&mut self) -> Option<LResult> {
-        self.skip_comment();
+        if let Err(why) = self.skip_comment() {
+            return Some(why.map(Err));
+        }
         self.start = false;
-        let (line, column) = (self.line, self.column);
+        let prev_keyword = self.prev_keyword.take();
+        let start = self.curr_pos();
         let r = match self.next_char()? {
+            '@' if !self
+                .lookahead()
+                .is_some_and(|c| c == '_' || c.is_alphanumeric()) =>
+            {
+                Err(LexError::UnexpectedChar('@', start))
+            }
             '@' => self.match_glob_var(),
             '.' => {
                 self.put_back();
                 self.match_num_literal('0')
             }
-            '\'' => self.match_string_literal(),
+            '\'' => self.match_string_literal(prev_keyword == Some(Keyword::Include)),
+            '#' => self.match_color_literal(),
             c if c.is_ascii_digit() => self.match_num_literal(c),
-            c if c.is_alphabetic() || c == '_' => self.match_identifier(),
-            c => Ok(LexToken::Symbol(c)),
+            c if c.is_alphabetic() || c == '_' => self.match_identifier(prev_keyword),
+            c if SYMBOLS.contains(c) => Ok(LexToken::Symbol(c)),
+            c => Err(LexError::UnexpectedChar(c, start)),
         };
-        Some(r.attach_pos(FilePos::new(line, column)))
+        if let Ok(LexToken::Keyword(kw)) = r {
+            self.prev_keyword = Some(kw);
+        }
+        Some(Pos::with_span(r, start, self.last_pos()))
+    }
+
+    fn curr_pos(&self) -> FilePos {
+        FilePos::new(self.line, self.column, self.byte_offset).in_file(self.file)
+    }
+
+    /// Position of the last consumed character
+    fn last_pos(&self) -> FilePos {
+        let offset = self.byte_offset - self.chars[self.offset - 1].len_utf8();
+        let pos = if self.column > 1 {
+            FilePos::new(self.line, self.column - 1, offset)
+        } else {
+            FilePos::new(self.line - 1, self.last_col, offset)
+        };
+        pos.in_file(self.file)
     }
 
     fn match_num_literal(&mut self, c: char) -> Result<LexToken, LexError> {
@@ -157,16 +348,19 @@ impl<'s, 'f> Lexer<'s, 'f> {
         }
     }
 
-    fn match_string_literal(&mut self) -> Result<LexToken, LexError> {
-        self.features
-            .expect(Feature::Types)
-            .map_err(LexError::MissingFeature)?;
+    fn match_string_literal(&mut self, include_path: bool) -> Result<LexToken, LexError> {
+        if !include_path {
+            self.features
+                .expect(Feature::Types)
+                .map_err(LexError::MissingFeature)?;
+        }
         let mut acc = String::new();
         loop {
             match self.next_char().ok_or(LexError::UnclosedString)? {
                 '\'' => return Ok(LexToken::StringLiteral(acc)),
                 '\\' => acc.push(match self.next_char().ok_or(LexError::UnclosedString)? {
                     '\'' => '\'',
+                    '"' => '"',
                     '\\' => '\\',
                     'n' => '\n',
                     't' => '\t',
@@ -177,11 +371,29 @@ impl<'s, 'f> Lexer<'s, 'f> {
         }
     }
 
-    fn match_identifier(&mut self) -> Result<LexToken, LexError> {
+    fn match_color_literal(&mut self) -> Result<LexToken, LexError> {
+        let str = self.get_identifier();
+        let color = match TColor::from_hex(&str) {
+            Ok(col) => col,
+            Err(_) if !str.chars().all(|c| c.is_ascii_hexdigit()) => {
+                TColor::from_name(&str).ok_or(ColorParseError::UnknownName(str))?
+            }
+            Err(why) => return Err(why.into()),
+        };
+        Ok(LexToken::ColorLiteral(color))
+    }
+
+    fn match_identifier(&mut self, prev_keyword: Option<Keyword>) -> Result<LexToken, LexError> {
         self.put_back();
         let str = self.get_identifier();
         if let Ok(kw) = str.parse::<Keyword>() {
-            if kw.enabled(self.features) {
+            if kw.enabled(self.features)
+                && kw
+                    .is_contextual()
+                    .is_none_or(|after| prev_keyword == Some(after))
+                && (!kw.needs_call()
+                    || prev_keyword != Some(Keyword::Calculation) && self.next_is_call())
+            {
                 return Ok(LexToken::Keyword(kw));
             }
         }
@@ -194,6 +406,14 @@ impl<'s, 'f> Lexer<'s, 'f> {
         }
     }
 
+    /// Whether the next char that is not whitespace opens an argument list
+    fn next_is_call(&self) -> bool {
+        self.chars[self.offset..]
+            .iter()
+            .find(|c| !c.is_whitespace())
+            .is_some_and(|&c| c == '(')
+    }
+
     fn get_identifier(&mut self) -> String {
         let mut str = String::new();
         while let Some(c) = self.lookahead() {
@@ -209,6 +429,7 @@ impl<'s, 'f> Lexer<'s, 'f> {
     fn next_char(&mut self) -> Option<char> {
         let c = self.lookahead()?;
         self.offset += 1;
+        self.byte_offset += c.len_utf8();
         if c == '\n' {
             self.line += 1;
             self.last_col = self.column;
@@ -226,27 +447,35 @@ impl<'s, 'f> Lexer<'s, 'f> {
     fn put_back(&mut self) {
         if self.offset > 0 {
             self.offset -= 1;
+            self.byte_offset -= self.chars[self.offset].len_utf8();
             if self.column > 1 {
                 self.column -= 1;
             } else {
+                self.line -= 1;
                 self.column = self.last_col;
             }
         }
     }
 
-    fn skip_comment(&mut self) {
+    /// skips whitespace, `"` / `;` line comments and nested `{ .. }` block comments
+    fn skip_comment(&mut self) -> Result<(), Pos<LexError>> {
         let mut comm = false;
+        let mut comm_start = (self.offset, self.curr_pos());
         let mut line = String::new();
         while let Some(c) = self.next_char() {
             if !comm {
-                if c == '"' {
-                    comm = true
+                if c == '"' || c == ';' {
+                    comm = true;
+                    comm_start = (self.offset - 1, self.last_pos());
+                } else if c == '{' {
+                    self.skip_block_comment()?;
                 } else if !c.is_whitespace() {
                     self.put_back();
-                    return;
+                    return Ok(());
                 }
             } else if c == '\n' {
                 comm = false;
+                self.keep_comment(comm_start, self.offset - 1);
                 let mut check_line = |prefix: &str, enabled| {
                     if let Some(feature) = line
                         .trim()
@@ -264,6 +493,133 @@ impl<'s, 'f> Lexer<'s, 'f> {
                 line.push(c);
             }
         }
+        if comm {
+            self.keep_comment(comm_start, self.offset);
+        }
+        Ok(())
+    }
+
+    /// called after the opening brace
+    fn skip_block_comment(&mut self) -> Result<(), Pos<LexError>> {
+        let open = self.last_pos();
+        let start = self.offset - 1;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next_char() {
+                Some('{') => depth += 1,
+                Some('}') => depth -= 1,
+                Some(_) => {}
+                None => return Err(Pos::new(LexError::UnclosedComment, open)),
+            }
+        }
+        self.keep_comment((start, open), self.offset);
+        Ok(())
+    }
+
+    fn keep_comment(&mut self, (start, pos): (usize, FilePos), end: usize) {
+        if let Some(comments) = &mut self.comments {
+            let text: String = self.chars[start..end].iter().collect();
+            let text = text.trim_end().to_string();
+            let end = end_pos(pos, &text);
+            comments.push(Pos::with_span(text, pos, end));
+        }
+    }
+}
+
+/// Position of the last char of `text` starting at `start`
+fn end_pos(start: FilePos, text: &str) -> FilePos {
+    let mut end = start;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if chars.peek().is_none() {
+            break;
+        }
+        end.offset += c.len_utf8();
+        if c == '\n' {
+            end.line += 1;
+            end.column = 1;
+        } else {
+            end.column += 1;
+        }
+    }
+    end
+}
+
+fn comment_class(comment: &Pos<String>) -> Pos<SyntaxClass> {
+    let (start, end) = comment.span();
+    Pos::with_span(SyntaxClass::Comment, start, end)
+}
+
+/// Turn [`Keyword::needs_call`] keywords into identifiers if `tokens` define a calculation with their name
+fn shadow_functions(symbols: &SymbolTable, tokens: &mut [Pos<LexToken>]) {
+    let shadowed: Vec<(Keyword, usize)> = tokens
+        .windows(2)
+        .filter_map(|pair| match (&*pair[0], &*pair[1]) {
+            (LexToken::Keyword(Keyword::Calculation), LexToken::Identifier(id)) => {
+                let (name, _) = symbols.get_index(*id)?;
+                let kw = name.parse::<Keyword>().ok()?;
+                kw.needs_call().then_some((kw, *id))
+            }
+            _ => None,
+        })
+        .collect();
+    if shadowed.is_empty() {
+        return;
+    }
+    for token in tokens.iter_mut() {
+        if let LexToken::Keyword(kw) = **token {
+            if let Some(&(_, id)) = shadowed.iter().find(|(shadow, _)| *shadow == kw) {
+                **token = LexToken::Identifier(id);
+            }
+        }
+    }
+}
+
+/// What a piece of source is highlighted as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyntaxClass {
+    Keyword,
+    Number,
+    String,
+    Color,
+    /// symbols like `+` or `(`
+    Operator,
+    /// names of paths, calcs and locals
+    Identifier,
+    /// global and predefined variables
+    Variable,
+    Comment,
+    Invalid,
+}
+
+/// Tokens of a [`Lexer`], see [`Lexer::tokens`]
+pub struct Tokens<'l, 's, 'f>(&'l mut Lexer<'s, 'f>);
+
+impl Iterator for Tokens<'_, '_, '_> {
+    type Item = Result<Pos<LexToken>, Pos<LexError>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let lres = self.0.next_token()?;
+        let (start, end) = lres.span();
+        Some(match lres.into_inner() {
+            Ok(token) => Ok(Pos::with_span(token, start, end)),
+            Err(why) => Err(Pos::with_span(why, start, end)),
+        })
+    }
+}
+
+/// Replacement of the bytes `range` of a source by `text`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn apply(&self, source: &str) -> String {
+        let mut res = source.to_string();
+        res.replace_range(self.range.clone(), &self.text);
+        res
     }
 }
 
@@ -281,12 +637,27 @@ pub enum LexToken {
     IntLiteral(i64),
     FloatLiteral(f64),
     StringLiteral(String),
+    ColorLiteral(TColor),
     Keyword(Keyword),
     GlobalVar(usize),
     PredefVar(PredefVar),
     Identifier(usize),
 }
 
+impl LexToken {
+    pub fn class(&self) -> SyntaxClass {
+        match self {
+            LexToken::Symbol(_) => SyntaxClass::Operator,
+            LexToken::IntLiteral(_) | LexToken::FloatLiteral(_) => SyntaxClass::Number,
+            LexToken::StringLiteral(_) => SyntaxClass::String,
+            LexToken::ColorLiteral(_) => SyntaxClass::Color,
+            LexToken::Keyword(_) => SyntaxClass::Keyword,
+            LexToken::GlobalVar(_) | LexToken::PredefVar(_) => SyntaxClass::Variable,
+            LexToken::Identifier(_) => SyntaxClass::Identifier,
+        }
+    }
+}
+
 impl Display for LexToken {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -294,6 +665,9 @@ impl Display for LexToken {
             LexToken::IntLiteral(i) => write!(f, "number `{i}`"),
             LexToken::FloatLiteral(val) => write!(f, "number `{val}`"),
             LexToken::StringLiteral(s) => write!(f, "string `{s}`"),
+            LexToken::ColorLiteral(col) => {
+                write!(f, "color `{}, {}, {}`", col.r, col.g, col.b)
+            }
             LexToken::Keyword(kw) => write!(f, "keyword `{kw}`"),
             LexToken::GlobalVar(v) => write!(f, "global variable #{v}"),
             LexToken::PredefVar(pdv) => write!(f, "global variable `@{}`", pdv.get_str()),
@@ -312,6 +686,12 @@ pub enum LexError {
     UnknownEscapeChar(char),
     #[error("string literal not closed")]
     UnclosedString,
+    #[error("block comment not closed")]
+    UnclosedComment,
+    #[error("unexpected character {0:?}")]
+    UnexpectedChar(char, FilePos),
     #[error("missing feature {0}")]
     MissingFeature(Feature),
+    #[error("{0}")]
+    Color(#[from] ColorParseError),
 }
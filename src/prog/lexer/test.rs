@@ -15,7 +15,11 @@ macro_rules! assert_lex {
             {
                 use crate::tokens::{Keyword::*, PredefVar::*};
                 use LexToken::*;
-                $(assert_eq!($lex.next_token(), Some(Ok($token).attach_pos(FilePos::new($line, $col))));)+
+                $(
+                    let token = $lex.next_token().unwrap();
+                    assert_eq!(token.get_pos(), FilePos::line_col($line, $col));
+                    assert_eq!(token.into_inner(), Ok($token));
+                )+
                 assert_eq!($lex.next_token(), None);
             }
         };
@@ -32,7 +36,7 @@ fn empty() {
 #[test]
 fn leading_whitespace() {
     lex_this!(lex, "     \n\n \t\t\n   \t\n  *");
-    lex.skip_comment();
+    lex.skip_comment().unwrap();
     assert_eq!(lex.next_char(), Some('*'));
 }
 
@@ -46,7 +50,7 @@ fn symbols() {
 fn skip_nothing() {
     lex_this!(lex1, "path");
     lex_this!(lex2, "path");
-    lex2.skip_comment();
+    lex2.skip_comment().unwrap();
     assert_eq!(lex1, lex2);
 }
 
@@ -55,3 +59,304 @@ fn num_literal() {
     lex_this!(lex, ".123");
     assert_lex!(lex, 1,1 FloatLiteral(0.123));
 }
+
+#[test]
+fn token_span() {
+    lex_this!(lex, "walk  12.5\n'a b'");
+    lex.features[Feature::Types] = crate::features::FeatureState::Enabled;
+    let spans: Vec<_> = lex.map(|t| t.span()).collect();
+    assert_eq!(
+        spans,
+        [
+            (FilePos::line_col(1, 1), FilePos::line_col(1, 4)),
+            (FilePos::line_col(1, 7), FilePos::line_col(1, 10)),
+            (FilePos::line_col(2, 1), FilePos::line_col(2, 5)),
+        ]
+    );
+}
+
+#[test]
+fn byte_offset() {
+    lex_this!(lex, "über ä\n  x");
+    let offsets: Vec<_> = lex.map(|t| t.get_pos().offset).collect();
+    assert_eq!(offsets, [0, 6, 11]);
+}
+
+#[test]
+fn color_literal() {
+    lex_this!(lex, "#Red #00ff00 #fff");
+    assert_lex!(lex,
+        1,1 ColorLiteral(TColor::new(100.0, 0.0, 0.0)),
+        1,6 ColorLiteral(TColor::new(0.0, 100.0, 0.0)),
+        1,14 ColorLiteral(TColor::new(100.0, 100.0, 100.0)),
+    );
+}
+
+#[test]
+fn bad_color_literal() {
+    lex_this!(lex, "#reddish #12345");
+    let errs: Vec<_> = lex.map(|t| t.into_inner()).collect();
+    assert_eq!(
+        errs,
+        [
+            Err(LexError::Color(ColorParseError::UnknownName(
+                "reddish".to_string()
+            ))),
+            Err(LexError::Color(ColorParseError::InvalidLength(5))),
+        ]
+    );
+}
+
+#[test]
+fn line_comments() {
+    lex_this!(lex, "walk 1 ; forward\n\" old style\nturn");
+    assert_lex!(lex,
+        1,1 Keyword(Walk),
+        1,6 IntLiteral(1),
+        3,1 Keyword(Turn),
+    );
+}
+
+#[test]
+fn block_comment() {
+    lex_this!(lex, "walk { spans\n { nested }\n lines } 2");
+    assert_lex!(lex,
+        1,1 Keyword(Walk),
+        3,10 IntLiteral(2),
+    );
+}
+
+#[test]
+fn kept_comments() {
+    lex_this!(lex, "walk 1 ; forward  \n{ a\n b } turn \" end");
+    let mut lex = lex.keeping_comments();
+    assert_eq!(lex.by_ref().count(), 3);
+    let comments: Vec<_> = lex
+        .take_comments()
+        .into_iter()
+        .map(|c| (c.get_pos(), c.into_inner()))
+        .collect();
+    assert_eq!(
+        comments,
+        [
+            (FilePos::line_col(1, 8), "; forward".to_string()),
+            (FilePos::line_col(2, 1), "{ a\n b }".to_string()),
+            (FilePos::line_col(3, 11), "\" end".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn unclosed_block_comment() {
+    lex_this!(lex, "walk\n  { never { closed }");
+    assert_eq!(
+        *lex.next_token().unwrap(),
+        Ok(LexToken::Keyword(Keyword::Walk))
+    );
+    let err = lex.next_token().unwrap();
+    assert_eq!(err.get_pos(), FilePos::line_col(2, 3));
+    assert_eq!(err.into_inner(), Err(LexError::UnclosedComment));
+    assert_eq!(lex.next_token(), None);
+}
+
+#[test]
+fn unexpected_chars() {
+    lex_this!(lex, "walk @ 1\n\tturn 🐢 2 \t\t$");
+    let errs: Vec<_> = lex.filter_map(|t| t.into_inner().err()).collect();
+    assert_eq!(
+        errs,
+        [
+            LexError::UnexpectedChar('@', FilePos::line_col(1, 6)),
+            LexError::UnexpectedChar('🐢', FilePos::line_col(2, 7)),
+            LexError::UnexpectedChar('$', FilePos::line_col(2, 13)),
+        ]
+    );
+    // byte offsets account for the four byte turtle
+    let offsets: Vec<_> = errs
+        .iter()
+        .filter_map(|err| match err {
+            LexError::UnexpectedChar(_, pos) => Some(pos.offset),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(offsets, [5, 15, 24]);
+}
+
+#[test]
+fn string_escapes() {
+    lex_this!(lex, r#"print 'a\'b\"c\\d\ne'"#);
+    assert_lex!(lex,
+        1,1 Keyword(Print),
+        1,7 StringLiteral("a'b\"c\\d\ne".to_string()),
+    );
+}
+
+#[test]
+fn unclosed_string() {
+    lex_this!(lex, "walk 1\nprint 'abc\nwalk 2");
+    let errs: Vec<_> = lex.filter(|t| t.is_err()).collect();
+    assert_eq!(errs.len(), 1);
+    assert_eq!(errs[0].get_pos(), FilePos::line_col(2, 7));
+    assert_eq!(*errs[0], Err(LexError::UnclosedString));
+}
+
+/// Tokens with every part of their positions, `FilePos` equality ignores the offset
+type Positioned = Vec<(LexToken, [usize; 6])>;
+
+fn positioned(res: &Result<Vec<Pos<LexToken>>, TurtleError>) -> Result<Positioned, String> {
+    match res {
+        Ok(tokens) => Ok(tokens
+            .iter()
+            .map(|tok| {
+                let (start, end) = tok.span();
+                let pos = [
+                    start.line,
+                    start.column,
+                    start.offset,
+                    end.line,
+                    end.column,
+                    end.offset,
+                ];
+                ((**tok).clone(), pos)
+            })
+            .collect()),
+        Err(why) => Err(format!("{why:?}")),
+    }
+}
+
+#[test]
+fn relex_matches_full_lex() {
+    const FRAGMENTS: &[&str] = &[
+        " ", "\n", "a", "walk", "1", ".5", "e", "+", "(", "@x", "@dir", "\"", "{", "}", "'",
+        "include", "ä",
+    ];
+    let mut source = String::from(
+        "\" +feature types\npath sq(len)\n  walk len { note }\n  turn 90\nendpath\n\nbegin\n  let x = 1.5e2\n  walk @dir + x\n  print 'done'\nend\n",
+    );
+    let mut symbols = SymbolTable::new();
+    let mut features = FeatureConf::default();
+    let mut tokens = Lexer::new(&mut symbols, &mut features, source.chars())
+        .collect_tokens()
+        .unwrap();
+    let mut rng = crate::debugger::Rng::new(0x7e57);
+    for _ in 0..2000 {
+        let boundaries: Vec<_> = source
+            .char_indices()
+            .map(|(idx, _)| idx)
+            .chain([source.len()])
+            .collect();
+        // replace up to three chars by up to two fragments
+        let start = rng.next_u64() as usize % boundaries.len();
+        let end = (start + rng.next_u64() as usize % 4).min(boundaries.len() - 1);
+        let edit = TextEdit {
+            range: boundaries[start]..boundaries[end],
+            text: (0..rng.next_u64() % 3)
+                .map(|_| FRAGMENTS[rng.next_u64() as usize % FRAGMENTS.len()])
+                .collect(),
+        };
+        let new_source = edit.apply(&source);
+
+        let (mut full_sym, mut full_feat) = (symbols.clone(), features);
+        let full = Lexer::new(&mut full_sym, &mut full_feat, new_source.chars()).collect_tokens();
+        let (mut inc_sym, mut inc_feat) = (symbols.clone(), features);
+        let inc = Lexer::relex(&mut inc_sym, &mut inc_feat, &tokens, &new_source, &edit);
+        assert_eq!(
+            positioned(&full),
+            positioned(&inc),
+            "{edit:?} on {source:?}"
+        );
+        assert_eq!(full_sym, inc_sym);
+        if let Ok(inc) = inc {
+            source = new_source;
+            tokens = inc;
+            symbols = inc_sym;
+            features = inc_feat;
+        }
+    }
+}
+
+#[test]
+fn streamed_tokens() {
+    let code = "path sq(len)\n  walk len\nendpath\nbegin\n  path sq(1.5) print @x ~\nend";
+    lex_this!(batch, code);
+    let Err(TurtleError::LexErrors(errs)) = batch.collect_tokens() else {
+        panic!("`~` is no token");
+    };
+    lex_this!(lex, code);
+    let mut stream = Vec::new();
+    // stop after a few tokens and resume where it paused
+    stream.extend(lex.tokens().take(4));
+    stream.extend(lex.tokens());
+    let (tokens, stream_errs): (Vec<_>, Vec<_>) = stream.into_iter().partition(Result::is_ok);
+    assert_eq!(
+        stream_errs
+            .into_iter()
+            .map(Result::unwrap_err)
+            .collect::<Vec<_>>(),
+        errs
+    );
+
+    lex_this!(batch, code.replace('~', ""));
+    let batch = batch.collect_tokens().unwrap();
+    assert_eq!(
+        tokens.into_iter().map(Result::unwrap).collect::<Vec<_>>(),
+        batch
+    );
+    assert_eq!(batch.len(), 17);
+}
+
+#[test]
+fn highlight_classes() {
+    lex_this!(
+        lex,
+        "walk @x + len*2 \" the rest\nprint 'hi' { a\nb } #ff0000 ~"
+    );
+    let classes: Vec<_> = lex
+        .highlight()
+        .into_iter()
+        .map(|class| {
+            let (start, end) = class.span();
+            (*class, (start.line, start.column), (end.line, end.column))
+        })
+        .collect();
+    use SyntaxClass::*;
+    assert_eq!(
+        classes,
+        [
+            (Keyword, (1, 1), (1, 4)),
+            (Variable, (1, 6), (1, 7)),
+            (Operator, (1, 9), (1, 9)),
+            (Identifier, (1, 11), (1, 13)),
+            (Operator, (1, 14), (1, 14)),
+            (Number, (1, 15), (1, 15)),
+            (Comment, (1, 17), (1, 26)),
+            (Keyword, (2, 1), (2, 5)),
+            (String, (2, 7), (2, 10)),
+            (Comment, (2, 12), (3, 3)),
+            (Color, (3, 5), (3, 11)),
+            (Invalid, (3, 13), (3, 13)),
+        ]
+    );
+}
+
+#[test]
+fn contextual_keywords() {
+    lex_this!(lex, "penstyle dotted dotted");
+    assert_lex!(lex, 1,1 Keyword(Penstyle), 1,10 Keyword(Dotted), 1,17 Identifier(0));
+}
+
+#[test]
+fn functions_as_identifiers() {
+    lex_this!(lex, "abs (1) + abs");
+    assert_lex!(lex, 1,1 Keyword(Abs), 1,5 Symbol('('), 1,6 IntLiteral(1), 1,7 Symbol(')'),
+        1,9 Symbol('+'), 1,11 Identifier(0));
+    lex_this!(lex, "calculation floor(x) floor(x)");
+    let tokens: Vec<LexToken> = lex
+        .collect_tokens()
+        .unwrap()
+        .into_iter()
+        .map(Pos::into_inner)
+        .collect();
+    assert_eq!(tokens[1], LexToken::Identifier(0));
+    assert_eq!(tokens[5], LexToken::Identifier(0));
+}
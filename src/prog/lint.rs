@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+
+use clap::ValueEnum;
+
+use crate::{
+    tokens::{Block, Expr, ExprKind, Statement, Variable, VariableKind},
+    Pos,
+};
+
+use super::TProgram;
+
+/// Kinds of warnings, e.g. to turn them into errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Lint {
+    UnusedVariable,
+}
+
+/// Suspicious code that is still a valid program
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum Warning {
+    #[error("variable `{0}` is never used")]
+    UnusedVariable(String),
+}
+
+impl Warning {
+    pub fn lint(&self) -> Lint {
+        match self {
+            Warning::UnusedVariable(_) => Lint::UnusedVariable,
+        }
+    }
+}
+
+/// Locals bound by `let` and read somewhere in a path, calc or main.
+///
+/// Locals are tracked by name, so a `let` shadowing a used one of the same
+/// name counts as used too.
+#[derive(Default)]
+struct Locals {
+    bound: Vec<Variable>,
+    used: HashSet<usize>,
+}
+
+impl Locals {
+    fn unused(self, prog: &TProgram, warnings: &mut Vec<Pos<Warning>>) {
+        let mut reported = HashSet::new();
+        for var in self.bound {
+            let VariableKind::Local(id, _) = var.kind else {
+                continue;
+            };
+            if !self.used.contains(&id) && reported.insert(id) {
+                let name = prog.symbols.get_index(id).map_or("", |(name, _)| name);
+                warnings.push(Pos::new(Warning::UnusedVariable(name.to_string()), var.pos));
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        for stmt in &block.statements {
+            self.stmt(stmt);
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Statement) {
+        if let Statement::Calc { var, .. } = stmt {
+            self.var(var);
+        }
+        stmt.for_each_expr(|expr| self.expr(expr));
+        stmt.for_each_block(|block| self.block(block));
+        if let Statement::Let(_, var) = stmt {
+            self.bound.push(var.clone());
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        if let ExprKind::Variable(var) = &expr.kind {
+            self.var(var);
+        }
+        expr.for_each_sub(|sub| self.expr(sub));
+    }
+
+    fn var(&mut self, var: &Variable) {
+        if let VariableKind::Local(id, _) = var.kind {
+            self.used.insert(id);
+        }
+    }
+}
+
+impl TProgram {
+    /// Warnings for the program, sorted by position
+    pub fn lint(&self) -> Vec<Pos<Warning>> {
+        let mut warnings = Vec::new();
+        let paths = self
+            .paths
+            .iter()
+            .chain(&self.key_event)
+            .chain(&self.mouse_event);
+        for path in paths {
+            let mut locals = Locals::default();
+            locals.block(&path.body);
+            locals.unused(self, &mut warnings);
+        }
+        for calc in &self.calcs {
+            let mut locals = Locals::default();
+            locals.block(&calc.body);
+            locals.expr(&calc.ret);
+            locals.unused(self, &mut warnings);
+        }
+        let mut locals = Locals::default();
+        locals.block(&self.main);
+        locals.unused(self, &mut warnings);
+        warnings.sort_by_key(|warn| warn.get_pos());
+        warnings
+    }
+}
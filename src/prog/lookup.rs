@@ -0,0 +1,278 @@
+use crate::{
+    pos::FilePos,
+    tokens::{Block, Expr, ExprKind, Statement, ValType, Variable, VariableKind},
+    Pos,
+};
+
+use super::TProgram;
+
+/// What's written at a position of the source, see [`TProgram::symbol_at`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolInfo {
+    /// `def` is the binding `let` or argument for locals, globals don't have one
+    Variable {
+        name: String,
+        global: bool,
+        def: Option<FilePos>,
+    },
+    /// call of a path or calc, `def` is the begin of its definition
+    Call {
+        name: String,
+        def: FilePos,
+        arity: usize,
+    },
+    /// predefined variable or function
+    Builtin(String),
+}
+
+impl SymbolInfo {
+    fn def(&self) -> Option<FilePos> {
+        match self {
+            SymbolInfo::Variable { def, .. } => *def,
+            SymbolInfo::Call { def, .. } => Some(*def),
+            SymbolInfo::Builtin(_) => None,
+        }
+    }
+}
+
+/// Where a symbol is written
+#[derive(Clone, Copy)]
+enum Extent {
+    Chars(usize),
+    /// the name of a path call has no position of its own, so the call takes
+    /// the rest of the line up to the given position
+    RestOfLine(Option<FilePos>),
+}
+
+/// Visits every symbol of a program with the definition it resolves to.
+///
+/// Symbols nested in others, like the arguments of a call, are visited first.
+struct Resolver<'p, F> {
+    prog: &'p TProgram,
+    /// visible `let`s and arguments of the enclosing blocks, the innermost last
+    scopes: Vec<Vec<(usize, FilePos)>>,
+    found: F,
+}
+
+impl<F: FnMut(FilePos, Extent, SymbolInfo)> Resolver<'_, F> {
+    fn name(&self, id: usize) -> String {
+        self.prog
+            .symbols
+            .get_index(id)
+            .map_or(String::new(), |(name, _)| name.clone())
+    }
+
+    fn program(&mut self) {
+        let prog = self.prog;
+        let paths = prog
+            .paths
+            .iter()
+            .chain(&prog.key_event)
+            .chain(&prog.mouse_event);
+        for path in paths {
+            self.scopes = vec![args(&path.args, &path.arg_pos)];
+            self.block(&path.body, None);
+        }
+        for calc in &prog.calcs {
+            self.scopes = vec![args(&calc.args, &calc.arg_pos)];
+            self.block(&calc.body, Some(calc.ret.start));
+            self.expr(&calc.ret);
+        }
+        self.scopes.clear();
+        self.block(&prog.main, None);
+    }
+
+    /// `bound` is the start of whatever follows the block, if known
+    fn block(&mut self, block: &Block, bound: Option<FilePos>) {
+        self.scopes.push(Vec::new());
+        for (idx, stmt) in block.statements.iter().enumerate() {
+            let next = block.statements.get(idx + 1).map(Pos::get_pos).or(bound);
+            self.stmt(stmt, next);
+        }
+        self.scopes.pop();
+    }
+
+    fn stmt(&mut self, stmt: &Pos<Statement>, next: Option<FilePos>) {
+        match &**stmt {
+            Statement::Store(expr, var) | Statement::Calc { var, val: expr, .. } => {
+                self.expr(expr);
+                self.var(var);
+            }
+            Statement::Let(expr, var) => {
+                self.expr(expr);
+                if let (VariableKind::Local(id, _), Some(scope)) =
+                    (var.kind, self.scopes.last_mut())
+                {
+                    scope.push((id, var.pos));
+                }
+                self.var(var);
+            }
+            Statement::PathCall(id, args) | Statement::Split(id, args) => {
+                args.iter().for_each(|e| self.expr(e));
+                let Some(path) = self.prog.paths.iter().find(|path| path.name == *id) else {
+                    return;
+                };
+                let before_args = args.first().map_or(next, |arg| Some(arg.start));
+                let info = SymbolInfo::Call {
+                    name: self.name(*id),
+                    def: path.body.begin,
+                    arity: path.args.len(),
+                };
+                (self.found)(stmt.get_pos(), Extent::RestOfLine(before_args), info);
+            }
+            Statement::RepeatLoop(expr, block) => {
+                self.block(block, Some(expr.start));
+                self.expr(expr);
+            }
+            Statement::IfElseBranch(expr, if_br, else_br) => {
+                self.expr(expr);
+                self.block(if_br, else_br.statements.first().map(Pos::get_pos));
+                self.block(else_br, next);
+            }
+            Statement::ForEach { item, list, body } => {
+                self.expr(list);
+                let mut scope = Vec::new();
+                if let VariableKind::Local(id, _) = item.kind {
+                    scope.push((id, item.pos));
+                }
+                self.scopes.push(scope);
+                self.var(item);
+                self.block(body, next);
+                self.scopes.pop();
+            }
+            stmt => {
+                if let Statement::CounterLoop { counter, .. } = stmt {
+                    self.var(counter);
+                }
+                stmt.for_each_expr(|expr| self.expr(expr));
+                stmt.for_each_block(|block| self.block(block, next));
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        expr.for_each_sub(|sub| self.expr(sub));
+        match &expr.kind {
+            ExprKind::Variable(var) => self.var(var),
+            ExprKind::FuncCall(func, _) => {
+                let name = func.to_string();
+                (self.found)(
+                    expr.start,
+                    Extent::Chars(name.len()),
+                    SymbolInfo::Builtin(name),
+                );
+            }
+            ExprKind::CalcCall(id, _) => {
+                let Some(calc) = self.prog.calcs.iter().find(|calc| calc.name == *id) else {
+                    return;
+                };
+                let name = self.name(*id);
+                let extent = Extent::Chars(name.chars().count());
+                let info = SymbolInfo::Call {
+                    name,
+                    def: calc.body.begin,
+                    arity: calc.args.len(),
+                };
+                (self.found)(expr.start, extent, info);
+            }
+            _ => {}
+        }
+    }
+
+    fn var(&mut self, var: &Variable) {
+        let (extent, info) = match var.kind {
+            VariableKind::Local(id, _) => {
+                let name = self.name(id);
+                let def = self
+                    .scopes
+                    .iter()
+                    .rev()
+                    .flat_map(|scope| scope.iter().rev())
+                    .find(|(bound, _)| *bound == id)
+                    .map(|(_, pos)| *pos);
+                let extent = Extent::Chars(name.chars().count());
+                let info = SymbolInfo::Variable {
+                    name,
+                    global: false,
+                    def,
+                };
+                (extent, info)
+            }
+            VariableKind::Global(id, _) => {
+                let name = self.name(id);
+                let extent = Extent::Chars(name.chars().count() + 1);
+                let info = SymbolInfo::Variable {
+                    name,
+                    global: true,
+                    def: None,
+                };
+                (extent, info)
+            }
+            VariableKind::GlobalPreDef(var_kind) => {
+                let name = var_kind.get_str();
+                let extent = Extent::Chars(name.len() + 1);
+                (extent, SymbolInfo::Builtin(format!("@{name}")))
+            }
+        };
+        (self.found)(var.pos, extent, info);
+    }
+}
+
+fn args(args: &[(usize, ValType)], pos: &[FilePos]) -> Vec<(usize, FilePos)> {
+    args.iter()
+        .map(|(id, _)| *id)
+        .zip(pos.iter().copied())
+        .collect()
+}
+
+/// whether `pos` is on the symbol starting at `start`
+fn contains(start: FilePos, extent: Extent, pos: FilePos) -> bool {
+    if start.file != pos.file || start.line != pos.line {
+        return false;
+    }
+    match extent {
+        Extent::Chars(len) => (start.column..start.column + len).contains(&pos.column),
+        Extent::RestOfLine(end) => start <= pos && end.is_none_or(|end| pos < end),
+    }
+}
+
+impl TProgram {
+    fn resolve(&self, found: impl FnMut(FilePos, Extent, SymbolInfo)) {
+        Resolver {
+            prog: self,
+            scopes: Vec::new(),
+            found,
+        }
+        .program();
+    }
+
+    /// The innermost variable, call or builtin at `pos`, for editor tooling like hovers
+    pub fn symbol_at(&self, pos: FilePos) -> Option<SymbolInfo> {
+        let mut res = None;
+        self.resolve(|start, extent, info| {
+            if res.is_none() && contains(start, extent, pos) {
+                res = Some(info);
+            }
+        });
+        res
+    }
+
+    /// Where the variable or call at `pos` is defined, `None` for globals and builtins
+    pub fn definition_of(&self, pos: FilePos) -> Option<FilePos> {
+        self.symbol_at(pos)?.def()
+    }
+
+    /// Every use of the let, argument, path or calc defined at `def`, in source order.
+    ///
+    /// Path calls are at the start of their statement.
+    pub fn references(&self, def: FilePos) -> Vec<FilePos> {
+        let mut res = Vec::new();
+        self.resolve(|start, _, info| {
+            if info.def() == Some(def) && start != def {
+                res.push(start);
+            }
+        });
+        res.sort();
+        res
+    }
+}
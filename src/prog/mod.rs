@@ -1,24 +1,42 @@
 use std::{
     cell::{Ref, RefCell},
     ops::Deref,
+    path::Path,
     str::FromStr,
 };
 
 use crate::{
+    diagnostic::{DiagnosticSink, Severity},
     features::FeatureConf,
     tokens::{ArgDefList, Block, EventKind, Expr, ParseToken, ValType},
-    Identified, SymbolTable, TurtleError,
+    FilePos, Identified, Pos, SymbolTable, TurtleError,
 };
 
-use lexer::Lexer;
-use parser::Parser;
+pub use bytecode::{Bytecode, Unsupported};
+pub use eval::{eval_expr, Environment, EvalError};
+pub use format::format;
+use include::Includes;
+use lexer::{LexError, LexToken, Lexer};
+pub use lint::{Lint, Warning};
+pub use lookup::SymbolInfo;
+use parser::{ParseError, Parser};
+pub use rename::RenameError;
 pub use semcheck::TypeError;
 
+pub(crate) mod bytecode;
+mod eval;
+mod format;
+mod include;
 pub mod lexer;
+mod lint;
+mod lookup;
 mod optimization;
 pub mod parser;
+mod rename;
 pub(crate) mod semcheck;
 mod side_effects;
+#[cfg(test)]
+mod test;
 
 #[derive(Default)]
 struct RawProg {
@@ -40,6 +58,7 @@ impl RawProg {
                 }
                 self.main = Some(block);
             }
+            ParseToken::Include(_) => unreachable!("resolved by Includes"),
             ParseToken::EventHandler(kind, func) => {
                 let curr = match kind {
                     EventKind::Mouse => &mut self.mouse_event,
@@ -82,6 +101,18 @@ impl RawProg {
     }
 }
 
+/// One diagnostic reported by [`TProgram::compile_all`]
+#[derive(Debug, thiserror::Error)]
+pub enum CompileError {
+    #[error("{0}")]
+    Lex(LexError),
+    #[error("{0}")]
+    Parse(ParseError),
+    /// Found after parsing succeeded, e.g. by the type check
+    #[error("{0}")]
+    Program(TurtleError),
+}
+
 /// A full and valid turtle program.
 #[derive(Debug)]
 pub struct TProgram {
@@ -97,9 +128,19 @@ pub struct TProgram {
 }
 
 impl TProgram {
+    /// Parse `code`, includes are resolved relative to the working directory
     pub fn parse(
         code: &str,
         print_symbols: bool,
+        features: FeatureConf,
+    ) -> Result<Self, TurtleError> {
+        Self::parse_in(code, None, print_symbols, features)
+    }
+
+    fn parse_in(
+        code: &str,
+        file: Option<&Path>,
+        print_symbols: bool,
         mut features: FeatureConf,
     ) -> Result<Self, TurtleError> {
         let mut symbols = SymbolTable::new();
@@ -109,24 +150,127 @@ impl TProgram {
                 println!("#{idx:<3} {name}");
             }
         }
-        let parser = Parser::new(&mut symbols, ltokens, &mut features);
+        let items =
+            Parser::new(&mut symbols, ltokens, &mut features).collect::<Result<Vec<_>, _>>()?;
         let mut raw = RawProg::default();
-        for item in parser {
-            raw.insert_item(item?)?;
-        }
+        Includes::new(file).insert_all(items, &mut raw, &mut symbols, &mut features)?;
         features.finalize();
         let mut this = raw.finish(symbols, features)?;
         this.semantic_check()?;
         Ok(this)
     }
 
+    /// Like [`TProgram::parse`], but the parser skips broken statements and
+    /// reports every error instead of only the first one.
+    ///
+    /// `file` is where `code` was read from, includes are resolved relative to it.
+    pub fn compile_all(
+        code: &str,
+        file: Option<&Path>,
+        mut features: FeatureConf,
+    ) -> Result<Self, Vec<Pos<CompileError>>> {
+        let mut symbols = SymbolTable::new();
+        let ltokens = Self::lex_all(&mut Lexer::new(&mut symbols, &mut features, code.chars()))?;
+        let items = Self::parse_items(&mut symbols, ltokens, &mut features)?;
+        let mut raw = RawProg::default();
+        let mut includes = Includes::new(file);
+        let mut errs = Vec::new();
+        for item in items {
+            let res = match item {
+                ParseToken::Include(inc) => {
+                    includes.include(inc, &mut raw, &mut symbols, &mut features)
+                }
+                item => raw.insert_item(item),
+            };
+            if let Err(why) = res {
+                errs.push(Self::program_error(why));
+            }
+        }
+        if !errs.is_empty() {
+            return Err(errs);
+        }
+        features.finalize();
+        raw.finish(symbols, features)
+            .and_then(|mut this| this.semantic_check().map(|_| this))
+            .map_err(|why| vec![Self::program_error(why)])
+    }
+
+    /// Like [`TProgram::compile_all`], but the errors and warnings go to `sink`.
+    ///
+    /// Warnings of the lints in `deny` are reported as errors and fail the compilation.
+    pub fn compile_reported(
+        code: &str,
+        file: Option<&Path>,
+        features: FeatureConf,
+        deny: &[Lint],
+        sink: &mut impl DiagnosticSink,
+    ) -> Option<Self> {
+        match Self::compile_all(code, file, features) {
+            Ok(this) => this.report_lints(deny, sink).then_some(this),
+            Err(errs) => {
+                for err in &errs {
+                    err.emit(sink);
+                }
+                None
+            }
+        }
+    }
+
+    /// Report the warnings of [`TProgram::lint`], `false` if one of them is denied
+    pub fn report_lints(&self, deny: &[Lint], sink: &mut impl DiagnosticSink) -> bool {
+        let mut ok = true;
+        for warn in self.lint() {
+            let severity = if deny.contains(&warn.lint()) {
+                ok = false;
+                Severity::Error
+            } else {
+                Severity::Warning
+            };
+            warn.emit(severity, sink);
+        }
+        ok
+    }
+
+    fn lex_all(lexer: &mut Lexer) -> Result<Vec<Pos<LexToken>>, Vec<Pos<CompileError>>> {
+        lexer.collect_tokens().map_err(|why| match why {
+            TurtleError::LexErrors(errs) => {
+                errs.into_iter().map(|e| e.map(CompileError::Lex)).collect()
+            }
+            why => vec![Self::program_error(why)],
+        })
+    }
+
+    /// All items of the file, or every parse error found in it
+    fn parse_items(
+        symbols: &mut SymbolTable,
+        ltokens: Vec<Pos<LexToken>>,
+        features: &mut FeatureConf,
+    ) -> Result<Vec<ParseToken>, Vec<Pos<CompileError>>> {
+        let (items, errs) = Parser::new(symbols, ltokens, features)
+            .recovering()
+            .parse_all();
+        if errs.is_empty() {
+            Ok(items)
+        } else {
+            Err(errs
+                .into_iter()
+                .map(|e| e.map(CompileError::Parse))
+                .collect())
+        }
+    }
+
+    fn program_error(why: TurtleError) -> Pos<CompileError> {
+        let pos = why.pos().unwrap_or_default();
+        Pos::new(CompileError::Program(why), pos)
+    }
+
     pub fn from_file(
         file: &str,
         print_symbols: bool,
         features: FeatureConf,
     ) -> Result<Self, TurtleError> {
         let code = std::fs::read_to_string(file)?;
-        let mut this = Self::parse(&code, print_symbols, features)?;
+        let mut this = Self::parse_in(&code, Some(Path::new(file)), print_symbols, features)?;
         this.name = Some(file.to_string());
         Ok(this)
     }
@@ -255,6 +399,8 @@ impl<T> Deref for MaybeRef<'_, T> {
 pub struct PathDef {
     pub name: usize,
     pub args: ArgDefList,
+    /// position of each argument's name
+    pub arg_pos: Vec<FilePos>,
     pub body: Block,
 }
 
@@ -263,6 +409,8 @@ pub struct PathDef {
 pub struct CalcDef {
     pub name: usize,
     pub args: ArgDefList,
+    /// position of each argument's name
+    pub arg_pos: Vec<FilePos>,
     pub ret_ty: ValType,
     pub body: Block,
     pub ret: Expr,
@@ -1,6 +1,6 @@
 use crate::{
-    pos::{Pos, Positionable},
-    tokens::{Block, Expr, ExprKind, Statement, Value},
+    pos::{FilePos, Pos, Positionable},
+    tokens::{Block, Expr, ExprKind, Statement, ValType, Value},
 };
 
 impl Expr {
@@ -12,6 +12,10 @@ impl Expr {
         }
     }
 
+    fn is_const_num(&self, num: f64) -> bool {
+        self.is_const().is_some_and(|v| v == Value::Number(num))
+    }
+
     pub fn const_fold(&mut self) {
         let mut kind = ExprKind::Const(Value::Boolean(false));
         std::mem::swap(&mut kind, &mut self.kind);
@@ -19,6 +23,14 @@ impl Expr {
     }
 }
 
+/// Results like `1 / 0` stay in the program, they aren't valid literals
+fn folded(val: Value, orig: impl FnOnce() -> ExprKind) -> ExprKind {
+    match val {
+        Value::Number(num) if !num.is_finite() => orig(),
+        val => ExprKind::Const(val),
+    }
+}
+
 impl ExprKind {
     fn const_fold(self) -> ExprKind {
         match self {
@@ -26,16 +38,21 @@ impl ExprKind {
             ExprKind::BiOperation(mut lhs, op, mut rhs) => {
                 lhs.const_fold();
                 rhs.const_fold();
-                if let (Some(lhs), Some(rhs)) = (lhs.is_const(), rhs.is_const()) {
-                    ExprKind::Const(op.eval(&lhs, &rhs))
+                // a type error is left for the run to report
+                if let Some(Ok(val)) = lhs
+                    .is_const()
+                    .zip(rhs.is_const())
+                    .map(|(l, r)| op.try_eval(&l, &r))
+                {
+                    folded(val, || ExprKind::BiOperation(lhs, op, rhs))
                 } else {
                     ExprKind::BiOperation(lhs, op, rhs)
                 }
             }
             ExprKind::UnOperation(op, mut expr) => {
                 expr.const_fold();
-                if let Some(val) = expr.is_const() {
-                    ExprKind::Const(op.eval(&val))
+                if let Some(Ok(val)) = expr.is_const().map(|val| op.try_eval(&val)) {
+                    ExprKind::Const(val)
                 } else {
                     ExprKind::UnOperation(op, expr)
                 }
@@ -54,10 +71,11 @@ impl ExprKind {
             }
             ExprKind::Convert(mut expr, to) => {
                 expr.const_fold();
-                if let Some(val) = expr.is_const() {
-                    ExprKind::Const(val.convert(to))
-                } else {
-                    ExprKind::Convert(expr, to)
+                match expr.is_const() {
+                    // the text of a number depends on `setprecision` at runtime
+                    Some(Value::Number(_)) if to == ValType::String => ExprKind::Convert(expr, to),
+                    Some(val) => folded(val.convert(to), || ExprKind::Convert(expr, to)),
+                    None => ExprKind::Convert(expr, to),
                 }
             }
             ExprKind::FuncCall(pdf, mut exprs) => {
@@ -68,6 +86,32 @@ impl ExprKind {
                 exprs.iter_mut().for_each(|e| e.const_fold());
                 ExprKind::CalcCall(id, exprs)
             }
+            ExprKind::List(mut items) => {
+                items.iter_mut().for_each(|e| e.const_fold());
+                match items
+                    .iter()
+                    .map(|e| e.is_const())
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(vals) => {
+                        ExprKind::Const(Value::List(vals.iter().map(Value::num).collect()))
+                    }
+                    None => ExprKind::List(items),
+                }
+            }
+            ExprKind::Index(mut list, mut idx) => {
+                list.const_fold();
+                idx.const_fold();
+                // an index outside the list is left to fail at runtime
+                match list
+                    .is_const()
+                    .zip(idx.is_const())
+                    .and_then(|(l, i)| l.item(&i))
+                {
+                    Some(val) => ExprKind::Const(Value::Number(val)),
+                    None => ExprKind::Index(list, idx),
+                }
+            }
         }
     }
 }
@@ -78,6 +122,7 @@ impl Pos<Statement> {
             Statement::MoveDist { dist, .. } => dist.const_fold(),
             Statement::Turn { by, .. } => by.const_fold(),
             Statement::Calc { val, .. } => val.const_fold(),
+            Statement::ForEach { list, .. } => list.const_fold(),
             Statement::CounterLoop { from, to, step, .. } => {
                 from.const_fold();
                 to.const_fold();
@@ -87,12 +132,39 @@ impl Pos<Statement> {
             }
             Statement::Direction(expr)
             | Statement::Store(expr, _)
+            | Statement::Let(expr, _)
             | Statement::Print(expr)
+            | Statement::PenWidth(expr)
+            | Statement::SetSpeed(expr)
+            | Statement::SetTurtle(expr)
+            | Statement::SetPrecision(expr)
+            | Statement::Rotate(expr)
             | Statement::IfBranch(expr, _)
             | Statement::IfElseBranch(expr, _, _)
             | Statement::DoLoop(expr, _)
             | Statement::WhileLoop(expr, _)
-            | Statement::RepeatLoop(expr, _) => expr.const_fold(),
+            | Statement::RepeatLoop(expr, _)
+            | Statement::Return(Some(expr)) => expr.const_fold(),
+            Statement::MoveTo { x, y, .. } => {
+                x.const_fold();
+                y.const_fold();
+            }
+            Statement::Arc { radius, angle, .. } => {
+                radius.const_fold();
+                if let Some(a) = angle {
+                    a.const_fold();
+                }
+            }
+            Statement::Scale(x, y) => {
+                x.const_fold();
+                if let Some(y) = y {
+                    y.const_fold();
+                }
+            }
+            Statement::Translate(x, y) => {
+                x.const_fold();
+                y.const_fold();
+            }
             Statement::Color(r, g, b) => {
                 r.const_fold();
                 g.const_fold();
@@ -104,12 +176,23 @@ impl Pos<Statement> {
                 }
             }
             Statement::MoveHome(_)
+            | Statement::PushTransform
+            | Statement::PopTransform
+            | Statement::SaveState
+            | Statement::RestoreState
+            | Statement::NewTurtle
+            | Statement::PenStyle(_)
+            | Statement::Pen(_)
+            | Statement::ShowTurtle(_)
             | Statement::Clear
+            | Statement::ClearScreen
             | Statement::Stop
             | Statement::Finish
+            | Statement::Return(None)
             | Statement::Mark
             | Statement::MoveMark(_)
-            | Statement::Wait => {}
+            | Statement::Wait
+            | Statement::Frame(_) => {}
         };
 
         let to_bool = |v: Value| v.bool();
@@ -117,23 +200,24 @@ impl Pos<Statement> {
 
         let pos = self.get_pos();
         match self.into_inner() {
+            Statement::MoveDist { dist, .. } if dist.is_const_num(0.0) => Vec::new(),
             Statement::IfBranch(expr, block) => match expr.is_const().map(to_bool) {
-                Some(true) => block.statements,
+                Some(true) => block.inline(&expr, pos),
                 Some(false) => Vec::new(),
                 None => vec![Statement::IfBranch(expr, block).attach_pos(pos)],
             },
             Statement::IfElseBranch(expr, if_block, else_block) => {
                 match expr.is_const().map(to_bool) {
-                    Some(true) => if_block.statements,
-                    Some(false) => else_block.statements,
+                    Some(true) => if_block.inline(&expr, pos),
+                    Some(false) => else_block.inline(&expr, pos),
                     None => {
                         vec![Statement::IfElseBranch(expr, if_block, else_block).attach_pos(pos)]
                     }
                 }
             }
+            // a fractional count is left to fail at runtime
             Statement::DoLoop(expr, block) => match expr.is_const().map(to_num) {
-                Some(..0.0) => Vec::new(),
-                Some(1.0..2.0) => block.statements,
+                Some(count) if count <= 0.0 && count.fract() == 0.0 => Vec::new(),
                 _ => vec![Statement::DoLoop(expr, block).attach_pos(pos)],
             },
             Statement::CounterLoop {
@@ -144,11 +228,13 @@ impl Pos<Statement> {
                 step,
                 body,
             } => {
-                if let (Some(from), Some(to)) =
+                if let (Some(start), Some(end)) =
                     (from.is_const().map(to_num), to.is_const().map(to_num))
                 {
-                    if from != to && (from > to) == up {
-                        return Vec::new();
+                    // the loop body never runs, but the counter is still set
+                    if up == (start >= end) && step.as_ref().is_none_or(|s| s.is_const().is_some())
+                    {
+                        return vec![Statement::Store(from, counter).attach_pos(pos)];
                     }
                 }
                 vec![Statement::CounterLoop {
@@ -169,8 +255,8 @@ impl Pos<Statement> {
                 }
             }
             Statement::RepeatLoop(expr, block) => {
-                if expr.is_const().is_some_and(|v| !v.bool()) {
-                    block.statements
+                if expr.is_const().is_some_and(|v| v.bool()) {
+                    block.inline(&expr, pos)
                 } else {
                     vec![Statement::RepeatLoop(expr, block).attach_pos(pos)]
                 }
@@ -181,6 +267,22 @@ impl Pos<Statement> {
 }
 
 impl Block {
+    /// Statements of a block that runs exactly once, to replace the statement at `pos`
+    ///
+    /// Blocks binding locals with `let` keep their scope, as an `if true` at `cond`.
+    fn inline(self, cond: &Expr, pos: FilePos) -> Vec<Pos<Statement>> {
+        if self
+            .statements
+            .iter()
+            .any(|stmt| matches!(**stmt, Statement::Let(..)))
+        {
+            let cond = ExprKind::Const(Value::Boolean(true)).at(cond.start, cond.end);
+            vec![Statement::IfBranch(cond, self).attach_pos(pos)]
+        } else {
+            self.statements
+        }
+    }
+
     pub fn const_fold(&mut self) {
         let mut new_stmt = Vec::new();
         for stmt in self.statements.drain(..) {
@@ -1,6 +1,7 @@
 use std::fmt::Display;
 
 use crate::{
+    debugger::FuncType,
     features::{Feature, FeatureConf, FeatureState},
     prog::{lexer::LexToken, CalcDef, PathDef},
     tokens::*,
@@ -20,6 +21,10 @@ pub struct Parser<'s, 'f> {
     pos: usize,
     symbols: &'s mut SymbolTable,
     features: &'f mut FeatureConf,
+    /// errors skipped over while recovering, `None` if recovery is off
+    errors: Option<Vec<Pos<ParseError>>>,
+    /// definition the statements belong to, decides what `return` means
+    func: FuncType,
 }
 
 impl<'s, 'f> Parser<'s, 'f> {
@@ -33,6 +38,63 @@ impl<'s, 'f> Parser<'s, 'f> {
             pos: 0,
             symbols,
             features,
+            errors: None,
+            func: FuncType::Main,
+        }
+    }
+
+    /// Keep parsing after a broken statement or definition instead of
+    /// stopping, see [`Parser::parse_all`]
+    pub fn recovering(mut self) -> Self {
+        self.errors = Some(Vec::new());
+        self
+    }
+
+    /// Parse all remaining items, collecting every error on the way.
+    ///
+    /// Only meaningful on a [`recovering`](Parser::recovering) parser.
+    pub fn parse_all(&mut self) -> (Vec<ParseToken>, Vec<Pos<ParseError>>) {
+        let mut items = Vec::new();
+        while let Some(res) = self.parse_next() {
+            match res {
+                Ok(item) => items.push(item),
+                Err(why) if self.errors.is_some() => {
+                    self.report(why);
+                    self.sync_item();
+                }
+                Err(why) => return (items, vec![why]),
+            }
+        }
+        (items, self.errors.take().unwrap_or_default())
+    }
+
+    /// Store an error during recovery, dropping an end of file already reported
+    fn report(&mut self, why: Pos<ParseError>) {
+        let errors = self.errors.as_mut().expect("only called while recovering");
+        let dup_end = matches!(*why, ParseError::UnexpectedEnd)
+            && errors
+                .last()
+                .is_some_and(|last| matches!(**last, ParseError::UnexpectedEnd));
+        if !dup_end {
+            errors.push(why);
+        }
+    }
+
+    /// Skip to the next path, calculation, main block or event handler
+    fn sync_item(&mut self) {
+        self.pos += 1;
+        while let Some(tok) = self.lookahead() {
+            if let LexToken::Keyword(
+                Keyword::Path
+                | Keyword::Calculation
+                | Keyword::Begin
+                | Keyword::Event
+                | Keyword::Include,
+            ) = tok
+            {
+                break;
+            }
+            self.pos += 1;
         }
     }
 
@@ -40,10 +102,15 @@ impl<'s, 'f> Parser<'s, 'f> {
         self.pos = 0;
     }
 
+    /// The next token not consumed yet
+    pub fn next_unparsed(&self) -> Option<&Pos<LexToken>> {
+        self.ltokens.get(self.pos)
+    }
+
     pub fn parse_next(&mut self) -> Option<PRes<ParseToken>> {
         self.eof()?;
         let begin = self.curr_pos();
-        Some(if self.match_keyword(Keyword::Path) {
+        let res = if self.match_keyword(Keyword::Path) {
             self.parse_path(begin)
         } else if self.match_keyword(Keyword::Calculation) {
             self.parse_calc(begin)
@@ -51,9 +118,17 @@ impl<'s, 'f> Parser<'s, 'f> {
             self.parse_main(begin)
         } else if self.match_keyword(Keyword::Event) {
             self.parse_event(begin)
+        } else if self.match_keyword(Keyword::Include) {
+            match self.next_token_err() {
+                Ok(LexToken::StringLiteral(file)) => Ok(ParseToken::Include(Pos::new(file, begin))),
+                Ok(_) => Err(self.unexpected_last_token(TokenExpectation::FileName)),
+                Err(why) => Err(why),
+            }
         } else {
             Err(self.unexpected_token(TokenExpectation::BlockStart))
-        })
+        };
+        self.func = FuncType::Main;
+        Some(res)
     }
 
     fn lookahead(&self) -> Option<LexToken> {
@@ -91,13 +166,30 @@ impl<'s, 'f> Parser<'s, 'f> {
     }
 
     fn match_identifier(&mut self) -> PRes<usize> {
-        let Some(LexToken::Identifier(name)) = self.lookahead() else {
+        let Some(LexToken::Identifier(name)) = self.lookahead().map(|tok| self.identifier(tok))
+        else {
             return Err(self.unexpected_token(TokenExpectation::AnyIdentifier));
         };
         self.pos += 1;
         Ok(name)
     }
 
+    /// `token` as an identifier if it is a keyword that only starts statements,
+    /// see [`Keyword::only_starts_statement`]
+    fn identifier(&mut self, token: LexToken) -> LexToken {
+        match token {
+            LexToken::Keyword(kw) if kw.only_starts_statement() => {
+                let name = kw.to_string();
+                let idx = match self.symbols.get_index_of(&name) {
+                    Some(idx) => idx,
+                    None => self.symbols.insert_full(name, Identified::Unknown).0,
+                };
+                LexToken::Identifier(idx)
+            }
+            token => token,
+        }
+    }
+
     fn match_symbol(&mut self, c: char) -> bool {
         match self.lookahead() {
             Some(LexToken::Symbol(found)) if found == c => {
@@ -124,7 +216,15 @@ impl<'s, 'f> Parser<'s, 'f> {
             .expect("should be set by parser")
             .1;
         if *old_kind != Identified::Unknown && *old_kind != kind {
-            Err(ParseError::ConflictingIdentifiers(ident, *old_kind, kind).attach_pos(pos))
+            match (*old_kind, kind) {
+                (Identified::Path(expected), Identified::Path(got))
+                | (Identified::Calc(expected), Identified::Calc(got)) => {
+                    Err(ParseError::ArgCount(got, expected).attach_pos(pos))
+                }
+                _ => {
+                    Err(ParseError::ConflictingIdentifiers(ident, *old_kind, kind).attach_pos(pos))
+                }
+            }
         } else {
             *old_kind = kind;
             Ok(())
@@ -154,12 +254,14 @@ impl<'s, 'f> Parser<'s, 'f> {
             LexToken::Keyword(Keyword::Key) => EventKind::Key,
             _ => return Err(self.unexpected_last_token(TokenExpectation::EventKind)),
         };
-        let args = self.parse_proto_args(false)?;
+        let (args, arg_pos) = self.parse_proto_args(false)?;
+        self.func = FuncType::Event(kind);
         Ok(ParseToken::EventHandler(
             kind,
             PathDef {
                 name: 0,
                 args,
+                arg_pos,
                 body: self.parse_statements(begin, Keyword::EndEvent)?,
             },
         ))
@@ -167,49 +269,56 @@ impl<'s, 'f> Parser<'s, 'f> {
 
     fn parse_path(&mut self, begin: FilePos) -> PRes<ParseToken> {
         let name = self.match_identifier()?;
-        let args = self.parse_proto_args(true)?;
+        let (args, arg_pos) = self.parse_proto_args(true)?;
         self.set_ident_type(name, Identified::Path(args.len()))?;
+        self.func = FuncType::Path(name);
         Ok(ParseToken::PathDef(PathDef {
             name,
             args,
+            arg_pos,
             body: self.parse_statements(begin, Keyword::Endpath)?,
         }))
     }
 
     fn parse_calc(&mut self, begin: FilePos) -> PRes<ParseToken> {
         let name = self.match_identifier()?;
-        let args = self.parse_proto_args(false)?;
+        let (args, arg_pos) = self.parse_proto_args(false)?;
         let ret_ty = self.parse_type_hint()?;
         self.set_ident_type(name, Identified::Calc(args.len()))?;
+        self.func = FuncType::Calc(name);
         let stmts = self.parse_statements(begin, Keyword::Returns)?;
         let ret = self.parse_expr()?;
         self.expect_keyword(Keyword::Endcalc)?;
         Ok(ParseToken::CalcDef(CalcDef {
             name,
             args,
+            arg_pos,
             ret_ty,
             body: stmts,
             ret,
         }))
     }
 
-    fn parse_proto_args(&mut self, optional: bool) -> PRes<Vec<(usize, ValType)>> {
+    /// The arguments and the position of their names
+    fn parse_proto_args(&mut self, optional: bool) -> PRes<(ArgDefList, Vec<FilePos>)> {
+        let mut res = Vec::new();
+        let mut pos = Vec::new();
         if optional && !self.match_symbol('(') {
-            return Ok(Vec::new());
+            return Ok((res, pos));
         } else if !optional {
             self.expect_symbol('(')?;
         }
-        let mut res = Vec::new();
         while !self.match_symbol(')') {
             if !res.is_empty() {
                 self.expect_symbol(',')?;
             }
+            pos.push(self.curr_pos());
             let arg = self.match_identifier()?;
             self.set_ident_type(arg, Identified::LocalVar)?;
             let ty = self.parse_type_hint()?;
             res.push((arg, ty));
         }
-        Ok(res)
+        Ok((res, pos))
     }
 
     fn parse_type_hint(&mut self) -> PRes<ValType> {
@@ -239,7 +348,8 @@ impl<'s, 'f> Parser<'s, 'f> {
 
     fn parse_variable(&mut self) -> PRes<Variable> {
         let pos = self.curr_pos();
-        match self.next_token_err()? {
+        let token = self.next_token_err()?;
+        match self.identifier(token) {
             LexToken::GlobalVar(id) => {
                 self.set_ident_type(id, Identified::GlobalVar)?;
                 Ok(Variable {
@@ -354,8 +464,22 @@ impl<'s, 'f> Parser<'s, 'f> {
     }
 
     fn parse_operand(&mut self) -> PRes<Expr> {
+        let mut expr = self.parse_primary()?;
+        // `list[1]`, while `list [1]` are two list elements or print arguments
+        while self.adjacent() && self.match_symbol('[') {
+            self.expect_feature(Feature::Types)?;
+            let idx = self.parse_expr()?;
+            self.expect_symbol(']')?;
+            let start = expr.start;
+            expr = ExprKind::Index(Box::new(expr), Box::new(idx)).at(start, self.last_pos());
+        }
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> PRes<Expr> {
         let start = self.curr_pos();
-        Ok(match self.next_token_err()? {
+        let token = self.next_token_err()?;
+        Ok(match self.identifier(token) {
             LexToken::Symbol('(') => {
                 let expr = self.parse_expr()?;
                 self.expect_symbol(')')?;
@@ -366,6 +490,14 @@ impl<'s, 'f> Parser<'s, 'f> {
                 self.expect_symbol('|')?;
                 ExprKind::Absolute(Box::new(expr)).at(start, self.last_pos())
             }
+            LexToken::Symbol('[') => {
+                self.expect_feature(Feature::Types)?;
+                let mut items = Vec::new();
+                while !self.match_symbol(']') {
+                    items.push(self.parse_expr()?);
+                }
+                ExprKind::List(items).at(start, self.last_pos())
+            }
             LexToken::Symbol('-') => {
                 ExprKind::UnOperation(UnOperator::Negate, Box::new(self.parse_operand()?))
                     .at(start, self.last_pos())
@@ -446,7 +578,12 @@ impl<'s, 'f> Parser<'s, 'f> {
             LexToken::Symbol('*') => BiOperator::Mul,
             LexToken::Symbol('/') => BiOperator::Div,
             LexToken::Symbol('^') => BiOperator::Exp,
-            LexToken::Symbol('=') => BiOperator::Equal,
+            LexToken::Symbol('=') => {
+                // `==` is accepted as well
+                self.match_symbol('=');
+                BiOperator::Equal
+            }
+            LexToken::Symbol('!') if self.match_symbol('=') => BiOperator::UnEqual,
             LexToken::Symbol('<') => {
                 if self.match_symbol('=') {
                     BiOperator::LessEqual
@@ -480,6 +617,16 @@ impl<'s, 'f> Parser<'s, 'f> {
         self.ltokens[self.pos.clamp(0, self.ltokens.len() - 1)].get_pos()
     }
 
+    /// Whether the next token starts right where the last one ended
+    fn adjacent(&self) -> bool {
+        if self.pos == 0 || self.eof().is_none() {
+            return false;
+        }
+        let (_, end) = self.ltokens[self.pos - 1].span();
+        let next = self.ltokens[self.pos].get_pos();
+        next.line == end.line && next.column == end.column + 1
+    }
+
     fn last_pos(&self) -> FilePos {
         self.ltokens[(self.pos - 1).clamp(0, self.ltokens.len() - 1)].get_pos()
     }
@@ -522,6 +669,8 @@ pub enum ParseError {
     ArgCount(usize, usize),
     #[error("missing feature {0}")]
     MissingFeature(Feature),
+    #[error("return outside of a path or calculation")]
+    ReturnOutsideFunction,
 }
 
 #[derive(Debug, PartialEq)]
@@ -535,6 +684,8 @@ pub enum TokenExpectation {
     PredefFunc,
     ValType,
     EventKind,
+    LineStyle,
+    FileName,
 }
 
 impl Display for TokenExpectation {
@@ -548,7 +699,9 @@ impl Display for TokenExpectation {
             TokenExpectation::Expr => write!(f, "expression"),
             TokenExpectation::PredefFunc => write!(f, "predefined function or type"),
             TokenExpectation::ValType => write!(f, "type"),
+            TokenExpectation::FileName => write!(f, "file name in quotes"),
             TokenExpectation::EventKind => write!(f, "event kind"),
+            TokenExpectation::LineStyle => write!(f, "`solid`, `dashed` or `dotted`"),
         }
     }
 }
@@ -1,28 +1,157 @@
 use super::{LexToken, PRes, ParseError, Parser, TokenExpectation};
-use crate::{features::Feature, tokens::*, FilePos, Identified, Pos, Positionable};
+use crate::{
+    debugger::{window::LineStyle, FuncType},
+    features::Feature,
+    tokens::*,
+    FilePos, Identified, Pos, Positionable,
+};
 
 impl Parser<'_, '_> {
     pub(super) fn parse_statements(&mut self, begin: FilePos, end_key: Keyword) -> PRes<Block> {
+        self.parse_block(begin, |parser| parser.match_keyword(end_key))
+    }
+
+    /// Parse statements until `at_end` consumes the end of the block
+    fn parse_block(
+        &mut self,
+        begin: FilePos,
+        mut at_end: impl FnMut(&mut Self) -> bool,
+    ) -> PRes<Block> {
         let mut statements = Vec::new();
-        while !self.match_keyword(end_key) {
-            statements.push(self.parse_stm()?);
+        while !at_end(self) {
+            let start = self.pos;
+            match self.parse_stm() {
+                Ok(stmt) => statements.push(stmt),
+                Err(why) if self.errors.is_some() => {
+                    self.report(why);
+                    if self.eof().is_none() {
+                        break;
+                    }
+                    self.sync_stm(start);
+                }
+                Err(why) => return Err(why),
+            }
         }
         Ok(Block { begin, statements })
     }
 
+    /// Skip the broken statement starting at token `start`, including the
+    /// body of a loop or branch it opens.
+    fn sync_stm(&mut self, start: usize) {
+        self.pos = start;
+        let mut depth = 0usize;
+        while let Some(tok) = self.next_token() {
+            if let LexToken::Symbol(c @ ('[' | ']')) = tok {
+                // list literals and indices are balanced, like bracketed foreach bodies
+                if c == '[' {
+                    depth += 1;
+                } else {
+                    depth = depth.saturating_sub(1);
+                }
+            }
+            if tok == LexToken::Keyword(Keyword::Foreach) && self.foreach_in_brackets() {
+                continue;
+            }
+            if let LexToken::Keyword(kw) = tok {
+                match kw {
+                    // `do` also appears in while and counter headers, so count `times`
+                    Keyword::If
+                    | Keyword::Times
+                    | Keyword::While
+                    | Keyword::Counter
+                    | Keyword::Foreach
+                    | Keyword::Repeat
+                    | Keyword::Frame => depth += 1,
+                    Keyword::Endif | Keyword::Done | Keyword::Until | Keyword::Endframe => {
+                        depth = depth.saturating_sub(1)
+                    }
+                    _ => {}
+                }
+            }
+            match self.lookahead() {
+                Some(LexToken::Keyword(kw)) if depth == 0 && is_sync_point(kw) => break,
+                Some(LexToken::Symbol(']')) if depth == 0 => break,
+                _ => {}
+            }
+        }
+    }
+
+    /// Whether the foreach whose keyword was just consumed has its body in
+    /// brackets instead of `do ... done`
+    fn foreach_in_brackets(&mut self) -> bool {
+        let start = self.pos;
+        // the header itself might be what is broken
+        let _ = self.match_identifier();
+        self.match_keyword(Keyword::In);
+        let _ = self.parse_expr();
+        let brackets = self.lookahead() == Some(LexToken::Symbol('['));
+        self.pos = start;
+        brackets
+    }
+
     pub fn parse_stm(&mut self) -> PRes<Pos<Statement>> {
+        let fp = self.curr_pos();
+        if self.at_assignment() {
+            let var = self.parse_variable()?;
+            self.expect_symbol('=')?;
+            let expr = self.parse_expr()?;
+            Self::check_writeable(&var)?;
+            return Ok(Statement::Store(expr, var).attach_pos(fp));
+        }
         let Some(LexToken::Keyword(kw)) = self.lookahead() else {
             return Err(self.unexpected_token(TokenExpectation::Statement));
         };
-        let fp = self.curr_pos();
         self.pos += 1;
         Ok(match kw {
             Keyword::Walk => self.parse_move(true),
             Keyword::Jump => self.parse_move(false),
+            Keyword::Home => Ok(Statement::MoveHome(true)),
             Keyword::Turn => self.parse_turn(),
             Keyword::Direction => Ok(Statement::Direction(self.parse_expr()?)),
+            Keyword::Arc => self.parse_arc(false),
+            Keyword::Circle => self.parse_arc(true),
             Keyword::Color => self.parse_color(),
+            Keyword::Penwidth => Ok(Statement::PenWidth(self.parse_expr()?)),
+            Keyword::Setspeed => Ok(Statement::SetSpeed(self.parse_expr()?)),
+            Keyword::Penstyle => self.parse_pen_style(),
+            Keyword::Push => {
+                self.expect_feature(Feature::Transforms)?;
+                Ok(Statement::PushTransform)
+            }
+            Keyword::Pop => {
+                self.expect_feature(Feature::Transforms)?;
+                Ok(Statement::PopTransform)
+            }
+            Keyword::Scale => {
+                self.expect_feature(Feature::Transforms)?;
+                let x = self.parse_expr()?;
+                let y = if self.match_symbol(',') {
+                    Some(self.parse_expr()?)
+                } else {
+                    None
+                };
+                Ok(Statement::Scale(x, y))
+            }
+            Keyword::Translate => {
+                self.expect_feature(Feature::Transforms)?;
+                let x = self.parse_expr()?;
+                self.expect_symbol(',')?;
+                Ok(Statement::Translate(x, self.parse_expr()?))
+            }
+            Keyword::Rotate => {
+                self.expect_feature(Feature::Transforms)?;
+                Ok(Statement::Rotate(self.parse_expr()?))
+            }
+            Keyword::Penup => Ok(Statement::Pen(false)),
+            Keyword::Pendown => Ok(Statement::Pen(true)),
+            Keyword::Showturtle => Ok(Statement::ShowTurtle(true)),
+            Keyword::Hideturtle => Ok(Statement::ShowTurtle(false)),
+            Keyword::Savestate => Ok(Statement::SaveState),
+            Keyword::Restorestate => Ok(Statement::RestoreState),
+            Keyword::Newturtle => Ok(Statement::NewTurtle),
+            Keyword::Setturtle => Ok(Statement::SetTurtle(self.parse_expr()?)),
             Keyword::Clear => Ok(Statement::Clear),
+            Keyword::Clearscreen => Ok(Statement::ClearScreen),
             Keyword::Stop => Ok(Statement::Stop),
             Keyword::Finish => Ok(Statement::Finish),
             Keyword::Path => self.parse_path_call(false),
@@ -30,13 +159,20 @@ impl Parser<'_, '_> {
                 let expr = self.parse_expr()?;
                 self.expect_keyword(Keyword::In)?;
                 let var = self.parse_variable()?;
-                if let VariableKind::GlobalPreDef(pdv) = var.kind {
-                    if !pdv.is_writeable() {
-                        return Err(ParseError::WriteToReadOnly(pdv).attach_pos(var.pos));
-                    }
-                }
+                Self::check_writeable(&var)?;
                 Ok(Statement::Store(expr, var))
             }
+            Keyword::Let => {
+                let pos = self.curr_pos();
+                let id = self.match_identifier()?;
+                self.set_ident_type(id, Identified::LocalVar)?;
+                self.expect_symbol('=')?;
+                let var = Variable {
+                    pos,
+                    kind: VariableKind::Local(id, ValType::Any),
+                };
+                Ok(Statement::Let(self.parse_expr()?, var))
+            }
             Keyword::Add => self.parse_calc_stm(Keyword::To, BiOperator::Add, false),
             Keyword::Sub => self.parse_calc_stm(Keyword::From, BiOperator::Sub, false),
             Keyword::Mul => self.parse_calc_stm(Keyword::By, BiOperator::Mul, true),
@@ -48,7 +184,11 @@ impl Parser<'_, '_> {
             Keyword::Mark => Ok(Statement::Mark),
             Keyword::Print => {
                 self.expect_feature(Feature::Types)?;
-                Ok(Statement::Print(self.parse_expr()?))
+                Ok(Statement::Print(self.parse_print_args()?))
+            }
+            Keyword::Setprecision => {
+                self.expect_feature(Feature::Types)?;
+                Ok(Statement::SetPrecision(self.parse_expr()?))
             }
             Keyword::Split => {
                 self.expect_feature(Feature::Multithreading)?;
@@ -68,6 +208,26 @@ impl Parser<'_, '_> {
                 ))
             }
             Keyword::Counter => self.parse_counter(fp),
+            Keyword::Foreach => {
+                self.expect_feature(Feature::Types)?;
+                let pos = self.curr_pos();
+                let id = self.match_identifier()?;
+                self.set_ident_type(id, Identified::LocalVar)?;
+                let item = Variable {
+                    pos,
+                    kind: VariableKind::Local(id, ValType::Any),
+                };
+                self.expect_keyword(Keyword::In)?;
+                let list = self.parse_expr()?;
+                // `foreach x in list [ ... ]` is short for `foreach x in list do ... done`
+                let body = if self.match_symbol('[') {
+                    self.parse_block(fp, |parser| parser.match_symbol(']'))?
+                } else {
+                    self.expect_keyword(Keyword::Do)?;
+                    self.parse_statements(fp, Keyword::Done)?
+                };
+                Ok(Statement::ForEach { item, list, body })
+            }
             Keyword::While => {
                 let cond = self.parse_expr()?;
                 self.expect_keyword(Keyword::Do)?;
@@ -80,16 +240,95 @@ impl Parser<'_, '_> {
                 let stmts = self.parse_statements(fp, Keyword::Until)?;
                 Ok(Statement::RepeatLoop(self.parse_expr()?, stmts))
             }
+            Keyword::Return => match self.func {
+                FuncType::Calc(_) => Ok(Statement::Return(Some(self.parse_expr()?))),
+                FuncType::Path(_) | FuncType::Event(_) => Ok(Statement::Return(None)),
+                FuncType::Main => Err(ParseError::ReturnOutsideFunction.attach_pos(fp)),
+            },
+            Keyword::Frame => Ok(Statement::Frame(
+                self.parse_statements(fp, Keyword::Endframe)?,
+            )),
             c => Err(ParseError::UnknownStatement(c).attach_pos(fp)),
         }?
         .attach_pos(fp))
     }
 
+    /// `<variable> = <expr>` is the only statement not starting with a keyword
+    fn at_assignment(&self) -> bool {
+        let is_var = match self.lookahead() {
+            Some(LexToken::Identifier(_) | LexToken::GlobalVar(_) | LexToken::PredefVar(_)) => true,
+            Some(LexToken::Keyword(kw)) => kw.only_starts_statement(),
+            _ => false,
+        };
+        is_var
+            && self
+                .ltokens
+                .get(self.pos + 1)
+                .is_some_and(|t| **t == LexToken::Symbol('='))
+    }
+
+    /// `print a b ..` joins the string forms of all expressions
+    fn parse_print_args(&mut self) -> PRes<Expr> {
+        let as_string = |expr: Expr| match expr.kind {
+            ExprKind::Const(Value::String(_)) => expr,
+            _ => {
+                let (start, end) = (expr.start, expr.end);
+                ExprKind::Convert(Box::new(expr), ValType::String).at(start, end)
+            }
+        };
+        let mut msg = self.parse_expr()?;
+        if !self.starts_operand() {
+            return Ok(msg);
+        }
+        msg = as_string(msg);
+        while self.starts_operand() {
+            let part = as_string(self.parse_expr()?);
+            let (start, end) = (msg.start, part.end);
+            msg = ExprKind::BiOperation(Box::new(msg), BiOperator::Add, Box::new(part))
+                .at(start, end);
+        }
+        Ok(msg)
+    }
+
+    /// Next token can begin another expression, without being a new statement
+    fn starts_operand(&self) -> bool {
+        match self.lookahead() {
+            Some(
+                LexToken::Symbol('(' | '|' | '[')
+                | LexToken::IntLiteral(_)
+                | LexToken::FloatLiteral(_)
+                | LexToken::StringLiteral(_)
+                | LexToken::Keyword(Keyword::Not | Keyword::True | Keyword::False),
+            ) => true,
+            Some(LexToken::Identifier(_) | LexToken::GlobalVar(_) | LexToken::PredefVar(_)) => {
+                !self.at_assignment()
+            }
+            Some(LexToken::Keyword(kw)) => {
+                PredefFunc::parse(kw).is_some() || ValType::parse(kw).is_some()
+            }
+            _ => false,
+        }
+    }
+
+    fn check_writeable(var: &Variable) -> PRes<()> {
+        match var.kind {
+            VariableKind::GlobalPreDef(pdv) if !pdv.is_writeable() => {
+                Err(ParseError::WriteToReadOnly(pdv).attach_pos(var.pos))
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub(super) fn parse_move(&mut self, draw: bool) -> PRes<Statement> {
         if self.match_keyword(Keyword::Home) {
             Ok(Statement::MoveHome(draw))
         } else if self.match_keyword(Keyword::Mark) {
             Ok(Statement::MoveMark(draw))
+        } else if self.match_keyword(Keyword::To) {
+            let x = self.parse_expr()?;
+            self.expect_symbol(',')?;
+            let y = self.parse_expr()?;
+            Ok(Statement::MoveTo { x, y, draw })
         } else {
             let back = self.match_keyword(Keyword::Back);
             let dist = self.parse_expr()?;
@@ -112,7 +351,47 @@ impl Parser<'_, '_> {
         }
     }
 
+    pub(super) fn parse_pen_style(&mut self) -> PRes<Statement> {
+        let style = match self.next_token_err()? {
+            LexToken::Keyword(Keyword::Solid) => LineStyle::Solid,
+            LexToken::Keyword(Keyword::Dashed) => LineStyle::Dashed,
+            LexToken::Keyword(Keyword::Dotted) => LineStyle::Dotted,
+            _ => return Err(self.unexpected_last_token(TokenExpectation::LineStyle)),
+        };
+        Ok(Statement::PenStyle(style))
+    }
+
+    pub(super) fn parse_arc(&mut self, full: bool) -> PRes<Statement> {
+        self.expect_feature(Feature::Shapes)?;
+        let left = self.match_keyword(Keyword::Left);
+        if !left {
+            let _ = self.match_keyword(Keyword::Right);
+        }
+        let radius = self.parse_expr()?;
+        let angle = if full {
+            None
+        } else {
+            self.expect_symbol(',')?;
+            Some(self.parse_expr()?)
+        };
+        Ok(Statement::Arc {
+            left,
+            radius,
+            angle,
+        })
+    }
+
     pub(super) fn parse_color(&mut self) -> PRes<Statement> {
+        if let Some(LexToken::ColorLiteral(col)) = self.lookahead() {
+            let pos = self.curr_pos();
+            self.pos += 1;
+            let channel = |c| ExprKind::Const(Value::Number(c)).at(pos, pos);
+            return Ok(Statement::Color(
+                channel(col.r),
+                channel(col.g),
+                channel(col.b),
+            ));
+        }
         let expr_red = self.parse_expr()?;
         self.expect_symbol(',')?;
         let expr_green = self.parse_expr()?;
@@ -207,3 +486,70 @@ impl Parser<'_, '_> {
         })
     }
 }
+
+/// Tokens a broken statement is skipped to: the start of the next statement
+/// or the end of the surrounding block
+fn is_sync_point(kw: Keyword) -> bool {
+    use Keyword::*;
+    matches!(
+        kw,
+        Walk | Jump
+            | Home
+            | Turn
+            | Direction
+            | Arc
+            | Circle
+            | Color
+            | Penwidth
+            | Setspeed
+            | Penstyle
+            | Push
+            | Pop
+            | Scale
+            | Translate
+            | Rotate
+            | Penup
+            | Pendown
+            | Showturtle
+            | Hideturtle
+            | Savestate
+            | Restorestate
+            | Newturtle
+            | Setturtle
+            | Clear
+            | Clearscreen
+            | Stop
+            | Finish
+            | Return
+            | Path
+            | Store
+            | Let
+            | Add
+            | Sub
+            | Mul
+            | Div
+            | Append
+            | Mark
+            | Print
+            | Setprecision
+            | Split
+            | Wait
+            | If
+            | Do
+            | Counter
+            | Foreach
+            | While
+            | Repeat
+            | Frame
+            | End
+            | Endpath
+            | Endcalc
+            | Returns
+            | EndEvent
+            | Endif
+            | Else
+            | Done
+            | Until
+            | Endframe
+    )
+}
@@ -13,7 +13,7 @@ fn unfinished_expression() {
     let res = parser.parse_expr().unwrap_err();
     assert_eq!(
         res,
-        ParseError::UnexpectedEnd.attach_pos(FilePos::new(2, 1))
+        ParseError::UnexpectedEnd.attach_pos(FilePos::line_col(2, 1))
     );
 }
 
@@ -202,3 +202,41 @@ fn functions() {
         )
     );
 }
+
+#[test]
+fn comparison_aliases() {
+    parse_this!(
+        parser =>
+        IntLiteral(1),
+        Symbol('!'),
+        Symbol('='),
+        IntLiteral(2),
+    );
+    assert_eq!(
+        parser.parse_expr().unwrap(),
+        expr!(
+            1 4 BiOperation(
+                1 1 Const(Number(1.0)),
+                UnEqual,
+                4 4 Const(Number(2.0))
+            )
+        )
+    );
+    parse_this!(
+        parser =>
+        IntLiteral(1),
+        Symbol('='),
+        Symbol('='),
+        IntLiteral(2),
+    );
+    assert_eq!(
+        parser.parse_expr().unwrap(),
+        expr!(
+            1 4 BiOperation(
+                1 1 Const(Number(1.0)),
+                Equal,
+                4 4 Const(Number(2.0))
+            )
+        )
+    );
+}
@@ -23,7 +23,7 @@ macro_rules! parse_this {
             let mut cnt = 0;
             $(
                 cnt += 1;
-                ltokens.push($lex.attach_pos(crate::FilePos::new(cnt, 1)));
+                ltokens.push($lex.attach_pos(crate::FilePos::line_col(cnt, 1)));
             )+
         }
         let mut feat = crate::features::FeatureConf::default();
@@ -43,7 +43,7 @@ macro_rules! parse_this {
 macro_rules! block {
     ($start:literal { $($spos:literal $skind:ident $sargs:tt)* }) => {
         crate::tokens::Block {
-            begin: crate::FilePos::new($start, 1),
+            begin: crate::FilePos::line_col($start, 1),
             statements: vec![
                 $(stmt!($spos $skind $sargs)),*
             ],
@@ -53,7 +53,7 @@ macro_rules! block {
 
 macro_rules! stmt {
     ($pos:literal $kind:ident $args:tt) => {
-        crate::Pos::new(stmt!(@args $kind $kind $args), crate::FilePos::new($pos, 1))
+        crate::Pos::new(stmt!(@args $kind $kind $args), crate::FilePos::line_col($pos, 1))
     };
     (@args MoveDist $kind:ident (
         $s:literal $e:literal $k:ident $a:tt ,
@@ -66,6 +66,17 @@ macro_rules! stmt {
             back: $back,
         }
     };
+    (@args MoveTo $kind:ident (
+        $xs:literal $xe:literal $xk:ident $xa:tt ,
+        $ys:literal $ye:literal $yk:ident $ya:tt ,
+        $draw:expr $(,)?
+    )) => {
+        crate::tokens::Statement::$kind {
+            x: expr!($xs $xe $xk $xa),
+            y: expr!($ys $ye $yk $ya),
+            draw: $draw,
+        }
+    };
     (@args MoveHome $kind:ident ($draw:expr)) => {
         crate::tokens::Statement::$kind($draw)
     };
@@ -106,6 +117,15 @@ macro_rules! stmt {
             var!($vp $vk $va)
         )
     };
+    (@args Let $kind:ident (
+        $s:literal $e:literal $k:ident $a:tt,
+        $vp:literal $vk:ident ($va:expr) $(,)?
+    )) => {
+        crate::tokens::Statement::$kind(
+            expr!($s $e $k $a),
+            var!($vp $vk $va)
+        )
+    };
     (@args Calc $kind:ident (
         $vp:literal $vk:ident ($va:expr),
         $s:literal $e:literal $k:ident $a:tt,
@@ -194,7 +214,7 @@ macro_rules! stmt {
 
 macro_rules! expr {
     ($start:literal $end:literal $kind:ident $args:tt) => {
-        expr!(@args $kind $kind $args).at(crate::FilePos::new($start, 1), crate::FilePos::new($end, 1))
+        expr!(@args $kind $kind $args).at(crate::FilePos::line_col($start, 1), crate::FilePos::line_col($end, 1))
     };
     (@args Const $kind:ident ($val:expr)) => {
         {
@@ -254,7 +274,7 @@ macro_rules! expr {
 
 macro_rules! var {
     ($pos:literal $kind:ident $arg:expr) => {
-        var!(@args $kind $kind $arg).at(crate::FilePos::new($pos, 1))
+        var!(@args $kind $kind $arg).at(crate::FilePos::line_col($pos, 1))
     };
     (@args GlobalPreDef $kind:ident $arg:expr) => {
         crate::tokens::VariableKind::$kind($arg)
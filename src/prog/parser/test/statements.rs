@@ -1,3 +1,8 @@
+use crate::{
+    pos::{FilePos, Positionable as _},
+    prog::parser::ParseError,
+};
+
 #[test]
 fn if_branch() {
     parse_this!(
@@ -74,3 +79,147 @@ fn if_else_branch() {
         )
     );
 }
+
+#[test]
+fn color_literal() {
+    parse_this!(
+        parser =>
+        Keyword(Color),
+        ColorLiteral(crate::debugger::TColor::new(100.0, 50.0, 0.0)),
+    );
+    assert_eq!(
+        parser.parse_stm().unwrap(),
+        stmt!(
+            1 Color(
+                2 2 Const(Number(100.0)),
+                2 2 Const(Number(50.0)),
+                2 2 Const(Number(0.0)),
+            )
+        )
+    );
+}
+
+#[test]
+fn path_call_arity() {
+    parse_this!(
+        parser (Path(2)) =>
+        Keyword(Path),
+        Identifier(0),
+        Symbol('('),
+        IntLiteral(1),
+        Symbol(')'),
+    );
+    assert_eq!(
+        parser.parse_stm().unwrap_err(),
+        ParseError::ArgCount(1, 2).attach_pos(FilePos::line_col(5, 1))
+    );
+}
+
+#[test]
+fn recover_after_broken_statements() {
+    let code = "begin
+  walk )
+  if 1 < then walk 2 endif
+  jump 3
+  turn * 3
+  walk 4
+end
+";
+    let errs = crate::TProgram::compile_all(code, None, crate::features::FeatureConf::default())
+        .unwrap_err();
+    let found: Vec<_> = errs.iter().map(|e| e.get_pos()).collect();
+    assert_eq!(
+        found,
+        [
+            FilePos::line_col(2, 8),
+            FilePos::line_col(3, 10),
+            FilePos::line_col(5, 8)
+        ]
+    );
+    assert!(errs
+        .iter()
+        .all(|e| matches!(**e, crate::CompileError::Parse(_))));
+}
+
+#[test]
+fn recover_in_bracketed_foreach() {
+    let code = "begin
+  foreach x in [1 2] [
+    walk )
+    walk x
+  ]
+  foreach in [1 2] [ walk 1 ]
+  turn * 3
+end
+";
+    let errs = crate::TProgram::compile_all(code, None, crate::features::FeatureConf::default())
+        .unwrap_err();
+    let found: Vec<_> = errs.iter().map(|e| e.get_pos()).collect();
+    assert_eq!(
+        found,
+        [
+            FilePos::line_col(3, 10),
+            FilePos::line_col(6, 11),
+            FilePos::line_col(7, 8)
+        ]
+    );
+}
+
+#[test]
+fn let_and_assignment() {
+    parse_this!(
+        parser (LocalVar) =>
+        Keyword(Let),
+        Identifier(0),
+        Symbol('='),
+        IntLiteral(1),
+        Identifier(0),
+        Symbol('='),
+        IntLiteral(2),
+    );
+    assert_eq!(
+        parser.parse_stm().unwrap(),
+        stmt!(1 Let(4 4 Const(Number(1.0)), 2 Local(0)))
+    );
+    assert_eq!(
+        parser.parse_stm().unwrap(),
+        stmt!(5 Store(7 7 Const(Number(2.0)), 5 Local(0)))
+    );
+}
+
+#[test]
+fn move_to() {
+    parse_this!(
+        parser =>
+        Keyword(Jump),
+        Keyword(To),
+        IntLiteral(1),
+        Symbol(','),
+        IntLiteral(2),
+    );
+    assert_eq!(
+        parser.parse_stm().unwrap(),
+        stmt!(
+            1 MoveTo(
+                3 3 Const(Number(1.0)),
+                5 5 Const(Number(2.0)),
+                false,
+            )
+        )
+    );
+}
+
+#[test]
+fn return_outside_function() {
+    parse_this!(
+        parser =>
+        Keyword(Walk),
+        IntLiteral(1),
+        Keyword(Return),
+    );
+    parser.parse_stm().unwrap();
+    assert_eq!(
+        parser.parse_stm().unwrap_err(),
+        ParseError::ReturnOutsideFunction.attach_pos(FilePos::line_col(3, 1))
+    );
+}
@@ -0,0 +1,117 @@
+use crate::{pos::FilePos, tokens::Keyword, Identified};
+
+use super::{
+    lexer::{LexToken, Lexer},
+    TProgram,
+};
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum RenameError {
+    #[error("`{0}` is not a valid identifier")]
+    InvalidName(String),
+    #[error("`{0}` is already bound")]
+    Collision(String),
+    #[error("no let, argument, path or calc is defined at {0}")]
+    NoDefinition(FilePos),
+    #[error("cannot rename in the included file at {0}")]
+    Included(FilePos),
+    #[error("the source doesn't match the program at {0}")]
+    SourceMismatch(FilePos),
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && name.parse::<Keyword>().is_err()
+}
+
+impl TProgram {
+    /// The symbol table index of whatever is defined at `def`
+    fn defined_at(&self, def: FilePos) -> Option<usize> {
+        let funcs = self.paths.iter().map(|p| (p.name, p.body.begin));
+        let calcs = self.calcs.iter().map(|c| (c.name, c.body.begin));
+        if let Some((name, _)) = funcs.chain(calcs).find(|(_, begin)| *begin == def) {
+            return Some(name);
+        }
+        let paths = self
+            .paths
+            .iter()
+            .chain(&self.key_event)
+            .chain(&self.mouse_event)
+            .map(|p| (&p.args, &p.arg_pos));
+        let calcs = self.calcs.iter().map(|c| (&c.args, &c.arg_pos));
+        for (args, arg_pos) in paths.chain(calcs) {
+            if let Some(idx) = arg_pos.iter().position(|pos| *pos == def) {
+                return Some(args[idx].0);
+            }
+        }
+        match self.symbol_at(def)? {
+            super::SymbolInfo::Variable {
+                name,
+                global: false,
+                def: Some(bound),
+            } if bound == def => self.symbols.get_index_of(&name),
+            _ => None,
+        }
+    }
+
+    /// Rename the let, argument, path or calc defined at `def` in `source`, which
+    /// the program was compiled from.
+    ///
+    /// Anything but the renamed identifiers is kept as is. Names are shared by all
+    /// functions, so `new_name` may not be used for anything else in the program.
+    pub fn rename(
+        &self,
+        source: &str,
+        def: FilePos,
+        new_name: &str,
+    ) -> Result<String, RenameError> {
+        if !is_identifier(new_name) {
+            return Err(RenameError::InvalidName(new_name.to_string()));
+        }
+        let id = self.defined_at(def).ok_or(RenameError::NoDefinition(def))?;
+        let (old_name, _) = self
+            .symbols
+            .get_index(id)
+            .expect("defined symbols are known");
+        if old_name == new_name {
+            return Ok(source.to_string());
+        }
+        if self
+            .symbols
+            .get(new_name)
+            .is_some_and(|kind| *kind != Identified::Unknown)
+        {
+            return Err(RenameError::Collision(new_name.to_string()));
+        }
+
+        let mut symbols = self.symbols.clone();
+        let mut features = self.features;
+        let tokens = Lexer::new(&mut symbols, &mut features, source.chars())
+            .collect_tokens()
+            .map_err(|_| RenameError::SourceMismatch(def))?;
+        let mut occurrences = self.references(def);
+        occurrences.push(def);
+        let mut offsets = Vec::new();
+        for pos in occurrences {
+            if pos.file.is_some() {
+                return Err(RenameError::Included(pos));
+            }
+            // calls point to their keyword, so take the first matching identifier from there
+            let token = tokens
+                .iter()
+                .skip_while(|tok| tok.get_pos() < pos)
+                .find(|tok| matches!(***tok, LexToken::Identifier(tok_id) if tok_id == id))
+                .ok_or(RenameError::SourceMismatch(pos))?;
+            offsets.push(token.get_pos().offset);
+        }
+        offsets.sort_unstable();
+        offsets.dedup();
+        let mut res = source.to_string();
+        for offset in offsets.into_iter().rev() {
+            res.replace_range(offset..offset + old_name.len(), new_name);
+        }
+        Ok(res)
+    }
+}
@@ -24,8 +24,8 @@ impl Expr {
             ExprKind::BiOperation(lhs, op, rhs) => {
                 let poss_types: Vec<_> = op
                     .types()
-                    .into_iter()
-                    .filter_map(|(inp, out)| (out == ty).then_some(inp))
+                    .iter()
+                    .filter_map(|&(inp, out)| (out == ty).then_some(inp))
                     .collect();
                 match poss_types.len() {
                     0 => Err(e_map(TypeError::BiOpWrongResult(*op, ty))),
@@ -73,6 +73,21 @@ impl Expr {
                 vt.assert(ty).map_err(e_map)?;
                 Ok(expr.val_type(ctx)?.1)
             }
+            ExprKind::List(items) => {
+                ValType::List.assert(ty).map_err(e_map)?;
+                let mut v = Vars::new();
+                for item in items {
+                    v &= item.expect_type(ValType::Number, ctx)?;
+                }
+                Ok(v)
+            }
+            ExprKind::Index(list, idx) => {
+                ValType::Number.assert(ty).map_err(e_map)?;
+                Ok(
+                    list.expect_type(ValType::List, ctx)?
+                        & idx.expect_type(ValType::Number, ctx)?,
+                )
+            }
             ExprKind::FuncCall(pdf, exprs) => {
                 pdf.ret_type().assert(ty).map_err(e_map)?;
                 let fargs = pdf.args();
@@ -176,6 +191,8 @@ impl Expr {
                     super::check_args(exprs, &proto.args, e_map, ctx)?,
                 ))
             }
+            ExprKind::List(_) => Ok((ValType::List, self.expect_type(ValType::List, ctx)?)),
+            ExprKind::Index(_, _) => Ok((ValType::Number, self.expect_type(ValType::Number, ctx)?)),
         }
     }
 
@@ -192,9 +209,14 @@ impl Expr {
             | ExprKind::Absolute(expr)
             | ExprKind::Bracket(expr)
             | ExprKind::Convert(expr, _) => expr.collect_variables(),
-            ExprKind::FuncCall(_, exprs) | ExprKind::CalcCall(_, exprs) => {
+            ExprKind::FuncCall(_, exprs) | ExprKind::CalcCall(_, exprs) | ExprKind::List(exprs) => {
                 exprs.iter().flat_map(|e| e.collect_variables()).collect()
             }
+            ExprKind::Index(list, idx) => {
+                let mut res = list.collect_variables();
+                res.append(&mut idx.collect_variables());
+                res
+            }
         }
     }
 }
@@ -15,6 +15,8 @@ pub(crate) struct CheckContext {
     pub(crate) protos: HashMap<usize, Prototype>,
     pub(crate) globals: HashMap<usize, ValType>,
     pub(crate) locals: HashMap<usize, ValType>,
+    /// result type of the calculation being checked
+    pub(crate) ret: Option<ValType>,
 }
 
 impl CheckContext {
@@ -45,6 +47,7 @@ impl TProgram {
                 .collect(),
             globals: HashMap::new(),
             locals: HashMap::new(),
+            ret: None,
         };
         type CheckFunc<'p> =
             dyn Fn(&'_ mut TProgram, &'_ mut CheckContext) -> Result<bool, TurtleError> + 'p;
@@ -118,6 +121,7 @@ impl TProgram {
             protos: HashMap::new(),
             globals: HashMap::new(),
             locals: HashMap::new(),
+            ret: None,
         };
         for path in &self.paths {
             path.collect_context(&mut res);
@@ -240,10 +244,12 @@ impl CalcDef {
 
     pub(crate) fn semantic_check(&mut self, ctx: &mut CheckContext) -> Result<bool, TurtleError> {
         ctx.locals = self.args.iter().cloned().collect();
+        ctx.ret = Some(self.ret_ty);
         let res = self
             .body
             .semantic_check_loop(ctx, FuncType::Calc(self.name));
         ctx.locals.clear();
+        ctx.ret = None;
         res
     }
 
@@ -338,7 +344,7 @@ pub fn collect_undef(vars: Vec<VariableKind>, local: bool) -> Vec<usize> {
         .collect()
 }
 
-#[derive(Debug, thiserror::Error)]
+#[derive(Debug, PartialEq, Clone, Copy, thiserror::Error)]
 pub enum TypeError {
     #[error("different types for operator {0}: {1} != {2}")]
     BiOpDifferentTypes(BiOperator, ValType, ValType),
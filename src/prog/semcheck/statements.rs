@@ -1,6 +1,6 @@
 use crate::{
     pos::Pos,
-    tokens::{Statement, ValType, VariableKind},
+    tokens::{ExprKind, Statement, ValType, Value, VariableKind},
     TurtleError,
 };
 
@@ -13,7 +13,36 @@ impl Pos<Statement> {
         match &mut **self {
             Statement::MoveDist { dist: expr, .. }
             | Statement::Turn { by: expr, .. }
-            | Statement::Direction(expr) => Ok(expr.expect_type(ValType::Number, ctx)?),
+            | Statement::Direction(expr)
+            | Statement::Rotate(expr)
+            | Statement::PenWidth(expr)
+            | Statement::SetSpeed(expr)
+            | Statement::SetTurtle(expr)
+            | Statement::SetPrecision(expr) => Ok(expr.expect_type(ValType::Number, ctx)?),
+            Statement::Scale(x, y) => {
+                let mut vars = x.expect_type(ValType::Number, ctx)?;
+                if let Some(y) = y {
+                    vars &= y.expect_type(ValType::Number, ctx)?;
+                }
+                Ok(vars)
+            }
+            Statement::Translate(x, y) => {
+                let x = x.expect_type(ValType::Number, ctx)?;
+                let y = y.expect_type(ValType::Number, ctx)?;
+                Ok(x & y)
+            }
+            Statement::Arc { radius, angle, .. } => {
+                let mut vars = radius.expect_type(ValType::Number, ctx)?;
+                if let Some(angle) = angle {
+                    vars &= angle.expect_type(ValType::Number, ctx)?;
+                }
+                Ok(vars)
+            }
+            Statement::MoveTo { x, y, .. } => {
+                let x = x.expect_type(ValType::Number, ctx)?;
+                let y = y.expect_type(ValType::Number, ctx)?;
+                Ok(x & y)
+            }
             Statement::Color(r, g, b) => {
                 let r = r.expect_type(ValType::Number, ctx)?;
                 let g = g.expect_type(ValType::Number, ctx)?;
@@ -21,12 +50,28 @@ impl Pos<Statement> {
                 Ok(r & g & b)
             }
             Statement::MoveHome(_)
+            | Statement::PushTransform
+            | Statement::PopTransform
+            | Statement::SaveState
+            | Statement::RestoreState
+            | Statement::NewTurtle
+            | Statement::PenStyle(_)
+            | Statement::Pen(_)
+            | Statement::ShowTurtle(_)
             | Statement::Clear
+            | Statement::ClearScreen
             | Statement::Stop
             | Statement::Finish
+            | Statement::Return(None)
             | Statement::Mark
             | Statement::Wait
             | Statement::MoveMark(_) => Ok(Vars::new()),
+            Statement::Return(Some(val)) => {
+                let ty = ctx
+                    .ret
+                    .expect("the parser only allows a value in calculations");
+                Ok(val.expect_type(ty, ctx)?)
+            }
             Statement::PathCall(id, exprs) | Statement::Split(id, exprs) => {
                 let args = ctx
                     .protos
@@ -36,7 +81,7 @@ impl Pos<Statement> {
                     .args;
                 super::check_args(exprs, &args, e_map, ctx)
             }
-            Statement::Store(expr, var) => {
+            Statement::Store(expr, var) | Statement::Let(expr, var) => {
                 let var_ty = var.val_type(ctx)?;
                 if var_ty.0 == ValType::Any {
                     let expr = expr.val_type(ctx)?;
@@ -80,7 +125,18 @@ impl Pos<Statement> {
                     }
                 }
             }
-            Statement::Print(expr) => Ok(expr.expect_type(ValType::String, ctx)?),
+            Statement::Print(expr) => match expr.val_type(ctx)? {
+                (ValType::String, vars) => Ok(vars),
+                (ValType::Any, _) => Ok(expr.expect_type(ValType::String, ctx)?),
+                (_, vars) => {
+                    // anything else is printed like `string(..)` would
+                    let (start, end) = (expr.start, expr.end);
+                    let placeholder = ExprKind::Const(Value::Boolean(false)).at(start, end);
+                    let inner = std::mem::replace(expr, placeholder);
+                    *expr = ExprKind::Convert(Box::new(inner), ValType::String).at(start, end);
+                    Ok(vars)
+                }
+            },
             Statement::IfBranch(expr, block)
             | Statement::WhileLoop(expr, block)
             | Statement::RepeatLoop(expr, block) => {
@@ -88,6 +144,7 @@ impl Pos<Statement> {
                 let block = block.semantic_check(ctx)?;
                 Ok(cond & block)
             }
+            Statement::Frame(block) => block.semantic_check(ctx),
             Statement::DoLoop(expr, block) => {
                 let count = expr.expect_type(ValType::Number, ctx)?;
                 let block = block.semantic_check(ctx)?;
@@ -117,6 +174,12 @@ impl Pos<Statement> {
                 let b = body.semantic_check(ctx)?;
                 Ok(f & t & s & b)
             }
+            Statement::ForEach { item, list, body } => {
+                item.expect_type(ValType::Number, ctx)?;
+                let l = list.expect_type(ValType::List, ctx)?;
+                let b = body.semantic_check(ctx)?;
+                Ok(l & b)
+            }
         }
     }
 
@@ -125,14 +188,54 @@ impl Pos<Statement> {
             Statement::MoveDist { dist: expr, .. }
             | Statement::Turn { by: expr, .. }
             | Statement::Direction(expr)
+            | Statement::PenWidth(expr)
+            | Statement::SetSpeed(expr)
+            | Statement::SetTurtle(expr)
+            | Statement::SetPrecision(expr)
+            | Statement::Rotate(expr)
             | Statement::Print(expr) => expr.collect_variables(),
+            Statement::Scale(x, y) => {
+                let mut res = x.collect_variables();
+                if let Some(y) = y {
+                    res.append(&mut y.collect_variables());
+                }
+                res
+            }
+            Statement::Translate(x, y) => {
+                let mut res = x.collect_variables();
+                res.append(&mut y.collect_variables());
+                res
+            }
             Statement::MoveHome(_)
+            | Statement::PushTransform
+            | Statement::PopTransform
+            | Statement::SaveState
+            | Statement::RestoreState
+            | Statement::NewTurtle
+            | Statement::PenStyle(_)
+            | Statement::Pen(_)
+            | Statement::ShowTurtle(_)
             | Statement::Clear
+            | Statement::ClearScreen
             | Statement::Stop
             | Statement::Finish
+            | Statement::Return(None)
             | Statement::Mark
             | Statement::MoveMark(_)
             | Statement::Wait => Vec::new(),
+            Statement::Return(Some(val)) => val.collect_variables(),
+            Statement::Arc { radius, angle, .. } => {
+                let mut res = radius.collect_variables();
+                if let Some(angle) = angle {
+                    res.append(&mut angle.collect_variables());
+                }
+                res
+            }
+            Statement::MoveTo { x, y, .. } => {
+                let mut res = x.collect_variables();
+                res.append(&mut y.collect_variables());
+                res
+            }
             Statement::Color(r, g, b) => {
                 let mut res = r.collect_variables();
                 res.append(&mut g.collect_variables());
@@ -142,7 +245,9 @@ impl Pos<Statement> {
             Statement::PathCall(_, exprs) | Statement::Split(_, exprs) => {
                 exprs.iter().flat_map(|e| e.collect_variables()).collect()
             }
-            Statement::Store(val, var) | Statement::Calc { var, val, .. } => {
+            Statement::Store(val, var)
+            | Statement::Let(val, var)
+            | Statement::Calc { var, val, .. } => {
                 let mut res = val.collect_variables();
                 res.push(var.kind);
                 res
@@ -155,6 +260,7 @@ impl Pos<Statement> {
                 res.append(&mut expr.collect_variables());
                 res
             }
+            Statement::Frame(block) => block.collect_variables(),
             Statement::IfElseBranch(expr, if_block, else_block) => {
                 let mut res = if_block.collect_variables();
                 res.append(&mut else_block.collect_variables());
@@ -178,6 +284,12 @@ impl Pos<Statement> {
                 };
                 res
             }
+            Statement::ForEach { item, list, body } => {
+                let mut res = body.collect_variables();
+                res.push(item.kind);
+                res.append(&mut list.collect_variables());
+                res
+            }
         }
     }
 }
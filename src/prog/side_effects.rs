@@ -15,10 +15,28 @@ impl Statement {
         match self {
             Statement::MoveDist { .. }
             | Statement::MoveHome(_)
+            | Statement::MoveTo { .. }
+            | Statement::Arc { .. }
             | Statement::Turn { .. }
             | Statement::Direction(_)
             | Statement::Color(_, _, _)
+            | Statement::PenWidth(_)
+            | Statement::SetSpeed(_)
+            | Statement::SetPrecision(_)
+            | Statement::PushTransform
+            | Statement::PopTransform
+            | Statement::SaveState
+            | Statement::RestoreState
+            | Statement::NewTurtle
+            | Statement::SetTurtle(_)
+            | Statement::Scale(_, _)
+            | Statement::Translate(_, _)
+            | Statement::Rotate(_)
+            | Statement::PenStyle(_)
+            | Statement::Pen(_)
+            | Statement::ShowTurtle(_)
             | Statement::Clear
+            | Statement::ClearScreen
             | Statement::Stop
             | Statement::Mark
             | Statement::MoveMark(_)
@@ -26,10 +44,12 @@ impl Statement {
             | Statement::Split(_, _)
             | Statement::Wait
             | Statement::Finish
-            | Statement::PathCall(_, _) => true,
-            Statement::Store(val, var) | Statement::Calc { var, val, .. } => {
-                var.side_effects() || val.side_effects(prog, rec)
-            }
+            | Statement::Return(_)
+            | Statement::PathCall(_, _)
+            | Statement::Frame(_) => true,
+            Statement::Store(val, var)
+            | Statement::Let(val, var)
+            | Statement::Calc { var, val, .. } => var.side_effects() || val.side_effects(prog, rec),
             Statement::IfBranch(expr, block)
             | Statement::DoLoop(expr, block)
             | Statement::WhileLoop(expr, block)
@@ -55,6 +75,9 @@ impl Statement {
                     || step.as_ref().is_some_and(|e| e.side_effects(prog, rec))
                     || body.side_effects(prog, rec)
             }
+            Statement::ForEach { item, list, body } => {
+                item.side_effects() || list.side_effects(prog, rec) || body.side_effects(prog, rec)
+            }
         }
     }
 }
@@ -70,7 +93,12 @@ impl Expr {
             | ExprKind::Absolute(expr)
             | ExprKind::Bracket(expr)
             | ExprKind::Convert(expr, _) => expr.side_effects(prog, rec),
-            ExprKind::FuncCall(_, exprs) => exprs.iter().any(|e| e.side_effects(prog, rec)),
+            ExprKind::FuncCall(_, exprs) | ExprKind::List(exprs) => {
+                exprs.iter().any(|e| e.side_effects(prog, rec))
+            }
+            ExprKind::Index(list, idx) => {
+                list.side_effects(prog, rec) || idx.side_effects(prog, rec)
+            }
             ExprKind::CalcCall(name, exprs) => {
                 exprs.iter().any(|e| e.side_effects(prog, rec))
                     || prog.get_calc(*name).is_ok_and(|calc| {
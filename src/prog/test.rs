@@ -0,0 +1,627 @@
+use std::path::PathBuf;
+
+use crate::{
+    debugger::{
+        config::RunConfig,
+        window::{ChannelWindow, WindowCmd},
+        RuntimeError,
+    },
+    diagnostic::{CollectingReporter, Severity},
+    eval_expr,
+    features::FeatureConf,
+    tokens::{ExprKind, Statement, Value},
+    Environment, EvalError, FilePos, Lint, Pos, RenameError, SymbolInfo, TProgram, TurtleError,
+};
+
+use super::{parser::ParseError, TypeError};
+
+/// Fresh directory in the system temp dir containing `files`
+fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("turtle_{test}_{}", std::process::id()));
+    _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("lib")).unwrap();
+    for (name, code) in files {
+        std::fs::write(dir.join(name), code).unwrap();
+    }
+    dir
+}
+
+fn parse_main(dir: &std::path::Path) -> Result<TProgram, TurtleError> {
+    TProgram::from_file(
+        dir.join("main.tg").to_str().unwrap(),
+        false,
+        FeatureConf::default(),
+    )
+}
+
+#[test]
+fn include_definitions() {
+    let dir = write_files(
+        "include",
+        &[
+            ("main.tg", "include 'lib/shapes.tg'\nbegin\n  path square(2)\nend\n"),
+            (
+                "lib/shapes.tg",
+                "include 'line.tg'\npath square(size)\n  do 4 times path side(size) done\nendpath\n",
+            ),
+            ("lib/line.tg", "path side(len)\n  walk len\n  turn right 90\nendpath\n"),
+        ],
+    );
+    let prog = parse_main(&dir).unwrap();
+    assert_eq!(prog.paths.len(), 2);
+}
+
+#[test]
+fn include_missing_file() {
+    let dir = write_files(
+        "include_missing",
+        &[("main.tg", "begin\nend\ninclude 'nope.tg'\n")],
+    );
+    match parse_main(&dir) {
+        Err(TurtleError::IncludeFailed(file, pos, _)) => {
+            assert_eq!(file, "nope.tg");
+            assert_eq!(pos, crate::FilePos::line_col(3, 1));
+        }
+        res => panic!("expected missing include, got {res:?}"),
+    }
+}
+
+#[test]
+fn include_cycle() {
+    let dir = write_files(
+        "include_cycle",
+        &[
+            ("main.tg", "include 'lib/a.tg'\nbegin\nend\n"),
+            ("lib/a.tg", "include '../main.tg'\n"),
+        ],
+    );
+    match parse_main(&dir) {
+        Err(TurtleError::IncludeCycle(file, pos)) => {
+            assert_eq!(file, "../main.tg");
+            assert!(pos.file.unwrap().path().ends_with("a.tg"));
+            assert_eq!((pos.line, pos.column), (1, 1));
+        }
+        res => panic!("expected include cycle, got {res:?}"),
+    }
+}
+
+#[test]
+fn include_error_position() {
+    let dir = write_files(
+        "include_error",
+        &[
+            ("main.tg", "include 'lib/a.tg'\nbegin\nend\n"),
+            ("lib/a.tg", "path p\n  walk )\nendpath\n"),
+        ],
+    );
+    let why = parse_main(&dir).unwrap_err();
+    let pos = why.pos().unwrap();
+    assert_eq!((pos.line, pos.column), (2, 8));
+    assert!(pos.to_string().ends_with("a.tg"));
+    let sources = crate::pos::SourceMap::new("");
+    assert!(why.with_context(&sources).ends_with("  walk )\n       ^"));
+}
+
+const MESSY: &str = "\" +feature types
+{ header
+  block }
+calculation half(x:num):num returns x/2 endcalc
+path  Square(len: num) \" draws
+        do 4 times walk len turn 90 done
+
+  ; after the loop
+endpath
+begin
+  store 0 in @g
+  @g=@g+1 \" bump
+  if @g==1 and not (@g!=2) then print 'g is' @g else home endif
+  color #ff8000
+  repeat add 0x10 to @g until @g>=100
+  path Square(half(-3))
+  \" trailing
+end
+";
+
+#[test]
+fn format_layout() {
+    assert_eq!(
+        crate::format(MESSY).unwrap(),
+        "\" +feature types
+{ header
+  block }
+calculation half(x: num): num
+  returns x / 2
+endcalc
+
+path Square(len: num) \" draws
+  do 4 times
+    walk len
+    turn right 90
+  done
+
+  ; after the loop
+endpath
+
+begin
+  store 0 in @g
+  @g = @g + 1 \" bump
+  if @g = 1 and not (@g <> 2) then
+    print 'g is' @g
+  else
+    walk home
+  endif
+  color #ff8000
+  repeat
+    add 0x10 to @g
+  until @g >= 100
+  path Square(half(-3))
+  \" trailing
+end
+"
+    );
+}
+
+#[test]
+fn format_is_idempotent() {
+    for code in [
+        MESSY,
+        include_str!("../../examples/pyth_frak.tg"),
+        include_str!("../../examples/sin_cos.tg"),
+        include_str!("../../examples/events.tg"),
+        include_str!("../../examples/types.tg"),
+        include_str!("../../examples/multithreading.tg"),
+    ] {
+        let once = crate::format(code).unwrap();
+        assert_eq!(crate::format(&once).unwrap(), once);
+    }
+}
+
+#[test]
+fn later_keywords_as_names() {
+    for code in [
+        include_str!("../../examples/circle.tg"),
+        include_str!("../../examples/spirale.tg"),
+        "path circle(r) walk r endpath begin path circle(1) store 2 in arc walk arc circle 1 end",
+        "begin store 2 in scale walk scale scale 2 push translate 1, 2 pop end",
+        "begin store 2 in frame store 1 in let walk frame + let frame let x = 1 endframe end",
+        "path return(penup) walk penup endpath begin path return(1) penup pendown end",
+        "begin store 1 in towards walk towards + towards(1, 2) end",
+    ] {
+        TProgram::parse(code, false, FeatureConf::default()).unwrap();
+    }
+}
+
+#[test]
+fn format_reports_errors() {
+    let errs = crate::format("begin\n  walk )\n  turn *\nend\n").unwrap_err();
+    let found: Vec<_> = errs.iter().map(|e| e.get_pos()).collect();
+    assert_eq!(
+        found,
+        [
+            crate::FilePos::line_col(2, 8),
+            crate::FilePos::line_col(3, 8)
+        ]
+    );
+}
+
+/// lines drawn by `prog`
+fn drawn(prog: &TProgram) -> Result<Vec<WindowCmd>, Pos<RuntimeError>> {
+    let (window, cmds, _events) = ChannelWindow::construct();
+    RunConfig::new(&[])
+        .window(window)
+        .skip_delay(true)
+        .exec(prog)?;
+    Ok(cmds
+        .try_iter()
+        .filter(|cmd| matches!(cmd, WindowCmd::Draw(..)))
+        .collect())
+}
+
+#[test]
+fn optimized_matches_unoptimized() {
+    let code = "begin
+      walk 10 * 5 / 25
+      if 1 < 2 then
+        turn left 45 + 45
+      else
+        walk 100
+      endif
+      if 'a' = 'b' then walk 100 endif
+      let x = 2
+      if 2 >= 1 then
+        let x = 5
+        walk x
+      endif
+      walk x
+      do 0 times walk 100 done
+      do 2 times walk @repcount done
+      counter i from 5 to 1 do walk 100 done
+      walk i
+      repeat turn left 90 walk 1 until true
+      if 1 / 0 > 5 then walk 1 endif
+    end";
+    let mut prog: TProgram = code.parse().unwrap();
+    let orig = drawn(&prog);
+    prog.optimize();
+    // dead branches and loops are gone, the `if` binding x stays a block
+    assert_eq!(prog.main.statements.len(), 11);
+    assert!(matches!(
+        &*prog.main.statements[0],
+        Statement::MoveDist { dist, .. } if dist.kind == ExprKind::Const(Value::Number(2.0))
+    ));
+    assert!(orig.as_ref().is_ok_and(|cmds| cmds.len() == 8));
+    assert_eq!(drawn(&prog), orig);
+}
+
+#[test]
+fn optimize_keeps_runtime_errors() {
+    let mut prog: TProgram = "begin do 3 / 2 times walk 1 done end".parse().unwrap();
+    prog.optimize();
+    let err = drawn(&prog).unwrap_err();
+    assert_eq!(*err, RuntimeError::NonIntegerCount(1.5));
+    assert_eq!(err.get_pos(), FilePos::line_col(1, 10));
+}
+
+#[test]
+fn forward_zero_folds() {
+    let mut prog: TProgram = "begin walk 10 * 0 jump 1 - 1 end".parse().unwrap();
+    prog.optimize();
+    assert!(prog.main.statements.is_empty());
+    assert_eq!(drawn(&prog), Ok(Vec::new()));
+}
+
+#[test]
+fn eval_arithmetic() {
+    let env = Environment::new().var("x", Value::Number(2.0));
+    assert_eq!(eval_expr("2 + 3 * 4", &env).unwrap(), Value::Number(14.0));
+    assert_eq!(eval_expr("x ^ 3 > 7", &env).unwrap(), Value::Boolean(true));
+}
+
+#[test]
+fn eval_errors() {
+    let env = Environment::new().var("x", Value::Number(2.0));
+    let err = eval_expr("x + @y", &env).unwrap_err();
+    assert!(matches!(&*err, EvalError::UndefinedVariable(name) if name == "@y"));
+    assert_eq!(err.get_pos(), FilePos::line_col(1, 5));
+
+    let err = eval_expr("2 +", &env).unwrap_err();
+    assert!(matches!(*err, EvalError::Parse(ParseError::UnexpectedEnd)));
+
+    let err = eval_expr("x 3", &env).unwrap_err();
+    assert!(matches!(*err, EvalError::TrailingToken(_)));
+    assert_eq!(err.get_pos(), FilePos::line_col(1, 3));
+
+    let err = eval_expr("1 + (x = 2)", &env).unwrap_err();
+    assert!(matches!(*err, EvalError::Type(_)));
+    assert_eq!(
+        err.span(),
+        (FilePos::line_col(1, 1), FilePos::line_col(1, 11))
+    );
+
+    assert_eq!(
+        eval_expr("[x 4][2] + listlen([x])", &env).unwrap(),
+        Value::Number(5.0)
+    );
+    let err = eval_expr("[x 4][3]", &env).unwrap_err();
+    assert!(matches!(*err, EvalError::IndexOutOfBounds(idx, 2) if idx == 3.0));
+    let err = eval_expr("[x 4][x / 3]", &env).unwrap_err();
+    assert!(matches!(*err, EvalError::NonIntegerIndex(_)));
+    let err = eval_expr("[1 'a']", &env).unwrap_err();
+    assert!(matches!(*err, EvalError::Type(_)));
+    assert_eq!(err.get_pos(), FilePos::line_col(1, 4));
+}
+
+#[test]
+fn operator_types() {
+    use crate::tokens::{BiOperator, UnOperator, ValType};
+    use BiOperator::*;
+
+    let num = Value::Number(2.0);
+    let text = Value::String("a".to_string());
+    let yes = Value::Boolean(true);
+    let vals = [&num, &text, &yes];
+    // which operand types each operator takes, in the order of `vals`
+    let defined = [
+        (Add, [true, true, false]),
+        (Sub, [true, false, false]),
+        (Mul, [true, false, false]),
+        (Div, [true, false, false]),
+        (Exp, [true, false, false]),
+        (Less, [true, true, false]),
+        (LessEqual, [true, true, false]),
+        (Greater, [true, true, false]),
+        (GreaterEqual, [true, true, false]),
+        (Equal, [true, true, true]),
+        (UnEqual, [true, true, true]),
+        (And, [false, false, true]),
+        (Or, [false, false, true]),
+    ];
+    for (op, ok) in defined {
+        for (val, ok) in vals.iter().zip(ok) {
+            let res = op.try_eval(val, val);
+            if ok {
+                assert!(res.is_ok(), "{op} for {}", val.val_type());
+            } else {
+                assert_eq!(res, Err(TypeError::BiOpWrongType(op, val.val_type())));
+            }
+        }
+        // mixed operands are never converted
+        assert_eq!(
+            op.try_eval(&num, &text),
+            Err(TypeError::BiOpDifferentTypes(
+                op,
+                ValType::Number,
+                ValType::String
+            ))
+        );
+    }
+    assert_eq!(
+        Add.try_eval(&text, &text),
+        Ok(Value::String("aa".to_string()))
+    );
+    assert_eq!(Less.try_eval(&text, &text), Ok(Value::Boolean(false)));
+
+    assert_eq!(UnOperator::Negate.try_eval(&num), Ok(Value::Number(-2.0)));
+    assert_eq!(
+        UnOperator::Negate.try_eval(&yes),
+        Err(TypeError::UnOpWrongType(
+            UnOperator::Negate,
+            ValType::Boolean
+        ))
+    );
+    assert_eq!(UnOperator::Not.try_eval(&yes), Ok(Value::Boolean(false)));
+    assert_eq!(
+        UnOperator::Not.try_eval(&num),
+        Err(TypeError::UnOpWrongType(UnOperator::Not, ValType::Number))
+    );
+}
+
+#[test]
+fn explicit_conversions() {
+    use crate::tokens::ValType;
+
+    let conv = |val: Value, to| val.convert(to);
+    assert_eq!(conv("2.5".into(), ValType::Number), Value::Number(2.5));
+    assert_eq!(conv("abc".into(), ValType::Number), Value::Number(0.0));
+    assert_eq!(conv("".into(), ValType::Number), Value::Number(0.0));
+    assert_eq!(conv(true.into(), ValType::Number), Value::Number(1.0));
+    assert_eq!(conv(0.0.into(), ValType::Boolean), Value::Boolean(false));
+    assert_eq!(conv((-0.5).into(), ValType::Boolean), Value::Boolean(true));
+    assert_eq!(conv("".into(), ValType::Boolean), Value::Boolean(false));
+    // any text is true, even one reading as a number
+    assert_eq!(conv("0".into(), ValType::Boolean), Value::Boolean(true));
+    assert_eq!(conv(false.into(), ValType::String), Value::from("false"));
+    assert_eq!(conv(3.0.into(), ValType::String), Value::from("3"));
+
+    let list = eval_expr("[1 2 3]", &Environment::new()).unwrap();
+    assert_eq!(list.convert(ValType::Number), Value::Number(3.0));
+    assert_eq!(list.convert(ValType::String), Value::from("[1 2 3]"));
+    assert_eq!(
+        Value::List(Vec::new()).convert(ValType::Boolean),
+        Value::Boolean(false)
+    );
+
+    let err = eval_expr("'1' + 1", &Environment::new()).unwrap_err();
+    assert!(matches!(
+        *err,
+        EvalError::Type(TypeError::BiOpDifferentTypes(..))
+    ));
+    assert_eq!(
+        eval_expr("num('1') + 1", &Environment::new()).unwrap(),
+        Value::Number(2.0)
+    );
+}
+
+#[test]
+fn errors_reach_sink() {
+    let mut sink = CollectingReporter::new();
+    let prog = TProgram::compile_reported(
+        "begin\n  walk 10 10\nend",
+        None,
+        FeatureConf::default(),
+        &[],
+        &mut sink,
+    );
+    assert!(prog.is_none());
+    assert!(sink.has_errors());
+    let [diag] = &sink.diagnostics[..] else {
+        panic!("expected one diagnostic, got {:?}", sink.diagnostics);
+    };
+    assert_eq!(diag.severity, Severity::Error);
+    assert_eq!(diag.pos, FilePos::line_col(2, 11));
+}
+
+#[test]
+fn unused_let_warns() {
+    let code = "begin\n  let used = 1\n  let unused = 2\n  walk used\nend";
+    let mut sink = CollectingReporter::new();
+    let prog = TProgram::compile_reported(code, None, FeatureConf::default(), &[], &mut sink);
+    assert!(prog.is_some());
+    let [diag] = &sink.diagnostics[..] else {
+        panic!("expected one diagnostic, got {:?}", sink.diagnostics);
+    };
+    assert_eq!(diag.severity, Severity::Warning);
+    assert_eq!(diag.pos, FilePos::line_col(3, 7));
+    assert!(diag.message.contains("`unused`"));
+
+    let mut sink = CollectingReporter::new();
+    let deny = [Lint::UnusedVariable];
+    let prog = TProgram::compile_reported(code, None, FeatureConf::default(), &deny, &mut sink);
+    assert!(prog.is_none());
+    assert!(sink.has_errors());
+}
+
+#[test]
+fn symbol_lookup() {
+    let code = "path square(len)
+  let side = len
+  walk side
+endpath
+
+begin
+  let n = 3
+  path square(n)
+  walk sin(@dir)
+end";
+    let prog: TProgram = code.parse().unwrap();
+    assert_eq!(
+        prog.symbol_at(FilePos::line_col(3, 9)),
+        Some(SymbolInfo::Variable {
+            name: "side".into(),
+            global: false,
+            def: Some(FilePos::line_col(2, 7)),
+        })
+    );
+    assert_eq!(
+        prog.symbol_at(FilePos::line_col(8, 10)),
+        Some(SymbolInfo::Call {
+            name: "square".into(),
+            def: FilePos::line_col(1, 1),
+            arity: 1,
+        })
+    );
+    // the argument is nested in the call
+    let Some(SymbolInfo::Variable { name, def, .. }) = prog.symbol_at(FilePos::line_col(8, 15))
+    else {
+        panic!("no variable in call");
+    };
+    assert_eq!((name.as_str(), def), ("n", Some(FilePos::line_col(7, 7))));
+    assert_eq!(
+        prog.symbol_at(FilePos::line_col(9, 8)),
+        Some(SymbolInfo::Builtin("sin".into()))
+    );
+    assert_eq!(
+        prog.symbol_at(FilePos::line_col(9, 12)),
+        Some(SymbolInfo::Builtin("@dir".into()))
+    );
+    let Some(SymbolInfo::Variable { def, .. }) = prog.symbol_at(FilePos::line_col(2, 15)) else {
+        panic!("no argument");
+    };
+    assert_eq!(def, Some(FilePos::line_col(1, 13)));
+    assert_eq!(prog.symbol_at(FilePos::line_col(7, 11)), None);
+}
+
+#[test]
+fn go_to_definition() {
+    let code = "calculation twice(x)
+returns x * 2 endcalc
+
+begin
+  let size = twice(5)
+  walk size + @unset
+end";
+    let prog: TProgram = code.parse().unwrap();
+    assert_eq!(
+        prog.definition_of(FilePos::line_col(5, 15)),
+        Some(FilePos::line_col(1, 1))
+    );
+    assert_eq!(
+        prog.definition_of(FilePos::line_col(6, 9)),
+        Some(FilePos::line_col(5, 7))
+    );
+    assert_eq!(
+        prog.definition_of(FilePos::line_col(2, 9)),
+        Some(FilePos::line_col(1, 19))
+    );
+    assert_eq!(prog.definition_of(FilePos::line_col(6, 16)), None);
+    assert_eq!(prog.definition_of(FilePos::line_col(6, 3)), None);
+}
+
+#[test]
+fn find_references() {
+    let code = "path dot
+  walk 1
+endpath
+
+begin
+  path dot
+  let x = 1
+  if x > 0 then
+    let x = 2
+    walk x
+    path dot
+  endif
+  walk x path dot
+end";
+    let prog: TProgram = code.parse().unwrap();
+    assert_eq!(
+        prog.references(FilePos::line_col(1, 1)),
+        vec![
+            FilePos::line_col(6, 3),
+            FilePos::line_col(11, 5),
+            FilePos::line_col(13, 10)
+        ]
+    );
+    assert_eq!(
+        prog.references(FilePos::line_col(7, 7)),
+        vec![FilePos::line_col(8, 6), FilePos::line_col(13, 8)]
+    );
+    assert_eq!(
+        prog.references(FilePos::line_col(9, 9)),
+        vec![FilePos::line_col(10, 10)]
+    );
+}
+
+#[test]
+fn rename_symbols() {
+    let code = "path dot(size)
+  walk size \" keep this comment
+endpath
+
+begin
+  let x = 1
+  if x > 0 then
+    let x = 2
+    path dot(x)
+  endif
+  walk x   path dot(3)
+end";
+    let prog: TProgram = code.parse().unwrap();
+    let renamed = prog.rename(code, FilePos::line_col(1, 1), "point").unwrap();
+    assert!(renamed.starts_with("path point(size)\n  walk size \" keep this comment"));
+    assert!(renamed.contains("    path point(x)\n"));
+    assert!(renamed.contains("  walk x   path point(3)\n"));
+
+    let renamed = prog.rename(code, FilePos::line_col(8, 9), "inner").unwrap();
+    assert!(renamed.contains("    let inner = 2\n    path dot(inner)\n"));
+    assert!(renamed.contains("  let x = 1\n  if x > 0"));
+    assert!(renamed.contains("  walk x   path dot(3)"));
+    renamed.parse::<TProgram>().unwrap();
+
+    let renamed = prog.rename(code, FilePos::line_col(1, 10), "len").unwrap();
+    assert!(renamed.starts_with("path dot(len)\n  walk len \""));
+}
+
+#[test]
+fn rename_rejected() {
+    let code = "path dot(size)
+  walk size
+endpath
+
+begin
+  let x = 1
+  path dot(x)
+end";
+    let prog: TProgram = code.parse().unwrap();
+    let def = FilePos::line_col(6, 7);
+    assert_eq!(
+        prog.rename(code, def, "size"),
+        Err(RenameError::Collision("size".into()))
+    );
+    assert_eq!(
+        prog.rename(code, def, "dot"),
+        Err(RenameError::Collision("dot".into()))
+    );
+    assert_eq!(
+        prog.rename(code, def, "2x"),
+        Err(RenameError::InvalidName("2x".into()))
+    );
+    assert_eq!(
+        prog.rename(code, def, "walk"),
+        Err(RenameError::InvalidName("walk".into()))
+    );
+    assert_eq!(
+        prog.rename(code, FilePos::line_col(7, 3), "y"),
+        Err(RenameError::NoDefinition(FilePos::line_col(7, 3)))
+    );
+}
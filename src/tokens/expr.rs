@@ -1,17 +1,17 @@
 use std::fmt::{Display, Write as _};
 
-use crate::{pos::FilePos, SymbolTable};
+use crate::{pos::FilePos, prog::TypeError, SymbolTable};
 
 use super::{ArgList, Narrate, PredefFunc, ValType, Value, Variable};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Expr {
     pub start: FilePos,
     pub end: FilePos,
     pub kind: ExprKind,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum ExprKind {
     Const(Value),
     Variable(Variable),
@@ -22,6 +22,10 @@ pub enum ExprKind {
     Convert(Box<Expr>, ValType),
     FuncCall(PredefFunc, ArgList),
     CalcCall(usize, ArgList),
+    /// `[10 20 30]`
+    List(Vec<Expr>),
+    /// `list[idx]`, counting from 1
+    Index(Box<Expr>, Box<Expr>),
 }
 
 impl ExprKind {
@@ -34,6 +38,26 @@ impl ExprKind {
     }
 }
 
+impl Expr {
+    /// Calls `f` on the operands, arguments or items of the expression
+    pub fn for_each_sub<'e>(&'e self, mut f: impl FnMut(&'e Expr)) {
+        match &self.kind {
+            ExprKind::Const(_) | ExprKind::Variable(_) => {}
+            ExprKind::BiOperation(lhs, _, rhs) | ExprKind::Index(lhs, rhs) => {
+                f(lhs);
+                f(rhs);
+            }
+            ExprKind::UnOperation(_, expr)
+            | ExprKind::Absolute(expr)
+            | ExprKind::Bracket(expr)
+            | ExprKind::Convert(expr, _) => f(expr),
+            ExprKind::FuncCall(_, args) | ExprKind::CalcCall(_, args) | ExprKind::List(args) => {
+                args.iter().for_each(f)
+            }
+        }
+    }
+}
+
 impl Narrate for Expr {
     fn narrate_buf(&self, symbols: &SymbolTable, buf: &mut String) {
         match &self.kind {
@@ -82,6 +106,22 @@ impl Narrate for Expr {
                 args.narrate_buf(symbols, buf);
                 let _ = write!(buf, ")");
             }
+            ExprKind::List(items) => {
+                let _ = write!(buf, "[");
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        let _ = write!(buf, " ");
+                    }
+                    item.narrate_buf(symbols, buf);
+                }
+                let _ = write!(buf, "]");
+            }
+            ExprKind::Index(list, idx) => {
+                list.narrate_buf(symbols, buf);
+                let _ = write!(buf, "[");
+                idx.narrate_buf(symbols, buf);
+                let _ = write!(buf, "]");
+            }
         }
     }
 }
@@ -104,28 +144,32 @@ pub enum BiOperator {
 }
 
 impl BiOperator {
-    pub fn types(&self) -> Vec<(ValType, ValType)> {
+    /// The operand types the operator is defined for with the type of the result.
+    ///
+    /// Arithmetic needs numbers, `+` also joins strings, comparisons need both
+    /// sides of the same type and `&&`/`||` booleans. Nothing is converted implicitly.
+    pub fn types(&self) -> &'static [(ValType, ValType)] {
         match self {
-            BiOperator::Add => vec![
+            BiOperator::Add => &[
                 (ValType::Number, ValType::Number),
                 (ValType::String, ValType::String),
             ],
             BiOperator::Sub | BiOperator::Mul | BiOperator::Div | BiOperator::Exp => {
-                vec![(ValType::Number, ValType::Number)]
+                &[(ValType::Number, ValType::Number)]
             }
             BiOperator::Less
             | BiOperator::LessEqual
             | BiOperator::Greater
-            | BiOperator::GreaterEqual => vec![
+            | BiOperator::GreaterEqual => &[
                 (ValType::Number, ValType::Boolean),
                 (ValType::String, ValType::Boolean),
             ],
-            BiOperator::Equal | BiOperator::UnEqual => vec![
+            BiOperator::Equal | BiOperator::UnEqual => &[
                 (ValType::Number, ValType::Boolean),
                 (ValType::String, ValType::Boolean),
                 (ValType::Boolean, ValType::Boolean),
             ],
-            BiOperator::And | BiOperator::Or => vec![(ValType::Boolean, ValType::Boolean)],
+            BiOperator::And | BiOperator::Or => &[(ValType::Boolean, ValType::Boolean)],
         }
     }
 
@@ -140,6 +184,18 @@ impl BiOperator {
         }
     }
 
+    /// Checks the operands against [`BiOperator::types`] before evaluating
+    pub fn try_eval(&self, lhs: &Value, rhs: &Value) -> Result<Value, TypeError> {
+        let (lty, rty) = (lhs.val_type(), rhs.val_type());
+        if lty != rty {
+            return Err(TypeError::BiOpDifferentTypes(*self, lty, rty));
+        }
+        if !self.types().iter().any(|(ty, _)| *ty == lty) {
+            return Err(TypeError::BiOpWrongType(*self, lty));
+        }
+        Ok(self.eval(lhs, rhs))
+    }
+
     pub fn eval(&self, lhs: &Value, rhs: &Value) -> Value {
         macro_rules! bi_op_helper {
             ($lhs:ident, $rhs:ident : {$($ty:ident => $res:expr),+ $(,)?}) => {
@@ -239,6 +295,14 @@ impl UnOperator {
         }
     }
 
+    /// Checks the operand against [`UnOperator::val_type`] before evaluating
+    pub fn try_eval(&self, val: &Value) -> Result<Value, TypeError> {
+        if val.val_type() != self.val_type() {
+            return Err(TypeError::UnOpWrongType(*self, val.val_type()));
+        }
+        Ok(self.eval(val))
+    }
+
     pub fn eval(&self, val: &Value) -> Value {
         match (self, val) {
             (Self::Negate, Value::Number(n)) => Value::Number(-*n),
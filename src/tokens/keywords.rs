@@ -73,12 +73,28 @@ keyword_enum! {
     Right,
     Direction,
     Color,
+    Penwidth,
+    Setspeed,
+    Penstyle,
+    Solid,
+    Dashed,
+    Dotted,
+    Penup,
+    Pendown,
+    Showturtle,
+    Hideturtle,
+    Savestate,
+    Restorestate,
+    Newturtle,
+    Setturtle,
     Clear,
+    Clearscreen,
     Stop,
     Finish,
     Path,
     Store,
     In,
+    Let,
     Add,
     To,
     Sub,
@@ -100,12 +116,16 @@ keyword_enum! {
     While,
     Repeat,
     Until,
+    Frame,
+    Endframe,
     Endpath,
     Calculation,
     Returns,
+    Return,
     Endcalc,
     Begin,
     End,
+    Include,
     Sin,
     Cos,
     Tan,
@@ -115,6 +135,11 @@ keyword_enum! {
     Or,
     Not,
     Arctan,
+    Abs,
+    Floor,
+    Ceil,
+    Random,
+    Towards,
     String if Types,
     Num if Types,
     Bool if Types,
@@ -122,12 +147,87 @@ keyword_enum! {
     True if Types,
     False if Types,
     Print if Types,
+    Setprecision if Types,
     Substr if Types,
     Strlen if Types,
+    Listlen if Types,
+    Foreach if Types,
     Split if Multithreading,
     Wait if Multithreading,
     Event if Events,
     EndEvent if Events,
     Key if Events,
     Mouse if Events,
+    Arc if Shapes,
+    Circle if Shapes,
+    Push if Transforms,
+    Pop if Transforms,
+    Scale if Transforms,
+    Translate if Transforms,
+    Rotate if Transforms,
+}
+
+impl Keyword {
+    /// The keyword that has to come right before this one, elsewhere it is an identifier.
+    ///
+    /// Keeps programs working that used the name before it became a keyword.
+    pub fn is_contextual(&self) -> Option<Keyword> {
+        match self {
+            Keyword::Solid | Keyword::Dashed | Keyword::Dotted => Some(Keyword::Penstyle),
+            _ => None,
+        }
+    }
+
+    /// Whether this is only a keyword where a statement or `include` starts, elsewhere
+    /// it is an identifier.
+    ///
+    /// The parser decides this, as statements may start after any token. Keeps
+    /// variables and paths of older programs named like later statements working.
+    pub fn only_starts_statement(&self) -> bool {
+        matches!(
+            self,
+            Keyword::Penwidth
+                | Keyword::Setspeed
+                | Keyword::Penstyle
+                | Keyword::Penup
+                | Keyword::Pendown
+                | Keyword::Showturtle
+                | Keyword::Hideturtle
+                | Keyword::Savestate
+                | Keyword::Restorestate
+                | Keyword::Newturtle
+                | Keyword::Setturtle
+                | Keyword::Clearscreen
+                | Keyword::Let
+                | Keyword::Frame
+                | Keyword::Endframe
+                | Keyword::Return
+                | Keyword::Include
+                | Keyword::Setprecision
+                | Keyword::Foreach
+                | Keyword::Arc
+                | Keyword::Circle
+                | Keyword::Push
+                | Keyword::Pop
+                | Keyword::Scale
+                | Keyword::Translate
+                | Keyword::Rotate
+        )
+    }
+
+    /// Whether this is only a keyword right before `(`, elsewhere it is an identifier.
+    ///
+    /// These functions came after the language, so their names may be variables or
+    /// calculations of older programs, see [`crate::prog::lexer::Lexer::collect_tokens`].
+    pub fn needs_call(&self) -> bool {
+        matches!(
+            self,
+            Keyword::Abs
+                | Keyword::Floor
+                | Keyword::Ceil
+                | Keyword::Random
+                | Keyword::Towards
+                | Keyword::Listlen
+        )
+    }
 }
@@ -4,6 +4,7 @@ use std::{
 };
 
 use crate::{
+    debugger::{Rng, TCoord},
     pos::FilePos,
     prog::{CalcDef, PathDef},
     Pos, SymbolTable,
@@ -30,6 +31,15 @@ pub struct Block {
     pub statements: Vec<Pos<Statement>>,
 }
 
+impl Block {
+    /// Whether nothing runs after the statement at `idx`, a `return` right after it included
+    pub fn ends_after(&self, idx: usize) -> bool {
+        self.statements
+            .get(idx + 1)
+            .is_none_or(|next| **next == Statement::Return(None))
+    }
+}
+
 pub trait Narrate {
     fn narrate_buf(&self, symbols: &SymbolTable, buf: &mut String);
 
@@ -72,9 +82,11 @@ pub enum ParseToken {
     CalcDef(CalcDef),
     EventHandler(EventKind, PathDef),
     StartBlock(Block),
+    /// `include '<file>'`, resolved by [`TProgram`](crate::TProgram)
+    Include(Pos<String>),
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Variable {
     pub pos: FilePos,
     pub kind: VariableKind,
@@ -118,14 +130,15 @@ impl Narrate for Variable {
 }
 
 macro_rules! predef_funcs {
-    ($($func:ident ($($arg:ident : $ty:ty),+) -> $ret:ty = $res:expr,)+) => {
-        #[derive(Debug, PartialEq)]
+    ($rng:ident, $pos:ident; $($func:ident ($($arg:ident : $ty:ty),+) -> $ret:ty = $res:expr,)+) => {
+        #[derive(Debug, PartialEq, Clone, Copy)]
         pub enum PredefFunc {
             $($func,)+
         }
 
         impl PredefFunc {
-            pub fn eval(&self, args: &[Value]) -> Value {
+            /// `pos` is the position of the evaluating turtle
+            pub fn eval(&self, args: &[Value], $rng: &mut Rng, $pos: TCoord) -> Value {
                 let mut args = args.iter();
                 match self {
                     $(Self::$func => {
@@ -167,13 +180,26 @@ macro_rules! predef_funcs {
     };
 }
 
+// angles are in degrees, like turtle headings
 predef_funcs! {
+    rng, pos;
     Sin (a: f64) -> f64 = (a * PI / 180.0).sin(),
     Cos (a: f64) -> f64 = (a * PI / 180.0).cos(),
     Tan (a: f64) -> f64 = (a * PI / 180.0).tan(),
     Sqrt (x: f64) -> f64 = x.sqrt(),
-    Rand (min: f64, max: f64) -> f64 = min + (max - min) * rand::random::<f64>(),
-    Substr (s: &str, start: f64, end: f64) -> &str = s[start as usize .. end as usize],
-    Strlen (s: &str) -> f64 = s.len() as f64,
+    Rand (min: f64, max: f64) -> f64 = min + (max - min) * rng.next_f64(),
+    // counted in chars, out of range parts of `start..end` are cut off
+    Substr (s: String, start: f64, end: f64) -> String = s
+        .chars()
+        .skip(start as usize)
+        .take((end as usize).saturating_sub(start as usize))
+        .collect::<String>(),
+    Strlen (s: String) -> f64 = s.chars().count() as f64,
+    Listlen (l: &[f64]) -> f64 = l.len() as f64,
     Arctan (a: f64) -> f64 = a.atan() * 180.0 / PI,
+    Abs (x: f64) -> f64 = x.abs(),
+    Floor (x: f64) -> f64 = x.floor(),
+    Ceil (x: f64) -> f64 = x.ceil(),
+    Random (max: f64) -> f64 = max * rng.next_f64(),
+    Towards (x: f64, y: f64) -> f64 = ((y - pos.1).atan2(x - pos.0) * 180.0 / PI).rem_euclid(360.0),
 }
@@ -18,6 +18,7 @@ pub enum PredefVar {
     Red,
     Green,
     Blue,
+    RepCount,
 }
 
 impl PredefVar {
@@ -44,6 +45,7 @@ impl PredefVar {
             PredefVar::Red => "red",
             PredefVar::Green => "green",
             PredefVar::Blue => "blue",
+            PredefVar::RepCount => "repcount",
         }
     }
 
@@ -69,6 +71,7 @@ impl PredefVar {
             PredefVar::Red,
             PredefVar::Green,
             PredefVar::Blue,
+            PredefVar::RepCount,
         ]
     }
 
@@ -86,6 +89,7 @@ impl PredefVar {
             PredefVar::Red => true,
             PredefVar::Blue => true,
             PredefVar::Green => true,
+            PredefVar::RepCount => false,
         }
     }
 
@@ -104,7 +108,8 @@ impl PredefVar {
             | PredefVar::Delay
             | PredefVar::Red
             | PredefVar::Green
-            | PredefVar::Blue => ValType::Number,
+            | PredefVar::Blue
+            | PredefVar::RepCount => ValType::Number,
             PredefVar::Arg(_) => ValType::String,
         }
     }
@@ -1,4 +1,4 @@
-use crate::SymbolTable;
+use crate::{debugger::window::LineStyle, SymbolTable};
 
 use super::{ArgList, BiOperator, Block, Expr, Narrate as _, Variable};
 
@@ -10,17 +10,57 @@ pub enum Statement {
         back: bool,
     },
     MoveHome(bool),
+    /// absolute move, the heading is kept
+    MoveTo {
+        x: Expr,
+        y: Expr,
+        draw: bool,
+    },
     Turn {
         left: bool,
         by: Expr,
     },
     Direction(Expr),
+    /// `angle` is `None` for a full circle
+    Arc {
+        left: bool,
+        radius: Expr,
+        angle: Option<Expr>,
+    },
     Color(Expr, Expr, Expr),
+    PenWidth(Expr),
+    /// sets `@delay` from a speed of 1, the slowest, up to 10 without any delay
+    SetSpeed(Expr),
+    PenStyle(LineStyle),
+    /// saves the current transform
+    PushTransform,
+    PopTransform,
+    /// saves position, heading, pen and color
+    SaveState,
+    RestoreState,
+    /// adds a turtle with its own position and pen and makes it the active one
+    NewTurtle,
+    /// activates the turtle with this number, counting from 1
+    SetTurtle(Expr),
+    /// the y factor defaults to the x factor
+    Scale(Expr, Option<Expr>),
+    Translate(Expr, Expr),
+    Rotate(Expr),
+    /// `true` lowers the pen
+    Pen(bool),
+    /// `true` shows the turtle as cursor
+    ShowTurtle(bool),
     Clear,
+    /// clears the window and resets the turtle
+    ClearScreen,
     Stop,
     Finish,
+    /// leaves the path or calculation, the value is the result of a calculation
+    Return(Option<Expr>),
     PathCall(usize, ArgList),
     Store(Expr, Variable),
+    /// binds a local in the innermost block, shadowing outer ones
+    Let(Expr, Variable),
     Calc {
         var: Variable,
         val: Expr,
@@ -29,6 +69,8 @@ pub enum Statement {
     Mark,
     MoveMark(bool),
     Print(Expr),
+    /// decimal places of numbers turned into text, a negative count restores the default
+    SetPrecision(Expr),
     Split(usize, ArgList),
     Wait,
     IfBranch(Expr, Block),
@@ -42,8 +84,16 @@ pub enum Statement {
         step: Option<Expr>,
         body: Block,
     },
+    /// `item` is bound to each element in a scope of its own
+    ForEach {
+        item: Variable,
+        list: Expr,
+        body: Block,
+    },
     WhileLoop(Expr, Block),
     RepeatLoop(Expr, Block),
+    /// the block is shown at once, see [`Window::begin_frame`](crate::debugger::window::Window::begin_frame)
+    Frame(Block),
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
@@ -58,24 +108,123 @@ impl Statement {
     pub fn kind(&self) -> StmtKind {
         match self {
             Self::Clear
+            | Self::ClearScreen
             | Self::MoveDist { draw: true, .. }
             | Self::MoveHome(true)
+            | Self::MoveTo { draw: true, .. }
+            | Self::Arc { .. }
             | Self::MoveMark(true) => StmtKind::Draw,
             Self::Direction(_)
             | Self::MoveDist { .. }
             | Self::MoveHome(_)
+            | Self::MoveTo { .. }
             | Self::MoveMark(_)
+            | Self::PushTransform
+            | Self::PopTransform
+            | Self::SaveState
+            | Self::RestoreState
+            | Self::NewTurtle
+            | Self::SetTurtle(_)
+            | Self::Scale(_, _)
+            | Self::Translate(_, _)
+            | Self::Rotate(_)
             | Self::Turn { .. } => StmtKind::Turtle,
             Self::IfBranch(_, _)
             | Self::IfElseBranch(_, _, _)
             | Self::DoLoop(_, _)
             | Self::CounterLoop { .. }
+            | Self::ForEach { .. }
             | Self::WhileLoop(_, _)
-            | Self::RepeatLoop(_, _) => StmtKind::Control,
+            | Self::RepeatLoop(_, _)
+            | Self::Frame(_) => StmtKind::Control,
             _ => StmtKind::Any,
         }
     }
 
+    /// Calls `f` on the expressions of the statement itself, like a loop condition,
+    /// but not on those in its blocks, see [`Statement::for_each_block`]
+    pub fn for_each_expr<'s>(&'s self, mut f: impl FnMut(&'s Expr)) {
+        match self {
+            Statement::MoveHome(_)
+            | Statement::PushTransform
+            | Statement::PopTransform
+            | Statement::SaveState
+            | Statement::RestoreState
+            | Statement::NewTurtle
+            | Statement::PenStyle(_)
+            | Statement::Pen(_)
+            | Statement::ShowTurtle(_)
+            | Statement::Clear
+            | Statement::ClearScreen
+            | Statement::Stop
+            | Statement::Finish
+            | Statement::Mark
+            | Statement::MoveMark(_)
+            | Statement::Wait
+            | Statement::Frame(_) => {}
+            Statement::MoveDist { dist: expr, .. }
+            | Statement::Turn { by: expr, .. }
+            | Statement::Direction(expr)
+            | Statement::PenWidth(expr)
+            | Statement::SetSpeed(expr)
+            | Statement::SetTurtle(expr)
+            | Statement::SetPrecision(expr)
+            | Statement::Rotate(expr)
+            | Statement::Print(expr)
+            | Statement::Store(expr, _)
+            | Statement::Let(expr, _)
+            | Statement::Calc { val: expr, .. }
+            | Statement::IfBranch(expr, _)
+            | Statement::IfElseBranch(expr, _, _)
+            | Statement::DoLoop(expr, _)
+            | Statement::WhileLoop(expr, _)
+            | Statement::RepeatLoop(expr, _)
+            | Statement::ForEach { list: expr, .. } => f(expr),
+            Statement::MoveTo { x, y, .. } | Statement::Translate(x, y) => {
+                f(x);
+                f(y);
+            }
+            Statement::Arc { radius, angle, .. } => {
+                f(radius);
+                angle.iter().for_each(f);
+            }
+            Statement::Color(r, g, b) => {
+                f(r);
+                f(g);
+                f(b);
+            }
+            Statement::Scale(x, y) => {
+                f(x);
+                y.iter().for_each(f);
+            }
+            Statement::Return(val) => val.iter().for_each(f),
+            Statement::PathCall(_, args) | Statement::Split(_, args) => args.iter().for_each(f),
+            Statement::CounterLoop { from, to, step, .. } => {
+                f(from);
+                f(to);
+                step.iter().for_each(f);
+            }
+        }
+    }
+
+    /// Calls `f` on the blocks nested in the statement, in source order
+    pub fn for_each_block<'s>(&'s self, mut f: impl FnMut(&'s Block)) {
+        match self {
+            Statement::IfBranch(_, block)
+            | Statement::DoLoop(_, block)
+            | Statement::WhileLoop(_, block)
+            | Statement::RepeatLoop(_, block)
+            | Statement::Frame(block)
+            | Statement::CounterLoop { body: block, .. }
+            | Statement::ForEach { body: block, .. } => f(block),
+            Statement::IfElseBranch(_, if_br, else_br) => {
+                f(if_br);
+                f(else_br);
+            }
+            _ => {}
+        }
+    }
+
     pub fn narrate(&self, symbols: &SymbolTable) {
         match self {
             Statement::MoveDist { dist, draw, back } => {
@@ -89,6 +238,14 @@ impl Statement {
             Statement::MoveHome(draw) => {
                 println!("{} home", if *draw { "walked" } else { "jumped" })
             }
+            Statement::MoveTo { x, y, draw } => {
+                println!(
+                    "{} to ({}, {})",
+                    if *draw { "walked" } else { "jumped" },
+                    x.narrate(symbols),
+                    y.narrate(symbols)
+                )
+            }
             Statement::Turn { left, by } => {
                 println!(
                     "turned {} by {}",
@@ -99,6 +256,20 @@ impl Statement {
             Statement::Direction(expr) => {
                 println!("set direction to {}", expr.narrate(symbols))
             }
+            Statement::Arc {
+                left,
+                radius,
+                angle,
+            } => {
+                println!(
+                    "walked {} by {} around radius {}",
+                    if *left { "left" } else { "right" },
+                    angle
+                        .as_ref()
+                        .map_or("360".to_string(), |a| a.narrate(symbols)),
+                    radius.narrate(symbols)
+                )
+            }
             Statement::Color(r, g, b) => {
                 println!(
                     "set color to ({}, {}, {})",
@@ -107,9 +278,43 @@ impl Statement {
                     b.narrate(symbols),
                 )
             }
+            Statement::PenWidth(expr) => {
+                println!("set pen width to {}", expr.narrate(symbols))
+            }
+            Statement::SetSpeed(expr) => println!("set speed to {}", expr.narrate(symbols)),
+            Statement::PenStyle(style) => println!("set pen style to {style}"),
+            Statement::SetPrecision(expr) => {
+                println!("set precision to {}", expr.narrate(symbols))
+            }
+            Statement::PushTransform => println!("saved transform"),
+            Statement::PopTransform => println!("restored transform"),
+            Statement::SaveState => println!("saved turtle"),
+            Statement::RestoreState => println!("restored turtle"),
+            Statement::NewTurtle => println!("made a new turtle"),
+            Statement::SetTurtle(id) => println!("switched to turtle {}", id.narrate(symbols)),
+            Statement::Scale(x, y) => match y {
+                Some(y) => println!("scaled by ({}, {})", x.narrate(symbols), y.narrate(symbols)),
+                None => println!("scaled by {}", x.narrate(symbols)),
+            },
+            Statement::Translate(x, y) => {
+                println!(
+                    "translated by ({}, {})",
+                    x.narrate(symbols),
+                    y.narrate(symbols)
+                )
+            }
+            Statement::Rotate(angle) => println!("rotated by {}", angle.narrate(symbols)),
+            Statement::Pen(down) => {
+                println!("{} pen", if *down { "lowered" } else { "lifted" })
+            }
+            Statement::ShowTurtle(visible) => {
+                println!("{} turtle", if *visible { "showed" } else { "hid" })
+            }
             Statement::Clear => println!("cleared screen"),
+            Statement::ClearScreen => println!("cleared screen and reset turtle"),
             Statement::Stop => println!("stopped turtle"),
             Statement::Finish => println!("finished drawing"),
+            Statement::Return(_) => println!("returned"),
             Statement::PathCall(id, args) => {
                 println!(
                     "started path {}({})",
@@ -127,6 +332,13 @@ impl Statement {
                     var.narrate(symbols),
                 )
             }
+            Statement::Let(expr, var) => {
+                println!(
+                    "declared {} as {}",
+                    var.narrate(symbols),
+                    expr.narrate(symbols),
+                )
+            }
             Statement::Calc { var, val, op } => {
                 let var = var.narrate(symbols);
                 let val = val.narrate(symbols);
@@ -158,8 +370,10 @@ impl Statement {
             Statement::IfElseBranch(_, _, _) => println!("finished if-else"),
             Statement::DoLoop(_, _) => println!("finished do loop"),
             Statement::CounterLoop { .. } => println!("finished counter loop"),
+            Statement::ForEach { .. } => println!("finished foreach loop"),
             Statement::WhileLoop(_, _) => println!("finished while loop"),
             Statement::RepeatLoop(_, _) => println!("finished repeat loop"),
+            Statement::Frame(_) => println!("finished frame"),
         }
     }
 }
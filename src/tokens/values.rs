@@ -8,6 +8,8 @@ pub enum ValType {
     Number,
     String,
     Boolean,
+    /// list of numbers
+    List,
 }
 
 impl ValType {
@@ -17,6 +19,7 @@ impl ValType {
             ValType::Number => Value::Number(0.0),
             ValType::String => Value::String(String::new()),
             ValType::Boolean => Value::Boolean(false),
+            ValType::List => Value::List(Vec::new()),
         }
     }
 
@@ -37,6 +40,7 @@ impl Display for ValType {
             ValType::Number => write!(f, "num"),
             ValType::String => write!(f, "string"),
             ValType::Boolean => write!(f, "bool"),
+            ValType::List => write!(f, "list"),
         }
     }
 }
@@ -65,11 +69,27 @@ impl From<bool> for ValType {
     }
 }
 
+impl From<&[f64]> for ValType {
+    fn from(_: &[f64]) -> Self {
+        Self::List
+    }
+}
+
+/// A value of the language, colors are three numbers and lists only hold numbers.
+///
+/// Values keep their type: an operator needs both operands of one type it's
+/// defined for (see [`BiOperator::types`](super::BiOperator::types)), only
+/// `print` and explicit conversions like `num(..)` take anything.
+///
+/// There are no separate integers on purpose: every number is an `f64`, which holds
+/// whole numbers up to 2^53 exactly, so arithmetic on them stays whole. Where an
+/// integer is needed, like loop counts and list indices, [`Value::int`] checks for one.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Value {
     Number(f64),
     String(String),
     Boolean(bool),
+    List(Vec<f64>),
 }
 
 impl Value {
@@ -78,14 +98,21 @@ impl Value {
             Value::Number(_) => ValType::Number,
             Value::String(_) => ValType::String,
             Value::Boolean(_) => ValType::Boolean,
+            Value::List(_) => ValType::List,
         }
     }
 
+    /// Never fails: strings that aren't a number become `0`, booleans `1` or `0`
+    /// and lists their length, only `0`, the empty string and list are `false`
     pub fn convert(&self, to: ValType) -> Value {
         match to {
             ValType::Number => self.num().into(),
             ValType::String => self.string().into(),
             ValType::Boolean => self.bool().into(),
+            ValType::List => match self {
+                Value::List(_) => self.clone(),
+                _ => Value::List(Vec::new()),
+            },
             ValType::Any => self.clone(),
         }
     }
@@ -95,14 +122,55 @@ impl Value {
             Value::Number(val) => *val,
             Value::String(s) => s.parse().unwrap_or_default(),
             Value::Boolean(b) => *b as i32 as f64,
+            Value::List(l) => l.len() as f64,
+        }
+    }
+
+    /// The number if it is an exact integer: whole and small enough that no
+    /// neighbouring integer rounds to it
+    pub fn int(&self) -> Option<i64> {
+        const MAX_EXACT: f64 = (1u64 << f64::MANTISSA_DIGITS) as f64;
+        match self {
+            Value::Number(val) if val.fract() == 0.0 && val.abs() <= MAX_EXACT => Some(*val as i64),
+            _ => None,
         }
     }
 
+    /// Element `idx` of a list, counting from 1
+    pub fn item(&self, idx: &Value) -> Option<f64> {
+        let Value::List(list) = self else {
+            return None;
+        };
+        let idx = usize::try_from(idx.int()?).ok()?;
+        list.get(idx.checked_sub(1)?).copied()
+    }
+
     pub fn string(&self) -> String {
         match self {
-            Value::Number(val) => val.to_string(),
+            Value::Number(val) => format_num(*val),
             Value::String(s) => s.clone(),
             Value::Boolean(b) => b.to_string(),
+            Value::List(l) => list_string(l, format_num),
+        }
+    }
+
+    /// Like [`Value::string`], but numbers that aren't whole get exactly
+    /// `digits` decimal places if given
+    pub fn string_with(&self, digits: Option<usize>) -> String {
+        match (self, digits) {
+            (Value::Number(val), Some(digits)) if val.is_finite() && val.fract() != 0.0 => {
+                let res = format!("{val:.digits$}");
+                // `-0.001` rounds to `-0.00`
+                if res.parse::<f64>() == Ok(0.0) {
+                    res.trim_start_matches('-').to_string()
+                } else {
+                    res
+                }
+            }
+            (Value::List(l), Some(_)) => {
+                list_string(l, |val| Value::Number(val).string_with(digits))
+            }
+            _ => self.string(),
         }
     }
 
@@ -111,6 +179,7 @@ impl Value {
             Value::Number(val) => *val != 0.0,
             Value::String(s) => !s.is_empty(),
             Value::Boolean(b) => *b,
+            Value::List(l) => !l.is_empty(),
         }
     }
 }
@@ -121,6 +190,7 @@ impl Display for Value {
             Value::Number(num) => write!(f, "{num}"),
             Value::String(s) => write!(f, "'{s}'"),
             Value::Boolean(b) => write!(f, "{b}"),
+            Value::List(l) => write!(f, "{}", list_string(l, |val| val.to_string())),
         }
     }
 }
@@ -137,18 +207,12 @@ impl From<&Value> for String {
     }
 }
 
-impl<'s> From<&'s Value> for &'s str {
-    fn from(value: &'s Value) -> &'s str {
+impl<'s> From<&'s Value> for &'s [f64] {
+    fn from(value: &'s Value) -> &'s [f64] {
         match value {
-            Value::Number(_) => unimplemented!("should actually never be reached"),
-            Value::String(s) => s,
-            Value::Boolean(b) => {
-                if *b {
-                    "true"
-                } else {
-                    "false"
-                }
-            }
+            Value::List(l) => l,
+            // only lists have items
+            _ => &[],
         }
     }
 }
@@ -182,3 +246,33 @@ impl From<bool> for Value {
         Self::Boolean(value)
     }
 }
+
+impl From<Vec<f64>> for Value {
+    fn from(value: Vec<f64>) -> Self {
+        Self::List(value)
+    }
+}
+
+/// `[1 2 3]`, the elements separated by spaces
+fn list_string(list: &[f64], num: impl Fn(f64) -> String) -> String {
+    let items: Vec<_> = list.iter().map(|val| num(*val)).collect();
+    format!("[{}]", items.join(" "))
+}
+
+/// Text form of a number, as used by `print` and `string(..)`.
+///
+/// Rounded to at most 6 decimal places with trailing zeros removed, so whole
+/// numbers have no decimal point: `3`, `0.5`, `-1.333333`. This is the default,
+/// `setprecision` switches to [`Value::string_with`].
+fn format_num(val: f64) -> String {
+    if !val.is_finite() {
+        return val.to_string();
+    }
+    let res = format!("{val:.6}");
+    let res = res.trim_end_matches('0').trim_end_matches('.');
+    if res == "-0" {
+        String::from("0")
+    } else {
+        res.to_string()
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    io::Write as _,
+    process::{Command, Output, Stdio},
+};
+
+fn turtle(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_turtle"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn run_check() {
+    let out = turtle(&["run", "--check", "examples/spirale.tg"], "");
+    assert!(out.status.success());
+    assert!(out.stderr.is_empty());
+
+    let out = turtle(&["run", "--check", "-"], "begin\n  walk )\nend\n");
+    assert_eq!(out.status.code(), Some(1));
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.starts_with("invalid turtle program: unexpected token"));
+    assert!(stderr.contains("at line 2, column 8\n  walk )\n       ^"));
+}
+
+#[test]
+fn run_stdin_max_steps() {
+    let prog = "begin\n  while true do walk 1 done\nend\n";
+    let out = turtle(&["run", "-w", "void", "--max-steps", "5", "-"], prog);
+    assert_eq!(out.status.code(), Some(1));
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.starts_with("runtime error: step limit of 5 statements exceeded at line 2"));
+
+    let out = turtle(&["run", "-w", "void", "-"], "begin walk 1 end");
+    assert!(out.status.success());
+}
+
+#[test]
+fn run_svg_output() {
+    let dir = std::env::temp_dir().join(format!("turtle_cli_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let svg = dir.join("square.svg");
+    let prog = "begin\n  do 4 times\n    walk 30\n    turn 90\n  done\n  stop\nend\n";
+    let out = turtle(&["run", "-", "--output", svg.to_str().unwrap()], prog);
+    assert!(out.status.success());
+    let doc = std::fs::read_to_string(&svg).unwrap();
+    assert_eq!(doc.matches("<line").count(), 4);
+    // fits the square instead of the default 20 x 15
+    assert!(
+        doc.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-31.5 -31.5 63 63\">")
+    );
+
+    let out = turtle(
+        &[
+            "run",
+            "-",
+            "--output",
+            svg.to_str().unwrap(),
+            "--size",
+            "100x50",
+        ],
+        prog,
+    );
+    assert!(out.status.success());
+    let doc = std::fs::read_to_string(&svg).unwrap();
+    assert!(doc.contains("viewBox=\"-50 -25 100 50\""));
+
+    let out = turtle(&["run", "-", "--output", "square.txt"], prog);
+    assert_eq!(out.status.code(), Some(1));
+    std::fs::remove_dir_all(dir).unwrap();
+}
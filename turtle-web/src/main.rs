@@ -19,7 +19,7 @@ use tower_http::cors::{Any, CorsLayer};
 use turtle::debugger::{
     config::RunConfig,
     interface::Strings,
-    window::{ChannelWindow, WindowCmd, WindowEvent},
+    window::{ChannelWindow, EventSender, WindowCmd, WindowEvent},
 };
 
 const TEST_CODE: &str = "
@@ -56,7 +56,7 @@ end
 
 struct SharedState {
     commands: Receiver<WindowCmd>,
-    events: Sender<WindowEvent>,
+    events: EventSender,
     inputs: Sender<String>,
     outputs: Receiver<Result<String, String>>,
 }
@@ -70,10 +70,13 @@ async fn main() -> Result<(), std::io::Error> {
     let (in_tx, in_rx) = mpsc::channel();
     let (interface, outputs) = Strings::construct(in_rx);
     thread::spawn(move || {
-        RunConfig::new(&[])
+        if let Err(why) = RunConfig::new(&[])
             .window(window)
             .debug_in(interface)
-            .exec(&prog);
+            .exec(&prog)
+        {
+            eprintln!("runtime error: {} at {}", *why, why.get_pos());
+        }
     });
     let state = SharedState {
         commands,
@@ -128,13 +131,17 @@ async fn get_drawings(State(state): AppState) -> Json<Value> {
             .lock()
             .commands
             .try_iter()
-            .filter_map(|cmd| match cmd {
-                WindowCmd::Draw(from, to, col) => Some(json!({
+            .flat_map(|cmd| match cmd {
+                WindowCmd::Draw(from, to, col) => vec![(from, to, col)],
+                WindowCmd::DrawBatch(lines) => lines,
+                _ => Vec::new(),
+            })
+            .map(|(from, to, col)| {
+                json!({
                     "start": from,
                     "end": to,
-                    "color": col
-                })),
-                _ => None,
+                    "color": [col.r, col.g, col.b]
+                })
             })
             .collect(),
     ))